@@ -0,0 +1,102 @@
+use cargo::core::Workspace;
+use cargo::core::dependency::Kind;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(Deserialize)]
+pub struct Options {
+    arg_crate: Vec<String>,
+    flag_dev: bool,
+    flag_build: bool,
+    flag_target: Option<String>,
+    flag_features: Vec<String>,
+    flag_optional: bool,
+    flag_path: Option<String>,
+    flag_git: Option<String>,
+    flag_registry: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    flag_offline: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Add dependencies to a Cargo.toml manifest file
+
+Usage:
+    cargo add [options] <crate>...
+
+Options:
+    -h, --help               Print this message
+    -D, --dev                Add as a development dependency
+    -B, --build              Add as a build dependency
+    --target TARGET          Add as a dependency of the given target platform
+    --features FEATURES      Space-separated list of features of the dependency to enable
+    --optional               Mark the dependency as optional
+    --path PATH              Add a local dependency found at PATH
+    --git URL                Add a dependency from the git repository at URL
+    --registry REGISTRY      Registry to look up and add the dependency from
+    --manifest-path PATH     Path to the manifest to add dependencies to
+    -v, --verbose ...        Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+    --offline                Run without accessing the network
+    -Z FLAG ...              Unstable (nightly-only) flags to Cargo
+
+Each <crate> is the name of a dependency, optionally followed by `@` and a
+version requirement such as `serde@1.0`. Without a requirement the newest
+version of the crate in the registry which is neither yanked nor a prerelease
+is added.
+
+TARGET is either a target triple or a `cfg(..)` expression, and the
+dependency is added to the `[target.TARGET.dependencies]` table (or the
+corresponding dev- or build- table).
+
+PATH is relative to the current directory, and is written to the manifest
+relative to the package being edited. The package found there must be called
+<crate>.
+
+The manifest is edited in place, leaving its formatting and comments as they
+were. If the edited manifest can't be read by Cargo it is left unchanged.
+";
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     options.flag_offline,
+                     &options.flag_z)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+
+    let kind = match (options.flag_dev, options.flag_build) {
+        (true, true) => {
+            return Err(format_err!("cannot specify both `--dev` and `--build`").into())
+        }
+        (true, false) => Kind::Development,
+        (false, true) => Kind::Build,
+        (false, false) => Kind::Normal,
+    };
+    let opts = ops::AddOptions {
+        crates: &options.arg_crate,
+        kind,
+        target: options.flag_target.as_ref().map(|s| &s[..]),
+        features: &options.flag_features,
+        optional: options.flag_optional,
+        path: options.flag_path.as_ref().map(|s| &s[..]),
+        git: options.flag_git.as_ref().map(|s| &s[..]),
+        registry: options.flag_registry.as_ref().map(|s| &s[..]),
+    };
+    ops::add(&ws, &opts)?;
+    Ok(())
+}
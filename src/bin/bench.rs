@@ -10,6 +10,7 @@ pub struct Options {
     flag_no_run: bool,
     flag_package: Vec<String>,
     flag_jobs: Option<u32>,
+    flag_profile: Option<String>,
     flag_features: Vec<String>,
     flag_all_features: bool,
     flag_no_default_features: bool,
@@ -19,6 +20,7 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_message_format: MessageFormat,
+    flag_ignore_rust_version: bool,
     flag_lib: bool,
     flag_bin: Vec<String>,
     flag_bins: bool,
@@ -32,6 +34,7 @@ pub struct Options {
     flag_no_fail_fast: bool,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     arg_args: Vec<String>,
     flag_all: bool,
     flag_exclude: Vec<String>,
@@ -65,6 +68,7 @@ Options:
     --all                        Benchmark all packages in the workspace
     --exclude SPEC ...           Exclude packages from the benchmark
     -j N, --jobs N               Number of parallel jobs, defaults to # of CPUs
+    --profile NAME               Build artifacts with the specified profile
     --features FEATURES          Space-separated list of features to also build
     --all-features               Build all available features
     --no-default-features        Do not build the `default` feature
@@ -74,9 +78,11 @@ Options:
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
     --message-format FMT         Error format: human, json [default: human]
+    --ignore-rust-version        Ignore the `rust-version` of packages
     --no-fail-fast               Run all benchmarks regardless of failure
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
+    --offline                    Run without accessing the network
     -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
 
 All of the trailing arguments are passed to the benchmark binaries generated
@@ -107,6 +113,7 @@ pub fn execute(mut options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
@@ -117,6 +124,9 @@ pub fn execute(mut options: Options, config: &mut Config) -> CliResult {
                                     &options.flag_package)?;
 
     let ops = ops::TestOptions {
+        junit: None,
+        test_jobs: None,
+        partition: None,
         no_run: options.flag_no_run,
         no_fail_fast: options.flag_no_fail_fast,
         only_doc: false,
@@ -128,7 +138,8 @@ pub fn execute(mut options: Options, config: &mut Config) -> CliResult {
             all_features: options.flag_all_features,
             no_default_features: options.flag_no_default_features,
             spec,
-            release: true,
+            release: options.flag_profile.is_none(),
+            profile: options.flag_profile.as_ref().map(|s| &s[..]),
             mode: ops::CompileMode::Bench,
             filter: ops::CompileFilter::new(options.flag_lib,
                                             &options.flag_bin, options.flag_bins,
@@ -139,6 +150,9 @@ pub fn execute(mut options: Options, config: &mut Config) -> CliResult {
             message_format: options.flag_message_format,
             target_rustdoc_args: None,
             target_rustc_args: None,
+            timings: false,
+            ignore_rust_version: options.flag_ignore_rust_version,
+            build_plan: false,
         },
     };
 
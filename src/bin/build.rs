@@ -9,6 +9,7 @@ use cargo::util::{CliResult, Config};
 pub struct Options {
     flag_package: Vec<String>,
     flag_jobs: Option<u32>,
+    flag_profile: Option<String>,
     flag_features: Vec<String>,
     flag_all_features: bool,
     flag_no_default_features: bool,
@@ -18,6 +19,9 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_message_format: MessageFormat,
+    flag_ignore_rust_version: bool,
+    flag_timings: bool,
+    flag_build_plan: bool,
     flag_release: bool,
     flag_lib: bool,
     flag_bin: Vec<String>,
@@ -30,6 +34,7 @@ pub struct Options {
     flag_benches: bool,
     flag_all_targets: bool,
     flag_locked: bool,
+    flag_offline: bool,
     flag_frozen: bool,
     flag_all: bool,
     flag_exclude: Vec<String>,
@@ -60,6 +65,7 @@ Options:
     --benches                    Build all benches
     --all-targets                Build all targets (lib and bin targets by default)
     --release                    Build artifacts in release mode, with optimizations
+    --profile NAME               Build artifacts with the specified profile
     --features FEATURES          Space-separated list of features to also build
     --all-features               Build all available features
     --no-default-features        Do not build the `default` feature
@@ -69,8 +75,12 @@ Options:
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
     --message-format FMT         Error format: human, json [default: human]
+    --ignore-rust-version        Ignore the `rust-version` of packages
+    --timings                    Output a build timing report to target/cargo-timings
+    --build-plan                 Output the build commands as JSON instead of running them
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
+    --offline                    Run without accessing the network
     -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
 
 If the --package argument is given, then SPEC is a package id specification
@@ -95,6 +105,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
@@ -117,6 +128,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         spec,
         mode: ops::CompileMode::Build,
         release: options.flag_release,
+        profile: options.flag_profile.as_ref().map(|s| &s[..]),
         filter: ops::CompileFilter::new(options.flag_lib,
                                         &options.flag_bin, options.flag_bins,
                                         &options.flag_test, options.flag_tests,
@@ -126,6 +138,9 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         message_format: options.flag_message_format,
         target_rustdoc_args: None,
         target_rustc_args: None,
+        timings: options.flag_timings,
+        ignore_rust_version: options.flag_ignore_rust_version,
+        build_plan: options.flag_build_plan,
     };
 
     ops::compile(&ws, &opts)?;
@@ -31,6 +31,7 @@ pub struct Flags {
     arg_command: String,
     arg_args: Vec<String>,
     flag_locked: bool,
+    flag_offline: bool,
     flag_frozen: bool,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
@@ -53,6 +54,7 @@ Options:
     --color WHEN        Coloring: auto, always, never
     --frozen            Require Cargo.lock and cache are up to date
     --locked            Require Cargo.lock is up to date
+    --offline           Run without accessing the network
     -Z FLAG ...         Unstable (nightly-only) flags to Cargo
 
 Some common cargo commands are (see all commands with --list):
@@ -105,12 +107,14 @@ fn main() {
 
 macro_rules! each_subcommand{
     ($mac:ident) => {
+        $mac!(add);
         $mac!(bench);
         $mac!(build);
         $mac!(check);
         $mac!(clean);
         $mac!(doc);
         $mac!(fetch);
+        $mac!(fix);
         $mac!(generate_lockfile);
         $mac!(git_checkout);
         $mac!(help);
@@ -118,20 +122,25 @@ macro_rules! each_subcommand{
         $mac!(install);
         $mac!(locate_project);
         $mac!(login);
+        $mac!(logout);
         $mac!(metadata);
         $mac!(new);
+        $mac!(outdated);
         $mac!(owner);
         $mac!(package);
         $mac!(pkgid);
         $mac!(publish);
         $mac!(read_manifest);
+        $mac!(rm);
         $mac!(run);
         $mac!(rustc);
         $mac!(rustdoc);
         $mac!(search);
         $mac!(test);
+        $mac!(tree);
         $mac!(uninstall);
         $mac!(update);
+        $mac!(vendor);
         $mac!(verify_project);
         $mac!(version);
         $mac!(yank);
@@ -154,6 +163,7 @@ fn execute(flags: Flags, config: &mut Config) -> CliResult {
                    &flags.flag_color,
                    flags.flag_frozen,
                    flags.flag_locked,
+                   flags.flag_offline,
                    &flags.flag_z)?;
 
     init_git_transports(config);
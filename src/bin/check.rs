@@ -2,7 +2,7 @@ use std::env;
 
 use cargo::core::Workspace;
 use cargo::ops::{self, CompileOptions, MessageFormat, Packages};
-use cargo::util::{CliResult, CliError, Config};
+use cargo::util::{CliResult, Config};
 use cargo::util::important_paths::find_root_manifest_for_wd;
 
 pub const USAGE: &'static str = "
@@ -38,8 +38,11 @@ Options:
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
     --message-format FMT         Error format: human, json [default: human]
+    --ignore-rust-version        Ignore the `rust-version` of packages
+    --timings                    Output a build timing report to target/cargo-timings
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
+    --offline                    Run without accessing the network
     -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
 
 If the --package argument is given, then SPEC is a package id specification
@@ -56,7 +59,8 @@ the manifest. The default profile for this command is `dev`, but passing
 the --release flag will use the `release` profile instead.
 
 The `--profile test` flag can be used to check unit tests with the
-`#[cfg(test)]` attribute.
+`#[cfg(test)]` attribute. Any other profile name selects a profile defined
+in the manifest to check with, such as `release` or a custom profile.
 ";
 
 #[derive(Deserialize)]
@@ -72,6 +76,8 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_message_format: MessageFormat,
+    flag_ignore_rust_version: bool,
+    flag_timings: bool,
     flag_release: bool,
     flag_lib: bool,
     flag_bin: Vec<String>,
@@ -84,6 +90,7 @@ pub struct Options {
     flag_benches: bool,
     flag_all_targets: bool,
     flag_locked: bool,
+    flag_offline: bool,
     flag_frozen: bool,
     flag_all: bool,
     flag_exclude: Vec<String>,
@@ -101,6 +108,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
@@ -110,15 +118,10 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                                     &options.flag_exclude,
                                     &options.flag_package)?;
 
-    let test = match options.flag_profile.as_ref().map(|t| &t[..]) {
-            Some("test") => true,
-            None => false,
-            Some(profile) => {
-                let err = format_err!("unknown profile: `{}`, only `test` is \
-                                       currently supported", profile);
-                return Err(CliError::new(err, 101))
-            }
-        };
+    let (test, profile) = match options.flag_profile.as_ref().map(|t| &t[..]) {
+        Some("test") => (true, None),
+        profile => (false, profile),
+    };
 
     let opts = CompileOptions {
         config,
@@ -130,6 +133,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         spec,
         mode: ops::CompileMode::Check{test },
         release: options.flag_release,
+        profile,
         filter: ops::CompileFilter::new(options.flag_lib,
                                         &options.flag_bin, options.flag_bins,
                                         &options.flag_test, options.flag_tests,
@@ -139,6 +143,9 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         message_format: options.flag_message_format,
         target_rustdoc_args: None,
         target_rustc_args: None,
+        timings: options.flag_timings,
+        ignore_rust_version: options.flag_ignore_rust_version,
+        build_plan: false,
     };
 
     ops::compile(&ws, &opts)?;
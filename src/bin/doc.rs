@@ -20,12 +20,14 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_message_format: MessageFormat,
+    flag_ignore_rust_version: bool,
     flag_package: Vec<String>,
     flag_lib: bool,
     flag_bin: Vec<String>,
     flag_bins: bool,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     flag_all: bool,
     flag_exclude: Vec<String>,
     #[serde(rename = "flag_Z")]
@@ -59,8 +61,10 @@ Options:
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
     --message-format FMT         Error format: human, json [default: human]
+    --ignore-rust-version        Ignore the `rust-version` of packages
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
+    --offline                    Run without accessing the network
     -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
 
 By default the documentation for the local package and all dependencies is
@@ -85,6 +89,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
@@ -113,10 +118,14 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                                             false),
             message_format: options.flag_message_format,
             release: options.flag_release,
+            profile: None,
             mode: ops::CompileMode::Doc {
                 deps: !options.flag_no_deps,
             },
             target_rustc_args: None,
+            timings: false,
+            ignore_rust_version: options.flag_ignore_rust_version,
+            build_plan: false,
             target_rustdoc_args: None,
         },
     };
@@ -11,6 +11,7 @@ pub struct Options {
     flag_color: Option<String>,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
 }
@@ -29,6 +30,7 @@ Options:
     --color WHEN             Coloring: auto, always, never
     --frozen                 Require Cargo.lock and cache are up to date
     --locked                 Require Cargo.lock is up to date
+    --offline                Run without accessing the network
     -Z FLAG ...              Unstable (nightly-only) flags to Cargo
 
 If a lockfile is available, this command will ensure that all of the git
@@ -47,6 +49,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
     let ws = Workspace::new(&root, config)?;
@@ -0,0 +1,157 @@
+use std::env;
+
+use cargo::core::Workspace;
+use cargo::ops::{self, CompileOptions, FixOptions, MessageFormat, Packages};
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+pub const USAGE: &'static str = "
+Automatically fix the warnings reported by rustc for a local package
+
+Usage:
+    cargo fix [options]
+
+Options:
+    -h, --help                   Print this message
+    -p SPEC, --package SPEC ...  Package(s) to fix
+    --all                        Fix all packages in the workspace
+    --exclude SPEC ...           Exclude packages from the fixes
+    -j N, --jobs N               Number of parallel jobs, defaults to # of CPUs
+    --lib                        Fix only this package's library
+    --bin NAME                   Fix only the specified binary
+    --bins                       Fix all binaries
+    --example NAME               Fix only the specified example
+    --examples                   Fix all examples
+    --test NAME                  Fix only the specified test target
+    --tests                      Fix all tests
+    --bench NAME                 Fix only the specified bench target
+    --benches                    Fix all benches
+    --all-targets                Fix all targets (default)
+    --release                    Fix artifacts in release mode, with optimizations
+    --features FEATURES          Space-separated list of features to also fix
+    --all-features               Fix all available features
+    --no-default-features        Do not fix the `default` feature
+    --target TRIPLE              Fix for the target triple
+    --epoch                      Migrate the package to the next epoch
+    --allow-dirty                Fix code even if the working directory is dirty
+    --manifest-path PATH         Path to the manifest to fix
+    -v, --verbose ...            Use verbose output
+    -q, --quiet                  No output printed to stdout
+    --color WHEN                 Coloring: auto, always, never
+    --ignore-rust-version        Ignore the `rust-version` of packages
+    --frozen                     Require Cargo.lock and cache are up to date
+    --locked                     Require Cargo.lock is up to date
+    --offline                    Run without accessing the network
+    -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
+
+This command checks the selected packages like `cargo check` does, and applies
+the suggestions rustc is confident about to their source code. The packages
+are checked again afterwards, and the changes to any file that no longer
+compiles are backed out. All targets are fixed unless some are selected.
+
+With the --epoch flag the lints for the next epoch are enabled as well, and
+once the code is migrated the `rust` key of the manifest is updated.
+
+As the changes are made in place, `cargo fix` refuses to run on a working
+directory with uncommitted changes unless --allow-dirty is passed.
+
+If the --package argument is given, then SPEC is a package id specification
+which indicates which package should be fixed. If it is not given, then the
+current package is fixed. For more information on SPEC and its format, see the
+`cargo help pkgid` command.
+";
+
+#[derive(Deserialize)]
+pub struct Options {
+    flag_package: Vec<String>,
+    flag_jobs: Option<u32>,
+    flag_features: Vec<String>,
+    flag_all_features: bool,
+    flag_no_default_features: bool,
+    flag_target: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_ignore_rust_version: bool,
+    flag_release: bool,
+    flag_lib: bool,
+    flag_bin: Vec<String>,
+    flag_bins: bool,
+    flag_example: Vec<String>,
+    flag_examples: bool,
+    flag_test: Vec<String>,
+    flag_tests: bool,
+    flag_bench: Vec<String>,
+    flag_benches: bool,
+    flag_all_targets: bool,
+    flag_epoch: bool,
+    flag_allow_dirty: bool,
+    flag_locked: bool,
+    flag_offline: bool,
+    flag_frozen: bool,
+    flag_all: bool,
+    flag_exclude: Vec<String>,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    debug!("executing; cmd=cargo-fix; args={:?}",
+           env::args().collect::<Vec<_>>());
+
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     options.flag_offline,
+                     &options.flag_z)?;
+
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+
+    let spec = Packages::from_flags(options.flag_all,
+                                    &options.flag_exclude,
+                                    &options.flag_package)?;
+
+    let all_targets = options.flag_all_targets || !(
+        options.flag_lib || options.flag_bins || options.flag_examples ||
+        options.flag_tests || options.flag_benches ||
+        !options.flag_bin.is_empty() || !options.flag_example.is_empty() ||
+        !options.flag_test.is_empty() || !options.flag_bench.is_empty());
+
+    let compile_opts = CompileOptions {
+        config,
+        jobs: options.flag_jobs,
+        target: options.flag_target.as_ref().map(|t| &t[..]),
+        features: &options.flag_features,
+        all_features: options.flag_all_features,
+        no_default_features: options.flag_no_default_features,
+        spec,
+        mode: ops::CompileMode::Check { test: false },
+        release: options.flag_release,
+        profile: None,
+        filter: ops::CompileFilter::new(options.flag_lib,
+                                        &options.flag_bin, options.flag_bins,
+                                        &options.flag_test, options.flag_tests,
+                                        &options.flag_example, options.flag_examples,
+                                        &options.flag_bench, options.flag_benches,
+                                        all_targets),
+        message_format: MessageFormat::Human,
+        target_rustdoc_args: None,
+        target_rustc_args: None,
+        timings: false,
+        ignore_rust_version: options.flag_ignore_rust_version,
+        build_plan: false,
+    };
+
+    let opts = FixOptions {
+        compile_opts,
+        epoch: options.flag_epoch,
+        allow_dirty: options.flag_allow_dirty,
+    };
+
+    ops::fix(&ws, &opts)?;
+    Ok(())
+}
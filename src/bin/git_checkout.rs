@@ -11,6 +11,7 @@ pub struct Options {
     flag_color: Option<String>,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
 }
@@ -29,6 +30,7 @@ Options:
     --color WHEN             Coloring: auto, always, never
     --frozen                 Require Cargo.lock and cache are up to date
     --locked                 Require Cargo.lock is up to date
+    --offline                Run without accessing the network
     -Z FLAG ...              Unstable (nightly-only) flags to Cargo
 ";
 
@@ -38,6 +40,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
     let Options { flag_url: url, flag_reference: reference, .. } = options;
 
@@ -21,6 +21,7 @@ pub struct Options {
     flag_force: bool,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
 
     arg_crate: Vec<String>,
     flag_vers: Option<String>,
@@ -70,6 +71,7 @@ Build and install options:
     --color WHEN              Coloring: auto, always, never
     --frozen                  Require Cargo.lock and cache are up to date
     --locked                  Require Cargo.lock is up to date
+    --offline                 Run without accessing the network
     -Z FLAG ...               Unstable (nightly-only) flags to Cargo
 
 This command manages Cargo's local set of installed binary crates. Only packages
@@ -115,6 +117,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
 
     let compile_opts = ops::CompileOptions {
@@ -127,6 +130,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         spec: ops::Packages::Packages(&[]),
         mode: ops::CompileMode::Build,
         release: !options.flag_debug,
+        profile: None,
         filter: ops::CompileFilter::new(false,
                                         &options.flag_bin, options.flag_bins,
                                         &[], false,
@@ -135,6 +139,9 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                                         false),
         message_format: ops::MessageFormat::Human,
         target_rustc_args: None,
+        timings: false,
+        ignore_rust_version: false,
+        build_plan: false,
         target_rustdoc_args: None,
     };
 
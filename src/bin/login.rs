@@ -15,6 +15,7 @@ pub struct Options {
     flag_color: Option<String>,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
     flag_registry: Option<String>,
@@ -34,9 +35,13 @@ Options:
     --color WHEN             Coloring: auto, always, never
     --frozen                 Require Cargo.lock and cache are up to date
     --locked                 Require Cargo.lock is up to date
+    --offline                Run without accessing the network
     -Z FLAG ...              Unstable (nightly-only) flags to Cargo
     --registry REGISTRY      Registry to use
 
+The token is stored in $CARGO_HOME/credentials, unless a credential process
+is configured for the registry with `credential-process` in `.cargo/config`,
+in which case it's handed to that process to keep.
 ";
 
 pub fn execute(options: Options, config: &mut Config) -> CliResult {
@@ -45,6 +50,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
 
     if options.flag_registry.is_some() && !config.cli_unstable().unstable_options {
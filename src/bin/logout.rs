@@ -0,0 +1,54 @@
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+
+#[derive(Deserialize)]
+pub struct Options {
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    flag_offline: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+    flag_registry: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Remove the api token of a registry saved by `cargo login`.
+
+Usage:
+    cargo logout [options]
+
+Options:
+    -h, --help               Print this message
+    -v, --verbose ...        Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+    --offline                Run without accessing the network
+    -Z FLAG ...              Unstable (nightly-only) flags to Cargo
+    --registry REGISTRY      Registry to use
+
+The token is removed from $CARGO_HOME/credentials, or erased by the credential
+process configured for the registry.
+";
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     options.flag_offline,
+                     &options.flag_z)?;
+
+    if options.flag_registry.is_some() && !config.cli_unstable().unstable_options {
+        return Err(format_err!("registry option is an unstable feature and \
+                                requires -Zunstable-options to use.").into());
+    }
+
+    ops::registry_logout(config, options.flag_registry)?;
+    Ok(())
+}
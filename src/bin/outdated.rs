@@ -0,0 +1,95 @@
+use cargo;
+use cargo::core::{Verbosity, Workspace};
+use cargo::ops::{self, MessageFormat, Packages};
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(Deserialize)]
+pub struct Options {
+    flag_package: Vec<String>,
+    flag_workspace: bool,
+    flag_all: bool,
+    flag_exclude: Vec<String>,
+    flag_depth: Option<usize>,
+    flag_message_format: MessageFormat,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    flag_offline: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Display the dependencies which have newer versions available
+
+Usage:
+    cargo outdated [options]
+
+Options:
+    -h, --help                   Print this message
+    -p SPEC, --package SPEC ...  Package to check the dependencies of
+    --workspace                  Check the dependencies of all packages in the workspace
+    --all                        Alias for --workspace
+    --exclude SPEC ...           Exclude packages from the check
+    -d N, --depth N              Only check dependencies up to N levels deep,
+                                 1 being the direct dependencies
+    --message-format FMT         Output format: human, json [default: human]
+    --manifest-path PATH         Path to the manifest
+    -v, --verbose ...            Use verbose output
+    -q, --quiet                  No output printed to stdout
+    --color WHEN                 Coloring: auto, always, never
+    --frozen                     Require Cargo.lock and cache are up to date
+    --locked                     Require Cargo.lock is up to date
+    --offline                    Run without accessing the network
+    -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
+
+The versions in Cargo.lock are compared against the registries they come
+from. For each registry dependency with a newer version available, the locked
+version is printed along with the newest version accepted by the version
+requirements on it, which `cargo update` would move to, and the newest version
+published. Yanked versions are never suggested. Dependencies from paths and
+git repositories are not checked.
+
+If the --package argument is given, then SPEC is a package id specification
+which indicates which package's dependencies should be checked. If it is not
+given, then the current package is checked. For more information on SPEC and
+its format, see the `cargo help pkgid` command.
+";
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     options.flag_offline,
+                     &options.flag_z)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+
+    let spec = Packages::from_flags(options.flag_workspace || options.flag_all,
+                                    &options.flag_exclude,
+                                    &options.flag_package)?;
+    let opts = ops::OutdatedOptions {
+        spec,
+        depth: options.flag_depth,
+    };
+
+    let report = ops::outdated(&ws, &opts)?;
+    match options.flag_message_format {
+        MessageFormat::Json => cargo::print_json(&report),
+        MessageFormat::Human if report.dependencies.is_empty() => {
+            config.shell().status("Finished", "all dependencies are up to date")?;
+        }
+        MessageFormat::Human => {
+            if config.shell().verbosity() != Verbosity::Quiet {
+                print!("{}", report);
+            }
+        }
+    }
+    Ok(())
+}
@@ -14,6 +14,7 @@ pub struct Options {
     flag_list: bool,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
     flag_registry: Option<String>,
@@ -37,6 +38,7 @@ Options:
     --color WHEN             Coloring: auto, always, never
     --frozen                 Require Cargo.lock and cache are up to date
     --locked                 Require Cargo.lock is up to date
+    --offline                Run without accessing the network
     -Z FLAG ...              Unstable (nightly-only) flags to Cargo
     --registry REGISTRY      Registry to use
 
@@ -55,6 +57,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
     let opts = ops::OwnersOptions {
         krate: options.arg_crate,
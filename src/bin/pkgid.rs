@@ -11,6 +11,7 @@ pub struct Options {
     flag_manifest_path: Option<String>,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     flag_package: Option<String>,
     arg_spec: Option<String>,
     #[serde(rename = "flag_Z")]
@@ -32,6 +33,7 @@ Options:
     --color WHEN             Coloring: auto, always, never
     --frozen                 Require Cargo.lock and cache are up to date
     --locked                 Require Cargo.lock is up to date
+    --offline                Run without accessing the network
     -Z FLAG ...              Unstable (nightly-only) flags to Cargo
 
 Given a <spec> argument, print out the fully qualified package id specifier.
@@ -61,6 +63,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
     let root = find_root_manifest_for_wd(options.flag_manifest_path.clone(), config.cwd())?;
     let ws = Workspace::new(&root, config)?;
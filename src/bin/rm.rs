@@ -0,0 +1,75 @@
+use cargo::core::Workspace;
+use cargo::core::dependency::Kind;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(Deserialize)]
+pub struct Options {
+    arg_crate: Vec<String>,
+    flag_dev: bool,
+    flag_build: bool,
+    flag_target: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    flag_offline: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Remove dependencies from a Cargo.toml manifest file
+
+Usage:
+    cargo rm [options] <crate>...
+
+Options:
+    -h, --help               Print this message
+    -D, --dev                Remove a development dependency
+    -B, --build              Remove a build dependency
+    --target TARGET          Remove a dependency of the given target platform
+    --manifest-path PATH     Path to the manifest to remove dependencies from
+    -v, --verbose ...        Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+    --offline                Run without accessing the network
+    -Z FLAG ...              Unstable (nightly-only) flags to Cargo
+
+The manifest is edited in place, leaving its formatting and comments as they
+were. If the edited manifest can't be read by Cargo, for example because a
+feature still refers to a removed optional dependency, it is left unchanged.
+";
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     options.flag_offline,
+                     &options.flag_z)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+
+    let kind = match (options.flag_dev, options.flag_build) {
+        (true, true) => {
+            return Err(format_err!("cannot specify both `--dev` and `--build`").into())
+        }
+        (true, false) => Kind::Development,
+        (false, true) => Kind::Build,
+        (false, false) => Kind::Normal,
+    };
+    let opts = ops::RemoveOptions {
+        crates: &options.arg_crate,
+        kind,
+        target: options.flag_target.as_ref().map(|s| &s[..]),
+    };
+    ops::remove(&ws, &opts)?;
+    Ok(())
+}
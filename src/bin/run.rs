@@ -11,6 +11,7 @@ pub struct Options {
     flag_example: Option<String>,
     flag_package: Option<String>,
     flag_jobs: Option<u32>,
+    flag_profile: Option<String>,
     flag_features: Vec<String>,
     flag_all_features: bool,
     flag_no_default_features: bool,
@@ -20,9 +21,11 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_message_format: MessageFormat,
+    flag_ignore_rust_version: bool,
     flag_release: bool,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     arg_args: Vec<String>,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
@@ -41,6 +44,7 @@ Options:
     -p SPEC, --package SPEC      Package with the target to run
     -j N, --jobs N               Number of parallel jobs, defaults to # of CPUs
     --release                    Build artifacts in release mode, with optimizations
+    --profile NAME               Build artifacts with the specified profile
     --features FEATURES          Space-separated list of features to also build
     --all-features               Build all available features
     --no-default-features        Do not build the `default` feature
@@ -50,8 +54,10 @@ Options:
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
     --message-format FMT         Error format: human, json [default: human]
+    --ignore-rust-version        Ignore the `rust-version` of packages
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
+    --offline                    Run without accessing the network
     -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
 
 If neither `--bin` nor `--example` are given, then if the project only has one
@@ -70,6 +76,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
@@ -94,6 +101,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         no_default_features: options.flag_no_default_features,
         spec,
         release: options.flag_release,
+        profile: options.flag_profile.as_ref().map(|s| &s[..]),
         mode: ops::CompileMode::Build,
         filter: if examples.is_empty() && bins.is_empty() {
             ops::CompileFilter::Default { required_features_filterable: false, }
@@ -108,6 +116,9 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         message_format: options.flag_message_format,
         target_rustdoc_args: None,
         target_rustc_args: None,
+        timings: false,
+        ignore_rust_version: options.flag_ignore_rust_version,
+        build_plan: false,
     };
 
     let ws = Workspace::new(&root, config)?;
@@ -3,7 +3,7 @@ use std::env;
 use cargo::core::Workspace;
 use cargo::ops::{self, CompileOptions, CompileMode, MessageFormat, Packages};
 use cargo::util::important_paths::{find_root_manifest_for_wd};
-use cargo::util::{CliResult, CliError, Config};
+use cargo::util::{CliResult, Config};
 
 #[derive(Deserialize)]
 pub struct Options {
@@ -19,6 +19,7 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_message_format: MessageFormat,
+    flag_ignore_rust_version: bool,
     flag_release: bool,
     flag_lib: bool,
     flag_bin: Vec<String>,
@@ -33,6 +34,7 @@ pub struct Options {
     flag_profile: Option<String>,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
 }
@@ -68,8 +70,10 @@ Options:
     -q, --quiet              No output printed to stdout
     --color WHEN             Coloring: auto, always, never
     --message-format FMT     Error format: human, json [default: human]
+    --ignore-rust-version    Ignore the `rust-version` of packages
     --frozen                 Require Cargo.lock and cache are up to date
     --locked                 Require Cargo.lock is up to date
+    --offline                Run without accessing the network
     -Z FLAG ...              Unstable (nightly-only) flags to Cargo
 
 The specified target for the current package (or package specified by SPEC if
@@ -94,20 +98,17 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path,
                                          config.cwd())?;
-    let mode = match options.flag_profile.as_ref().map(|t| &t[..]) {
-        Some("dev") | None => CompileMode::Build,
-        Some("test") => CompileMode::Test,
-        Some("bench") => CompileMode::Bench,
-        Some("check") => CompileMode::Check {test: false},
-        Some(mode) => {
-            let err = format_err!("unknown profile: `{}`, use dev,
-                                   test, or bench", mode);
-            return Err(CliError::new(err, 101))
-        }
+    let (mode, profile) = match options.flag_profile.as_ref().map(|t| &t[..]) {
+        Some("dev") | None => (CompileMode::Build, None),
+        Some("test") => (CompileMode::Test, None),
+        Some("bench") => (CompileMode::Bench, None),
+        Some("check") => (CompileMode::Check {test: false}, None),
+        Some(profile) => (CompileMode::Build, Some(profile)),
     };
 
     let spec = options.flag_package.map_or_else(Vec::new, |s| vec![s]);
@@ -122,6 +123,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         spec: Packages::Packages(&spec),
         mode,
         release: options.flag_release,
+        profile,
         filter: ops::CompileFilter::new(options.flag_lib,
                                         &options.flag_bin, options.flag_bins,
                                         &options.flag_test, options.flag_tests,
@@ -131,6 +133,9 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         message_format: options.flag_message_format,
         target_rustdoc_args: None,
         target_rustc_args: options.arg_opts.as_ref().map(|a| &a[..]),
+        timings: false,
+        ignore_rust_version: options.flag_ignore_rust_version,
+        build_plan: false,
     };
 
     let ws = Workspace::new(&root, config)?;
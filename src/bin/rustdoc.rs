@@ -18,6 +18,7 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_message_format: MessageFormat,
+    flag_ignore_rust_version: bool,
     flag_package: Option<String>,
     flag_lib: bool,
     flag_bin: Vec<String>,
@@ -31,6 +32,7 @@ pub struct Options {
     flag_all_targets: bool,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
 }
@@ -66,8 +68,10 @@ Options:
     -q, --quiet              No output printed to stdout
     --color WHEN             Coloring: auto, always, never
     --message-format FMT     Error format: human, json [default: human]
+    --ignore-rust-version    Ignore the `rust-version` of packages
     --frozen                 Require Cargo.lock and cache are up to date
     --locked                 Require Cargo.lock is up to date
+    --offline                Run without accessing the network
     -Z FLAG ...              Unstable (nightly-only) flags to Cargo
 
 The specified target for the current package (or package specified by SPEC if
@@ -89,6 +93,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path,
@@ -107,6 +112,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
             no_default_features: options.flag_no_default_features,
             spec: Packages::Packages(&spec),
             release: options.flag_release,
+            profile: None,
             filter: ops::CompileFilter::new(options.flag_lib,
                                             &options.flag_bin, options.flag_bins,
                                             &options.flag_test, options.flag_tests,
@@ -117,6 +123,9 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
             mode: ops::CompileMode::Doc { deps: false },
             target_rustdoc_args: Some(&options.arg_opts),
             target_rustc_args: None,
+            timings: false,
+            ignore_rust_version: options.flag_ignore_rust_version,
+            build_plan: false,
         },
     };
 
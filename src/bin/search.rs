@@ -13,6 +13,7 @@ pub struct Options {
     flag_limit: Option<u32>,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     arg_query: Vec<String>,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
@@ -36,6 +37,7 @@ Options:
     --limit LIMIT            Limit the number of results (default: 10, max: 100)
     --frozen                 Require Cargo.lock and cache are up to date
     --locked                 Require Cargo.lock is up to date
+    --offline                Run without accessing the network
     -Z FLAG ...              Unstable (nightly-only) flags to Cargo
     --registry REGISTRY      Registry to use
 ";
@@ -46,6 +48,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
     let Options {
         flag_index: index,
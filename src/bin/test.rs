@@ -11,6 +11,9 @@ pub struct Options {
     flag_features: Vec<String>,
     flag_all_features: bool,
     flag_jobs: Option<u32>,
+    flag_test_jobs: Option<u32>,
+    flag_partition: Option<String>,
+    flag_profile: Option<String>,
     flag_manifest_path: Option<String>,
     flag_no_default_features: bool,
     flag_no_run: bool,
@@ -31,10 +34,14 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_message_format: MessageFormat,
+    flag_junit: Option<String>,
+    flag_ignore_rust_version: bool,
+    flag_timings: bool,
     flag_release: bool,
     flag_no_fail_fast: bool,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     flag_all: bool,
     flag_exclude: Vec<String>,
     #[serde(rename = "flag_Z")]
@@ -68,7 +75,10 @@ Options:
     --all                        Test all packages in the workspace
     --exclude SPEC ...           Exclude packages from the test
     -j N, --jobs N               Number of parallel builds, see below for details
+    --test-jobs N                Number of test binaries to run at once
+    --partition PARTITION        Run only a part of the tests, see below for details
     --release                    Build artifacts in release mode, with optimizations
+    --profile NAME               Build artifacts with the specified profile
     --features FEATURES          Space-separated list of features to also build
     --all-features               Build all available features
     --no-default-features        Do not build the `default` feature
@@ -78,9 +88,13 @@ Options:
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
     --message-format FMT         Error format: human, json [default: human]
+    --junit PATH                 Write the results of the tests to PATH as JUnit XML
+    --ignore-rust-version        Ignore the `rust-version` of packages
+    --timings                    Output a build timing report to target/cargo-timings
     --no-fail-fast               Run all tests regardless of failure
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
+    --offline                    Run without accessing the network
     -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
 
 All of the trailing arguments are passed to the test binaries generated for
@@ -106,6 +120,18 @@ to the test binaries:
 
     cargo test -- --test-threads=1
 
+Test binaries are run one after another unless --test-jobs, or the
+`build.test-jobs` config key, allows running several of them at once. The
+output of each binary is then printed when it has finished.
+
+To split the tests up between several machines, --partition runs only one of
+N parts of them. It is either `count:K/N`, which deals out the test binaries
+in turn and runs the K-th part, or `hash:K/N`, which assigns the individual
+tests of each binary by a hash of their names. With `hash` a binary is run
+once for each of its tests in the part, passing the name of the test along
+with `--exact`, which also applies to any filters given. Doctests are
+assigned per library.
+
 Compilation can be configured via the `test` profile in the manifest.
 
 By default the rust test harness hides output from test execution to
@@ -117,6 +143,11 @@ by passing `--nocapture` to the test binaries:
 To get the list of all options available for the test binaries use this:
 
     cargo test -- --help
+
+With `--message-format json` the results of individual tests are printed as
+JSON messages, the output of the test binaries being parsed for them instead
+of printed. The `--junit` report collects the results of all test binaries
+and doctests which ran into a single file.
 ";
 
 pub fn execute(mut options: Options, config: &mut Config) -> CliResult {
@@ -128,6 +159,7 @@ pub fn execute(mut options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
@@ -158,6 +190,12 @@ pub fn execute(mut options: Options, config: &mut Config) -> CliResult {
         no_run: options.flag_no_run,
         no_fail_fast: options.flag_no_fail_fast,
         only_doc: options.flag_doc,
+        junit: options.flag_junit.as_ref().map(|p| config.cwd().join(p)),
+        test_jobs: options.flag_test_jobs,
+        partition: match options.flag_partition {
+            Some(ref s) => Some(s.parse()?),
+            None => None,
+        },
         compile_opts: ops::CompileOptions {
             config,
             jobs: options.flag_jobs,
@@ -167,11 +205,15 @@ pub fn execute(mut options: Options, config: &mut Config) -> CliResult {
             no_default_features: options.flag_no_default_features,
             spec,
             release: options.flag_release,
+            profile: options.flag_profile.as_ref().map(|s| &s[..]),
             mode,
             filter,
             message_format: options.flag_message_format,
             target_rustdoc_args: None,
             target_rustc_args: None,
+            timings: options.flag_timings,
+            ignore_rust_version: options.flag_ignore_rust_version,
+            build_plan: false,
         },
     };
 
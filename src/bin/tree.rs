@@ -0,0 +1,107 @@
+use cargo::core::{Verbosity, Workspace};
+use cargo::ops::{self, Packages};
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(Deserialize)]
+pub struct Options {
+    flag_package: Vec<String>,
+    flag_all: bool,
+    flag_exclude: Vec<String>,
+    flag_features: Vec<String>,
+    flag_all_features: bool,
+    flag_no_default_features: bool,
+    flag_target: Option<String>,
+    flag_kind: Option<String>,
+    flag_invert: Option<String>,
+    flag_duplicates: bool,
+    flag_show_features: bool,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    flag_offline: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Display a tree visualization of the dependency graph
+
+Usage:
+    cargo tree [options]
+
+Options:
+    -h, --help                   Print this message
+    -p SPEC, --package SPEC ...  Package to display the dependency tree of
+    --all                        Display the trees of all packages in the workspace
+    --exclude SPEC ...           Exclude packages from the display
+    --features FEATURES          Space-separated list of features to activate
+    --all-features               Activate all available features
+    --no-default-features        Do not activate the `default` feature
+    --target TRIPLE              Only include dependencies active for the target triple
+    -k KINDS, --kind KINDS       Comma-separated dependency kinds to display:
+                                 normal, build, dev (defaults to all of them)
+    -i SPEC, --invert SPEC       Display the packages which depend on SPEC
+    -d, --duplicates             Display the packages present at multiple versions
+                                 and the packages which depend on them
+    --show-features              Display the features activated for each package
+    --manifest-path PATH         Path to the manifest
+    -v, --verbose ...            Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet                  No output printed to stdout
+    --color WHEN                 Coloring: auto, always, never
+    --frozen                     Require Cargo.lock and cache are up to date
+    --locked                     Require Cargo.lock is up to date
+    --offline                    Run without accessing the network
+    -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
+
+The dependency graph is resolved exactly as `cargo build` would resolve it,
+including the lock file, overrides and the requested features. Packages which
+have already been displayed elsewhere in the tree are marked with `(*)` and
+their dependencies are not repeated.
+
+If the --package argument is given, then SPEC is a package id specification
+which indicates which package's tree should be displayed. If it is not given,
+then the current package is displayed. For more information on SPEC and its
+format, see the `cargo help pkgid` command.
+";
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     options.flag_offline,
+                     &options.flag_z)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+
+    let spec = Packages::from_flags(options.flag_all,
+                                    &options.flag_exclude,
+                                    &options.flag_package)?;
+    let kinds = match options.flag_kind {
+        Some(ref kinds) => ops::parse_dependency_kinds(kinds)?,
+        None => ops::parse_dependency_kinds("normal,build,dev")?,
+    };
+
+    let opts = ops::TreeOptions {
+        features: options.flag_features,
+        no_default_features: options.flag_no_default_features,
+        all_features: options.flag_all_features,
+        spec,
+        target: options.flag_target.as_ref().map(|t| &t[..]),
+        kinds,
+        invert: options.flag_invert.as_ref().map(|s| &s[..]),
+        duplicates: options.flag_duplicates,
+        show_features: options.flag_show_features,
+    };
+
+    let tree = ops::tree(&ws, &opts)?;
+    if config.shell().verbosity() != Verbosity::Quiet {
+        print!("{}", tree);
+    }
+    Ok(())
+}
@@ -10,6 +10,7 @@ pub struct Options {
     flag_color: Option<String>,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
 
@@ -32,6 +33,7 @@ Options:
     --color WHEN              Coloring: auto, always, never
     --frozen                  Require Cargo.lock and cache are up to date
     --locked                  Require Cargo.lock is up to date
+    --offline                 Run without accessing the network
     -Z FLAG ...               Unstable (nightly-only) flags to Cargo
 
 The argument SPEC is a package id specification (see `cargo help pkgid`) to
@@ -46,6 +48,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
 
     let root = options.flag_root.as_ref().map(|s| &s[..]);
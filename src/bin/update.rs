@@ -10,12 +10,14 @@ pub struct Options {
     flag_package: Vec<String>,
     flag_aggressive: bool,
     flag_precise: Option<String>,
+    flag_lockfile_version: Option<String>,
     flag_manifest_path: Option<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
 }
@@ -31,12 +33,14 @@ Options:
     -p SPEC, --package SPEC ...  Package to update
     --aggressive                 Force updating all dependencies of <name> as well
     --precise PRECISE            Update a single dependency to exactly PRECISE
+    --lockfile-version VERSION   Write the lock file in format VERSION (1 or 2)
     --manifest-path PATH         Path to the crate's manifest
     -v, --verbose ...            Use verbose output (-vv very verbose/build.rs output)
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
+    --offline                    Run without accessing the network
     -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
 
 This command requires that a `Cargo.lock` already exists as generated by
@@ -57,6 +61,13 @@ be updated to.
 If SPEC is not given, then all dependencies will be re-resolved and
 updated.
 
+The format of an existing lock file is kept when it's updated, and new lock
+files are written in version 1. Passing the
+`--lockfile-version` flag without SPEC migrates the lock file to another
+format, leaving all dependencies at their currently recorded versions.
+Version 2 lists the checksum of each package along with it, and only names the
+version and source of a dependency when needed.
+
 For more information about package id specifications, see `cargo help pkgid`.
 ";
 
@@ -67,13 +78,26 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_color,
                      options.flag_frozen,
                      options.flag_locked,
+                     options.flag_offline,
                      &options.flag_z)?;
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
 
+    let lockfile_version = match options.flag_lockfile_version {
+        Some(ref v) => match v.parse() {
+            Ok(version) => Some(version),
+            Err(()) => {
+                return Err(format_err!("invalid lock file version `{}`, \
+                                        expected `1` or `2`", v).into())
+            }
+        },
+        None => None,
+    };
+
     let update_opts = ops::UpdateOptions {
         aggressive: options.flag_aggressive,
         precise: options.flag_precise.as_ref().map(|s| &s[..]),
         to_update: &options.flag_package,
+        lockfile_version,
         config,
     };
 
@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(Deserialize)]
+pub struct Options {
+    arg_path: Option<String>,
+    flag_no_delete: bool,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    flag_offline: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Vendor all dependencies for a project locally
+
+Usage:
+    cargo vendor [options] [<path>]
+
+Options:
+    -h, --help               Print this message
+    --no-delete              Don't delete older crates in the vendor directory
+    --manifest-path PATH     Path to the manifest to vendor dependencies for
+    -v, --verbose ...        Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+    --offline                Run without accessing the network
+    -Z FLAG ...              Unstable (nightly-only) flags to Cargo
+
+This command resolves the dependency graph of the project and copies the
+source of every registry and git dependency into <path> (which defaults to
+`vendor`), one directory per package named `<name>-<version>`. Each package
+is accompanied by a `.cargo-checksum.json` file so the directory can be used
+as a `directory` source.
+
+Once finished, the `[source]` configuration needed to build the project
+against the vendored sources is printed to stdout. It should be added to
+`.cargo/config` next to the project.
+";
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     options.flag_offline,
+                     &options.flag_z)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+
+    let destination = options.arg_path.as_ref().map(|s| &s[..]).unwrap_or("vendor");
+    let opts = ops::VendorOptions {
+        destination: Path::new(destination),
+        no_delete: options.flag_no_delete,
+    };
+    let source_config = ops::vendor(&ws, &opts)?;
+
+    config.shell().status("Vendored", format!("dependencies into `{}`", destination))?;
+    print!("{}", source_config);
+    Ok(())
+}
@@ -17,6 +17,7 @@ pub struct Flags {
     flag_color: Option<String>,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_offline: bool,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
 }
@@ -36,6 +37,7 @@ Options:
     --color WHEN            Coloring: auto, always, never
     --frozen                Require Cargo.lock and cache are up to date
     --locked                Require Cargo.lock is up to date
+    --offline               Run without accessing the network
     -Z FLAG ...             Unstable (nightly-only) flags to Cargo
 ";
 
@@ -45,6 +47,7 @@ pub fn execute(args: Flags, config: &mut Config) -> CliResult {
                      &args.flag_color,
                      args.flag_frozen,
                      args.flag_locked,
+                     args.flag_offline,
                      &args.flag_z)?;
 
     let mut contents = String::new();
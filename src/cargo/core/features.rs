@@ -55,6 +55,16 @@ pub enum Epoch {
     Epoch2018,
 }
 
+impl Epoch {
+    /// The epoch following this one, if there is any
+    pub fn next(&self) -> Option<Epoch> {
+        match *self {
+            Epoch::Epoch2015 => Some(Epoch::Epoch2018),
+            Epoch::Epoch2018 => None,
+        }
+    }
+}
+
 impl fmt::Display for Epoch {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -276,7 +286,6 @@ impl Features {
 pub struct CliUnstable {
     pub print_im_a_teapot: bool,
     pub unstable_options: bool,
-    pub offline: bool,
     pub no_index_update: bool,
     pub avoid_dev_deps: bool,
 }
@@ -309,7 +318,8 @@ impl CliUnstable {
         match k {
             "print-im-a-teapot" => self.print_im_a_teapot = parse_bool(v)?,
             "unstable-options" => self.unstable_options = true,
-            "offline" => self.offline = true,
+            "offline" => bail!("the `-Z offline` flag has been stabilized, \
+                                use `--offline` instead"),
             "no-index-update" => self.no_index_update = true,
             "avoid-dev-deps" => self.avoid_dev_deps = true,
             _ => bail!("unknown `-Z` flag specified: {}", k),
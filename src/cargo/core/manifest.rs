@@ -1,6 +1,7 @@
 use std::collections::{HashMap, BTreeMap};
 use std::fmt;
 use std::path::{PathBuf, Path};
+use std::ptr;
 use std::rc::Rc;
 use std::hash::{Hash, Hasher};
 
@@ -9,8 +10,8 @@ use serde::ser;
 use url::Url;
 
 use core::{Dependency, PackageId, Summary, SourceId, PackageIdSpec};
-use core::{WorkspaceConfig, Epoch, Features, Feature};
-use util::Config;
+use core::{WorkspaceConfig, Epoch, Features, Feature, ResolveBehavior};
+use util::{Config, RustVersion};
 use util::toml::TomlManifest;
 use util::errors::*;
 
@@ -39,6 +40,7 @@ pub struct Manifest {
     features: Features,
     epoch: Epoch,
     im_a_teapot: Option<bool>,
+    resolve_behavior: Option<ResolveBehavior>,
 }
 
 /// When parsing `Cargo.toml`, some warnings should silenced
@@ -56,6 +58,7 @@ pub struct VirtualManifest {
     patch: HashMap<Url, Vec<Dependency>>,
     workspace: WorkspaceConfig,
     profiles: Profiles,
+    resolve_behavior: Option<ResolveBehavior>,
 }
 
 /// General metadata about a package which is just blindly uploaded to the
@@ -157,6 +160,8 @@ impl ser::Serialize for TargetKind {
 // though are definitely needed!
 #[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize)]
 pub struct Profile {
+    /// The name of the profile in `Cargo.toml` these settings came from
+    pub name: String,
     pub opt_level: String,
     #[serde(skip_serializing)]
     pub lto: Lto,
@@ -203,6 +208,15 @@ pub struct Profiles {
     pub check: Profile,
     pub check_test: Profile,
     pub doctest: Profile,
+    /// Profiles used instead of the ones above for packages matching a
+    /// `[profile.*.overrides."<spec>"]` table, sorted by spec
+    pub overrides: Vec<(PackageIdSpec, Profiles)>,
+    /// Profiles used instead of the ones above for build scripts,
+    /// procedural macros and their dependencies
+    pub build_override: Option<Box<Profiles>>,
+    /// Custom profiles defined in `Cargo.toml`, along with whether they're
+    /// based on `release` rather than `dev`
+    pub custom: BTreeMap<String, (bool, Profiles)>,
 }
 
 /// Information about a binary, a library, an example, etc. that is part of the
@@ -275,6 +289,7 @@ impl Manifest {
                features: Features,
                epoch: Epoch,
                im_a_teapot: Option<bool>,
+               resolve_behavior: Option<ResolveBehavior>,
                original: Rc<TomlManifest>) -> Manifest {
         Manifest {
             summary,
@@ -294,6 +309,7 @@ impl Manifest {
             original,
             im_a_teapot,
             publish_lockfile,
+            resolve_behavior,
         }
     }
 
@@ -316,6 +332,9 @@ impl Manifest {
     pub fn links(&self) -> Option<&str> {
         self.links.as_ref().map(|s| &s[..])
     }
+    pub fn rust_version(&self) -> Option<&RustVersion> {
+        self.summary.rust_version()
+    }
 
     pub fn workspace_config(&self) -> &WorkspaceConfig {
         &self.workspace
@@ -368,18 +387,26 @@ impl Manifest {
     pub fn epoch(&self) -> Epoch {
         self.epoch
     }
+
+    /// The `resolver` key of this manifest, which only applies when it's the
+    /// root of its workspace.
+    pub fn resolve_behavior(&self) -> Option<ResolveBehavior> {
+        self.resolve_behavior
+    }
 }
 
 impl VirtualManifest {
     pub fn new(replace: Vec<(PackageIdSpec, Dependency)>,
                patch: HashMap<Url, Vec<Dependency>>,
                workspace: WorkspaceConfig,
-               profiles: Profiles) -> VirtualManifest {
+               profiles: Profiles,
+               resolve_behavior: Option<ResolveBehavior>) -> VirtualManifest {
         VirtualManifest {
             replace,
             patch,
             workspace,
             profiles,
+            resolve_behavior,
         }
     }
 
@@ -398,6 +425,10 @@ impl VirtualManifest {
     pub fn profiles(&self) -> &Profiles {
         &self.profiles
     }
+
+    pub fn resolve_behavior(&self) -> Option<ResolveBehavior> {
+        self.resolve_behavior
+    }
 }
 
 impl Target {
@@ -645,6 +676,70 @@ impl fmt::Display for Target {
     }
 }
 
+impl Profiles {
+    /// Returns the set of profiles to use when building with `--profile
+    /// name`, along with whether it's a release build.
+    pub fn select(&self, name: &str) -> CargoResult<(bool, &Profiles)> {
+        match name {
+            "dev" => Ok((false, self)),
+            "release" => Ok((true, self)),
+            "test" | "bench" | "doc" => {
+                bail!("profile `{}` cannot be selected with `--profile`, \
+                       it's chosen automatically by the command being run", name)
+            }
+            _ => match self.custom.get(name) {
+                Some(&(release, ref profiles)) => Ok((release, profiles)),
+                None => bail!("profile `{}` is not defined", name),
+            },
+        }
+    }
+
+    /// Returns the profile that `pkg` should be built with in place of
+    /// `base`, which must be one of the profiles of `self`.
+    ///
+    /// `for_host` indicates whether the unit is a build script, a procedural
+    /// macro, or a dependency of one, in which case `build-override` is taken
+    /// into account before any package-specific overrides.
+    pub fn for_package<'a>(&'a self,
+                           base: &'a Profile,
+                           pkg: &PackageId,
+                           for_host: bool) -> &'a Profile {
+        let slot = match self.slots().iter().position(|p| ptr::eq(*p, base)) {
+            Some(slot) => slot,
+            None => return base,
+        };
+        let profiles = match self.build_override {
+            Some(ref profiles) if for_host => profiles,
+            _ => self,
+        };
+        for &(ref spec, ref overridden) in profiles.overrides.iter() {
+            if spec.matches(pkg) {
+                return overridden.slots()[slot]
+            }
+        }
+        profiles.slots()[slot]
+    }
+
+    /// Returns whether `profile` was picked by `for_package` due to a
+    /// `build-override` table.
+    pub fn is_build_override(&self, profile: &Profile) -> bool {
+        let profiles = match self.build_override {
+            Some(ref profiles) => profiles,
+            None => return false,
+        };
+        let contains = |set: &Profiles| {
+            set.slots().iter().any(|p| ptr::eq(*p, profile))
+        };
+        contains(profiles) || profiles.overrides.iter().any(|o| contains(&o.1))
+    }
+
+    fn slots(&self) -> [&Profile; 11] {
+        [&self.release, &self.dev, &self.test, &self.test_deps, &self.bench,
+         &self.bench_deps, &self.doc, &self.custom_build, &self.check,
+         &self.check_test, &self.doctest]
+    }
+}
+
 impl Profile {
     pub fn default_dev() -> Profile {
         Profile {
@@ -658,6 +753,7 @@ impl Profile {
 
     pub fn default_release() -> Profile {
         Profile {
+            name: "release".to_string(),
             opt_level: "3".to_string(),
             debuginfo: None,
             ..Profile::default()
@@ -666,6 +762,7 @@ impl Profile {
 
     pub fn default_test() -> Profile {
         Profile {
+            name: "test".to_string(),
             test: true,
             ..Profile::default_dev()
         }
@@ -673,6 +770,7 @@ impl Profile {
 
     pub fn default_bench() -> Profile {
         Profile {
+            name: "bench".to_string(),
             test: true,
             ..Profile::default_release()
         }
@@ -680,6 +778,7 @@ impl Profile {
 
     pub fn default_doc() -> Profile {
         Profile {
+            name: "doc".to_string(),
             doc: true,
             ..Profile::default_dev()
         }
@@ -719,6 +818,7 @@ impl Profile {
 impl Default for Profile {
     fn default() -> Profile {
         Profile {
+            name: "dev".to_string(),
             opt_level: "0".to_string(),
             lto: Lto::Bool(false),
             codegen_units: None,
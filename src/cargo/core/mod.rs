@@ -2,15 +2,16 @@ pub use self::dependency::Dependency;
 pub use self::features::{Epoch, Features, Feature, CliUnstable};
 pub use self::manifest::{EitherManifest, VirtualManifest};
 pub use self::manifest::{Manifest, Target, TargetKind, Profile, LibKind, Profiles};
-pub use self::package::{Package, PackageSet};
+pub use self::package::{Package, PackageSet, Downloads};
 pub use self::package_id::PackageId;
 pub use self::package_id_spec::PackageIdSpec;
 pub use self::registry::Registry;
-pub use self::resolver::Resolve;
+pub use self::resolver::{Resolve, ResolveBehavior, ResolveVersion};
 pub use self::shell::{Shell, Verbosity};
-pub use self::source::{Source, SourceId, SourceMap, GitReference};
-pub use self::summary::Summary;
+pub use self::source::{Source, SourceId, SourceMap, GitReference, MaybePackage};
+pub use self::summary::{Summary, FeatureValue};
 pub use self::workspace::{Members, Workspace, WorkspaceConfig, WorkspaceRootConfig};
+pub use self::workspace::find_workspace_root;
 
 pub mod source;
 pub mod package;
@@ -1,19 +1,25 @@
 use std::cell::{Ref, RefCell};
-use std::collections::{HashMap, BTreeMap};
+use std::collections::{HashMap, HashSet, BTreeMap};
 use std::fmt;
 use std::hash;
+use std::mem;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use curl::easy::{Easy, HttpVersion};
+use curl::multi::{EasyHandle, Multi};
 use semver::Version;
 use serde::ser;
 use toml;
 use lazycell::LazyCell;
 
 use core::{Dependency, Manifest, PackageId, SourceId, Target};
-use core::{Summary, SourceMap};
+use core::{Summary, SourceMap, MaybePackage};
 use ops;
-use util::{Config, internal, lev_distance};
-use util::errors::{CargoResult, CargoResultExt};
+use util::{Config, Progress, internal, lev_distance};
+use util::errors::{CargoError, CargoResult, CargoResultExt, HttpNot200};
+use util::network::Retry;
 
 /// Information about a package that is available somewhere in the file system.
 ///
@@ -179,16 +185,19 @@ impl hash::Hash for Package {
 pub struct PackageSet<'cfg> {
     packages: HashMap<PackageId, LazyCell<Package>>,
     sources: RefCell<SourceMap<'cfg>>,
+    config: &'cfg Config,
 }
 
 impl<'cfg> PackageSet<'cfg> {
     pub fn new(package_ids: &[PackageId],
-               sources: SourceMap<'cfg>) -> PackageSet<'cfg> {
+               sources: SourceMap<'cfg>,
+               config: &'cfg Config) -> PackageSet<'cfg> {
         PackageSet {
             packages: package_ids.iter().map(|id| {
                 (id.clone(), LazyCell::new())
             }).collect(),
             sources: RefCell::new(sources),
+            config,
         }
     }
 
@@ -197,24 +206,239 @@ impl<'cfg> PackageSet<'cfg> {
     }
 
     pub fn get(&self, id: &PackageId) -> CargoResult<&Package> {
-        let slot = self.packages.get(id).ok_or_else(|| {
+        Ok(self.get_many(Some(id))?.remove(0))
+    }
+
+    /// Returns all of the packages in `ids`, downloading the ones which
+    /// aren't available yet.
+    ///
+    /// Downloads from sources which support it are all performed at the same
+    /// time, so fetching a large number of crates doesn't cost a network
+    /// round trip for each one of them.
+    pub fn get_many<'a, I>(&self, ids: I) -> CargoResult<Vec<&Package>>
+        where I: IntoIterator<Item = &'a PackageId>
+    {
+        let ids = ids.into_iter().collect::<Vec<_>>();
+        let mut downloads = None;
+        let mut started = HashSet::new();
+        for id in ids.iter() {
+            let slot = self.slot(id)?;
+            if slot.filled() || !started.insert(*id) {
+                continue
+            }
+            let mut sources = self.sources.borrow_mut();
+            let source = sources.get_mut(id.source_id()).ok_or_else(|| {
+                internal(format!("couldn't find source for `{}`", id))
+            })?;
+            let pkg = source.start_download(id).chain_err(|| {
+                format_err!("unable to get packages from source")
+            })?;
+            match pkg {
+                MaybePackage::Ready(pkg) => assert!(slot.fill(pkg).is_ok()),
+                MaybePackage::Download { url, descriptor } => {
+                    if downloads.is_none() {
+                        downloads = Some(Downloads::new(self.config)?);
+                    }
+                    let downloads = downloads.as_mut().unwrap();
+                    downloads.enqueue((*id).clone(), url, descriptor)?;
+                }
+            }
+        }
+
+        if let Some(mut downloads) = downloads {
+            while downloads.remaining() > 0 {
+                let (id, data) = downloads.wait()?;
+                let mut sources = self.sources.borrow_mut();
+                let source = sources.get_mut(id.source_id()).ok_or_else(|| {
+                    internal(format!("couldn't find source for `{}`", id))
+                })?;
+                let pkg = source.finish_download(&id, data).chain_err(|| {
+                    format_err!("unable to get packages from source")
+                })?;
+                assert!(self.slot(&id)?.fill(pkg).is_ok());
+            }
+        }
+
+        ids.iter().map(|id| {
+            Ok(self.slot(id)?.borrow().unwrap())
+        }).collect()
+    }
+
+    fn slot(&self, id: &PackageId) -> CargoResult<&LazyCell<Package>> {
+        self.packages.get(id).ok_or_else(|| {
             internal(format!("couldn't find `{}` in package set", id))
+        })
+    }
+
+    pub fn sources(&self) -> Ref<SourceMap<'cfg>> {
+        self.sources.borrow()
+    }
+}
+
+/// A set of `.crate` files being downloaded at the same time over one curl
+/// `Multi` handle.
+///
+/// Requests are multiplexed over HTTP/2 connections where both libcurl and
+/// the server support it, which can be turned off with `http.multiplexing`.
+/// Each transfer is retried on spurious failures just like
+/// `network::with_retry` would.
+pub struct Downloads<'cfg> {
+    config: &'cfg Config,
+    multi: Multi,
+    multiplexing: bool,
+    /// Transfers in flight, keyed by the token of their curl handle
+    pending: HashMap<usize, (Download<'cfg>, EasyHandle)>,
+    /// Transfers which have finished but haven't been returned from `wait`
+    finished: Vec<(PackageId, Vec<u8>)>,
+    next_token: usize,
+    total: usize,
+    done: usize,
+    progress: Progress<'cfg>,
+}
+
+struct Download<'cfg> {
+    id: PackageId,
+    url: String,
+    data: Arc<Mutex<Vec<u8>>>,
+    retry: Retry<'cfg>,
+}
+
+impl<'cfg> Downloads<'cfg> {
+    pub fn new(config: &'cfg Config) -> CargoResult<Downloads<'cfg>> {
+        let multiplexing = config.get_bool("http.multiplexing")?
+                                 .map(|b| b.val)
+                                 .unwrap_or(true);
+        let mut multi = Multi::new();
+        multi.pipelining(false, multiplexing).chain_err(|| {
+            "failed to enable multiplexing in curl"
         })?;
-        if let Some(pkg) = slot.borrow() {
-            return Ok(pkg)
+        // Most of our downloads come from the same host, so rather than
+        // opening a connection per crate prefer to share a couple of them.
+        multi.set_max_host_connections(2)?;
+
+        Ok(Downloads {
+            config,
+            multi,
+            multiplexing,
+            pending: HashMap::new(),
+            finished: Vec::new(),
+            next_token: 0,
+            total: 0,
+            done: 0,
+            progress: Progress::new("Downloading", config),
+        })
+    }
+
+    /// Starts downloading `url` on behalf of the package `id`.
+    pub fn enqueue(&mut self, id: PackageId, url: String, descriptor: String)
+                   -> CargoResult<()> {
+        self.config.shell().status("Downloading", descriptor)?;
+
+        let data = Arc::new(Mutex::new(Vec::new()));
+        let mut handle = ops::http_handle(self.config)?;
+        handle.get(true)?;
+        handle.url(&url)?;
+        handle.follow_location(true)?;
+        if self.multiplexing {
+            // Not every build of libcurl supports HTTP/2, in which case we
+            // just carry on with HTTP/1.1.
+            if let Err(e) = handle.http_version(HttpVersion::V2) {
+                debug!("HTTP/2 is not available: {}", e);
+            }
+            handle.pipewait(true)?;
         }
-        let mut sources = self.sources.borrow_mut();
-        let source = sources.get_mut(id.source_id()).ok_or_else(|| {
-            internal(format!("couldn't find source for `{}`", id))
-        })?;
-        let pkg = source.download(id).chain_err(|| {
-            format_err!("unable to get packages from source")
+        let buf = data.clone();
+        handle.write_function(move |bytes| {
+            buf.lock().unwrap().extend_from_slice(bytes);
+            Ok(bytes.len())
         })?;
-        assert!(slot.fill(pkg).is_ok());
-        Ok(slot.borrow().unwrap())
+
+        let token = self.next_token;
+        self.next_token += 1;
+        self.total += 1;
+        let download = Download {
+            id,
+            url,
+            data,
+            retry: Retry::new(self.config)?,
+        };
+        self.add(token, download, handle)
     }
 
-    pub fn sources(&self) -> Ref<SourceMap<'cfg>> {
-        self.sources.borrow()
+    fn add(&mut self, token: usize, download: Download<'cfg>, handle: Easy)
+           -> CargoResult<()> {
+        let mut handle = self.multi.add(handle)?;
+        handle.set_token(token)?;
+        self.pending.insert(token, (download, handle));
+        Ok(())
+    }
+
+    /// Number of downloads which haven't been returned from `wait` yet.
+    pub fn remaining(&self) -> usize {
+        self.pending.len() + self.finished.len()
+    }
+
+    /// Blocks until one of the enqueued downloads finishes, returning the
+    /// package it was for along with the downloaded bytes.
+    pub fn wait(&mut self) -> CargoResult<(PackageId, Vec<u8>)> {
+        loop {
+            if let Some(ret) = self.finished.pop() {
+                return Ok(ret)
+            }
+            if self.pending.is_empty() {
+                return Err(internal("no downloads are in progress"))
+            }
+
+            self.multi.perform().chain_err(|| {
+                "failed to perform http requests"
+            })?;
+
+            let mut results = Vec::new();
+            {
+                let pending = &self.pending;
+                self.multi.messages(|msg| {
+                    let token = msg.token().expect("failed to read token");
+                    let handle = &pending[&token].1;
+                    if let Some(result) = msg.result_for(handle) {
+                        results.push((token, result));
+                    }
+                });
+            }
+
+            for (token, result) in results {
+                let (mut download, handle) = self.pending.remove(&token).unwrap();
+                let handle = self.multi.remove(handle)?;
+                let ret = result.map_err(CargoError::from).and_then(|()| {
+                    let code = handle.response_code()?;
+                    if code != 200 && code != 0 {
+                        let url = handle.effective_url()?.unwrap_or(&download.url);
+                        Err(HttpNot200 { code, url: url.to_string() }.into())
+                    } else {
+                        Ok(())
+                    }
+                }).chain_err(|| {
+                    format!("failed to download from `{}`", download.url)
+                }).map_err(|e| e.into());
+                match download.retry.check(ret)? {
+                    Some(()) => {
+                        let data = mem::replace(&mut *download.data.lock().unwrap(),
+                                                Vec::new());
+                        self.finished.push((download.id, data));
+                        self.done += 1;
+                    }
+                    None => {
+                        download.data.lock().unwrap().clear();
+                        self.add(token, download, handle)?;
+                    }
+                }
+            }
+
+            self.progress.tick(self.done, self.total)?;
+            if self.finished.is_empty() && !self.pending.is_empty() {
+                self.multi.wait(&mut [], Duration::new(1, 0)).chain_err(|| {
+                    "failed to wait on curl `Multi`"
+                })?;
+            }
+        }
     }
 }
@@ -9,7 +9,7 @@ use core::{Package, PackageId, SourceId, Workspace, Dependency};
 use util::{Graph, Config, internal};
 use util::errors::{CargoResult, CargoResultExt, CargoError};
 
-use super::Resolve;
+use super::{Resolve, ResolveVersion};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableResolve {
@@ -49,7 +49,7 @@ impl EncodableResolve {
             for pkg in packages.iter() {
                 let enc_id = EncodablePackageId {
                     name: pkg.name.clone(),
-                    version: pkg.version.clone(),
+                    version: Some(pkg.version.clone()),
                     source: pkg.source.clone(),
                 };
 
@@ -74,17 +74,31 @@ impl EncodableResolve {
             (live_pkgs, all_pkgs)
         };
 
+        // Version 2 lock files leave out the version and source of
+        // dependencies when the name alone is enough to tell which package
+        // is meant.
+        let mut by_name = HashMap::new();
+        for enc_id in all_pkgs.iter() {
+            by_name.entry(&enc_id.name[..]).or_insert_with(Vec::new).push(enc_id);
+        }
         let lookup_id = |enc_id: &EncodablePackageId| -> CargoResult<Option<PackageId>> {
-            match live_pkgs.get(enc_id) {
-                Some(&(ref id, _)) => Ok(Some(id.clone())),
-                None => if all_pkgs.contains(enc_id) {
-                    // Package is found in the lockfile, but it is
-                    // no longer a member of the workspace.
-                    Ok(None)
-                } else {
-                    Err(internal(format!("package `{}` is specified as a dependency, \
-                                          but is missing from the package list", enc_id)))
-                }
+            if let Some(&(ref id, _)) = live_pkgs.get(enc_id) {
+                return Ok(Some(id.clone()))
+            }
+            let candidates = by_name.get(&enc_id.name[..]).map(|ids| {
+                ids.iter().filter(|id| {
+                    (enc_id.version.is_none() || id.version == enc_id.version) &&
+                        (enc_id.source.is_none() || id.source == enc_id.source)
+                }).collect::<Vec<_>>()
+            }).unwrap_or_default();
+            match candidates.len() {
+                // Package is found in the lockfile, but it may no longer be
+                // a member of the workspace.
+                1 => Ok(live_pkgs.get(*candidates[0]).map(|&(ref id, _)| id.clone())),
+                0 => Err(internal(format!("package `{}` is specified as a dependency, \
+                                           but is missing from the package list", enc_id))),
+                _ => Err(internal(format!("package `{}` is specified as a dependency, \
+                                           but is ambiguous in the package list", enc_id))),
             }
         };
 
@@ -166,6 +180,24 @@ impl EncodableResolve {
             metadata.remove(&k);
         }
 
+        // Version 2 lists the checksums along with each package instead.
+        let mut version = ResolveVersion::V1;
+        for &(ref id, pkg) in live_pkgs.values() {
+            if let Some(ref checksum) = pkg.checksum {
+                version = ResolveVersion::V2;
+                checksums.insert(id.clone(), Some(checksum.clone()));
+            }
+        }
+
+        // Otherwise only version 2 leaves the version out of references to
+        // packages. A lock file which doesn't tell is kept as version 1.
+        let mut ids = packages.iter().flat_map(|pkg| {
+            pkg.dependencies.iter().flat_map(|deps| deps.iter()).chain(pkg.replace.iter())
+        });
+        if ids.any(|id| id.version.is_none()) {
+            version = ResolveVersion::V2;
+        }
+
         let mut unused_patches = Vec::new();
         for pkg in self.patch.unused {
             let id = match pkg.source.as_ref().or_else(|| path_deps.get(&pkg.name)) {
@@ -183,6 +215,7 @@ impl EncodableResolve {
             checksums,
             metadata,
             unused_patches,
+            version,
         })
     }
 }
@@ -260,20 +293,29 @@ pub struct EncodableDependency {
     name: String,
     version: String,
     source: Option<SourceId>,
+    /// Only used by version 2 lock files
+    checksum: Option<String>,
     dependencies: Option<Vec<EncodablePackageId>>,
     replace: Option<EncodablePackageId>,
 }
 
+/// A reference to a package in the lock file, as `name version (source)`.
+///
+/// The version and source are only left out by version 2 lock files, and
+/// only when they're not needed to tell packages apart.
 #[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Clone)]
 pub struct EncodablePackageId {
     name: String,
-    version: String,
+    version: Option<String>,
     source: Option<SourceId>
 }
 
 impl fmt::Display for EncodablePackageId {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} {}", self.name, self.version)?;
+        write!(f, "{}", self.name)?;
+        if let Some(ref v) = self.version {
+            write!(f, " {}", v)?;
+        }
         if let Some(ref s) = self.source {
             write!(f, " ({})", s.to_url())?;
         }
@@ -287,9 +329,7 @@ impl FromStr for EncodablePackageId {
     fn from_str(s: &str) -> CargoResult<EncodablePackageId> {
         let mut s = s.splitn(3, ' ');
         let name = s.next().unwrap();
-        let version = s.next().ok_or_else(|| {
-            internal("invalid serialized PackageId")
-        })?;
+        let version = s.next();
         let source_id = match s.next() {
             Some(s) => {
                 if s.starts_with('(') && s.ends_with(')') {
@@ -303,7 +343,7 @@ impl FromStr for EncodablePackageId {
 
         Ok(EncodablePackageId {
             name: name.to_string(),
-            version: version.to_string(),
+            version: version.map(|v| v.to_string()),
             source: source_id
         })
     }
@@ -340,20 +380,24 @@ impl<'a, 'cfg> ser::Serialize for WorkspaceResolve<'a, 'cfg> {
         let mut ids: Vec<&PackageId> = self.resolve.graph.iter().collect();
         ids.sort();
 
+        let state = EncodeState::new(self.resolve);
+
         let encodable = ids.iter().filter_map(|&id| {
-            Some(encodable_resolve_node(id, self.resolve))
+            Some(encodable_resolve_node(id, self.resolve, &state))
         }).collect::<Vec<_>>();
 
         let mut metadata = self.resolve.metadata.clone();
 
-        for id in ids.iter().filter(|id| !id.source_id().is_path()) {
-            let checksum = match self.resolve.checksums[*id] {
-                Some(ref s) => &s[..],
-                None => "<none>",
-            };
-            let id = encodable_package_id(id);
-            metadata.insert(format!("checksum {}", id.to_string()),
-                            checksum.to_string());
+        if self.resolve.version == ResolveVersion::V1 {
+            for id in ids.iter().filter(|id| !id.source_id().is_path()) {
+                let checksum = match self.resolve.checksums[*id] {
+                    Some(ref s) => &s[..],
+                    None => "<none>",
+                };
+                let id = encodable_package_id(id, &state);
+                metadata.insert(format!("checksum {}", id.to_string()),
+                                checksum.to_string());
+            }
         }
 
         let metadata = if metadata.is_empty() { None } else { Some(metadata) };
@@ -364,6 +408,7 @@ impl<'a, 'cfg> ser::Serialize for WorkspaceResolve<'a, 'cfg> {
                     name: id.name().to_string(),
                     version: id.version().to_string(),
                     source: encode_source(id.source_id()),
+                    checksum: None,
                     dependencies: None,
                     replace: None,
                 }
@@ -378,36 +423,75 @@ impl<'a, 'cfg> ser::Serialize for WorkspaceResolve<'a, 'cfg> {
     }
 }
 
-fn encodable_resolve_node(id: &PackageId, resolve: &Resolve)
+/// What's needed to write the shorter package references of version 2 lock
+/// files, `None` for version 1.
+struct EncodeState<'a> {
+    counts: Option<HashMap<&'a str, HashMap<String, usize>>>,
+}
+
+impl<'a> EncodeState<'a> {
+    fn new(resolve: &'a Resolve) -> EncodeState<'a> {
+        let counts = if resolve.version == ResolveVersion::V2 {
+            let mut counts = HashMap::new();
+            for id in resolve.graph.iter() {
+                let versions = counts.entry(id.name()).or_insert_with(HashMap::new);
+                *versions.entry(id.version().to_string()).or_insert(0) += 1;
+            }
+            Some(counts)
+        } else {
+            None
+        };
+        EncodeState { counts }
+    }
+}
+
+fn encodable_resolve_node(id: &PackageId, resolve: &Resolve, state: &EncodeState)
                           -> EncodableDependency {
     let (replace, deps) = match resolve.replacement(id) {
         Some(id) => {
-            (Some(encodable_package_id(id)), None)
+            (Some(encodable_package_id(id, state)), None)
         }
         None => {
             let mut deps = resolve.graph.edges(id)
                                   .into_iter().flat_map(|a| a)
-                                  .map(encodable_package_id)
+                                  .map(|id| encodable_package_id(id, state))
                                   .collect::<Vec<_>>();
             deps.sort();
             (None, Some(deps))
         }
     };
 
+    let checksum = match resolve.version {
+        ResolveVersion::V1 => None,
+        ResolveVersion::V2 => resolve.checksums.get(id).and_then(|c| c.clone()),
+    };
+
     EncodableDependency {
         name: id.name().to_string(),
         version: id.version().to_string(),
         source: encode_source(id.source_id()),
+        checksum,
         dependencies: deps,
         replace,
     }
 }
 
-fn encodable_package_id(id: &PackageId) -> EncodablePackageId {
+fn encodable_package_id(id: &PackageId, state: &EncodeState) -> EncodablePackageId {
+    let mut version = Some(id.version().to_string());
+    let mut source = encode_source(id.source_id()).map(|s| s.with_precise(None));
+    if let Some(ref counts) = state.counts {
+        let versions = &counts[id.name()];
+        if versions.values().sum::<usize>() == 1 {
+            version = None;
+            source = None;
+        } else if versions[&id.version().to_string()] == 1 {
+            source = None;
+        }
+    }
     EncodablePackageId {
         name: id.name().to_string(),
-        version: id.version().to_string(),
-        source: encode_source(id.source_id()).map(|s| s.with_precise(None)),
+        version,
+        source,
     }
 }
 
@@ -53,16 +53,17 @@ use std::fmt;
 use std::iter::FromIterator;
 use std::ops::Range;
 use std::rc::Rc;
+use std::str::FromStr;
 use std::time::{Instant, Duration};
 
 use semver;
 use url::Url;
 
-use core::{PackageId, Registry, SourceId, Summary, Dependency};
+use core::{PackageId, Registry, SourceId, Summary, Dependency, FeatureValue};
 use core::PackageIdSpec;
 use core::interning::InternedString;
 use util::config::Config;
-use util::Graph;
+use util::{Graph, RustVersion};
 use util::errors::{CargoResult, CargoError};
 use util::profile;
 use util::graph::{Nodes, Edges};
@@ -86,6 +87,50 @@ pub struct Resolve {
     checksums: HashMap<PackageId, Option<String>>,
     metadata: Metadata,
     unused_patches: Vec<PackageId>,
+    version: ResolveVersion,
+}
+
+/// The format of the lock file a `Resolve` is written as.
+///
+/// The version of an existing lock file is detected when reading it, and
+/// kept when writing it back out, so that a new Cargo doesn't rewrite the
+/// whole file in another format. New lock files use version 1, which older
+/// Cargo can read, unless version 2 is asked for with `cargo update
+/// --lockfile-version 2`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ResolveVersion {
+    /// The checksums of all packages are listed together in `[metadata]`,
+    /// and dependencies always name the version and source of a package.
+    V1,
+    /// Each package lists its own checksum, and dependencies only name what
+    /// is needed to tell packages apart.
+    V2,
+}
+
+impl Default for ResolveVersion {
+    fn default() -> ResolveVersion {
+        ResolveVersion::V1
+    }
+}
+
+impl fmt::Display for ResolveVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ResolveVersion::V1 => f.write_str("1"),
+            ResolveVersion::V2 => f.write_str("2"),
+        }
+    }
+}
+
+impl FromStr for ResolveVersion {
+    type Err = ();
+    fn from_str(s: &str) -> Result<ResolveVersion, ()> {
+        match s {
+            "1" => Ok(ResolveVersion::V1),
+            "2" => Ok(ResolveVersion::V2),
+            _ => Err(()),
+        }
+    }
 }
 
 pub struct Deps<'a> {
@@ -97,6 +142,29 @@ pub struct DepsNotReplaced<'a> {
     edges: Option<Edges<'a, PackageId>>,
 }
 
+/// How the features of a package are resolved, selected with the `resolver`
+/// key of the workspace root's manifest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResolveBehavior {
+    /// Features are unified across every use of a package.
+    V1,
+    /// Features are resolved separately for packages built for the host and
+    /// for the target, leaving out dev-dependencies unless they're built and
+    /// dependencies for other platforms.
+    V2,
+}
+
+impl FromStr for ResolveBehavior {
+    type Err = ();
+    fn from_str(s: &str) -> Result<ResolveBehavior, ()> {
+        match s {
+            "1" => Ok(ResolveBehavior::V1),
+            "2" => Ok(ResolveBehavior::V2),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum Method<'a> {
     Everything, // equivalent to Required { dev_deps: true, all_features: true, .. }
@@ -223,6 +291,8 @@ unable to verify that `{0}` is the same as when the lockfile was generated
 
         // Be sure to just copy over any unknown metadata.
         self.metadata = previous.metadata.clone();
+        // The lock file keeps its format unless asked to migrate.
+        self.version = previous.version;
         Ok(())
     }
 
@@ -246,6 +316,12 @@ unable to verify that `{0}` is the same as when the lockfile was generated
         &self.replacements
     }
 
+    /// The features enabled for `pkg`, which are passed to the compiler as
+    /// `feature` cfgs.
+    ///
+    /// Besides the features listed in `[features]` this includes the
+    /// optional dependencies which were enabled through their implicit
+    /// feature, but not those only enabled with `dep:name`.
     pub fn features(&self, pkg: &PackageId) -> &HashSet<String> {
         self.features.get(pkg).unwrap_or(&self.empty_features)
     }
@@ -256,6 +332,10 @@ unable to verify that `{0}` is the same as when the lockfile was generated
         v
     }
 
+    pub fn checksums(&self) -> &HashMap<PackageId, Option<String>> {
+        &self.checksums
+    }
+
     pub fn query(&self, spec: &str) -> CargoResult<&PackageId> {
         PackageIdSpec::query_str(spec, self.iter())
     }
@@ -263,6 +343,15 @@ unable to verify that `{0}` is the same as when the lockfile was generated
     pub fn unused_patches(&self) -> &[PackageId] {
         &self.unused_patches
     }
+
+    /// The format of the lock file this was read from, or will be written as.
+    pub fn version(&self) -> ResolveVersion {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: ResolveVersion) {
+        self.version = version;
+    }
 }
 
 impl fmt::Debug for Resolve {
@@ -360,9 +449,13 @@ struct Context {
 type Activations = HashMap<InternedString, HashMap<SourceId, Rc<Vec<Summary>>>>;
 
 /// Builds the list of all packages required to build the first argument.
+///
+/// If `rust_version` is given, candidates whose own `rust-version` is
+/// compatible with it are tried before newer ones which aren't.
 pub fn resolve(summaries: &[(Summary, Method)],
                replacements: &[(PackageIdSpec, Dependency)],
                registry: &mut Registry,
+               rust_version: Option<&RustVersion>,
                config: Option<&Config>,
                print_warnings: bool) -> CargoResult<Resolve> {
     let cx = Context {
@@ -374,7 +467,8 @@ pub fn resolve(summaries: &[(Summary, Method)],
         warnings: RcList::new(),
     };
     let _p = profile::start("resolving");
-    let cx = activate_deps_loop(cx, &mut RegistryQueryer::new(registry, replacements), summaries, config)?;
+    let mut registry = RegistryQueryer::new(registry, replacements, rust_version);
+    let cx = activate_deps_loop(cx, &mut registry, summaries, config)?;
 
     let mut resolve = Resolve {
         graph: cx.graph(),
@@ -386,6 +480,7 @@ pub fn resolve(summaries: &[(Summary, Method)],
             (k.clone(), v.iter().map(|x| x.to_string()).collect())
         }).collect(),
         unused_patches: Vec::new(),
+        version: ResolveVersion::default(),
     };
 
     for summary in cx.activations.values()
@@ -586,15 +681,19 @@ impl ConflictReason {
 struct RegistryQueryer<'a> {
     registry: &'a mut (Registry + 'a),
     replacements: &'a [(PackageIdSpec, Dependency)],
+    rust_version: Option<&'a RustVersion>,
     // TODO: with nll the Rc can be removed
     cache: HashMap<Dependency, Rc<Vec<Candidate>>>,
 }
 
 impl<'a> RegistryQueryer<'a> {
-    fn new(registry: &'a mut Registry, replacements: &'a [(PackageIdSpec, Dependency)],) -> Self {
+    fn new(registry: &'a mut Registry,
+           replacements: &'a [(PackageIdSpec, Dependency)],
+           rust_version: Option<&'a RustVersion>) -> Self {
         RegistryQueryer {
             registry,
             replacements,
+            rust_version,
             cache: HashMap::new(),
         }
     }
@@ -672,9 +771,20 @@ impl<'a> RegistryQueryer<'a> {
         }
 
         // When we attempt versions for a package, we'll want to start at
-        // the maximum version and work our way down.
+        // the maximum version and work our way down. Versions which can't be
+        // built with the requested `rust_version` are only tried after all of
+        // those which can.
+        let rust_version = self.rust_version;
+        let compatible = |c: &Candidate| {
+            match (c.summary.rust_version(), rust_version) {
+                (Some(required), Some(max)) => required.is_compatible_with(max),
+                _ => true,
+            }
+        };
         ret.sort_unstable_by(|a, b| {
-            b.summary.version().cmp(a.summary.version())
+            compatible(b).cmp(&compatible(a)).then_with(|| {
+                b.summary.version().cmp(a.summary.version())
+            })
         });
 
         let out = Rc::new(ret);
@@ -1151,10 +1261,10 @@ fn activation_error(cx: &Context,
     };
 
     if let Some(config) = config {
-        if config.cli_unstable().offline {
-            msg.push_str("\nAs a reminder, you're using offline mode (-Z offline) \
+        if config.offline() {
+            msg.push_str("\nAs a reminder, you're using offline mode (--offline) \
             which can sometimes cause surprising resolution failures, \
-            if this error is too confusing you may with to retry \
+            if this error is too confusing you may wish to retry \
             without the offline flag.");
         }
     }
@@ -1188,6 +1298,12 @@ struct Requirements<'a> {
     // features were enabled.
     used: HashSet<&'a str>,
     visited: HashSet<&'a str>,
+    // Features of dependencies which are only enabled if the dependency is
+    // enabled through something else, from `dep?/feat`.
+    weak: Vec<(&'a str, &'a str)>,
+    // Requested features which are actually optional dependencies that can
+    // only be enabled with `dep:name`.
+    unknown: Vec<&'a str>,
 }
 
 impl<'r> Requirements<'r> {
@@ -1197,11 +1313,16 @@ impl<'r> Requirements<'r> {
             deps: HashMap::new(),
             used: HashSet::new(),
             visited: HashSet::new(),
+            weak: Vec::new(),
+            unknown: Vec::new(),
         }
     }
 
     fn require_crate_feature(&mut self, package: &'r str, feat: &'r str) {
-        self.used.insert(package);
+        // Dependencies enabled with `dep:` don't have an implicit feature
+        if !self.summary.is_namespaced_dep(package) {
+            self.used.insert(package);
+        }
         self.deps.entry(package)
             .or_insert((false, Vec::new()))
             .1.push(feat.to_string());
@@ -1220,6 +1341,10 @@ impl<'r> Requirements<'r> {
         if self.seen(pkg) {
             return;
         }
+        self.enable_dependency(pkg);
+    }
+
+    fn enable_dependency(&mut self, pkg: &'r str) {
         self.deps.entry(pkg).or_insert((false, Vec::new())).0 = true;
     }
 
@@ -1240,19 +1365,27 @@ impl<'r> Requirements<'r> {
         if feat.is_empty() { return Ok(()) }
 
         // If this feature is of the form `foo/bar`, then we just lookup package
-        // `foo` and enable its feature `bar`. Otherwise this feature is of the
+        // `foo` and enable its feature `bar`, which for `foo?/bar` is deferred
+        // until we know whether `foo` is enabled. `dep:foo` enables the
+        // dependency `foo` but no feature. Otherwise this feature is of the
         // form `foo` and we need to recurse to enable the feature `foo` for our
         // own package, which may end up enabling more features or just enabling
         // a dependency.
-        let mut parts = feat.splitn(2, '/');
-        let feat_or_package = parts.next().unwrap();
-        match parts.next() {
-            Some(feat) => {
-                self.require_crate_feature(feat_or_package, feat);
+        match FeatureValue::new(feat) {
+            FeatureValue::DepFeature { dep, feature, weak: false } => {
+                self.require_crate_feature(dep, feature);
+            }
+            FeatureValue::DepFeature { dep, feature, weak: true } => {
+                self.weak.push((dep, feature));
             }
-            None => {
+            FeatureValue::Dep(dep) => {
+                self.enable_dependency(dep);
+            }
+            FeatureValue::Feature(feat_or_package) => {
                 if self.summary.features().contains_key(feat_or_package) {
                     self.require_feature(feat_or_package)?;
+                } else if self.summary.is_namespaced_dep(feat_or_package) {
+                    self.unknown.push(feat_or_package);
                 } else {
                     self.require_dependency(feat_or_package);
                 }
@@ -1262,6 +1395,15 @@ impl<'r> Requirements<'r> {
     }
 }
 
+fn has_weak_features(s: &Summary) -> bool {
+    s.features().values().flat_map(|list| list.iter()).any(|f| {
+        match FeatureValue::new(f) {
+            FeatureValue::DepFeature { weak, .. } => weak,
+            _ => false,
+        }
+    })
+}
+
 /// Takes requested features for a single package from the input Method and
 /// recurses to find all requested features, dependencies and requested
 /// dependency features in a Requirements object, returning it to the resolver.
@@ -1275,7 +1417,11 @@ fn build_requirements<'a, 'b: 'a>(s: &'a Summary, method: &'b Method)
                 reqs.require_feature(key)?;
             }
             for dep in s.dependencies().iter().filter(|d| d.is_optional()) {
-                reqs.require_dependency(dep.name());
+                if s.is_namespaced_dep(dep.name()) {
+                    reqs.enable_dependency(dep.name());
+                } else {
+                    reqs.require_dependency(dep.name());
+                }
             }
         }
         Method::Required { features: requested_features, .. } =>  {
@@ -1399,6 +1545,23 @@ impl Context {
         let mut reqs = build_requirements(s, method)?;
         let mut ret = Vec::new();
 
+        // Whether a weak dependency feature applies depends on all features
+        // of this package, including those enabled by earlier activations.
+        if has_weak_features(s) {
+            if let Some(prev) = self.resolve_features.get(s.package_id()) {
+                for feat in prev.iter() {
+                    let feat = s.features().keys().map(|k| &k[..]).chain({
+                        s.dependencies().iter()
+                                        .filter(|d| d.is_optional())
+                                        .map(|d| d.name())
+                    }).find(|k| *k == &**feat);
+                    if let Some(feat) = feat {
+                        reqs.add_feature(feat)?;
+                    }
+                }
+            }
+        }
+
         // Next, collect all actually enabled dependencies and their features.
         for dep in deps {
             // Skip optional dependencies, but not those enabled through a feature
@@ -1419,6 +1582,9 @@ impl Context {
             }
             let mut base = base.1;
             base.extend(dep.features().iter().cloned());
+            base.extend(reqs.weak.iter()
+                            .filter(|&&(name, _)| name == dep.name())
+                            .map(|&(_, feat)| feat.to_string()));
             for feature in base.iter() {
                 if feature.contains('/') {
                     return Err(format_err!("feature names may not contain slashes: `{}`", feature).into());
@@ -1430,9 +1596,10 @@ impl Context {
         // Any remaining entries in feature_deps are bugs in that the package does not actually
         // have those dependencies.  We classified them as dependencies in the first place
         // because there is no such feature, either.
-        if !reqs.deps.is_empty() {
+        if !reqs.deps.is_empty() || !reqs.unknown.is_empty() {
             let unknown = reqs.deps.keys()
                                    .map(|s| &s[..])
+                                   .chain(reqs.unknown.iter().cloned())
                                    .collect::<Vec<&str>>();
             let features = unknown.join(", ");
             return Err(match parent {
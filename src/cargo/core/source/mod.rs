@@ -1,7 +1,7 @@
 use std::collections::hash_map::{HashMap, Values, IterMut};
 
 use core::{Package, PackageId, Registry};
-use util::CargoResult;
+use util::{CargoResult, internal};
 
 mod source_id;
 
@@ -22,6 +22,25 @@ pub trait Source: Registry {
     /// version specified.
     fn download(&mut self, package: &PackageId) -> CargoResult<Package>;
 
+    /// Begins fetching the package specified, for sources which are able to
+    /// download several packages at the same time.
+    ///
+    /// If the package is available without any network transfer it is
+    /// returned straight away. Otherwise the transfer described by
+    /// `MaybePackage::Download` should be performed by the caller, and the
+    /// bytes it produces handed back to `finish_download`.
+    fn start_download(&mut self, package: &PackageId) -> CargoResult<MaybePackage> {
+        self.download(package).map(MaybePackage::Ready)
+    }
+
+    /// Completes a download started with `start_download`, given the contents
+    /// of the URL it asked for.
+    fn finish_download(&mut self, package: &PackageId, _data: Vec<u8>)
+                       -> CargoResult<Package> {
+        Err(internal(format!("source does not support downloading `{}`",
+                             package)))
+    }
+
     /// Generates a unique string which represents the fingerprint of the
     /// current state of the source.
     ///
@@ -61,6 +80,17 @@ impl<'a, T: Source + ?Sized + 'a> Source for Box<T> {
         (**self).download(id)
     }
 
+    /// Forwards to `Source::start_download`
+    fn start_download(&mut self, id: &PackageId) -> CargoResult<MaybePackage> {
+        (**self).start_download(id)
+    }
+
+    /// Forwards to `Source::finish_download`
+    fn finish_download(&mut self, id: &PackageId, data: Vec<u8>)
+                       -> CargoResult<Package> {
+        (**self).finish_download(id, data)
+    }
+
     /// Forwards to `Source::fingerprint`
     fn fingerprint(&self, pkg: &Package) -> CargoResult<String> {
         (**self).fingerprint(pkg)
@@ -72,6 +102,18 @@ impl<'a, T: Source + ?Sized + 'a> Source for Box<T> {
     }
 }
 
+/// The result of `Source::start_download`
+pub enum MaybePackage {
+    /// The package was available locally
+    Ready(Package),
+    /// The package needs to be downloaded from `url`
+    Download {
+        url: String,
+        /// What to call the package in status messages
+        descriptor: String,
+    },
+}
+
 /// A `HashMap` of `SourceId` -> `Box<Source>`
 #[derive(Default)]
 pub struct SourceMap<'src> {
@@ -222,6 +222,14 @@ impl SourceId {
         }
     }
 
+    /// Is this source a registry whose index is fetched file by file over
+    /// HTTP rather than cloned with git, as requested by prefixing its URL
+    /// with `sparse+`
+    pub fn is_sparse(&self) -> bool {
+        self.inner.kind == Kind::Registry &&
+            self.inner.url.scheme().starts_with("sparse+")
+    }
+
     /// Is this source from an alternative registry
     pub fn is_alt_registry(&self) -> bool {
         self.is_registry() && self.inner.name.is_some()
@@ -1,11 +1,11 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::mem;
 use std::rc::Rc;
 
 use semver::Version;
 use core::{Dependency, PackageId, SourceId};
 
-use util::CargoResult;
+use util::{CargoResult, RustVersion};
 
 /// Subset of a `Manifest`. Contains only the most important information about
 /// a package.
@@ -23,6 +23,7 @@ struct Inner {
     features: BTreeMap<String, Vec<String>>,
     checksum: Option<String>,
     links: Option<String>,
+    rust_version: Option<RustVersion>,
 }
 
 impl Summary {
@@ -30,8 +31,15 @@ impl Summary {
                dependencies: Vec<Dependency>,
                features: BTreeMap<String, Vec<String>>,
                links: Option<String>) -> CargoResult<Summary> {
+        let namespaced = features.values().flat_map(|list| list.iter()).filter_map(|f| {
+            match FeatureValue::new(f) {
+                FeatureValue::Dep(dep) => Some(dep),
+                _ => None,
+            }
+        }).collect::<HashSet<_>>();
         for dep in dependencies.iter() {
-            if features.get(dep.name()).is_some() {
+            if features.get(dep.name()).is_some() &&
+               !(dep.is_optional() && namespaced.contains(dep.name())) {
                 bail!("Features and dependencies cannot have the \
                        same name: `{}`", dep.name())
             }
@@ -41,26 +49,56 @@ impl Summary {
             }
         }
         for (feature, list) in features.iter() {
-            for dep in list.iter() {
-                let mut parts = dep.splitn(2, '/');
-                let dep = parts.next().unwrap();
-                let is_reexport = parts.next().is_some();
-                if !is_reexport && features.get(dep).is_some() { continue }
-                match dependencies.iter().find(|d| d.name() == dep) {
-                    Some(d) => {
-                        if d.is_optional() || is_reexport { continue }
-                        bail!("Feature `{}` depends on `{}` which is not an \
-                               optional dependency.\nConsider adding \
-                               `optional = true` to the dependency",
-                               feature, dep)
+            for value in list.iter() {
+                let find = |name: &str| dependencies.iter().find(|d| d.name() == name);
+                match FeatureValue::new(value) {
+                    FeatureValue::Feature(dep) => {
+                        if features.get(dep).is_some() { continue }
+                        match find(dep) {
+                            Some(d) if namespaced.contains(dep) => {
+                                bail!("Feature `{}` includes `{}` which is neither a \
+                                       dependency nor another feature.\nThe optional \
+                                       dependency `{}` is enabled with `dep:{}`",
+                                       feature, dep, d.name(), d.name())
+                            }
+                            Some(d) => {
+                                if d.is_optional() { continue }
+                                bail!("Feature `{}` depends on `{}` which is not an \
+                                       optional dependency.\nConsider adding \
+                                       `optional = true` to the dependency",
+                                       feature, dep)
+                            }
+                            None => {
+                                bail!("Feature `{}` includes `{}` which is neither \
+                                       a dependency nor another feature", feature, dep)
+                            }
+                        }
                     }
-                    None if is_reexport => {
-                        bail!("Feature `{}` requires a feature of `{}` which is not a \
-                               dependency", feature, dep)
+                    FeatureValue::Dep(dep) => {
+                        match find(dep) {
+                            Some(d) if d.is_optional() => {}
+                            Some(_) => {
+                                bail!("Feature `{}` includes `dep:{}`, but `{}` is not \
+                                       an optional dependency", feature, dep, dep)
+                            }
+                            None => {
+                                bail!("Feature `{}` includes `dep:{}`, but `{}` is not \
+                                       a dependency", feature, dep, dep)
+                            }
+                        }
                     }
-                    None => {
-                        bail!("Feature `{}` includes `{}` which is neither \
-                               a dependency nor another feature", feature, dep)
+                    FeatureValue::DepFeature { dep, weak, .. } => {
+                        match find(dep) {
+                            Some(d) if weak && !d.is_optional() => {
+                                bail!("Feature `{}` includes `{}` with a `?`, but `{}` \
+                                       is not an optional dependency", feature, value, dep)
+                            }
+                            Some(_) => {}
+                            None => {
+                                bail!("Feature `{}` requires a feature of `{}` which is not a \
+                                       dependency", feature, dep)
+                            }
+                        }
                     }
                 }
             }
@@ -72,6 +110,7 @@ impl Summary {
                 features,
                 checksum: None,
                 links,
+                rust_version: None,
             }),
         })
     }
@@ -82,12 +121,23 @@ impl Summary {
     pub fn source_id(&self) -> &SourceId { self.package_id().source_id() }
     pub fn dependencies(&self) -> &[Dependency] { &self.inner.dependencies }
     pub fn features(&self) -> &BTreeMap<String, Vec<String>> { &self.inner.features }
+
+    /// Whether the optional dependency `name` is enabled with `dep:name`, in
+    /// which case it has no implicit feature of the same name.
+    pub fn is_namespaced_dep(&self, name: &str) -> bool {
+        self.features().values().flat_map(|list| list.iter()).any(|f| {
+            FeatureValue::new(f) == FeatureValue::Dep(name)
+        })
+    }
     pub fn checksum(&self) -> Option<&str> {
         self.inner.checksum.as_ref().map(|s| &s[..])
     }
     pub fn links(&self) -> Option<&str> {
         self.inner.links.as_ref().map(|s| &s[..])
     }
+    pub fn rust_version(&self) -> Option<&RustVersion> {
+        self.inner.rust_version.as_ref()
+    }
 
     pub fn override_id(mut self, id: PackageId) -> Summary {
         Rc::make_mut(&mut self.inner).package_id = id;
@@ -99,6 +149,11 @@ impl Summary {
         self
     }
 
+    pub fn set_rust_version(mut self, rust_version: Option<RustVersion>) -> Summary {
+        Rc::make_mut(&mut self.inner).rust_version = rust_version;
+        self
+    }
+
     pub fn map_dependencies<F>(mut self, f: F) -> Summary
         where F: FnMut(Dependency) -> Dependency {
         {
@@ -123,6 +178,39 @@ impl Summary {
     }
 }
 
+/// One of the entries in the list of a feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureValue<'a> {
+    /// `name`, either another feature or an optional dependency which is
+    /// enabled along with the implicit feature of the same name.
+    Feature(&'a str),
+    /// `dep:name`, enables an optional dependency without enabling a feature
+    /// of the same name.
+    Dep(&'a str),
+    /// `dep/feature` enables a feature of a dependency, along with the
+    /// dependency itself if it's optional. The weak `dep?/feature` instead
+    /// only enables the feature if the dependency is enabled by something
+    /// else.
+    DepFeature { dep: &'a str, feature: &'a str, weak: bool },
+}
+
+impl<'a> FeatureValue<'a> {
+    pub fn new(value: &'a str) -> FeatureValue<'a> {
+        if value.starts_with("dep:") {
+            return FeatureValue::Dep(&value[4..])
+        }
+        let mut parts = value.splitn(2, '/');
+        let dep = parts.next().unwrap();
+        match parts.next() {
+            Some(feature) if dep.ends_with('?') => {
+                FeatureValue::DepFeature { dep: &dep[..dep.len() - 1], feature, weak: true }
+            }
+            Some(feature) => FeatureValue::DepFeature { dep, feature, weak: false },
+            None => FeatureValue::Feature(dep),
+        }
+    }
+}
+
 impl PartialEq for Summary {
     fn eq(&self, other: &Summary) -> bool {
         self.inner.package_id == other.inner.package_id
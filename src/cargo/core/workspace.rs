@@ -7,11 +7,11 @@ use glob::glob;
 use url::Url;
 
 use core::{Package, VirtualManifest, EitherManifest, SourceId};
-use core::{PackageIdSpec, Dependency, Profile, Profiles};
+use core::{PackageIdSpec, Dependency, Profile, Profiles, ResolveBehavior};
 use util::{Config, Filesystem};
 use util::errors::{CargoResult, CargoResultExt};
 use util::paths;
-use util::toml::read_manifest;
+use util::toml::{read_manifest, InheritableFields};
 
 /// The core abstraction in Cargo for working with a workspace of crates.
 ///
@@ -105,6 +105,7 @@ pub struct WorkspaceRootConfig {
     members: Option<Vec<String>>,
     default_members: Option<Vec<String>>,
     exclude: Vec<String>,
+    inheritable: InheritableFields,
 }
 
 /// An iterator over the member packages of a workspace, returned by
@@ -228,6 +229,17 @@ impl<'cfg> Workspace<'cfg> {
         }
     }
 
+    /// Returns how features are resolved in this workspace, as set by the
+    /// `resolver` key of the root manifest.
+    pub fn resolve_behavior(&self) -> ResolveBehavior {
+        let root = self.root_manifest.as_ref().unwrap_or(&self.current_manifest);
+        let behavior = match *self.packages.get(root) {
+            MaybePackage::Package(ref p) => p.manifest().resolve_behavior(),
+            MaybePackage::Virtual(ref vm) => vm.resolve_behavior(),
+        };
+        behavior.unwrap_or(ResolveBehavior::V1)
+    }
+
     /// Returns the root path of this workspace.
     ///
     /// That is, this returns the path of the directory containing the
@@ -313,14 +325,6 @@ impl<'cfg> Workspace<'cfg> {
     /// if some other transient error happens.
     fn find_root(&mut self, manifest_path: &Path)
                  -> CargoResult<Option<PathBuf>> {
-        fn read_root_pointer(member_manifest: &Path, root_link: &str) -> CargoResult<PathBuf> {
-            let path = member_manifest.parent().unwrap()
-                .join(root_link)
-                .join("Cargo.toml");
-            debug!("find_root - pointer {}", path.display());
-            Ok(paths::normalize_path(&path))
-        };
-
         {
             let current = self.packages.load(manifest_path)?;
             match *current.workspace_config() {
@@ -329,43 +333,17 @@ impl<'cfg> Workspace<'cfg> {
                     return Ok(Some(manifest_path.to_path_buf()))
                 }
                 WorkspaceConfig::Member { root: Some(ref path_to_root) } => {
-                    return Ok(Some(read_root_pointer(manifest_path, path_to_root)?))
+                    return Ok(Some(read_root_pointer(manifest_path, path_to_root)))
                 }
                 WorkspaceConfig::Member { root: None } => {}
             }
         }
 
-        for path in paths::ancestors(manifest_path).skip(2) {
-            let ances_manifest_path = path.join("Cargo.toml");
-            debug!("find_root - trying {}", ances_manifest_path.display());
-            if ances_manifest_path.exists() {
-                match *self.packages.load(&ances_manifest_path)?.workspace_config() {
-                    WorkspaceConfig::Root(ref ances_root_config) => {
-                        debug!("find_root - found a root checking exclusion");
-                        if !ances_root_config.is_excluded(manifest_path) {
-                            debug!("find_root - found!");
-                            return Ok(Some(ances_manifest_path))
-                        }
-                    }
-                    WorkspaceConfig::Member { root: Some(ref path_to_root) } => {
-                        debug!("find_root - found pointer");
-                        return Ok(Some(read_root_pointer(&ances_manifest_path, path_to_root)?))
-                    }
-                    WorkspaceConfig::Member { .. } => {}
-                }
-            }
-
-            // Don't walk across `CARGO_HOME` when we're looking for the
-            // workspace root. Sometimes a project will be organized with
-            // `CARGO_HOME` pointing inside of the workspace root or in the
-            // current project, but we don't want to mistakenly try to put
-            // crates.io crates into the workspace by accident.
-            if self.config.home() == path {
-                break
-            }
-        }
-
-        Ok(None)
+        let config = self.config;
+        let packages = &mut self.packages;
+        find_root_in_ancestors(manifest_path, config, |path| {
+            Ok(packages.load(path)?.workspace_config().clone())
+        })
     }
 
     /// After the root of a workspace has been located, probes for all members
@@ -608,6 +586,9 @@ impl<'cfg> Workspace<'cfg> {
                 check: Profile::default_check(),
                 check_test: Profile::default_check_test(),
                 doctest: Profile::default_doctest(),
+                overrides: Vec::new(),
+                build_override: None,
+                custom: BTreeMap::new(),
             };
 
             for pkg in self.members().filter(|p| p.manifest_path() != root_manifest) {
@@ -629,6 +610,71 @@ impl<'cfg> Workspace<'cfg> {
     }
 }
 
+/// Finds the root manifest of the workspace that the member at
+/// `manifest_path` belongs to, without loading the workspace itself.
+///
+/// This is used while `manifest_path` is still being parsed, so it only looks
+/// at the manifests of its ancestor directories, and any `package.workspace`
+/// pointer of the member must be followed by the caller.
+pub fn find_workspace_root(manifest_path: &Path, config: &Config)
+                           -> CargoResult<Option<PathBuf>> {
+    find_root_in_ancestors(manifest_path, config, |path| {
+        let source_id = SourceId::for_path(path.parent().unwrap())?;
+        let (manifest, _nested_paths) = read_manifest(path, &source_id, config)?;
+        Ok(match manifest {
+            EitherManifest::Real(ref m) => m.workspace_config().clone(),
+            EitherManifest::Virtual(ref vm) => vm.workspace_config().clone(),
+        })
+    })
+}
+
+fn read_root_pointer(member_manifest: &Path, root_link: &str) -> PathBuf {
+    let path = member_manifest.parent().unwrap()
+        .join(root_link)
+        .join("Cargo.toml");
+    debug!("find_root - pointer {}", path.display());
+    paths::normalize_path(&path)
+}
+
+/// Walks up from `manifest_path` looking for the manifest of a workspace root
+/// which doesn't exclude it, or of a member pointing at one. `load` reads the
+/// workspace configuration of a manifest.
+fn find_root_in_ancestors<F>(manifest_path: &Path, config: &Config, mut load: F)
+                             -> CargoResult<Option<PathBuf>>
+    where F: FnMut(&Path) -> CargoResult<WorkspaceConfig>
+{
+    for path in paths::ancestors(manifest_path).skip(2) {
+        let ances_manifest_path = path.join("Cargo.toml");
+        debug!("find_root - trying {}", ances_manifest_path.display());
+        if ances_manifest_path.exists() {
+            match load(&ances_manifest_path)? {
+                WorkspaceConfig::Root(ref ances_root_config) => {
+                    debug!("find_root - found a root checking exclusion");
+                    if !ances_root_config.is_excluded(manifest_path) {
+                        debug!("find_root - found!");
+                        return Ok(Some(ances_manifest_path))
+                    }
+                }
+                WorkspaceConfig::Member { root: Some(ref path_to_root) } => {
+                    debug!("find_root - found pointer");
+                    return Ok(Some(read_root_pointer(&ances_manifest_path, path_to_root)))
+                }
+                WorkspaceConfig::Member { .. } => {}
+            }
+        }
+
+        // Don't walk across `CARGO_HOME` when we're looking for the
+        // workspace root. Sometimes a project will be organized with
+        // `CARGO_HOME` pointing inside of the workspace root or in the
+        // current project, but we don't want to mistakenly try to put
+        // crates.io crates into the workspace by accident.
+        if config.home() == path {
+            break
+        }
+    }
+
+    Ok(None)
+}
 
 impl<'cfg> Packages<'cfg> {
     fn get(&self, manifest_path: &Path) -> &MaybePackage {
@@ -695,15 +741,23 @@ impl WorkspaceRootConfig {
         members: &Option<Vec<String>>,
         default_members: &Option<Vec<String>>,
         exclude: &Option<Vec<String>>,
+        inheritable: InheritableFields,
     ) -> WorkspaceRootConfig {
         WorkspaceRootConfig {
             root_dir: root_dir.to_path_buf(),
             members: members.clone(),
             default_members: default_members.clone(),
             exclude: exclude.clone().unwrap_or_default(),
+            inheritable,
         }
     }
 
+    /// The `[workspace.package]` and `[workspace.dependencies]` values which
+    /// members can inherit.
+    pub fn inheritable(&self) -> &InheritableFields {
+        &self.inheritable
+    }
+
     /// Checks the path against the `excluded` list.
     ///
     /// This method does NOT consider the `members` list.
@@ -0,0 +1,204 @@
+use std::path::PathBuf;
+
+use semver::VersionReq;
+
+use core::{Dependency, Package, Source, SourceId, Workspace};
+use core::dependency::Kind;
+use ops;
+use sources::SourceConfigMap;
+use util::Config;
+use util::errors::{CargoResult, CargoResultExt};
+use util::paths;
+use util::toml;
+use util::toml::edit::{self, DepTable, ManifestEditor};
+
+pub struct AddOptions<'a> {
+    /// The crates to add, each optionally followed by `@<version requirement>`.
+    pub crates: &'a [String],
+    pub kind: Kind,
+    pub target: Option<&'a str>,
+    pub features: &'a [String],
+    pub optional: bool,
+    pub path: Option<&'a str>,
+    pub git: Option<&'a str>,
+    pub registry: Option<&'a str>,
+}
+
+pub struct RemoveOptions<'a> {
+    pub crates: &'a [String],
+    pub kind: Kind,
+    pub target: Option<&'a str>,
+}
+
+/// Adds dependencies to the manifest of the current package.
+///
+/// Registry dependencies without an explicit version requirement are added
+/// with the newest version of the crate which isn't yanked or a prerelease.
+pub fn add(ws: &Workspace, opts: &AddOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let pkg = ws.current()?;
+    if opts.path.is_some() && opts.git.is_some() {
+        bail!("cannot specify both `--path` and `--git`")
+    }
+    if (opts.path.is_some() || opts.git.is_some()) && opts.crates.len() > 1 {
+        bail!("`--path` and `--git` can only be used when adding a single crate")
+    }
+
+    let table = DepTable { kind: opts.kind, target: opts.target };
+    let features = opts.features.iter()
+                                .flat_map(|s| s.split_whitespace())
+                                .collect::<Vec<_>>();
+    let original = paths::read(pkg.manifest_path())?;
+    let mut manifest = ManifestEditor::new(&original);
+    let mut registry = None;
+
+    for spec in opts.crates {
+        let (name, req) = match spec.find('@') {
+            Some(i) => (&spec[..i], Some(&spec[i + 1..])),
+            None => (&spec[..], None),
+        };
+        if let Some(req) = req {
+            if let Err(e) = req.parse::<VersionReq>() {
+                bail!("the version requirement `{}` for `{}` is invalid: {}",
+                      req, name, e)
+            }
+        }
+
+        let mut fields = Vec::new();
+        if let Some(path) = opts.path {
+            let path = local_path(config, pkg, name, path)?;
+            if let Some(req) = req {
+                fields.push(("version", edit::string(req)));
+            }
+            fields.push(("path", edit::string(&path)));
+            config.shell().status("Adding", format!("{} (local) to {}", name, table))?;
+        } else if let Some(git) = opts.git {
+            if let Some(req) = req {
+                fields.push(("version", edit::string(req)));
+            }
+            fields.push(("git", edit::string(git)));
+            config.shell().status("Adding", format!("{} (git) to {}", name, table))?;
+        } else {
+            if registry.is_none() {
+                registry = Some(load_registry(config, opts.registry)?);
+            }
+            let (ref source_id, ref mut source) = *registry.as_mut().unwrap();
+            let version = newest_version(&mut **source, source_id, name, req)?;
+            let req = req.map(|s| s.to_string()).unwrap_or_else(|| version.clone());
+            fields.push(("version", edit::string(&req)));
+            if let Some(registry) = opts.registry {
+                fields.push(("registry", edit::string(registry)));
+            }
+            config.shell().status("Adding", format!("{} v{} to {}", name, version, table))?;
+        }
+        if !features.is_empty() {
+            fields.push(("features", edit::string_array(features.iter().cloned())));
+        }
+        if opts.optional {
+            fields.push(("optional", "true".to_string()));
+        }
+        manifest.insert(&table, name, &fields);
+    }
+
+    write_manifest(pkg, config, &original, &manifest.to_string())
+}
+
+/// Removes dependencies from the manifest of the current package.
+pub fn remove(ws: &Workspace, opts: &RemoveOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let pkg = ws.current()?;
+    let table = DepTable { kind: opts.kind, target: opts.target };
+    let original = paths::read(pkg.manifest_path())?;
+    let mut manifest = ManifestEditor::new(&original);
+    for name in opts.crates {
+        manifest.remove(&table, name)?;
+        config.shell().status("Removing", format!("{} from {}", name, table))?;
+    }
+    write_manifest(pkg, config, &original, &manifest.to_string())
+}
+
+fn load_registry<'cfg>(config: &'cfg Config, registry: Option<&str>)
+                       -> CargoResult<(SourceId, Box<Source + 'cfg>)> {
+    let source_id = match registry {
+        Some(registry) => SourceId::alt_registry(config, registry)?,
+        None => SourceId::crates_io(config)?,
+    };
+    let mut source = SourceConfigMap::new(config)?.load(&source_id)?;
+    source.update()?;
+    Ok((source_id, source))
+}
+
+/// Checks that the package at `path`, relative to the current directory, is
+/// called `name`, and returns its path relative to the root of `pkg`.
+fn local_path(config: &Config, pkg: &Package, name: &str, path: &str)
+              -> CargoResult<String> {
+    let root = paths::normalize_path(&config.cwd().join(path));
+    let source_id = SourceId::for_path(&root)?;
+    let (dep, _) = ops::read_package(&root.join("Cargo.toml"), &source_id, config)
+        .chain_err(|| format!("failed to read the package at `{}`", path))?;
+    if dep.name() != name {
+        bail!("the package at `{}` is called `{}`, not `{}`",
+              path, dep.name(), name)
+    }
+
+    // Walk up from the package until reaching a directory which contains
+    // the dependency.
+    let mut base = paths::normalize_path(pkg.root());
+    let mut relative = PathBuf::new();
+    loop {
+        if let Some(rest) = paths::without_prefix(&root, &base) {
+            relative.push(rest);
+            break
+        }
+        if !base.pop() {
+            return Ok(root.display().to_string())
+        }
+        relative.push("..");
+    }
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    Ok(relative.display().to_string())
+}
+
+/// Finds the newest version of `name` matching `req`, ignoring yanked
+/// versions and, unless `req` asks for them, prereleases.
+fn newest_version(source: &mut Source,
+                  source_id: &SourceId,
+                  name: &str,
+                  req: Option<&str>) -> CargoResult<String> {
+    let dep = Dependency::parse_no_deprecated(name, req, source_id)?;
+    let summaries = source.query_vec(&dep)?;
+    let newest = summaries.iter()
+                          .map(|s| s.version())
+                          .filter(|v| req.is_some() || v.pre.is_empty())
+                          .max();
+    match newest {
+        Some(version) => Ok(version.to_string()),
+        None => match req {
+            Some(req) => {
+                bail!("could not find `{}` in {} with version `{}`",
+                      name, source_id, req)
+            }
+            None => bail!("could not find `{}` in {}", name, source_id),
+        },
+    }
+}
+
+/// Writes the edited manifest, making sure that Cargo can still read it.
+///
+/// If it can't the original contents are put back.
+fn write_manifest(pkg: &Package,
+                  config: &Config,
+                  original: &str,
+                  contents: &str) -> CargoResult<()> {
+    let path = pkg.manifest_path();
+    paths::write(path, contents.as_bytes())?;
+    if let Err(e) = toml::read_manifest(path, pkg.package_id().source_id(), config) {
+        paths::write(path, original.as_bytes())?;
+        let msg = format!("the edited manifest would be invalid, `{}` has \
+                           been left unchanged", path.display());
+        return Err(e.context(msg).into())
+    }
+    Ok(())
+}
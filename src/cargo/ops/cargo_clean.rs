@@ -6,7 +6,7 @@ use core::{Profiles, Workspace};
 use util::Config;
 use util::errors::{CargoResult, CargoResultExt};
 use util::paths;
-use ops::{self, Context, BuildConfig, Kind, Unit};
+use ops::{self, Context, BuildConfig, FeaturesFor, Kind, Unit};
 
 pub struct CleanOptions<'a> {
     pub spec: &'a [String],
@@ -56,16 +56,19 @@ pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CargoResult<()> {
                 let Profiles {
                     ref release, ref dev, ref test, ref bench, ref doc,
                     ref custom_build, ref test_deps, ref bench_deps, ref check,
-                    ref check_test, ref doctest,
+                    ref check_test, ref doctest, ..
                 } = *profiles;
-                let profiles = [release, dev, test, bench, doc, custom_build,
-                                test_deps, bench_deps, check, check_test, doctest];
-                for profile in profiles.iter() {
+                let base_profiles = [release, dev, test, bench, doc, custom_build,
+                                     test_deps, bench_deps, check, check_test,
+                                     doctest];
+                for profile in base_profiles.iter() {
                     units.push(Unit {
                         pkg,
                         target,
-                        profile,
+                        profile: profiles.for_package(*profile, pkgid,
+                                                      target.for_host()),
                         kind: *kind,
+                        features_for: FeaturesFor::Normal,
                     });
                 }
             }
@@ -55,10 +55,21 @@ pub struct CompileOptions<'a> {
     pub filter: CompileFilter<'a>,
     /// Whether this is a release build or not
     pub release: bool,
+    /// The name of the profile to build with, `dev` or `release` depending
+    /// on `release` if not given
+    pub profile: Option<&'a str>,
     /// Mode for this compile.
     pub mode: CompileMode,
     /// `--error_format` flag for the compiler.
     pub message_format: MessageFormat,
+    /// Whether to write a report of how long each unit took to build
+    pub timings: bool,
+    /// Whether to build packages even if their `rust-version` is newer than
+    /// the compiler in use
+    pub ignore_rust_version: bool,
+    /// Whether to output the commands of the build as JSON instead of
+    /// running them
+    pub build_plan: bool,
     /// Extra arguments to be passed to rustdoc (for main crate and dependencies)
     pub target_rustdoc_args: Option<&'a [String]>,
     /// The specified target will be compiled with all the available arguments,
@@ -79,8 +90,12 @@ impl<'a> CompileOptions<'a> {
             spec: ops::Packages::Packages(&[]),
             mode,
             release: false,
+            profile: None,
             filter: CompileFilter::Default { required_features_filterable: false },
             message_format: MessageFormat::Human,
+            timings: false,
+            ignore_rust_version: false,
+            build_plan: false,
             target_rustdoc_args: None,
             target_rustc_args: None,
         }
@@ -219,7 +234,8 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
                       -> CargoResult<ops::Compilation<'a>> {
     let CompileOptions { config, jobs, target, spec, features,
                          all_features, no_default_features,
-                         release, mode, message_format,
+                         release, profile, mode, message_format, timings,
+                         ignore_rust_version, build_plan,
                          ref filter,
                          ref target_rustdoc_args,
                          ref target_rustc_args } = *options;
@@ -230,7 +246,15 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
         bail!("jobs must be at least 1")
     }
 
-    let profiles = ws.profiles();
+    let (release, profiles) = match profile {
+        Some(name) => {
+            if release && name != "release" {
+                bail!("conflicting usage of --profile={} and --release", name)
+            }
+            ws.profiles().select(name)?
+        }
+        None => (release, ws.profiles()),
+    };
 
     let specs = spec.into_package_id_specs(ws)?;
     let features = Method::split_features(features);
@@ -315,8 +339,15 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
         let _p = profile::start("compiling");
         let mut build_config = scrape_build_config(config, jobs, target)?;
         build_config.release = release;
+        build_config.profile = match profile {
+            Some("dev") | Some("release") | None => None,
+            Some(name) => Some(name.to_string()),
+        };
         build_config.test = mode == CompileMode::Test || mode == CompileMode::Bench;
         build_config.json_messages = message_format == MessageFormat::Json;
+        build_config.timings = timings;
+        build_config.ignore_rust_version = ignore_rust_version;
+        build_config.build_plan = build_plan;
         if let CompileMode::Doc { deps } = mode {
             build_config.doc_all = deps;
         }
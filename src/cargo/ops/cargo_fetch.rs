@@ -4,9 +4,10 @@ use util::CargoResult;
 
 /// Executes `cargo fetch`.
 pub fn fetch<'a>(ws: &Workspace<'a>) -> CargoResult<(Resolve, PackageSet<'a>)> {
-    let (packages, resolve) = ops::resolve_ws(ws)?;
-    for id in resolve.iter() {
-        packages.get(id)?;
+    if ws.config().offline() {
+        bail!("can't fetch dependencies in the offline mode (--offline)");
     }
+    let (packages, resolve) = ops::resolve_ws(ws)?;
+    packages.get_many(resolve.iter())?;
     Ok((resolve, packages))
 }
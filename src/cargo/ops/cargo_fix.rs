@@ -0,0 +1,465 @@
+//! Applying the fixes suggested by rustc to the source of a package.
+//!
+//! The packages are checked through an `Executor` which asks rustc for JSON
+//! diagnostics, and the suggestions rustc considers `MachineApplicable` are
+//! applied to the files they point at, leaving out those overlapping with
+//! another one. The packages are then checked again to make sure the fixes
+//! didn't break anything, with the edits to any file which got new errors
+//! backed out, and this is repeated until there is nothing left to fix.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::iter;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use git2;
+use serde_json;
+
+use core::{Epoch, Package, PackageId, Target, Workspace};
+use ops::{self, CompileOptions, Executor, Unit};
+use util::{paths, Config, ProcessBuilder};
+use util::errors::{internal, CargoResult};
+use util::toml::{self, edit};
+use util::toml::edit::ManifestEditor;
+
+/// How many rounds of fixes are applied at most, as fixing some code can
+/// lead to more suggestions.
+const MAX_PASSES: usize = 4;
+
+pub struct FixOptions<'a> {
+    pub compile_opts: CompileOptions<'a>,
+    /// Whether to migrate the packages to the next epoch
+    pub epoch: bool,
+    /// Whether to go ahead even if the workspace has uncommitted changes
+    pub allow_dirty: bool,
+}
+
+/// Fixes the warnings of the selected workspace members, and migrates them
+/// to the next epoch with `--epoch`.
+pub fn fix(ws: &Workspace, opts: &FixOptions) -> CargoResult<()> {
+    let config = ws.config();
+    if !opts.allow_dirty {
+        check_version_control(ws)?;
+    }
+
+    let specs = opts.compile_opts.spec.into_package_id_specs(ws)?;
+    let mut packages = HashMap::new();
+    let mut migrate = Vec::new();
+    for member in ws.members() {
+        if !specs.iter().any(|spec| spec.matches(member.package_id())) {
+            continue
+        }
+        let mut lints = Vec::new();
+        if opts.epoch {
+            let epoch = member.manifest().epoch();
+            match epoch.next() {
+                Some(next) => {
+                    lints.push("-W".to_string());
+                    lints.push(migration_lints(next).to_string());
+                    migrate.push((member, next));
+                }
+                None => {
+                    config.shell().warn(format!("`{}` is already on the latest \
+                                                 epoch ({})", member.name(), epoch))?;
+                }
+            }
+        }
+        packages.insert(member.package_id().clone(), FixPackage {
+            root: member.root().to_path_buf(),
+            lints,
+        });
+    }
+    let target_dir = ws.target_dir().into_path_unlocked();
+    let exec = Arc::new(FixExecutor {
+        packages,
+        output: Mutex::new(Vec::new()),
+    });
+    let can_fix = |file: &Path| {
+        !file.starts_with(&target_dir) &&
+            exec.packages.values().any(|p| file.starts_with(&p.root))
+    };
+
+    let mut last = Check::run(ws, &opts.compile_opts, &exec, true);
+    if last.result.is_err() {
+        last.print(ws)?;
+        return last.result
+    }
+
+    let mut fixed = BTreeMap::new();
+    let mut backed_out = HashSet::new();
+    for _ in 0..MAX_PASSES {
+        let edits = apply(last.diagnostics(), |file| {
+            !backed_out.contains(file) && can_fix(file)
+        })?;
+        if edits.is_empty() {
+            break
+        }
+
+        let mut next = Check::run(ws, &opts.compile_opts, &exec, false);
+        let before = last.errors();
+        let after = next.errors();
+        let mut broken = edits.keys().filter(|file| {
+            after.get(*file) > before.get(*file)
+        }).cloned().collect::<Vec<_>>();
+        // If the errors can't be pinned on any of the files, none of the
+        // edits can be trusted.
+        if next.result.is_err() && broken.is_empty() {
+            broken = edits.keys().cloned().collect();
+        }
+        for file in broken.iter() {
+            paths::write(file, edits[file].original.as_bytes())?;
+            config.shell().warn(format!("failed to automatically apply fixes \
+                                         suggested by rustc to `{}`, backing \
+                                         out the changes", display(ws, file)))?;
+            backed_out.insert(file.clone());
+        }
+        for (file, edit) in edits.iter() {
+            if !broken.contains(file) {
+                *fixed.entry(file.clone()).or_insert(0) += edit.fixes;
+            }
+        }
+        if !broken.is_empty() {
+            next = Check::run(ws, &opts.compile_opts, &exec, false);
+        }
+        last = next;
+        if last.result.is_err() {
+            break
+        }
+    }
+
+    for (file, fixes) in fixed.iter() {
+        config.shell().status("Fixed", format!("{} ({} {})", display(ws, file), fixes,
+                                               if *fixes == 1 { "fix" } else { "fixes" }))?;
+    }
+    if last.result.is_ok() {
+        for &(pkg, epoch) in migrate.iter() {
+            update_epoch(pkg, epoch, config)?;
+            config.shell().status("Migrated", format!("`{}` to the {} epoch",
+                                                      pkg.name(), epoch))?;
+        }
+    }
+    last.print(ws)?;
+    last.result
+}
+
+/// The lints which point out code that has to change for `epoch`.
+fn migration_lints(epoch: Epoch) -> &'static str {
+    match epoch {
+        Epoch::Epoch2015 => "rust-2015-compatibility",
+        Epoch::Epoch2018 => "rust-2018-compatibility",
+    }
+}
+
+/// Fails if any file of the workspace has changes which aren't committed,
+/// so that the fixes can be reviewed and reverted with version control.
+fn check_version_control(ws: &Workspace) -> CargoResult<()> {
+    let repo = match git2::Repository::discover(ws.root()) {
+        Ok(repo) => repo,
+        // Without any version control there is nothing to check
+        Err(_) => return Ok(()),
+    };
+    let workdir = match repo.workdir() {
+        Some(workdir) => workdir.to_path_buf(),
+        None => return Ok(()),
+    };
+    let target_dir = ws.target_dir().into_path_unlocked();
+    let mut status_opts = git2::StatusOptions::new();
+    status_opts.include_untracked(true).include_ignored(false);
+    let dirty = repo.statuses(Some(&mut status_opts))?.iter().filter_map(|entry| {
+        entry.path().map(|path| workdir.join(path))
+    }).filter(|path| {
+        path.starts_with(ws.root()) && !path.starts_with(&target_dir)
+    }).map(|path| {
+        format!("  * {}", display(ws, &path))
+    }).collect::<Vec<_>>();
+    if dirty.is_empty() {
+        return Ok(())
+    }
+    bail!("the working directory of this workspace has uncommitted changes, and \
+           `cargo fix` can potentially perform destructive changes; if you'd \
+           like to suppress this error pass `--allow-dirty`, or commit the \
+           changes to these files:\n\n{}\n", dirty.join("\n"))
+}
+
+/// Sets the `rust` key of the manifest of `pkg` to `epoch`, along with the
+/// `epoch` cargo feature it requires.
+fn update_epoch(pkg: &Package, epoch: Epoch, config: &Config) -> CargoResult<()> {
+    let path = pkg.manifest_path();
+    let contents = paths::read(path)?;
+    let toml = toml::parse(&contents, path, config)?;
+    let mut manifest = ManifestEditor::new(&contents);
+
+    let mut features = toml.get("cargo-features").and_then(|f| f.as_array()).map(|f| {
+        f.iter().filter_map(|f| f.as_str()).collect::<Vec<_>>()
+    }).unwrap_or_default();
+    if !features.contains(&"epoch") {
+        features.push("epoch");
+        manifest.set(&[], "cargo-features", &edit::string_array(features))?;
+    }
+    let table = if toml.get("package").is_some() { "package" } else { "project" };
+    manifest.set(&[table], "rust", &edit::string(&epoch.to_string()))?;
+    paths::write(path, manifest.to_string().as_bytes())
+}
+
+fn display(ws: &Workspace, path: &Path) -> String {
+    let cwd = ws.config().cwd();
+    path.strip_prefix(cwd).unwrap_or(path).display().to_string()
+}
+
+struct FixPackage {
+    root: PathBuf,
+    /// Extra `-W` flags to pass to rustc
+    lints: Vec<String>,
+}
+
+/// Runs rustc with JSON diagnostics, collecting them instead of printing
+/// them.
+struct FixExecutor {
+    /// The packages which are being fixed
+    packages: HashMap<PackageId, FixPackage>,
+    output: Mutex<Vec<Output>>,
+}
+
+/// A line rustc wrote to stderr, kept in order to be printed through the
+/// shell once the packages have been checked.
+enum Output {
+    Diagnostic(Diagnostic),
+    Text(String),
+}
+
+impl Executor for FixExecutor {
+    fn exec(&self, mut cmd: ProcessBuilder, id: &PackageId, _target: &Target)
+            -> CargoResult<()> {
+        if let Some(package) = self.packages.get(id) {
+            cmd.args(&package.lints);
+        }
+        cmd.arg("--error-format").arg("json");
+        let cwd = cmd.get_cwd().map(|p| p.to_path_buf()).unwrap_or_default();
+        let mut output = Vec::new();
+        let result = cmd.exec_with_streaming(
+            &mut |line| if !line.is_empty() {
+                Err(internal(&format!("compiler stdout is not empty: `{}`", line)))
+            } else {
+                Ok(())
+            },
+            &mut |line| {
+                if !line.starts_with('{') {
+                    output.push(Output::Text(line.to_string()));
+                } else if let Ok(mut diagnostic) = serde_json::from_str::<Diagnostic>(line) {
+                    diagnostic.resolve_paths(&cwd);
+                    output.push(Output::Diagnostic(diagnostic));
+                }
+                Ok(())
+            },
+            false,
+        );
+        self.output.lock().unwrap().extend(output);
+        result.map(|_| ())
+    }
+
+    fn force_rebuild(&self, unit: &Unit) -> bool {
+        // Fresh units wouldn't tell us about what there is to fix
+        self.packages.contains_key(unit.pkg.package_id())
+    }
+}
+
+/// The outcome of checking the packages.
+struct Check {
+    result: CargoResult<()>,
+    output: Vec<Output>,
+}
+
+impl Check {
+    fn run(ws: &Workspace,
+           opts: &CompileOptions,
+           exec: &Arc<FixExecutor>,
+           first: bool) -> Check {
+        // Warnings about the manifests only need to be shown once
+        let result = if first {
+            ops::compile_with_exec(ws, opts, exec.clone())
+        } else {
+            ops::compile_ws(ws, None, opts, exec.clone())
+        };
+        let output = mem::replace(&mut *exec.output.lock().unwrap(), Vec::new());
+        Check {
+            result: result.map(|_| ()),
+            output,
+        }
+    }
+
+    fn diagnostics<'a>(&'a self) -> Box<Iterator<Item = &'a Diagnostic> + 'a> {
+        Box::new(self.output.iter().filter_map(|output| match *output {
+            Output::Diagnostic(ref diagnostic) => Some(diagnostic),
+            Output::Text(..) => None,
+        }))
+    }
+
+    /// The number of errors in each file.
+    fn errors(&self) -> HashMap<PathBuf, usize> {
+        let mut errors = HashMap::new();
+        for diagnostic in self.diagnostics().filter(|d| d.level == "error") {
+            if let Some(span) = diagnostic.spans.iter().find(|s| s.is_primary) {
+                *errors.entry(span.file_name.clone()).or_insert(0) += 1;
+            }
+        }
+        errors
+    }
+
+    /// Prints the diagnostics as rustc would have, without the duplicates
+    /// from checking a file as part of several targets.
+    fn print(&self, ws: &Workspace) -> CargoResult<()> {
+        let mut seen = HashSet::new();
+        let mut shell = ws.config().shell();
+        for output in self.output.iter() {
+            match *output {
+                Output::Diagnostic(ref diagnostic) => {
+                    if let Some(ref rendered) = diagnostic.rendered {
+                        if seen.insert(rendered) {
+                            write!(shell.err(), "{}", rendered)?;
+                        }
+                    }
+                }
+                Output::Text(ref line) => writeln!(shell.err(), "{}", line)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct Diagnostic {
+    level: String,
+    #[serde(default)]
+    spans: Vec<DiagnosticSpan>,
+    #[serde(default)]
+    children: Vec<Diagnostic>,
+    rendered: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DiagnosticSpan {
+    file_name: PathBuf,
+    byte_start: usize,
+    byte_end: usize,
+    #[serde(default)]
+    is_primary: bool,
+    suggested_replacement: Option<String>,
+    suggestion_applicability: Option<String>,
+}
+
+impl Diagnostic {
+    /// Makes the file names absolute, rustc gives them relative to the
+    /// directory it ran in.
+    fn resolve_paths(&mut self, cwd: &Path) {
+        for span in self.spans.iter_mut() {
+            span.file_name = cwd.join(&span.file_name);
+        }
+        for child in self.children.iter_mut() {
+            child.resolve_paths(cwd);
+        }
+    }
+
+    /// The suggestions of this diagnostic which can be applied as they are,
+    /// each being a set of replacements which go together.
+    fn suggestions(&self) -> Vec<Vec<Replacement>> {
+        iter::once(self).chain(self.children.iter()).filter_map(|d| {
+            let spans = d.spans.iter().filter(|s| {
+                s.suggested_replacement.is_some()
+            }).collect::<Vec<_>>();
+            let applicable = spans.iter().all(|s| {
+                s.suggestion_applicability.as_ref().map(|a| &a[..]) == Some("MachineApplicable")
+            });
+            if spans.is_empty() || !applicable {
+                return None
+            }
+            let replacements = spans.iter().map(|s| {
+                Replacement {
+                    file: s.file_name.clone(),
+                    start: s.byte_start,
+                    end: s.byte_end,
+                    text: s.suggested_replacement.clone().unwrap(),
+                }
+            }).collect::<Vec<_>>();
+            // Alternatives for the same code come as one suggestion, and
+            // there's no telling which one to pick.
+            let conflicting = replacements.iter().enumerate().any(|(i, a)| {
+                replacements[i + 1..].iter().any(|b| a.overlaps(b))
+            });
+            if conflicting {
+                None
+            } else {
+                Some(replacements)
+            }
+        }).collect()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Replacement {
+    file: PathBuf,
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+impl Replacement {
+    fn overlaps(&self, other: &Replacement) -> bool {
+        self.file == other.file &&
+            (self.start == other.start ||
+             (self.start < other.end && other.start < self.end))
+    }
+}
+
+/// The changes made to a file.
+struct Edit {
+    original: String,
+    fixes: usize,
+}
+
+/// Applies the suggestions found in `diagnostics` to the files `can_fix`
+/// accepts, skipping those which overlap a suggestion already taken.
+fn apply<'a, I, F>(diagnostics: I, can_fix: F) -> CargoResult<BTreeMap<PathBuf, Edit>>
+    where I: IntoIterator<Item = &'a Diagnostic>,
+          F: Fn(&Path) -> bool
+{
+    let mut seen = HashSet::new();
+    let mut taken = Vec::new();
+    let mut fixes = HashMap::new();
+    for suggestion in diagnostics.into_iter().flat_map(|d| d.suggestions()) {
+        if !seen.insert(suggestion.clone()) {
+            continue
+        }
+        if !suggestion.iter().all(|r| can_fix(&r.file)) ||
+           suggestion.iter().any(|r| taken.iter().any(|t| r.overlaps(t))) {
+            continue
+        }
+        let files = suggestion.iter().map(|r| r.file.clone()).collect::<HashSet<_>>();
+        for file in files {
+            *fixes.entry(file).or_insert(0) += 1;
+        }
+        taken.extend(suggestion);
+    }
+
+    let mut edits = BTreeMap::new();
+    for (file, fixes) in fixes {
+        let original = paths::read(&file)?;
+        let mut replacements = taken.iter().filter(|r| r.file == file).collect::<Vec<_>>();
+        replacements.sort_by(|a, b| b.start.cmp(&a.start));
+        let valid = replacements.iter().all(|r| {
+            r.start <= r.end && r.end <= original.len() &&
+                original.is_char_boundary(r.start) && original.is_char_boundary(r.end)
+        });
+        if !valid {
+            continue
+        }
+        let mut contents = original.clone();
+        for r in replacements {
+            contents = format!("{}{}{}", &contents[..r.start], r.text, &contents[r.end..]);
+        }
+        paths::write(&file, contents.as_bytes())?;
+        edits.insert(file, Edit { original, fixes });
+    }
+    Ok(edits)
+}
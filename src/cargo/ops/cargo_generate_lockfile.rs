@@ -4,7 +4,7 @@ use termcolor::Color::{self, Cyan, Green, Red};
 
 use core::PackageId;
 use core::registry::PackageRegistry;
-use core::{Resolve, SourceId, Workspace};
+use core::{Resolve, ResolveVersion, SourceId, Workspace};
 use core::resolver::Method;
 use ops;
 use util::config::Config;
@@ -15,22 +15,44 @@ pub struct UpdateOptions<'a> {
     pub to_update: &'a [String],
     pub precise: Option<&'a str>,
     pub aggressive: bool,
+    /// The format to write the lock file in, keeping the current one if
+    /// `None`
+    pub lockfile_version: Option<ResolveVersion>,
 }
 
 pub fn generate_lockfile(ws: &Workspace) -> CargoResult<()> {
-    let mut registry = PackageRegistry::new(ws.config())?;
-    let resolve = ops::resolve_with_previous(&mut registry,
-                                             ws,
-                                             Method::Everything,
-                                             None,
-                                             None,
-                                             &[],
-                                             true,
-                                             true)?;
+    let resolve = resolve_from_scratch(ws)?;
     ops::write_pkg_lockfile(ws, &resolve)?;
     Ok(())
 }
 
+fn resolve_from_scratch(ws: &Workspace) -> CargoResult<Resolve> {
+    let mut registry = PackageRegistry::new(ws.config())?;
+    ops::resolve_with_previous(&mut registry,
+                               ws,
+                               Method::Everything,
+                               None,
+                               None,
+                               &[],
+                               true,
+                               true)
+}
+
+/// Rewrites the lock file in the format of `version`, without updating any
+/// of the dependencies.
+fn migrate_lockfile(ws: &Workspace, version: ResolveVersion) -> CargoResult<()> {
+    let mut resolve = match ops::load_pkg_lockfile(ws)? {
+        Some(resolve) => resolve,
+        None => resolve_from_scratch(ws)?,
+    };
+    if resolve.version() != version {
+        ws.config().shell().status("Migrating",
+                                   format!("Cargo.lock to version {}", version))?;
+        resolve.set_version(version);
+    }
+    ops::write_pkg_lockfile(ws, &resolve)
+}
+
 pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions)
                        -> CargoResult<()> {
 
@@ -42,7 +64,13 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions)
         bail!("you can't generate a lockfile for an empty workspace.")
     }
 
-    if opts.config.cli_unstable().offline {
+    if let Some(version) = opts.lockfile_version {
+        if opts.to_update.is_empty() {
+            return migrate_lockfile(ws, version)
+        }
+    }
+
+    if opts.config.offline() {
         bail!("you can't update in the offline mode");
     }
 
@@ -85,14 +113,17 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions)
         registry.add_sources(&sources)?;
     }
 
-    let resolve = ops::resolve_with_previous(&mut registry,
-                                             ws,
-                                             Method::Everything,
-                                             Some(&previous_resolve),
-                                             Some(&to_avoid),
-                                             &[],
-                                             true,
-                                             true)?;
+    let mut resolve = ops::resolve_with_previous(&mut registry,
+                                                 ws,
+                                                 Method::Everything,
+                                                 Some(&previous_resolve),
+                                                 Some(&to_avoid),
+                                                 &[],
+                                                 true,
+                                                 true)?;
+    if let Some(version) = opts.lockfile_version {
+        resolve.set_version(version);
+    }
 
     // Summarize what is changing for the user.
     let print_change = |status: &str, msg: String, color: Color| {
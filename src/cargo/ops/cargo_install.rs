@@ -59,6 +59,11 @@ pub fn install(root: Option<&str>,
                vers: Option<&str>,
                opts: &ops::CompileOptions,
                force: bool) -> CargoResult<()> {
+    if opts.config.offline() && !source_id.is_path() {
+        bail!("can't install from `{}` in the offline mode (--offline), \
+               use --path to install a local crate instead", source_id);
+    }
+
     let root = resolve_root(root, opts.config)?;
     let map = SourceConfigMap::new(opts.config)?;
 
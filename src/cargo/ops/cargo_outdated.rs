@@ -0,0 +1,206 @@
+use std::cmp;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use semver::Version;
+
+use core::{Dependency, PackageId, Registry, Summary, Workspace};
+use core::registry::PackageRegistry;
+use ops::{self, Packages};
+use util::errors::{CargoResult, CargoResultExt};
+
+pub struct OutdatedOptions<'a> {
+    /// The packages whose dependencies are checked.
+    pub spec: Packages<'a>,
+    /// Only check dependencies at most this many edges away from the
+    /// selected packages, where 1 means only their direct dependencies.
+    pub depth: Option<usize>,
+}
+
+/// The registry dependencies of a workspace for which newer versions have
+/// been published than the ones in `Cargo.lock`.
+#[derive(Serialize)]
+pub struct OutdatedReport {
+    pub dependencies: Vec<OutdatedDependency>,
+}
+
+#[derive(Serialize)]
+pub struct OutdatedDependency {
+    pub name: String,
+    pub source: String,
+    /// The version in the lock file.
+    pub locked: Version,
+    /// The newest version which every version requirement on this package
+    /// in the graph accepts, what `cargo update` would move to.
+    pub compatible: Version,
+    /// The newest version published, ignoring requirements.
+    pub latest: Version,
+    /// The smallest number of edges between this package and one of the
+    /// selected packages.
+    pub depth: usize,
+}
+
+/// Checks the registry dependencies in the lock file of `ws` for versions
+/// newer than the locked ones.
+///
+/// Yanked versions are never reported, and neither are pre-releases unless
+/// the locked version is a pre-release itself.
+pub fn outdated(ws: &Workspace, opts: &OutdatedOptions) -> CargoResult<OutdatedReport> {
+    let config = ws.config();
+    let resolve = match ops::load_pkg_lockfile(ws)? {
+        Some(resolve) => resolve,
+        None => bail!("no Cargo.lock found for `{}`, run `cargo generate-lockfile` \
+                       to create one first", ws.root().display()),
+    };
+
+    let specs = opts.spec.into_package_id_specs(ws)?;
+    let roots = specs.iter()
+                     .map(|spec| spec.query(resolve.iter()))
+                     .collect::<CargoResult<Vec<_>>>()?;
+
+    // Walk the lock file breadth first so each package is found at its
+    // smallest depth.
+    let mut depths = HashMap::new();
+    let mut queue = VecDeque::new();
+    for &id in roots.iter() {
+        depths.insert(id, 0);
+        queue.push_back(id);
+    }
+    while let Some(id) = queue.pop_front() {
+        let depth = depths[&id] + 1;
+        if opts.depth.map_or(false, |max| depth > max) {
+            continue
+        }
+        for dep in resolve.deps_not_replaced(id) {
+            if !depths.contains_key(dep) {
+                depths.insert(dep, depth);
+                queue.push_back(dep);
+            }
+        }
+    }
+
+    let mut registry = PackageRegistry::new(config)?;
+    registry.lock_patches();
+
+    // The version requirements on a package are those written by each
+    // package depending on it, members from their manifests and everything
+    // else from the summary of its locked version.
+    let members = ws.members()
+                    .map(|pkg| (pkg.package_id(), pkg.summary().clone()))
+                    .collect::<HashMap<_, _>>();
+    let mut requirements = HashMap::new();
+    let mut visited = HashSet::new();
+    for (&id, &depth) in depths.iter() {
+        if opts.depth.map_or(false, |max| depth >= max) ||
+           resolve.deps_not_replaced(id).next().is_none() {
+            continue
+        }
+        let summary = match members.get(id) {
+            Some(summary) => summary.clone(),
+            None => locked_summary(&mut registry, id)?,
+        };
+        for dep_id in resolve.deps_not_replaced(id) {
+            if !visited.insert((id, dep_id)) {
+                continue
+            }
+            let reqs = requirements.entry(dep_id).or_insert_with(Vec::new);
+            for dep in summary.dependencies().iter().filter(|d| d.matches_id(dep_id)) {
+                reqs.push(dep.version_req().clone());
+            }
+        }
+    }
+
+    let mut dependencies = Vec::new();
+    for (&id, &depth) in depths.iter() {
+        if depth == 0 || !id.source_id().is_registry() {
+            continue
+        }
+
+        // Pre-releases are only candidates when one is already in use, and
+        // are only matched by a requirement naming a pre-release of the same
+        // version.
+        let req = if id.version().is_prerelease() {
+            format!(">={}", id.version())
+        } else {
+            "*".to_string()
+        };
+        let source_id = id.source_id().with_precise(None);
+        let dep = Dependency::parse_no_deprecated(id.name(), Some(&req), &source_id)?;
+        let mut versions = Vec::new();
+        registry.query(&dep, &mut |s| {
+            versions.push(s.version().clone());
+        }).chain_err(|| format_err!("failed to query versions of `{}`", id.name()))?;
+
+        let reqs = requirements.get(id).map(|r| &r[..]).unwrap_or(&[]);
+        let newest = |versions: &mut Iterator<Item = &Version>| {
+            versions.fold(id.version().clone(), |a, b| cmp::max(a, b.clone()))
+        };
+        let latest = newest(&mut versions.iter());
+        let compatible = newest(&mut versions.iter().filter(|v| {
+            reqs.iter().all(|req| req.matches(v))
+        }));
+
+        if compatible > *id.version() || latest > *id.version() {
+            dependencies.push(OutdatedDependency {
+                name: id.name().to_string(),
+                source: id.source_id().to_url().to_string(),
+                locked: id.version().clone(),
+                compatible,
+                latest,
+                depth,
+            });
+        }
+    }
+    dependencies.sort_by(|a, b| {
+        (a.depth, &a.name, &a.locked).cmp(&(b.depth, &b.name, &b.locked))
+    });
+    Ok(OutdatedReport { dependencies })
+}
+
+/// Looks up the summary of a locked package, even if it has been yanked
+/// since it was locked.
+fn locked_summary(registry: &mut PackageRegistry, id: &PackageId) -> CargoResult<Summary> {
+    let source_id = if id.source_id().is_registry() {
+        id.source_id().with_precise(Some("locked".to_string()))
+    } else {
+        id.source_id().clone()
+    };
+    let req = format!("={}", id.version());
+    let dep = Dependency::parse_no_deprecated(id.name(), Some(&req), &source_id)?;
+    let summaries = registry.query_vec(&dep)?;
+    match summaries.into_iter().next() {
+        Some(summary) => Ok(summary),
+        None => bail!("failed to find `{}` in its source, the lock file may \
+                       need to be updated", id),
+    }
+}
+
+impl fmt::Display for OutdatedReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut rows = vec![["Name".to_string(), "Locked".to_string(),
+                             "Compatible".to_string(), "Latest".to_string()]];
+        for dep in self.dependencies.iter() {
+            // Versions which are the same as the locked one are left out so
+            // the interesting ones stand out.
+            let show = |v: &Version| {
+                if v == &dep.locked { "---".to_string() } else { v.to_string() }
+            };
+            rows.push([dep.name.clone(), dep.locked.to_string(),
+                       show(&dep.compatible), show(&dep.latest)]);
+        }
+
+        let mut widths = [0; 4];
+        for row in rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = cmp::max(*width, cell.len());
+            }
+        }
+        for row in rows.iter() {
+            let line = row.iter().zip(widths.iter()).map(|(cell, &width)| {
+                format!("{:1$}", cell, width)
+            }).collect::<Vec<_>>().join("  ");
+            writeln!(f, "{}", line.trim_right())?;
+        }
+        Ok(())
+    }
+}
@@ -54,9 +54,10 @@ fn metadata_full(ws: &Workspace,
                                          &specs)?;
     let (packages, resolve) = deps;
 
-    let packages = packages.package_ids()
-                           .map(|i| packages.get(i).map(|p| p.clone()))
-                           .collect::<CargoResult<Vec<_>>>()?;
+    let packages = packages.get_many(packages.package_ids())?
+                           .into_iter()
+                           .cloned()
+                           .collect();
 
     Ok(ExportInfo {
         packages,
@@ -1,13 +1,14 @@
+use std::env;
 use std::fs::{self, File};
 use std::io::SeekFrom;
 use std::io::prelude::*;
-use std::path::{self, Path};
+use std::path::{self, Path, PathBuf};
 use std::sync::Arc;
 
 use flate2::read::GzDecoder;
 use flate2::{GzBuilder, Compression};
 use git2;
-use tar::{Archive, Builder, Header, EntryType};
+use tar::{Archive, Builder, Header, HeaderMode, EntryType};
 
 use core::{Package, Workspace, Source, SourceId};
 use sources::PathSource;
@@ -193,21 +194,29 @@ fn check_not_dirty(p: &Package, src: &PathSource) -> CargoResult<()> {
     }
 }
 
+/// The contents of a file in a package tarball.
+enum FileContents {
+    /// A file of the package, archived as is.
+    OnDisk(PathBuf),
+    /// A file generated while packaging, like the normalized manifest.
+    Generated(String),
+}
+
 fn tar(ws: &Workspace,
        src: &PathSource,
        dst: &File,
        filename: &str) -> CargoResult<()> {
-    // Prepare the encoder and its header
-    let filename = Path::new(filename);
-    let encoder = GzBuilder::new().filename(util::path2bytes(filename)?)
-                                  .write(dst, Compression::best());
-
-    // Put all package files into a compressed archive
-    let mut ar = Builder::new(encoder);
     let pkg = ws.current()?;
     let config = ws.config();
     let root = pkg.root();
-    for file in src.list_files(pkg)?.iter() {
+
+    // Packaging the same sources must always produce the same tarball, so
+    // the files are archived in order of their paths, and nothing which
+    // depends on the checkout they come from goes into their headers.
+    let mut src_files = src.list_files(pkg)?;
+    src_files.sort();
+    let mut files = Vec::new();
+    for file in src_files.iter() {
         let relative = util::without_prefix(file, root).unwrap();
         check_filename(relative)?;
         let relative = relative.to_str().ok_or_else(|| {
@@ -217,6 +226,31 @@ fn tar(ws: &Workspace,
         config.shell().verbose(|shell| {
             shell.status("Archiving", &relative)
         })?;
+        if relative == "Cargo.toml" {
+            files.push(("Cargo.toml.orig".to_string(), FileContents::OnDisk(file.clone())));
+            let toml = pkg.to_registry_toml(ws.config())?;
+            files.push((relative.to_string(), FileContents::Generated(toml)));
+        } else {
+            files.push((relative.to_string(), FileContents::OnDisk(file.clone())));
+        }
+    }
+    if include_lockfile(pkg) {
+        let lock = paths::read(&ws.root().join("Cargo.lock"))?;
+        files.push(("Cargo.lock".to_string(), FileContents::Generated(lock)));
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    let mtime = source_date_epoch()?.unwrap_or(DETERMINISTIC_TIMESTAMP);
+
+    // Prepare the encoder and its header, leaving out its timestamp.
+    let filename = Path::new(filename);
+    let encoder = GzBuilder::new().filename(util::path2bytes(filename)?)
+                                  .mtime(0)
+                                  .operating_system(GZIP_OS_UNKNOWN)
+                                  .write(dst, Compression::best());
+
+    // Put all package files into a compressed archive
+    let mut ar = Builder::new(encoder);
+    for &(ref relative, ref contents) in files.iter() {
         let path = format!("{}-{}{}{}", pkg.name(), pkg.version(),
                            path::MAIN_SEPARATOR, relative);
 
@@ -242,60 +276,72 @@ fn tar(ws: &Workspace,
         header.set_path(&path).chain_err(|| {
             format!("failed to add to archive: `{}`", relative)
         })?;
-        let mut file = File::open(file).chain_err(|| {
-            format!("failed to open for archiving: `{}`", file.display())
-        })?;
-        let metadata = file.metadata().chain_err(|| {
-            format!("could not learn metadata for: `{}`", relative)
-        })?;
-        header.set_metadata(&metadata);
 
-        if relative == "Cargo.toml" {
-            let orig = Path::new(&path).with_file_name("Cargo.toml.orig");
-            header.set_path(&orig)?;
-            header.set_cksum();
-            ar.append(&header, &mut file).chain_err(|| {
-                internal(format!("could not archive source file `{}`", relative))
-            })?;
-
-            let mut header = Header::new_ustar();
-            let toml = pkg.to_registry_toml(ws.config())?;
-            header.set_path(&path)?;
-            header.set_entry_type(EntryType::file());
-            header.set_mode(0o644);
-            header.set_size(toml.len() as u64);
-            header.set_cksum();
-            ar.append(&header, toml.as_bytes()).chain_err(|| {
-                internal(format!("could not archive source file `{}`", relative))
-            })?;
-        } else {
-            header.set_cksum();
-            ar.append(&header, &mut file).chain_err(|| {
-                internal(format!("could not archive source file `{}`", relative))
-            })?;
+        match *contents {
+            FileContents::OnDisk(ref file) => {
+                let mut file = File::open(file).chain_err(|| {
+                    format!("failed to open for archiving: `{}`", file.display())
+                })?;
+                let metadata = file.metadata().chain_err(|| {
+                    format!("could not learn metadata for: `{}`", relative)
+                })?;
+                // Only the size and whether the file is executable are kept,
+                // ownership is left out and the mode is either 0o755 or 0o644.
+                header.set_metadata_in_mode(&metadata, HeaderMode::Deterministic);
+                header.set_mtime(mtime);
+                header.set_cksum();
+                ar.append(&header, &mut file).chain_err(|| {
+                    internal(format!("could not archive source file `{}`", relative))
+                })?;
+            }
+            FileContents::Generated(ref contents) => {
+                header.set_entry_type(EntryType::file());
+                header.set_mode(0o644);
+                header.set_uid(0);
+                header.set_gid(0);
+                header.set_mtime(mtime);
+                header.set_size(contents.len() as u64);
+                header.set_cksum();
+                ar.append(&header, contents.as_bytes()).chain_err(|| {
+                    internal(format!("could not archive source file `{}`", relative))
+                })?;
+            }
         }
     }
 
-    if include_lockfile(pkg) {
-        let toml = paths::read(&ws.root().join("Cargo.lock"))?;
-        let path = format!("{}-{}{}Cargo.lock", pkg.name(), pkg.version(),
-                           path::MAIN_SEPARATOR);
-        let mut header = Header::new_ustar();
-        header.set_path(&path)?;
-        header.set_entry_type(EntryType::file());
-        header.set_mode(0o644);
-        header.set_size(toml.len() as u64);
-        header.set_cksum();
-        ar.append(&header, toml.as_bytes()).chain_err(|| {
-            internal("could not archive source file `Cargo.lock`")
-        })?;
-    }
-
     let encoder = ar.into_inner()?;
     encoder.finish()?;
     Ok(())
 }
 
+/// The modification time of the files in a package tarball unless
+/// `SOURCE_DATE_EPOCH` says otherwise, an arbitrary but non-zero one as not
+/// all tools deal well with a zero timestamp.
+const DETERMINISTIC_TIMESTAMP: u64 = 1153704088;
+
+/// The operating system field of a gzip header meaning "unknown", so the
+/// tarball doesn't depend on the platform it was made on.
+const GZIP_OS_UNKNOWN: u8 = 255;
+
+/// Reads the timestamp to give the files in a package tarball from
+/// `SOURCE_DATE_EPOCH`, as defined by https://reproducible-builds.org.
+fn source_date_epoch() -> CargoResult<Option<u64>> {
+    match env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => {
+            match epoch.trim().parse() {
+                Ok(epoch) => Ok(Some(epoch)),
+                Err(_) => bail!("invalid `SOURCE_DATE_EPOCH` of `{}`, expected \
+                                 the number of seconds since the Unix epoch",
+                                epoch),
+            }
+        }
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(env::VarError::NotUnicode(_)) => {
+            bail!("`SOURCE_DATE_EPOCH` is not valid unicode")
+        }
+    }
+}
+
 fn run_verify(ws: &Workspace, tar: &FileLock, opts: &PackageOpts) -> CargoResult<()> {
     let config = ws.config();
     let pkg = ws.current()?;
@@ -327,10 +373,14 @@ fn run_verify(ws: &Workspace, tar: &FileLock, opts: &PackageOpts) -> CargoResult
         spec: ops::Packages::Packages(&[]),
         filter: ops::CompileFilter::Default { required_features_filterable: true },
         release: false,
+        profile: None,
         message_format: ops::MessageFormat::Human,
         mode: ops::CompileMode::Build,
         target_rustdoc_args: None,
         target_rustc_args: None,
+        timings: false,
+        ignore_rust_version: false,
+        build_plan: false,
     }, Arc::new(DefaultExecutor))?;
 
     Ok(())
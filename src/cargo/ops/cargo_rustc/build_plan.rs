@@ -0,0 +1,153 @@
+//! The compilation graph of a build, for use by other build systems.
+//!
+//! When `--build-plan` is passed every unit still goes through the same
+//! preparation as a regular build, but instead of running the commands the
+//! `JobQueue` would run, the commands are recorded here. Once all units have
+//! been prepared they're printed to stdout as a single JSON document, listing
+//! each unit with its dependencies ahead of it.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+
+use serde_json;
+
+use core::{PackageId, TargetKind};
+use util::{CargoResult, ProcessBuilder};
+
+use super::{Context, Kind, Unit};
+
+pub struct BuildPlan<'a> {
+    /// Whether `--build-plan` was passed, nothing is recorded otherwise
+    enabled: bool,
+    /// The command each unit would have run
+    commands: HashMap<Unit<'a>, ProcessBuilder>,
+}
+
+#[derive(Serialize)]
+struct SerializedBuildPlan<'a> {
+    invocations: Vec<Invocation<'a>>,
+}
+
+/// A single process the build would run.
+#[derive(Serialize)]
+struct Invocation<'a> {
+    package_id: &'a PackageId,
+    target_name: &'a str,
+    target_kind: &'a TargetKind,
+    /// `host` or `target`, whether this is built for the host when cross
+    /// compiling
+    kind: &'static str,
+    /// Indices of the invocations which must finish before this one
+    deps: Vec<usize>,
+    /// Whether the command is completed at build time with what build
+    /// scripts print, like `-L` and `--cfg` flags for rustc or `DEP_*`
+    /// variables for other build scripts
+    requires_build_script_output: bool,
+    /// The files produced, or the `OUT_DIR` of a build script being run
+    outputs: Vec<PathBuf>,
+    program: String,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+    cwd: Option<PathBuf>,
+}
+
+impl<'a> BuildPlan<'a> {
+    pub fn new(enabled: bool) -> BuildPlan<'a> {
+        BuildPlan {
+            enabled,
+            commands: HashMap::new(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Records the command which would be run for `unit`.
+    pub fn add(&mut self, unit: &Unit<'a>, cmd: &ProcessBuilder) {
+        if self.enabled {
+            self.commands.insert(*unit, cmd.clone());
+        }
+    }
+
+    /// Prints the recorded commands of `units` and all of their dependencies.
+    ///
+    /// The plan has to be taken out of `cx` first, as looking up the outputs
+    /// of units needs the context mutably.
+    pub fn output<'cfg>(&self, cx: &mut Context<'a, 'cfg>, units: &[Unit<'a>])
+                        -> CargoResult<()> {
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+        for unit in units {
+            self.visit(cx, unit, &mut visited, &mut order)?;
+        }
+        let indices = order.iter().enumerate().map(|(i, unit)| (*unit, i))
+                                              .collect::<HashMap<_, _>>();
+
+        let mut invocations = Vec::new();
+        for unit in order.iter() {
+            let cmd = &self.commands[unit];
+            let deps = cx.dep_targets(unit)?;
+            let requires_build_script_output = deps.iter().any(|d| {
+                d.profile.run_custom_build
+            }) || cx.build_scripts.get(unit).map_or(false, |scripts| {
+                !scripts.to_link.is_empty() || !scripts.plugins.is_empty()
+            });
+            let mut deps = deps.iter().filter_map(|d| indices.get(d).cloned())
+                                      .collect::<Vec<_>>();
+            deps.sort();
+            deps.dedup();
+            let outputs = if unit.profile.run_custom_build {
+                vec![cx.build_script_out_dir(unit)]
+            } else {
+                cx.target_filenames(unit)?.iter().map(|&(ref dst, _, _)| {
+                    dst.clone()
+                }).collect()
+            };
+            invocations.push(Invocation {
+                package_id: unit.pkg.package_id(),
+                target_name: unit.target.name(),
+                target_kind: unit.target.kind(),
+                kind: match unit.kind {
+                    Kind::Host => "host",
+                    Kind::Target => "target",
+                },
+                deps,
+                requires_build_script_output,
+                outputs,
+                program: cmd.get_program().to_string_lossy().into_owned(),
+                args: cmd.get_args().iter().map(|arg| {
+                    arg.to_string_lossy().into_owned()
+                }).collect(),
+                env: cmd.get_envs().iter().filter_map(|(key, value)| {
+                    value.as_ref().map(|v| (key.clone(), v.to_string_lossy().into_owned()))
+                }).collect(),
+                cwd: cmd.get_cwd().map(|p| p.to_path_buf()),
+            });
+        }
+
+        let plan = SerializedBuildPlan { invocations };
+        println!("{}", serde_json::to_string(&plan)?);
+        Ok(())
+    }
+
+    /// Orders the units with a command so that each one comes after all of
+    /// its dependencies. Units without a command of their own, like
+    /// overridden build scripts, are skipped over.
+    fn visit<'cfg>(&self,
+                   cx: &Context<'a, 'cfg>,
+                   unit: &Unit<'a>,
+                   visited: &mut HashSet<Unit<'a>>,
+                   order: &mut Vec<Unit<'a>>) -> CargoResult<()> {
+        if !visited.insert(*unit) {
+            return Ok(())
+        }
+        for dep in cx.dep_targets(unit)?.iter() {
+            self.visit(cx, dep, visited, order)?;
+        }
+        if self.commands.contains_key(unit) {
+            order.push(*unit);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,511 @@
+//! A cache of compiled dependencies shared between target directories.
+//!
+//! Libraries of registry and git packages are compiled the same way in every
+//! workspace using them, so with `build-cache.dir` or `build-cache.url` set
+//! in `.cargo/config` their outputs are stored in the cache after being
+//! compiled, and restored from it instead of running rustc when a unit with
+//! the same inputs is built again.
+//!
+//! The key of a unit covers everything which goes into its fingerprint and
+//! metadata hash, apart from paths local to a machine. Part of it is only
+//! known once the build is underway: the output of the package's build
+//! script and the keys of the dependencies, so keys are finished as the
+//! units are run. A unit depending on anything which can't be cached, like a
+//! path dependency, isn't cached either.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use curl::easy::Easy;
+use filetime::{self, FileTime};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use tar::{Archive, Builder};
+
+use core::PackageId;
+use ops;
+use util::{self, internal, paths, Config};
+use util::errors::{CargoResult, CargoResultExt};
+
+use super::custom_build::BuildState;
+use super::fingerprint;
+use super::job::Work;
+use super::{Context, Kind, TargetFileType, Unit};
+
+/// Where compiled artifacts are stored and looked up.
+///
+/// Entries are opaque blobs looked up by the hex string key of a unit.
+pub trait CacheBackend: Send + Sync {
+    /// Returns the entry for `key`, or `None` if it isn't cached.
+    fn get(&self, key: &str) -> CargoResult<Option<Vec<u8>>>;
+
+    /// Stores `data` as the entry for `key`.
+    fn put(&self, key: &str, data: &[u8]) -> CargoResult<()>;
+
+    /// Removes entries until the cache takes up at most `max_size` bytes,
+    /// returning how many were removed.
+    fn evict(&self, _max_size: u64) -> CargoResult<usize> {
+        Ok(0)
+    }
+}
+
+/// A cache in a local directory, with an entry per file.
+///
+/// The modification time of an entry is updated whenever it is used, so that
+/// the least recently used entries are the first to be evicted.
+pub struct LocalCache {
+    dir: PathBuf,
+}
+
+impl LocalCache {
+    pub fn new(dir: PathBuf) -> LocalCache {
+        LocalCache { dir }
+    }
+
+    fn entry(&self, key: &str) -> PathBuf {
+        self.dir.join(&key[..2]).join(format!("{}.tar.gz", key))
+    }
+}
+
+impl CacheBackend for LocalCache {
+    fn get(&self, key: &str) -> CargoResult<Option<Vec<u8>>> {
+        let path = self.entry(key);
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(_) => return Ok(None),
+        };
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).chain_err(|| {
+            format!("failed to read `{}`", path.display())
+        })?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let now = FileTime::from_seconds_since_1970(now.as_secs(), now.subsec_nanos());
+        // Failing to record the use only makes the entry go sooner
+        let _ = filetime::set_file_times(&path, now, now);
+        Ok(Some(data))
+    }
+
+    fn put(&self, key: &str, data: &[u8]) -> CargoResult<()> {
+        let path = self.entry(key);
+        fs::create_dir_all(path.parent().unwrap()).chain_err(|| {
+            format!("failed to create directory `{}`", self.dir.display())
+        })?;
+        // Other builds may be using the cache at the same time, so the entry
+        // only appears once it's complete.
+        let tmp = path.with_extension(format!("tmp{}", ::std::process::id()));
+        paths::write(&tmp, data)?;
+        fs::rename(&tmp, &path).chain_err(|| {
+            format!("failed to move `{}` into place", tmp.display())
+        })?;
+        Ok(())
+    }
+
+    fn evict(&self, max_size: u64) -> CargoResult<usize> {
+        let mut entries = Vec::new();
+        let dirs = match fs::read_dir(&self.dir) {
+            Ok(dirs) => dirs,
+            Err(_) => return Ok(0),
+        };
+        for dir in dirs {
+            let dir = dir?.path();
+            if !dir.is_dir() {
+                continue
+            }
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("gz") {
+                    continue
+                }
+                let meta = fs::metadata(&path)?;
+                entries.push((FileTime::from_last_modification_time(&meta),
+                              meta.len(), path));
+            }
+        }
+
+        let mut size = entries.iter().map(|e| e.1).sum::<u64>();
+        entries.sort();
+        let mut removed = 0;
+        for &(_, len, ref path) in entries.iter() {
+            if size <= max_size {
+                break
+            }
+            paths::remove_file(path)?;
+            size -= len;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+/// A cache served over HTTP, where entries are fetched with `GET` and stored
+/// with `PUT` requests to `<url>/<key>`.
+pub struct HttpCache {
+    url: String,
+    handle: Mutex<Easy>,
+}
+
+impl HttpCache {
+    pub fn new(url: &str, handle: Easy) -> HttpCache {
+        HttpCache {
+            url: url.trim_right_matches('/').to_string(),
+            handle: Mutex::new(handle),
+        }
+    }
+}
+
+impl CacheBackend for HttpCache {
+    fn get(&self, key: &str) -> CargoResult<Option<Vec<u8>>> {
+        let url = format!("{}/{}", self.url, key);
+        let mut handle = self.handle.lock().unwrap();
+        handle.get(true)?;
+        handle.url(&url)?;
+        let mut data = Vec::new();
+        {
+            let mut transfer = handle.transfer();
+            transfer.write_function(|buf| {
+                data.extend_from_slice(buf);
+                Ok(buf.len())
+            })?;
+            transfer.perform().chain_err(|| {
+                format!("failed to fetch `{}`", url)
+            })?;
+        }
+        match handle.response_code()? {
+            200 => Ok(Some(data)),
+            404 => Ok(None),
+            code => bail!("failed to fetch `{}`, got {}", url, code),
+        }
+    }
+
+    fn put(&self, key: &str, data: &[u8]) -> CargoResult<()> {
+        let url = format!("{}/{}", self.url, key);
+        let mut handle = self.handle.lock().unwrap();
+        handle.upload(true)?;
+        handle.in_filesize(data.len() as u64)?;
+        handle.url(&url)?;
+        let mut body = data;
+        {
+            let mut transfer = handle.transfer();
+            transfer.read_function(|buf| Ok(body.read(buf).unwrap_or(0)))?;
+            transfer.write_function(|buf| Ok(buf.len()))?;
+            transfer.perform().chain_err(|| {
+                format!("failed to upload to `{}`", url)
+            })?;
+        }
+        match handle.response_code()? {
+            code if code >= 200 && code < 300 => Ok(()),
+            code => bail!("failed to upload to `{}`, got {}", url, code),
+        }
+    }
+}
+
+/// The build cache of a compilation, along with the keys of the units built
+/// so far.
+pub struct BuildCache {
+    /// Looked up in order, with entries found in a later backend stored in
+    /// the earlier ones
+    backends: Vec<Box<CacheBackend>>,
+    max_size: Option<u64>,
+    /// The finished key of every unit which could be cached, by the part of
+    /// the key known up front
+    keys: Mutex<HashMap<String, String>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl BuildCache {
+    pub fn new(backends: Vec<Box<CacheBackend>>, max_size: Option<u64>) -> BuildCache {
+        BuildCache {
+            backends,
+            max_size,
+            keys: Mutex::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Sets up the cache configured in `build-cache`, if there is any.
+    pub fn from_config(config: &Config) -> CargoResult<Option<BuildCache>> {
+        let mut backends = Vec::<Box<CacheBackend>>::new();
+        if let Some(dir) = config.get_path("build-cache.dir")? {
+            backends.push(Box::new(LocalCache::new(dir.val)));
+        }
+        if let Some(url) = config.get_string("build-cache.url")? {
+            // Without network access only the local cache is used
+            if config.network_allowed() && !config.frozen() {
+                let handle = ops::http_handle(config)?;
+                backends.push(Box::new(HttpCache::new(&url.val, handle)));
+            }
+        }
+        let max_size = match config.get_string("build-cache.max-size")? {
+            Some(s) => {
+                Some(parse_size(&s.val).ok_or_else(|| {
+                    format_err!("`build-cache.max-size` must be a size like \
+                                 \"10GiB\" or \"500MB\", but found `{}` in {}",
+                                s.val, s.definition)
+                })?)
+            }
+            None => None,
+        };
+        if backends.is_empty() {
+            return Ok(None)
+        }
+        Ok(Some(BuildCache::new(backends, max_size)))
+    }
+
+    /// Reports how much the cache was used, and evicts entries going over
+    /// `build-cache.max-size`.
+    pub fn finish(&self, config: &Config) -> CargoResult<()> {
+        let hits = self.hits.load(Ordering::SeqCst);
+        let misses = self.misses.load(Ordering::SeqCst);
+        if hits + misses > 0 {
+            config.shell().status("Cache", format!(
+                "{} {}, {} {}",
+                hits, if hits == 1 { "hit" } else { "hits" },
+                misses, if misses == 1 { "miss" } else { "misses" }))?;
+        }
+        if let Some(max_size) = self.max_size {
+            let mut removed = 0;
+            for backend in self.backends.iter() {
+                removed += backend.evict(max_size)?;
+            }
+            if removed > 0 {
+                config.shell().verbose(|shell| {
+                    shell.status("Evicted", format!("{} entries from the build cache", removed))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> CargoResult<Option<Vec<u8>>> {
+        for (i, backend) in self.backends.iter().enumerate() {
+            if let Some(data) = backend.get(key)? {
+                for backend in self.backends[..i].iter() {
+                    backend.put(key, &data)?;
+                }
+                return Ok(Some(data))
+            }
+        }
+        Ok(None)
+    }
+
+    fn put(&self, key: &str, data: &[u8]) -> CargoResult<()> {
+        for backend in self.backends.iter() {
+            backend.put(key, data)?;
+        }
+        Ok(())
+    }
+
+    /// Finishes the key of a unit now that everything it depends on has been
+    /// built, returning `None` if anything it depends on couldn't be cached.
+    fn finish_key(&self, unit: &UnitKey, build_state: &BuildState) -> Option<String> {
+        let keys = self.keys.lock().unwrap();
+        let deps = unit.deps.iter().map(|dep| keys.get(dep)).collect::<Option<Vec<_>>>()?;
+        let outputs = build_state.outputs.lock().unwrap();
+        let build_output = outputs.get(&(unit.pkg.clone(), unit.kind)).map(|output| {
+            // The search paths point into the target directory, and only
+            // matter when linking anyway
+            (&output.library_links, &output.cfgs, &output.env)
+        });
+        Some(util::short_hash(&(&unit.key, deps, build_output)))
+    }
+
+    fn record(&self, unit: &UnitKey, key: String) {
+        self.keys.lock().unwrap().insert(unit.key.clone(), key);
+    }
+}
+
+/// What's needed to finish the key of a unit while the build runs.
+struct UnitKey {
+    /// The part of the key known up front
+    key: String,
+    /// The keys known up front of the libraries the unit depends on
+    deps: Vec<String>,
+    pkg: PackageId,
+    kind: Kind,
+}
+
+/// Wraps the `work` compiling `unit`, so its outputs are restored from the
+/// build cache instead when possible, and stored there otherwise.
+///
+/// Returns the work to run when the unit is dirty, and the work to run along
+/// with the usual when it's fresh.
+pub fn prepare<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
+                         unit: &Unit<'a>,
+                         work: Work) -> CargoResult<(Work, Work)> {
+    let cache = match cx.build_cache {
+        Some(ref cache) if cacheable(cx, unit)? => Arc::clone(cache),
+        _ => return Ok((work, Work::noop())),
+    };
+
+    let mut deps = Vec::new();
+    for dep in cx.dep_targets(unit)?.iter() {
+        if dep.target.is_custom_build() || dep.target.is_bin() {
+            continue
+        }
+        if !cacheable(cx, dep)? {
+            return Ok((work, Work::noop()))
+        }
+        deps.push(static_key(cx, dep)?);
+    }
+    deps.sort();
+    let unit_key = Arc::new(UnitKey {
+        key: static_key(cx, unit)?,
+        deps,
+        pkg: unit.pkg.package_id().clone(),
+        kind: unit.kind,
+    });
+
+    let out_dir = cx.out_dir(unit);
+    let dep_info = fingerprint::dep_info_loc(cx, unit);
+    let outputs = cx.target_filenames(unit)?.iter().filter(|f| {
+        f.2 != TargetFileType::DebugInfo
+    }).map(|f| f.0.clone()).collect::<Vec<_>>();
+    let name = unit.pkg.name().to_string();
+
+    let build_state = Arc::clone(&cx.build_state);
+    let fresh_state = Arc::clone(&cx.build_state);
+    let fresh_cache = Arc::clone(&cache);
+    let fresh_key = Arc::clone(&unit_key);
+
+    let dirty = Work::new(move |state| {
+        let key = match cache.finish_key(&unit_key, &build_state) {
+            Some(key) => key,
+            None => return work.call(state),
+        };
+
+        match cache.get(&key).and_then(|data| {
+            match data {
+                Some(data) => unpack(&data, &out_dir, &dep_info).map(|_| true),
+                None => Ok(false),
+            }
+        }) {
+            Ok(true) => {
+                cache.hits.fetch_add(1, Ordering::SeqCst);
+                cache.record(&unit_key, key);
+                return Ok(())
+            }
+            Ok(false) => {}
+            Err(e) => {
+                state.stderr(&format!("warning: failed to restore `{}` from the \
+                                       build cache: {}", name, e));
+            }
+        }
+        cache.misses.fetch_add(1, Ordering::SeqCst);
+
+        work.call(state)?;
+        if let Err(e) = pack(&outputs, &dep_info).and_then(|data| cache.put(&key, &data)) {
+            state.stderr(&format!("warning: failed to store `{}` in the build \
+                                   cache: {}", name, e));
+        }
+        cache.record(&unit_key, key);
+        Ok(())
+    });
+
+    let fresh = Work::new(move |_| {
+        if let Some(key) = fresh_cache.finish_key(&fresh_key, &fresh_state) {
+            fresh_cache.record(&fresh_key, key);
+        }
+        Ok(())
+    });
+
+    Ok((dirty, fresh))
+}
+
+/// Whether the outputs of `unit` can be shared with other builds.
+fn cacheable<'a, 'cfg>(cx: &Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoResult<bool> {
+    let profile = &unit.profile;
+    Ok(!unit.pkg.package_id().source_id().is_path() &&
+       unit.target.is_lib() &&
+       !profile.test && !profile.doc && !profile.run_custom_build &&
+       profile.rustc_args.is_none() &&
+       cx.incremental_args(unit)?.is_empty())
+}
+
+/// The part of the key of `unit` which is known before the build starts.
+fn static_key<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoResult<String> {
+    let pkg = unit.pkg;
+    let target = unit.target;
+    let src_path = target.src_path().strip_prefix(pkg.root()).unwrap_or(target.src_path());
+    let triple = match unit.kind {
+        Kind::Host => cx.host_triple().to_string(),
+        Kind::Target => cx.target_triple().to_string(),
+    };
+    let metadata = cx.target_metadata(unit);
+    Ok(util::short_hash(&(
+        ::version().to_string(),
+        pkg.package_id().to_string(),
+        pkg.package_id().source_id().precise(),
+        pkg.summary().checksum(),
+        fingerprint::pkg_fingerprint(cx, pkg)?,
+        (target.name(), target.kind(), target.rustc_crate_types(), src_path),
+        (unit.profile, cx.unit_features(unit), triple),
+        (&cx.config.rustc()?.verbose_version, cx.rustflags_args(unit)?),
+        cx.config.env_config()?,
+        pkg.manifest().epoch(),
+        metadata,
+    )))
+}
+
+/// Archives the outputs of a unit and its dep-info.
+fn pack(outputs: &[PathBuf], dep_info: &Path) -> CargoResult<Vec<u8>> {
+    let encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let mut ar = Builder::new(encoder);
+    for output in outputs.iter().filter(|o| o.exists()) {
+        let name = output.file_name().ok_or_else(|| internal("output without a file name"))?;
+        ar.append_path_with_name(output, Path::new("out").join(name))?;
+    }
+    if dep_info.exists() {
+        ar.append_path_with_name(dep_info, "dep-info")?;
+    }
+    Ok(ar.into_inner()?.finish()?)
+}
+
+/// Extracts an archive made by `pack` into the output directory of a unit.
+fn unpack(data: &[u8], out_dir: &Path, dep_info: &Path) -> CargoResult<()> {
+    let mut ar = Archive::new(GzDecoder::new(data));
+    for entry in ar.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let dst = if path == Path::new("dep-info") {
+            dep_info.to_path_buf()
+        } else {
+            match (path.strip_prefix("out").ok(), path.file_name()) {
+                (Some(rest), Some(name)) if rest == Path::new(name) => out_dir.join(name),
+                _ => bail!("unexpected file `{}` in the cache entry", path.display()),
+            }
+        };
+        fs::create_dir_all(dst.parent().unwrap())?;
+        if dst.exists() {
+            paths::remove_file(&dst)?;
+        }
+        entry.unpack(&dst)?;
+    }
+    Ok(())
+}
+
+/// Parses a size like `500MB` or `10GiB` into bytes.
+fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_digit(10)).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number = number.parse::<u64>().ok()?;
+    let multiplier = match &unit.trim().to_lowercase()[..] {
+        "" | "b" => 1,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        _ => return None,
+    };
+    number.checked_mul(multiplier)
+}
@@ -175,6 +175,14 @@ impl<'cfg> Compilation<'cfg> {
            .env("CARGO_PKG_HOMEPAGE", metadata.homepage.as_ref().unwrap_or(&String::new()))
            .env("CARGO_PKG_AUTHORS", &pkg.authors().join(":"))
            .cwd(pkg.root());
+
+        // Variables from the `[env]` configuration never override the ones
+        // Cargo sets itself
+        for (key, value) in self.config.env_config()?.iter() {
+            if !cmd.get_envs().contains_key(key) {
+                cmd.env(key, value);
+            }
+        }
         Ok(cmd)
     }
 }
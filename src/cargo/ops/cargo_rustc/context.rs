@@ -13,13 +13,16 @@ use std::cell::RefCell;
 use jobserver::Client;
 
 use core::{Package, PackageId, PackageSet, Resolve, Target, Profile};
-use core::{TargetKind, Profiles, Dependency, Workspace};
+use core::{TargetKind, Profiles, Dependency, Workspace, ResolveBehavior};
 use core::dependency::Kind as DepKind;
-use util::{self, ProcessBuilder, internal, Config, profile, Cfg, CfgExpr};
+use util::{self, ProcessBuilder, internal, Config, profile, Cfg, CfgExpr, RustVersion};
 use util::errors::{CargoResult, CargoResultExt};
 
 use super::TargetConfig;
+use super::build_plan::BuildPlan;
+use super::cache::BuildCache;
 use super::custom_build::{BuildState, BuildScripts, BuildDeps};
+use super::features::{self, FeaturesFor, ResolvedFeatures};
 use super::fingerprint::Fingerprint;
 use super::layout::Layout;
 use super::links::Links;
@@ -58,6 +61,10 @@ pub struct Unit<'a> {
     /// the host architecture so the host rustc can use it (when compiling to the target
     /// architecture).
     pub kind: Kind,
+    /// Which feature set of `pkg` to build with. With `resolver = "2"` the features of build
+    /// dependencies are kept apart from those of the final artifacts, see
+    /// `Context::unit_features`.
+    pub features_for: FeaturesFor,
 }
 
 /// Type of each file generated by a Unit.
@@ -92,6 +99,11 @@ pub struct Context<'a, 'cfg: 'a> {
     pub links: Links<'a>,
     pub used_in_plugin: HashSet<Unit<'a>>,
     pub jobserver: Client,
+    /// The commands recorded instead of being run, with `--build-plan`
+    pub build_plan: BuildPlan<'a>,
+    /// Where the outputs of registry and git dependencies are shared, if
+    /// `build-cache` is configured
+    pub build_cache: Option<Arc<BuildCache>>,
 
     /// The target directory layout for the host (and target if it is the same as host)
     host: Layout,
@@ -101,6 +113,8 @@ pub struct Context<'a, 'cfg: 'a> {
     host_info: TargetInfo,
     profiles: &'a Profiles,
     incremental_env: Option<bool>,
+    /// Features resolved per kind, with `resolver = "2"`
+    resolved_features: Option<ResolvedFeatures>,
 
     /// For each Unit, a list all files produced as a triple of
     ///
@@ -147,10 +161,15 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                build_config: BuildConfig,
                profiles: &'a Profiles) -> CargoResult<Context<'a, 'cfg>> {
 
-        let dest = if build_config.release { "release" } else { "debug" };
-        let host_layout = Layout::new(ws, None, dest)?;
+        // Custom profiles each get a directory of their own
+        let dest = match build_config.profile {
+            Some(ref name) => name.clone(),
+            None if build_config.release => "release".to_string(),
+            None => "debug".to_string(),
+        };
+        let host_layout = Layout::new(ws, None, &dest)?;
         let target_layout = match build_config.requested_target.as_ref() {
-            Some(target) => Some(Layout::new(ws, Some(target), dest)?),
+            Some(target) => Some(Layout::new(ws, Some(target), &dest)?),
             None => None,
         };
 
@@ -185,6 +204,8 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             host_info: TargetInfo::default(),
             compilation: Compilation::new(config),
             build_state: Arc::new(BuildState::new(&build_config)),
+            build_plan: BuildPlan::new(build_config.build_plan),
+            build_cache: BuildCache::from_config(config)?.map(Arc::new),
             build_config,
             fingerprints: HashMap::new(),
             profiles,
@@ -194,6 +215,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             links: Links::new(),
             used_in_plugin: HashSet::new(),
             incremental_env,
+            resolved_features: None,
             jobserver,
             build_script_overridden: HashSet::new(),
 
@@ -248,6 +270,60 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         Ok(())
     }
 
+    /// Resolves the features of each package separately for build
+    /// dependencies and the final artifacts if the workspace asks for it with
+    /// `resolver = "2"`.
+    ///
+    /// This needs the target information from `probe_target_info`, and until
+    /// it's called every unit uses the features from the `Resolve`.
+    pub fn resolve_features(&mut self, units: &[Unit<'a>]) -> CargoResult<()> {
+        if self.ws.resolve_behavior() == ResolveBehavior::V2 {
+            let resolved = features::resolve(self, units)?;
+            self.resolved_features = Some(resolved);
+        }
+        Ok(())
+    }
+
+    /// Returns the features `unit` is compiled with, sorted by name.
+    pub fn unit_features(&self, unit: &Unit<'a>) -> Vec<&str> {
+        let id = unit.pkg.package_id();
+        match self.resolved_features {
+            Some(ref features) => features.features(id, unit.features_for),
+            None => self.resolve.features_sorted(id),
+        }
+    }
+
+    /// Returns whether the optional dependency `dep` of `unit`'s package is
+    /// enabled for `unit`.
+    fn dep_activated(&self, unit: &Unit<'a>, dep: &Dependency) -> bool {
+        let id = unit.pkg.package_id();
+        match self.resolved_features {
+            Some(ref features) => {
+                features.is_dep_activated(id, unit.features_for, dep.name())
+            }
+            // Dependencies enabled with `dep:` have no feature, but are only
+            // in the resolve graph at all if they were enabled.
+            None => {
+                unit.pkg.summary().is_namespaced_dep(dep.name()) ||
+                    self.resolve.features(id).contains(dep.name())
+            }
+        }
+    }
+
+    /// Returns the feature set of a dependency `dep` of `unit` using `target`.
+    fn dep_features_for(&self,
+                        unit: &Unit<'a>,
+                        dep: &PackageId,
+                        target: &Target) -> FeaturesFor {
+        match self.resolved_features {
+            Some(ref features) => {
+                let features_for = unit.features_for.for_dep(unit.target, target);
+                features.features_for(dep, features_for)
+            }
+            None => FeaturesFor::Normal,
+        }
+    }
+
     /// A recursive function that checks all crate types (`rlib`, ...) are in `crate_types`
     /// for this unit and its dependencies.
     ///
@@ -456,6 +532,43 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         }
     }
 
+    /// Returns the scratch directory of a package, which its integration tests
+    /// and benchmarks are free to write to.
+    pub fn target_tmpdir(&self, pkg: &Package) -> PathBuf {
+        self.ws.target_dir().into_path_unlocked().join("tmp").join(pkg.name())
+    }
+
+    /// Returns the environment variables set when compiling an integration
+    /// test or benchmark, `CARGO_BIN_EXE_<name>` with the path of each binary
+    /// of its package and `CARGO_TARGET_TMPDIR` with its scratch directory.
+    pub fn test_env(&mut self, unit: &Unit<'a>) -> CargoResult<Vec<(String, PathBuf)>> {
+        let mut env = Vec::new();
+        if !unit.profile.test || !(unit.target.is_test() || unit.target.is_bench()) {
+            return Ok(env)
+        }
+        for dep in self.dep_targets(unit)?.iter().filter(|u| u.target.is_bin()) {
+            // `cargo check` only produces metadata for the binary, but the
+            // path is still where the executable would be built
+            let bin = if dep.profile.check {
+                let pkg = dep.pkg.package_id();
+                let profile = self.profiles.for_package(self.lib_profile(), pkg, false);
+                Unit { profile, ..*dep }
+            } else {
+                *dep
+            };
+            let filenames = self.target_filenames(&bin)?;
+            let exe = filenames.iter().find(|&&(_, _, file_type)| {
+                file_type != TargetFileType::DebugInfo
+            });
+            if let Some(&(ref dst, ref link_dst, _)) = exe {
+                let path = link_dst.as_ref().unwrap_or(dst).clone();
+                env.push((format!("CARGO_BIN_EXE_{}", dep.target.name()), path));
+            }
+        }
+        env.push(("CARGO_TARGET_TMPDIR".to_string(), self.target_tmpdir(unit.pkg)));
+        Ok(env)
+    }
+
     fn pkg_dir(&mut self, unit: &Unit<'a>) -> String {
         let name = unit.pkg.package_id().name();
         match self.target_metadata(unit) {
@@ -546,7 +659,10 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
 
         // Also mix in enabled features to our metadata. This'll ensure that
         // when changing feature sets each lib is separately cached.
-        self.resolve.features_sorted(unit.pkg.package_id()).hash(&mut hasher);
+        self.unit_features(unit).hash(&mut hasher);
+        if unit.features_for != FeaturesFor::Normal {
+            unit.features_for.hash(&mut hasher);
+        }
 
         // Mix in the target-metadata of all the dependencies of this target
         if let Ok(deps) = self.dep_targets(unit) {
@@ -769,6 +885,75 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         Ok(Arc::new(ret))
     }
 
+    /// Downloads all of the packages which `units` transitively depend on.
+    ///
+    /// The unit graph is walked one level at a time and the packages needed
+    /// by each level are fetched together, rather than one by one as
+    /// `dep_targets` would otherwise load them.
+    pub fn download_deps(&self, units: &[Unit<'a>]) -> CargoResult<()> {
+        let mut visited = HashSet::new();
+        let mut level = units.to_vec();
+        while !level.is_empty() {
+            let ids = level.iter().flat_map(|unit| {
+                self.dep_package_ids(unit)
+            }).collect::<Vec<_>>();
+            self.packages.get_many(ids)?;
+
+            let mut next = Vec::new();
+            for unit in level {
+                if visited.insert(unit) {
+                    next.extend(self.dep_targets(&unit)?);
+                }
+            }
+            level = next;
+        }
+        Ok(())
+    }
+
+    /// Fails if any of the packages which would be built declares a
+    /// `rust-version` newer than the compiler in use.
+    pub fn check_rust_version(&self, units: &[Unit<'a>]) -> CargoResult<()> {
+        let rustc = RustVersion::from(&self.config.rustc()?.version);
+        let mut visited = HashSet::new();
+        let mut level = units.to_vec();
+        while !level.is_empty() {
+            let mut next = Vec::new();
+            for unit in level {
+                if !visited.insert(unit) {
+                    continue
+                }
+                if let Some(required) = unit.pkg.manifest().rust_version() {
+                    if !required.is_compatible_with(&rustc) {
+                        bail!("package `{}` cannot be built because it requires \
+                               rustc {} or newer, while the currently active \
+                               rustc version is {}\n\
+                               Use `--ignore-rust-version` to build anyway",
+                              unit.pkg.package_id(), required, rustc)
+                    }
+                }
+                next.extend(self.dep_targets(&unit)?);
+            }
+            level = next;
+        }
+        Ok(())
+    }
+
+    /// Returns the packages which `dep_targets` may load for `unit`.
+    ///
+    /// This errs on the side of including too much, as anything missed here
+    /// is simply downloaded later on when it's needed.
+    fn dep_package_ids(&self, unit: &Unit<'a>) -> Vec<&'a PackageId> {
+        self.resolve.deps(unit.pkg.package_id()).filter(|dep| {
+            unit.pkg.dependencies().iter().filter(|d| {
+                d.name() == dep.name() && d.version_req().matches(dep.version())
+            }).any(|d| {
+                let needed = d.is_transitive() || unit.target.is_test() ||
+                             unit.target.is_example() || unit.profile.test;
+                needed && self.dep_platform_activated(d, unit.kind)
+            })
+        }).collect()
+    }
+
     /// For a package, return all targets which are registered as dependencies
     /// for that package.
     pub fn dep_targets(&self, unit: &Unit<'a>) -> CargoResult<Vec<Unit<'a>>> {
@@ -806,7 +991,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
 
                 // If the dependency is optional, then we're only activating it
                 // if the corresponding feature was activated
-                if d.is_optional() && !self.resolve.features(id).contains(d.name()) {
+                if d.is_optional() && !self.dep_activated(unit, d) {
                     return false;
                 }
 
@@ -821,8 +1006,9 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                         let unit = Unit {
                             pkg,
                             target: t,
-                            profile: self.lib_or_check_profile(unit, t),
+                            profile: self.lib_or_check_profile(unit, id, t),
                             kind: unit.kind.for_target(t),
+                            features_for: self.dep_features_for(unit, id, t),
                         };
                         Ok(unit)
                     })
@@ -851,20 +1037,22 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         // Integration tests/benchmarks require binaries to be built
         if unit.profile.test &&
            (unit.target.is_test() || unit.target.is_bench()) {
+            let features = self.unit_features(unit);
             ret.extend(unit.pkg.targets().iter().filter(|t| {
                 let no_required_features = Vec::new();
 
                 t.is_bin() &&
                 // Skip binaries with required features that have not been selected.
                 t.required_features().unwrap_or(&no_required_features).iter().all(|f| {
-                    self.resolve.features(id).contains(f)
+                    features.contains(&&f[..])
                 })
             }).map(|t| {
                 Unit {
                     pkg: unit.pkg,
                     target: t,
-                    profile: self.lib_or_check_profile(unit, t),
+                    profile: self.lib_or_check_profile(unit, id, t),
                     kind: unit.kind.for_target(t),
+                    features_for: unit.features_for,
                 }
             }));
         }
@@ -940,8 +1128,9 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             ret.push(Unit {
                 pkg: dep,
                 target: lib,
-                profile: self.lib_or_check_profile(unit, lib),
+                profile: self.lib_or_check_profile(unit, dep.package_id(), lib),
                 kind: unit.kind.for_target(lib),
+                features_for: self.dep_features_for(unit, dep.package_id(), lib),
             });
             if self.build_config.doc_all {
                 ret.push(Unit {
@@ -949,6 +1138,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                     target: lib,
                     profile: &self.profiles.doc,
                     kind: unit.kind.for_target(lib),
+                    features_for: self.dep_features_for(unit, dep.package_id(), lib),
                 });
             }
         }
@@ -977,6 +1167,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                 target: t,
                 profile: &self.profiles.custom_build,
                 kind: unit.kind,
+                features_for: unit.features_for,
             }
         })
     }
@@ -986,13 +1177,14 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             Unit {
                 pkg: unit.pkg,
                 target: t,
-                profile: self.lib_or_check_profile(unit, t),
+                profile: self.lib_or_check_profile(unit, unit.pkg.package_id(), t),
                 kind: unit.kind.for_target(t),
+                features_for: unit.features_for,
             }
         })
     }
 
-    fn dep_platform_activated(&self, dep: &Dependency, kind: Kind) -> bool {
+    pub fn dep_platform_activated(&self, dep: &Dependency, kind: Kind) -> bool {
         // If this dependency is only available for certain platforms,
         // make sure we're only enabling it for that platform.
         let platform = match dep.platform() {
@@ -1054,18 +1246,30 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         }
     }
 
-    pub fn lib_or_check_profile(&self, unit: &Unit, target: &Target) -> &'a Profile {
-        if !target.is_custom_build() && !target.for_host()
+    /// Returns the profile to build `target` of `pkg` with, when it's a
+    /// dependency of `unit`.
+    pub fn lib_or_check_profile(&self,
+                                unit: &Unit,
+                                pkg: &PackageId,
+                                target: &Target) -> &'a Profile {
+        let base = if !target.is_custom_build() && !target.for_host()
             && (unit.profile.check || (unit.profile.doc && !unit.profile.test)) {
-                return &self.profiles.check
-        }
-        self.lib_profile()
+            &self.profiles.check
+        } else {
+            self.lib_profile()
+        };
+        // Procedural macros, and anything which build scripts or procedural
+        // macros depend on, use `build-override` just like build scripts.
+        let for_host = target.for_host() ||
+                       unit.target.is_custom_build() ||
+                       self.profiles.is_build_override(unit.profile);
+        self.profiles.for_package(base, pkg, for_host)
     }
 
-    pub fn build_script_profile(&self, _pkg: &PackageId) -> &'a Profile {
+    pub fn build_script_profile(&self, pkg: &PackageId) -> &'a Profile {
         // TODO: should build scripts always be built with the same library
         //       profile? How is this controlled at the CLI layer?
-        self.lib_profile()
+        self.profiles.for_package(self.lib_profile(), pkg, true)
     }
 
     pub fn incremental_args(&self, unit: &Unit) -> CargoResult<Vec<String>> {
@@ -111,7 +111,8 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     // Start preparing the process to execute, starting out with some
     // environment variables. Note that the profile-related environment
     // variables are not set with this the build script's profile but rather the
-    // package's library profile.
+    // package's library profile. The variables of the `[env]` configuration
+    // are set by `host_process`, and those below take precedence over them.
     let profile = cx.lib_profile();
     let to_exec = to_exec.into_os_string();
     let mut cmd = cx.compilation.host_process(to_exec, unit.pkg)?;
@@ -136,7 +137,7 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
 
     // Be sure to pass along all enabled features for this package, this is the
     // last piece of statically known information that we have.
-    for feat in cx.resolve.features(unit.pkg.package_id()).iter() {
+    for feat in cx.unit_features(unit) {
         cmd.env(&format!("CARGO_FEATURE_{}", super::envify(feat)), "1");
     }
 
@@ -158,6 +159,7 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
             None => { cmd.env(&k, ""); }
         }
     }
+    cx.build_plan.add(unit, &cmd);
 
     // Gather the set of native dependencies that this package has along with
     // some other variables to close over.
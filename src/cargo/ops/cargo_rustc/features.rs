@@ -0,0 +1,316 @@
+//! Feature resolution for workspaces using `resolver = "2"`.
+//!
+//! The dependency resolver unifies the features of a package across every
+//! place it's used, so a feature enabled through a build dependency, a
+//! dev-dependency or a dependency for another platform is enabled everywhere.
+//! Once the `cfg` values of the host and target are known this walks the
+//! resolve graph again, following only the edges which are actually built,
+//! and records the features of each package separately for what's linked
+//! into the final artifacts and what's only used while building.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use core::{FeatureValue, Package, PackageId, Dependency, Target};
+use core::dependency::Kind as DepKind;
+use util::errors::CargoResult;
+
+use super::{Context, Kind, Unit};
+
+/// Which of the feature sets of a package a `Unit` is built with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FeaturesFor {
+    /// Packages linked into the final artifacts.
+    Normal,
+    /// Build scripts, proc-macros and plugins, and everything they depend on.
+    ///
+    /// Only used with `resolver = "2"`, otherwise every unit is `Normal`.
+    HostDep,
+}
+
+impl FeaturesFor {
+    /// The feature set of a dependency using `target` of a unit which builds
+    /// `parent` with these features.
+    pub fn for_dep(self, parent: &Target, target: &Target) -> FeaturesFor {
+        if self == FeaturesFor::HostDep || parent.for_host() || target.for_host() {
+            FeaturesFor::HostDep
+        } else {
+            FeaturesFor::Normal
+        }
+    }
+}
+
+/// The features activated for each package, per feature set.
+pub struct ResolvedFeatures {
+    activated: HashMap<(PackageId, FeaturesFor), Activated>,
+    /// Packages which are built with the same features, as are all of their
+    /// dependencies, whether they're used while building or not.
+    unified: HashSet<PackageId>,
+}
+
+#[derive(Default)]
+struct Activated {
+    /// Enabled features, including the implicit features of enabled optional
+    /// dependencies.
+    features: BTreeSet<String>,
+    /// Enabled optional dependencies.
+    deps: HashSet<String>,
+    /// `dep?/feat` values waiting for `dep` to be enabled.
+    weak: Vec<(String, String)>,
+}
+
+impl ResolvedFeatures {
+    /// The features of `pkg` in the feature set `features_for`, sorted by
+    /// name.
+    pub fn features(&self, pkg: &PackageId, features_for: FeaturesFor) -> Vec<&str> {
+        match self.activated.get(&(pkg.clone(), features_for)) {
+            Some(a) => a.features.iter().map(|s| &s[..]).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The feature set `pkg` is built with when used as `features_for`.
+    ///
+    /// A package whose `HostDep` features turn out to be the same as its
+    /// `Normal` ones is built as `Normal`, so it's only compiled once.
+    pub fn features_for(&self, pkg: &PackageId, features_for: FeaturesFor) -> FeaturesFor {
+        if features_for == FeaturesFor::HostDep && self.unified.contains(pkg) {
+            FeaturesFor::Normal
+        } else {
+            features_for
+        }
+    }
+
+    /// Whether the optional dependency `dep` of `pkg` is enabled in the
+    /// feature set `features_for`.
+    pub fn is_dep_activated(&self,
+                            pkg: &PackageId,
+                            features_for: FeaturesFor,
+                            dep: &str) -> bool {
+        self.activated.get(&(pkg.clone(), features_for)).map_or(false, |a| {
+            a.deps.contains(dep)
+        })
+    }
+}
+
+/// Resolves the features of everything needed to build `units`.
+///
+/// The features requested for each of the `units` are the ones the
+/// dependency resolver settled on for it.
+pub fn resolve<'a, 'cfg>(cx: &Context<'a, 'cfg>, units: &[Unit<'a>])
+                         -> CargoResult<ResolvedFeatures> {
+    // Dev-dependencies only matter to packages whose tests, examples or
+    // benchmarks are being built
+    let dev_deps = units.iter().filter(|unit| {
+        unit.profile.test || unit.target.is_test() ||
+            unit.target.is_example() || unit.target.is_bench()
+    }).map(|unit| unit.pkg.package_id()).collect();
+
+    let mut resolver = FeatureResolver {
+        cx,
+        dev_deps,
+        activated: HashMap::new(),
+    };
+    for unit in units {
+        let features = cx.resolve.features_sorted(unit.pkg.package_id())
+                                 .into_iter()
+                                 .map(|s| s.to_string())
+                                 .collect::<Vec<_>>();
+        let node = Node { features_for: unit.features_for, kind: unit.kind };
+        resolver.activate_pkg(unit.pkg, node, &features)?;
+    }
+    let unified = unified(cx, &resolver.activated);
+    Ok(ResolvedFeatures { activated: resolver.activated, unified })
+}
+
+/// Finds the packages which have the same features in both feature sets, and
+/// whose dependencies all do too.
+fn unified(cx: &Context, activated: &HashMap<(PackageId, FeaturesFor), Activated>)
+           -> HashSet<PackageId> {
+    let mut unified = activated.keys().filter(|&&(ref id, features_for)| {
+        features_for == FeaturesFor::HostDep &&
+            activated.get(&(id.clone(), FeaturesFor::Normal)).map_or(false, |normal| {
+                let host = &activated[&(id.clone(), FeaturesFor::HostDep)];
+                normal.features == host.features && normal.deps == host.deps
+            })
+    }).map(|&(ref id, _)| id.clone()).collect::<HashSet<_>>();
+    loop {
+        let split = unified.iter().filter(|id| {
+            cx.resolve.deps(id).any(|dep| {
+                activated.contains_key(&(dep.clone(), FeaturesFor::HostDep)) &&
+                    !unified.contains(dep)
+            })
+        }).cloned().collect::<Vec<_>>();
+        if split.is_empty() {
+            return unified
+        }
+        for id in split {
+            unified.remove(&id);
+        }
+    }
+}
+
+/// A package is resolved once per feature set, and the `kind` selects which
+/// platform-specific dependencies it has.
+#[derive(Clone, Copy)]
+struct Node {
+    features_for: FeaturesFor,
+    kind: Kind,
+}
+
+struct FeatureResolver<'a: 'r, 'cfg: 'a, 'r> {
+    cx: &'r Context<'a, 'cfg>,
+    dev_deps: HashSet<&'a PackageId>,
+    activated: HashMap<(PackageId, FeaturesFor), Activated>,
+}
+
+impl<'a, 'cfg, 'r> FeatureResolver<'a, 'cfg, 'r> {
+    fn activate_pkg(&mut self,
+                    pkg: &'a Package,
+                    node: Node,
+                    features: &[String]) -> CargoResult<()> {
+        let key = (pkg.package_id().clone(), node.features_for);
+        if !self.activated.contains_key(&key) {
+            self.activated.insert(key, Activated::default());
+            // Required dependencies are enabled whatever the features are
+            for (dep_pkg, dep, dep_node) in self.deps(pkg, node)? {
+                if !dep.is_optional() {
+                    self.activate_dep(dep_pkg, dep, dep_node, &[])?;
+                }
+            }
+        }
+        for feature in features {
+            self.activate_fv(pkg, node, feature)?;
+        }
+        Ok(())
+    }
+
+    fn activate_dep(&mut self,
+                    pkg: &'a Package,
+                    dep: &Dependency,
+                    node: Node,
+                    extra: &[String]) -> CargoResult<()> {
+        let mut features = dep.features().to_vec();
+        if dep.uses_default_features() &&
+           pkg.summary().features().contains_key("default") {
+            features.push("default".to_string());
+        }
+        features.extend(extra.iter().cloned());
+        self.activate_pkg(pkg, node, &features)
+    }
+
+    fn activate_fv(&mut self,
+                   pkg: &'a Package,
+                   node: Node,
+                   value: &str) -> CargoResult<()> {
+        match FeatureValue::new(value) {
+            FeatureValue::Feature(feature) => {
+                match pkg.summary().features().get(feature) {
+                    Some(values) => {
+                        if !self.entry(pkg, node).features.insert(feature.to_string()) {
+                            return Ok(())
+                        }
+                        for value in values {
+                            self.activate_fv(pkg, node, value)?;
+                        }
+                    }
+                    // The implicit feature of an optional dependency
+                    None => self.activate_optional_dep(pkg, node, feature)?,
+                }
+            }
+            FeatureValue::Dep(dep) => {
+                self.activate_optional_dep(pkg, node, dep)?;
+            }
+            FeatureValue::DepFeature { dep, feature, weak } => {
+                if weak && !self.entry(pkg, node).deps.contains(dep) {
+                    self.entry(pkg, node).weak.push((dep.to_string(),
+                                                     feature.to_string()));
+                    return Ok(())
+                }
+                if !weak {
+                    self.activate_optional_dep(pkg, node, dep)?;
+                }
+                let feature = [feature.to_string()];
+                for (dep_pkg, d, dep_node) in self.deps(pkg, node)? {
+                    if d.name() == dep {
+                        self.activate_dep(dep_pkg, d, dep_node, &feature)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn activate_optional_dep(&mut self,
+                             pkg: &'a Package,
+                             node: Node,
+                             name: &str) -> CargoResult<()> {
+        if !pkg.dependencies().iter().any(|d| d.name() == name && d.is_optional()) {
+            return Ok(())
+        }
+        let weak = {
+            let activated = self.entry(pkg, node);
+            if !activated.deps.insert(name.to_string()) {
+                return Ok(())
+            }
+            if !pkg.summary().is_namespaced_dep(name) {
+                activated.features.insert(name.to_string());
+            }
+            let (weak, rest) = activated.weak.drain(..).partition::<Vec<_>, _>(|w| {
+                w.0 == name
+            });
+            activated.weak = rest;
+            weak.into_iter().map(|w| w.1).collect::<Vec<_>>()
+        };
+        for (dep_pkg, dep, dep_node) in self.deps(pkg, node)? {
+            if dep.name() == name {
+                self.activate_dep(dep_pkg, dep, dep_node, &weak)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn entry(&mut self, pkg: &Package, node: Node) -> &mut Activated {
+        self.activated.entry((pkg.package_id().clone(), node.features_for))
+                      .or_insert_with(Activated::default)
+    }
+
+    /// Returns the dependencies of `pkg` which are built when it's built as
+    /// `node`, along with how each of them is built.
+    fn deps(&self, pkg: &'a Package, node: Node)
+            -> CargoResult<Vec<(&'a Package, &'a Dependency, Node)>> {
+        let id = pkg.package_id();
+        let lib = pkg.targets().iter().find(|t| t.is_lib());
+        let mut ret = Vec::new();
+        for dep_id in self.cx.resolve.deps(id) {
+            let deps = pkg.dependencies().iter().filter(|d| {
+                d.name() == dep_id.name() && d.version_req().matches(dep_id.version())
+            });
+            for dep in deps {
+                let (features_for, kind) = match dep.kind() {
+                    DepKind::Build => (FeaturesFor::HostDep, Kind::Host),
+                    DepKind::Development if !self.dev_deps.contains(id) => continue,
+                    DepKind::Development | DepKind::Normal => {
+                        (node.features_for, node.kind)
+                    }
+                };
+                if !self.cx.dep_platform_activated(dep, kind) {
+                    continue
+                }
+                let dep_pkg = self.cx.get_package(dep_id)?;
+                let dep_node = match dep_pkg.targets().iter().find(|t| t.is_lib()) {
+                    Some(dep_lib) => {
+                        let features_for = match lib {
+                            Some(lib) => features_for.for_dep(lib, dep_lib),
+                            None if dep_lib.for_host() => FeaturesFor::HostDep,
+                            None => features_for,
+                        };
+                        Node { features_for, kind: kind.for_target(dep_lib) }
+                    }
+                    None => Node { features_for, kind },
+                };
+                ret.push((dep_pkg, dep, dep_node));
+            }
+        }
+        Ok(ret)
+    }
+}
@@ -1,4 +1,6 @@
+use std::collections::BTreeMap;
 use std::env;
+use std::ffi::OsString;
 use std::fs;
 use std::hash::{self, Hasher};
 use std::path::{Path, PathBuf};
@@ -404,6 +406,15 @@ fn calculate<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
         let fingerprint = pkg_fingerprint(cx, unit.pkg)?;
         LocalFingerprint::Precalculated(fingerprint)
     };
+    // rustc doesn't tell us which environment variables a crate reads with
+    // `env!`, so any change to the `[env]` configuration rebuilds everything.
+    let mut local = vec![local];
+    local.extend(env_config_fingerprints(cx.config.env_config()?));
+    // Likewise for the paths integration tests are compiled with, which
+    // change with the binaries of the package.
+    local.extend(cx.test_env(unit)?.into_iter().map(|(key, path)| {
+        LocalFingerprint::EnvBased(key, Some(path.display().to_string()))
+    }));
     let mut deps = deps;
     deps.sort_by(|&(ref a, _), &(ref b, _)| a.cmp(b));
     let extra_flags = if unit.profile.doc {
@@ -418,9 +429,9 @@ fn calculate<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
         // Note that .0 is hashed here, not .1 which is the cwd. That doesn't
         // actually affect the output artifact so there's no need to hash it.
         path: util::hash_u64(&super::path_args(cx, unit).0),
-        features: format!("{:?}", cx.resolve.features_sorted(unit.pkg.package_id())),
+        features: format!("{:?}", cx.unit_features(unit)),
         deps,
-        local: vec![local],
+        local,
         memoized_hash: Mutex::new(None),
         epoch: unit.pkg.manifest().epoch(),
         rustflags: extra_flags,
@@ -495,6 +506,7 @@ pub fn prepare_build_cmd<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     let key = (unit.pkg.package_id().clone(), unit.kind);
     let pkg_root = unit.pkg.root().to_path_buf();
     let target_root = cx.target_root().to_path_buf();
+    let env_config = cx.config.env_config()?.clone();
     let write_fingerprint = Work::new(move |_| {
         if let Some(output_path) = output_path {
             let outputs = state.outputs.lock().unwrap();
@@ -502,7 +514,10 @@ pub fn prepare_build_cmd<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
             if !outputs.rerun_if_changed.is_empty() ||
                !outputs.rerun_if_env_changed.is_empty() {
                 let deps = BuildDeps::new(&output_path, Some(outputs));
-                fingerprint.local = local_fingerprints_deps(&deps, &target_root, &pkg_root);
+                fingerprint.local = local_fingerprints_deps(&deps,
+                                                            &target_root,
+                                                            &pkg_root,
+                                                            &env_config);
                 fingerprint.update_local(&target_root)?;
             }
         }
@@ -546,10 +561,17 @@ fn build_script_local_fingerprints<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
     // Ok so now we're in "new mode" where we can have files listed as
     // dependencies as well as env vars listed as dependencies. Process them all
     // here.
-    Ok((local_fingerprints_deps(deps, cx.target_root(), unit.pkg.root()), Some(output)))
+    let local = local_fingerprints_deps(deps,
+                                        cx.target_root(),
+                                        unit.pkg.root(),
+                                        cx.config.env_config()?);
+    Ok((local, Some(output)))
 }
 
-fn local_fingerprints_deps(deps: &BuildDeps, target_root: &Path, pkg_root: &Path)
+fn local_fingerprints_deps(deps: &BuildDeps,
+                           target_root: &Path,
+                           pkg_root: &Path,
+                           env_config: &BTreeMap<String, OsString>)
     -> Vec<LocalFingerprint>
 {
     debug!("new local fingerprints deps");
@@ -561,14 +583,27 @@ fn local_fingerprints_deps(deps: &BuildDeps, target_root: &Path, pkg_root: &Path
         local.push(LocalFingerprint::mtime(target_root, mtime, output));
     }
 
+    // Build scripts see the variables of the `[env]` configuration in place
+    // of Cargo's own environment
     for var in deps.rerun_if_env_changed.iter() {
-        let val = env::var(var).ok();
+        let val = match env_config.get(var) {
+            Some(val) => Some(val.to_string_lossy().into_owned()),
+            None => env::var(var).ok(),
+        };
         local.push(LocalFingerprint::EnvBased(var.clone(), val));
     }
 
     local
 }
 
+fn env_config_fingerprints(env_config: &BTreeMap<String, OsString>)
+                           -> Vec<LocalFingerprint> {
+    env_config.iter().map(|(key, val)| {
+        let val = val.to_string_lossy().into_owned();
+        LocalFingerprint::EnvBased(key.clone(), Some(val))
+    }).collect()
+}
+
 fn write_fingerprint(loc: &Path, fingerprint: &Fingerprint) -> CargoResult<()> {
     let hash = fingerprint.hash();
     debug!("write fingerprint: {}", loc.display());
@@ -649,7 +684,7 @@ fn dep_info_mtime_if_fresh(pkg: &Package, dep_info: &Path)
     }
 }
 
-fn pkg_fingerprint(cx: &Context, pkg: &Package) -> CargoResult<String> {
+pub fn pkg_fingerprint(cx: &Context, pkg: &Package) -> CargoResult<String> {
     let source_id = pkg.package_id().source_id();
     let sources = cx.packages.sources();
 
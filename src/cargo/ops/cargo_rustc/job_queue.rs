@@ -13,8 +13,9 @@ use util::{Config, DependencyQueue, Fresh, Dirty, Freshness};
 use util::{CargoResult, ProcessBuilder, profile, internal, CargoResultExt};
 use {handle_error};
 
-use super::{Context, Kind, Unit};
+use super::{Context, FeaturesFor, Kind, Unit};
 use super::job::Job;
+use super::timings::Timings;
 
 /// A management structure of the entire dependency graph to compile.
 ///
@@ -30,7 +31,8 @@ pub struct JobQueue<'a> {
     compiled: HashSet<&'a PackageId>,
     documented: HashSet<&'a PackageId>,
     counts: HashMap<&'a PackageId, usize>,
-    is_release: bool,
+    profile_name: String,
+    timings: Timings,
 }
 
 /// A helper structure for metadata about the state of a building package.
@@ -40,6 +42,10 @@ struct PendingBuild {
     /// Current freshness state of this package. Any dirty target within a
     /// package will cause the entire package to become dirty.
     fresh: Freshness,
+    /// Whether any job for this package has started running yet
+    started: bool,
+    /// The index of this package in `JobQueue::timings`
+    timing_id: usize,
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Hash)]
@@ -48,6 +54,7 @@ struct Key<'a> {
     target: &'a Target,
     profile: &'a Profile,
     kind: Kind,
+    features_for: FeaturesFor,
 }
 
 pub struct JobState<'a> {
@@ -88,7 +95,8 @@ impl<'a> JobQueue<'a> {
             compiled: HashSet::new(),
             documented: HashSet::new(),
             counts: HashMap::new(),
-            is_release: cx.build_config.release,
+            profile_name: cx.build_config.profile_name().to_string(),
+            timings: Timings::new(cx.build_config.timings),
         }
     }
 
@@ -163,6 +171,7 @@ impl<'a> JobQueue<'a> {
         // successful and otherwise wait for pending work to finish if it failed
         // and then immediately return.
         let mut error = None;
+        let mut unlocked_by = None;
         let start_time = Instant::now();
         loop {
             // Dequeue as much work as we can, learning about everything
@@ -173,9 +182,13 @@ impl<'a> JobQueue<'a> {
                 let total_fresh = jobs.iter().fold(fresh, |fresh, &(_, f)| {
                     f.combine(fresh)
                 });
+                let timing_id = self.timings.unit_ready(key.pkg, key.target,
+                                                        key.profile, unlocked_by);
                 self.pending.insert(key, PendingBuild {
                     amt: jobs.len(),
                     fresh: total_fresh,
+                    started: false,
+                    timing_id,
                 });
                 for (job, f) in jobs {
                     queue.push((key, job, f.combine(fresh)));
@@ -184,6 +197,7 @@ impl<'a> JobQueue<'a> {
                     }
                 }
             }
+            unlocked_by = None;
 
             // Now that we've learned of all possible work that we can execute
             // try to spawn it so long as we've got a jobserver token which says
@@ -206,6 +220,8 @@ impl<'a> JobQueue<'a> {
             // to the jobserver itself.
             tokens.truncate(self.active - 1);
 
+            self.timings.mark_concurrency(self.active, queue.len(), self.queue.len());
+
             match self.rx.recv().unwrap() {
                 Message::Run(cmd) => {
                     cx.config.shell().verbose(|c| c.status("Running", &cmd))?;
@@ -228,7 +244,7 @@ impl<'a> JobQueue<'a> {
                         drop(tokens.pop());
                     }
                     match result {
-                        Ok(()) => self.finish(key, cx)?,
+                        Ok(()) => unlocked_by = self.finish(key, cx)?,
                         Err(e) => {
                             let msg = "The following warnings were emitted during compilation:";
                             self.emit_warnings(Some(msg), key, cx)?;
@@ -253,7 +269,7 @@ impl<'a> JobQueue<'a> {
             }
         }
 
-        let build_type = if self.is_release { "release" } else { "dev" };
+        let build_type = &self.profile_name;
         let profile = cx.lib_profile();
         let mut opt_type = String::from(if profile.opt_level == "0" { "unoptimized" }
                                         else { "optimized" });
@@ -270,7 +286,7 @@ impl<'a> JobQueue<'a> {
                                   opt_type,
                                   time_elapsed);
             cx.config.shell().status("Finished", message)?;
-            Ok(())
+            self.timings.finished(cx)
         } else if let Some(e) = error {
             Err(e)
         } else {
@@ -292,6 +308,14 @@ impl<'a> JobQueue<'a> {
         self.active += 1;
         *self.counts.get_mut(key.pkg).unwrap() -= 1;
 
+        {
+            let state = self.pending.get_mut(&key).unwrap();
+            if !state.started {
+                state.started = true;
+                self.timings.unit_start(state.timing_id, state.fresh);
+            }
+        }
+
         let my_tx = self.tx.clone();
         let doit = move || {
             let res = job.run(fresh, &JobState {
@@ -332,7 +356,9 @@ impl<'a> JobQueue<'a> {
         Ok(())
     }
 
-    fn finish(&mut self, key: Key<'a>, cx: &mut Context) -> CargoResult<()> {
+    /// Marks one job of `key` as finished, returning the timing id of the
+    /// unit if that was the last of its jobs.
+    fn finish(&mut self, key: Key<'a>, cx: &mut Context) -> CargoResult<Option<usize>> {
         if key.profile.run_custom_build && cx.show_warnings(key.pkg) {
             self.emit_warnings(None, key, cx)?;
         }
@@ -341,8 +367,10 @@ impl<'a> JobQueue<'a> {
         state.amt -= 1;
         if state.amt == 0 {
             self.queue.finish(&key, state.fresh);
+            self.timings.unit_finished(state.timing_id);
+            return Ok(Some(state.timing_id))
         }
-        Ok(())
+        Ok(None)
     }
 
     // This isn't super trivial because we don't want to print loads and
@@ -394,6 +422,7 @@ impl<'a> Key<'a> {
             target: unit.target,
             profile: unit.profile,
             kind: unit.kind,
+            features_for: unit.features_for,
         }
     }
 
@@ -404,6 +433,7 @@ impl<'a> Key<'a> {
             target: self.target,
             profile: self.profile,
             kind: self.kind,
+            features_for: self.features_for,
         };
         let targets = cx.dep_targets(&unit)?;
         Ok(targets.iter().filter_map(|unit| {
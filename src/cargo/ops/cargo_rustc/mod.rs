@@ -3,6 +3,7 @@ use std::env;
 use std::ffi::{OsStr, OsString};
 use std::fs;
 use std::io::{self, Write};
+use std::mem;
 use std::path::{self, PathBuf};
 use std::sync::Arc;
 
@@ -19,6 +20,7 @@ use util::paths;
 use util::errors::{CargoResult, CargoResultExt, Internal};
 use util::Freshness;
 
+use self::build_plan::BuildPlan;
 use self::job::{Job, Work};
 use self::job_queue::JobQueue;
 
@@ -27,17 +29,22 @@ use self::output_depinfo::output_depinfo;
 pub use self::compilation::Compilation;
 pub use self::context::{Context, Unit, TargetFileType};
 pub use self::custom_build::{BuildOutput, BuildMap, BuildScripts};
+pub use self::features::FeaturesFor;
 pub use self::layout::is_bad_artifact_name;
 
+mod build_plan;
+mod cache;
 mod compilation;
 mod context;
 mod custom_build;
+mod features;
 mod fingerprint;
 mod job;
 mod job_queue;
 mod layout;
 mod links;
 mod output_depinfo;
+mod timings;
 
 /// Whether an object is for the host arch, or the target arch.
 ///
@@ -65,12 +72,32 @@ pub struct BuildConfig {
     pub jobs: u32,
     /// Whether we are building for release
     pub release: bool,
+    /// The name of the custom profile being built with, if any
+    pub profile: Option<String>,
     /// Whether we are running tests
     pub test: bool,
     /// Whether we are building documentation
     pub doc_all: bool,
     /// Whether to print std output in json format (for machine reading)
     pub json_messages: bool,
+    /// Whether to record and report how long each unit took to build
+    pub timings: bool,
+    /// Whether to build packages whose `rust-version` is newer than rustc
+    pub ignore_rust_version: bool,
+    /// Whether to print the commands of the build as JSON instead of running
+    /// them
+    pub build_plan: bool,
+}
+
+impl BuildConfig {
+    /// The name of the profile being built with, as shown to the user
+    pub fn profile_name(&self) -> &str {
+        match self.profile {
+            Some(ref name) => name,
+            None if self.release => "release",
+            None => "dev",
+        }
+    }
 }
 
 /// Information required to build for a target
@@ -152,8 +179,10 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
             Unit {
                 pkg,
                 target,
-                profile,
+                profile: profiles.for_package(profile, pkg.package_id(),
+                                              target.for_host()),
                 kind: if target.for_host() {Kind::Host} else {default_kind},
+                features_for: FeaturesFor::Normal,
             }
         })
     }).collect::<Vec<_>>();
@@ -164,7 +193,15 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
     let mut queue = JobQueue::new(&cx);
 
     cx.prepare()?;
+    // Fetch everything we know we'll need up front, and then anything which
+    // only turned out to be needed once the target's `cfg` values are known.
+    cx.download_deps(&units)?;
     cx.probe_target_info(&units)?;
+    cx.download_deps(&units)?;
+    cx.resolve_features(&units)?;
+    if !cx.build_config.ignore_rust_version {
+        cx.check_rust_version(&units)?;
+    }
     cx.build_used_in_plugin_map(&units)?;
     custom_build::build_map(&mut cx, &units)?;
 
@@ -177,8 +214,17 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
         compile(&mut cx, &mut queue, unit, exec)?;
     }
 
+    if cx.build_plan.enabled() {
+        let plan = mem::replace(&mut cx.build_plan, BuildPlan::new(false));
+        plan.output(&mut cx, &units)?;
+        return Ok(cx.compilation)
+    }
+
     // Now that we've figured out everything that we're going to do, do it!
     queue.execute(&mut cx)?;
+    if let Some(ref cache) = cx.build_cache {
+        cache.finish(config)?;
+    }
 
     for unit in units.iter() {
         for &(ref dst, ref link_dst, file_type) in cx.target_filenames(unit)?.iter() {
@@ -227,11 +273,11 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
                 }));
         }
 
-        let feats = cx.resolve.features(unit.pkg.package_id());
+        let feats = cx.unit_features(unit).iter().map(|feat| {
+            format!("feature=\"{}\"", feat)
+        }).collect::<HashSet<_>>();
         if !feats.is_empty() {
-            cx.compilation.cfgs.entry(unit.pkg.package_id().clone()).or_insert_with(|| {
-                feats.iter().map(|feat| format!("feature=\"{}\"", feat)).collect()
-            });
+            cx.compilation.cfgs.entry(unit.pkg.package_id().clone()).or_insert(feats);
         }
         let rustdocflags = cx.rustdocflags_args(unit)?;
         if !rustdocflags.is_empty() {
@@ -281,16 +327,20 @@ fn compile<'a, 'cfg: 'a>(cx: &mut Context<'a, 'cfg>,
         (Work::noop(), Work::noop(), Freshness::Fresh)
     } else {
         let (mut freshness, dirty, fresh) = fingerprint::prepare_target(cx, unit)?;
-        let work = if unit.profile.doc {
-            rustdoc(cx, unit)?
+        let force_rebuild = exec.force_rebuild(unit);
+        let (work, cached) = if unit.profile.doc {
+            (rustdoc(cx, unit)?, Work::noop())
+        } else if force_rebuild {
+            (rustc(cx, unit, exec)?, Work::noop())
         } else {
-            rustc(cx, unit, exec)?
+            let work = rustc(cx, unit, exec)?;
+            cache::prepare(cx, unit, work)?
         };
         // Need to link targets on both the dirty and fresh
         let dirty = work.then(link_targets(cx, unit, false)?).then(dirty);
-        let fresh = link_targets(cx, unit, true)?.then(fresh);
+        let fresh = link_targets(cx, unit, true)?.then(cached).then(fresh);
 
-        if exec.force_rebuild(unit) {
+        if force_rebuild {
             freshness = Freshness::Dirty;
         }
 
@@ -325,6 +375,18 @@ fn rustc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
         rustc.arg("--cap-lints").arg("warn");
     }
 
+    // Integration tests and benchmarks are told where the binaries of their
+    // package are and where they may keep files.
+    let test_env = cx.test_env(unit)?;
+    let target_tmpdir = if test_env.is_empty() {
+        None
+    } else {
+        Some(cx.target_tmpdir(unit.pkg))
+    };
+    for (key, path) in test_env {
+        rustc.env(&key, path);
+    }
+
     let filenames = cx.target_filenames(unit)?;
     let root = cx.out_dir(unit);
     let kind = unit.kind;
@@ -351,6 +413,7 @@ fn rustc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
     let dep_info_loc = fingerprint::dep_info_loc(cx, unit);
 
     rustc.args(&cx.rustflags_args(unit)?);
+    cx.build_plan.add(unit, &rustc);
     let json_messages = cx.build_config.json_messages;
     let package_id = unit.pkg.package_id().clone();
     let target = unit.target.clone();
@@ -391,6 +454,10 @@ fn rustc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
             }
         }
 
+        if let Some(ref target_tmpdir) = target_tmpdir {
+            fs::create_dir_all(target_tmpdir)?;
+        }
+
         state.running(&rustc);
         if json_messages {
             exec.exec_json(rustc, &package_id, &target,
@@ -509,7 +576,7 @@ fn link_targets<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
     let package_id = unit.pkg.package_id().clone();
     let target = unit.target.clone();
     let profile = unit.profile.clone();
-    let features = cx.resolve.features_sorted(&package_id).into_iter()
+    let features = cx.unit_features(unit).into_iter()
         .map(|s| s.to_owned())
         .collect();
     let json_messages = cx.build_config.json_messages;
@@ -676,7 +743,7 @@ fn rustdoc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
 
     rustdoc.arg("-o").arg(doc_dir);
 
-    for feat in cx.resolve.features_sorted(unit.pkg.package_id()) {
+    for feat in cx.unit_features(unit) {
         rustdoc.arg("--cfg").arg(&format!("feature=\"{}\"", feat));
     }
 
@@ -865,7 +932,7 @@ fn build_base_args<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
     // We ideally want deterministic invocations of rustc to ensure that
     // rustc-caching strategies like sccache are able to cache more, so sort the
     // feature list here.
-    for feat in cx.resolve.features_sorted(unit.pkg.package_id()) {
+    for feat in cx.unit_features(unit) {
         cmd.arg("--cfg").arg(&format!("feature=\"{}\"", feat));
     }
 
@@ -0,0 +1,410 @@
+//! Recording of how long each unit of work takes during a build.
+//!
+//! When `--timings` is passed the `JobQueue` reports every unit as it becomes
+//! ready, starts and finishes, along with how many units are running, waiting
+//! for a jobserver token, or blocked on their dependencies. Once the build is
+//! over this is written out as a self-contained HTML report and a JSON file
+//! under `target/cargo-timings`.
+
+use std::fmt::Write;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use serde_json;
+
+use core::{PackageId, Profile, Target, TargetKind};
+use util::{CargoResult, Freshness};
+use util::paths;
+
+use super::Context;
+
+pub struct Timings {
+    /// Whether `--timings` was passed, nothing is recorded otherwise
+    enabled: bool,
+    /// When the job queue started draining
+    start: Instant,
+    /// Every unit seen so far, in the order they became ready
+    units: Vec<UnitTime>,
+    /// Snapshots of the job queue taken whenever its state changes
+    concurrency: Vec<Concurrency>,
+}
+
+/// Timing information about a single unit.
+///
+/// All times are in seconds, `ready` and `start` are relative to the start of
+/// the build while `rmeta_time` and `rlib_time` are relative to `start`.
+#[derive(Serialize)]
+struct UnitTime {
+    name: String,
+    version: String,
+    target: String,
+    mode: &'static str,
+    fresh: bool,
+    /// When all of the unit's dependencies had finished
+    ready: f64,
+    /// When the unit actually started running
+    start: f64,
+    duration: f64,
+    /// When the crate's metadata was available to dependents
+    rmeta_time: Option<f64>,
+    /// When the crate's rlib was available to dependents
+    rlib_time: Option<f64>,
+    /// Indices of the units which were waiting on this one to finish
+    unlocked_units: Vec<usize>,
+    #[serde(skip)]
+    linkable: bool,
+    #[serde(skip)]
+    check: bool,
+}
+
+/// A snapshot of the state of the job queue.
+#[derive(Serialize, Clone, Copy, PartialEq)]
+struct Concurrency {
+    t: f64,
+    /// Number of units currently running
+    active: usize,
+    /// Number of units ready to run, but waiting for a jobserver token
+    waiting: usize,
+    /// Number of units waiting on their dependencies to finish
+    inactive: usize,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    total: f64,
+    units: &'a [UnitTime],
+    concurrency: &'a [Concurrency],
+}
+
+impl Timings {
+    pub fn new(enabled: bool) -> Timings {
+        Timings {
+            enabled,
+            start: Instant::now(),
+            units: Vec::new(),
+            concurrency: Vec::new(),
+        }
+    }
+
+    /// Records that a unit had all of its dependencies finish, returning an
+    /// index used to refer to it later on.
+    pub fn unit_ready(&mut self,
+                      pkg: &PackageId,
+                      target: &Target,
+                      profile: &Profile,
+                      unlocked_by: Option<usize>) -> usize {
+        if !self.enabled {
+            return 0
+        }
+        let id = self.units.len();
+        let now = self.elapsed();
+        self.units.push(UnitTime {
+            name: pkg.name().to_string(),
+            version: pkg.version().to_string(),
+            target: target_description(target),
+            mode: mode(profile),
+            fresh: true,
+            ready: now,
+            start: now,
+            duration: 0.0,
+            rmeta_time: None,
+            rlib_time: None,
+            unlocked_units: Vec::new(),
+            linkable: target.linkable(),
+            check: profile.check,
+        });
+        if let Some(parent) = unlocked_by {
+            self.units[parent].unlocked_units.push(id);
+        }
+        id
+    }
+
+    /// Records that the first job of a unit has started running.
+    pub fn unit_start(&mut self, id: usize, fresh: Freshness) {
+        if !self.enabled {
+            return
+        }
+        let now = self.elapsed();
+        let unit = &mut self.units[id];
+        unit.start = now;
+        unit.fresh = fresh == Freshness::Fresh;
+    }
+
+    /// Records that the last job of a unit has finished.
+    pub fn unit_finished(&mut self, id: usize) {
+        if !self.enabled {
+            return
+        }
+        let now = self.elapsed();
+        let unit = &mut self.units[id];
+        unit.duration = now - unit.start;
+        // Without pipelining rustc produces a library's metadata and rlib
+        // together, so both become available when the unit finishes.
+        if unit.linkable {
+            unit.rmeta_time = Some(unit.duration);
+            if !unit.check {
+                unit.rlib_time = Some(unit.duration);
+            }
+        }
+    }
+
+    /// Takes a snapshot of the job queue, if anything has changed.
+    pub fn mark_concurrency(&mut self,
+                            active: usize,
+                            waiting: usize,
+                            inactive: usize) {
+        if !self.enabled {
+            return
+        }
+        let t = self.elapsed();
+        if let Some(last) = self.concurrency.last() {
+            if (last.active, last.waiting, last.inactive) ==
+               (active, waiting, inactive) {
+                return
+            }
+        }
+        self.concurrency.push(Concurrency { t, active, waiting, inactive });
+    }
+
+    /// Writes out the HTML and JSON reports once the build has finished.
+    pub fn finished(&mut self, cx: &Context) -> CargoResult<()> {
+        if !self.enabled {
+            return Ok(())
+        }
+        self.mark_concurrency(0, 0, 0);
+        let total = self.elapsed();
+
+        let dir = cx.ws.target_dir().join("cargo-timings").into_path_unlocked();
+        fs::create_dir_all(&dir)?;
+
+        let report = Report {
+            total,
+            units: &self.units,
+            concurrency: &self.concurrency,
+        };
+        let json = dir.join("cargo-timing.json");
+        paths::write(&json, serde_json::to_string_pretty(&report)?.as_bytes())?;
+
+        let html = dir.join("cargo-timing.html");
+        paths::write(&html, self.render_html(total, cx).as_bytes())?;
+
+        cx.config.shell().status("Timing", format!("report saved to {}",
+                                                   html.display()))?;
+        Ok(())
+    }
+
+    fn elapsed(&self) -> f64 {
+        to_secs(self.start.elapsed())
+    }
+
+    fn render_html(&self, total: f64, cx: &Context) -> String {
+        let mut out = String::new();
+        let dirty = self.units.iter().filter(|u| !u.fresh).count();
+        let max_concurrency = self.concurrency.iter()
+            .map(|c| c.active)
+            .max()
+            .unwrap_or(0);
+        let scale = if total > 0.0 { 100.0 / total } else { 0.0 };
+
+        out.push_str(HTML_HEADER);
+        let _ = write!(out, "<h1>Cargo Build Timings</h1>\n\
+                             <table class=\"summary\">\n\
+                             <tr><td>Targets:</td><td>{}</td></tr>\n\
+                             <tr><td>Profile:</td><td>{}</td></tr>\n\
+                             <tr><td>Total units:</td><td>{}</td></tr>\n\
+                             <tr><td>Fresh units:</td><td>{}</td></tr>\n\
+                             <tr><td>Dirty units:</td><td>{}</td></tr>\n\
+                             <tr><td>Max concurrency:</td><td>{} (jobs={})</td></tr>\n\
+                             <tr><td>Total time:</td><td>{:.1}s</td></tr>\n\
+                             </table>\n",
+                       escape(&root_names(cx)),
+                       escape(cx.build_config.profile_name()),
+                       self.units.len(),
+                       self.units.len() - dirty,
+                       dirty,
+                       max_concurrency,
+                       cx.build_config.jobs,
+                       total);
+
+        // Each unit is drawn as a bar spanning from when it started to when
+        // it finished, with a thinner bar in front of it for the time it
+        // spent ready but waiting for a token.
+        out.push_str("<h2>Timeline</h2>\n<div class=\"timeline\">\n");
+        for unit in self.units.iter() {
+            let _ = write!(out, "<div class=\"row\">\
+                                 <span class=\"label\">{} v{} {}</span>\
+                                 <span class=\"track\">\
+                                 <span class=\"wait\" style=\"left:{:.3}%;width:{:.3}%\"></span>\
+                                 <span class=\"bar {}\" style=\"left:{:.3}%;width:{:.3}%\" \
+                                 title=\"{:.2}s\"></span></span></div>\n",
+                           escape(&unit.name),
+                           escape(&unit.version),
+                           escape(&unit.target),
+                           unit.ready * scale,
+                           (unit.start - unit.ready) * scale,
+                           if unit.fresh { "fresh" } else { unit.mode },
+                           unit.start * scale,
+                           unit.duration * scale,
+                           unit.duration);
+        }
+        out.push_str("</div>\n");
+
+        out.push_str("<h2>Concurrency</h2>\n");
+        out.push_str(&self.render_concurrency(total));
+
+        let mut units = self.units.iter().enumerate().collect::<Vec<_>>();
+        units.sort_by(|a, b| {
+            b.1.duration.partial_cmp(&a.1.duration).unwrap()
+        });
+        out.push_str("<h2>Units</h2>\n<table class=\"units\">\n\
+                      <thead><tr><th></th><th>Unit</th><th>Mode</th>\
+                      <th>Total</th><th>rmeta</th><th>rlib</th>\
+                      <th>Waited</th><th>Unlocked</th></tr></thead>\n<tbody>\n");
+        for (i, &(_, unit)) in units.iter().enumerate() {
+            let unlocked = unit.unlocked_units.iter().map(|&id| {
+                let u = &self.units[id];
+                format!("{} v{} {}", u.name, u.version, u.target)
+            }).collect::<Vec<_>>().join(", ");
+            let _ = write!(out, "<tr><td>{}.</td><td>{} v{} {}</td><td>{}</td>\
+                                 <td>{:.2}s</td><td>{}</td><td>{}</td>\
+                                 <td>{:.2}s</td><td>{}</td></tr>\n",
+                           i + 1,
+                           escape(&unit.name),
+                           escape(&unit.version),
+                           escape(&unit.target),
+                           if unit.fresh { "fresh" } else { unit.mode },
+                           unit.duration,
+                           optional_secs(unit.rmeta_time),
+                           optional_secs(unit.rlib_time),
+                           unit.start - unit.ready,
+                           escape(&unlocked));
+        }
+        out.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+        out
+    }
+
+    /// Draws the number of active, waiting and inactive units over time as
+    /// step graphs in an inline SVG.
+    fn render_concurrency(&self, total: f64) -> String {
+        const WIDTH: f64 = 1000.0;
+        const HEIGHT: f64 = 200.0;
+
+        let max = self.concurrency.iter()
+            .map(|c| c.active.max(c.waiting).max(c.inactive))
+            .max()
+            .unwrap_or(0)
+            .max(1) as f64;
+        let x = |t: f64| if total > 0.0 { t / total * WIDTH } else { 0.0 };
+        let y = |n: usize| HEIGHT - n as f64 / max * HEIGHT;
+
+        let mut out = String::new();
+        let _ = write!(out, "<svg class=\"concurrency\" viewBox=\"0 0 {} {}\" \
+                             preserveAspectRatio=\"none\">\n", WIDTH, HEIGHT);
+        let series: [(&str, fn(&Concurrency) -> usize); 3] = [
+            ("active", |c| c.active),
+            ("waiting", |c| c.waiting),
+            ("inactive", |c| c.inactive),
+        ];
+        for &(class, get) in series.iter() {
+            let mut points = String::new();
+            let mut prev = None;
+            for c in self.concurrency.iter() {
+                if let Some(n) = prev {
+                    let _ = write!(points, "{:.1},{:.1} ", x(c.t), y(n));
+                }
+                let _ = write!(points, "{:.1},{:.1} ", x(c.t), y(get(c)));
+                prev = Some(get(c));
+            }
+            let _ = write!(out, "<polyline class=\"{}\" points=\"{}\"/>\n",
+                           class, points.trim_right());
+        }
+        out.push_str("</svg>\n<p class=\"legend\">\
+                      <span class=\"active\">running</span> \
+                      <span class=\"waiting\">waiting for a token</span> \
+                      <span class=\"inactive\">waiting on dependencies</span>\
+                      </p>\n");
+        out
+    }
+}
+
+fn to_secs(d: Duration) -> f64 {
+    d.as_secs() as f64 + f64::from(d.subsec_nanos()) / 1_000_000_000.0
+}
+
+fn optional_secs(t: Option<f64>) -> String {
+    t.map(|t| format!("{:.2}s", t)).unwrap_or_default()
+}
+
+fn root_names(cx: &Context) -> String {
+    cx.ws.members()
+        .map(|p| format!("{} v{}", p.name(), p.version()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn target_description(target: &Target) -> String {
+    match *target.kind() {
+        TargetKind::Lib(..) => "lib".to_string(),
+        TargetKind::Bin => format!("bin \"{}\"", target.name()),
+        TargetKind::Test => format!("test \"{}\"", target.name()),
+        TargetKind::Bench => format!("bench \"{}\"", target.name()),
+        TargetKind::ExampleLib(..) |
+        TargetKind::ExampleBin => format!("example \"{}\"", target.name()),
+        TargetKind::CustomBuild => "build script".to_string(),
+    }
+}
+
+fn mode(profile: &Profile) -> &'static str {
+    if profile.run_custom_build {
+        "run-custom-build"
+    } else if profile.doc {
+        "doc"
+    } else if profile.check {
+        "check"
+    } else if profile.test {
+        "test"
+    } else {
+        "build"
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+     .replace('<', "&lt;")
+     .replace('>', "&gt;")
+     .replace('"', "&quot;")
+}
+
+static HTML_HEADER: &'static str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Cargo Build Timings</title>
+<style>
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+td, th { padding: 2px 8px; text-align: left; }
+table.units tbody tr:nth-child(even) { background: #f4f4f4; }
+.timeline { border: 1px solid #ccc; padding: 4px; }
+.row { display: flex; height: 16px; margin: 2px 0; font-size: 12px; }
+.label { width: 300px; flex: none; overflow: hidden; white-space: nowrap; }
+.track { position: relative; flex: 1; }
+.track span { position: absolute; top: 0; bottom: 0; min-width: 1px; }
+.wait { background: #eee; top: 6px !important; bottom: 6px !important; }
+.bar { background: #95cce8; }
+.bar.fresh { background: #ddd; }
+.bar.check { background: #d4b3ea; }
+.bar.run-custom-build { background: #f0b165; }
+.bar.test { background: #a6d58a; }
+svg.concurrency { width: 100%; height: 200px; border: 1px solid #ccc; }
+svg polyline { fill: none; stroke-width: 2; vector-effect: non-scaling-stroke; }
+polyline.active { stroke: #1f77b4; }
+polyline.waiting { stroke: #d62728; }
+polyline.inactive { stroke: #aaa; }
+.legend span.active { color: #1f77b4; }
+.legend span.waiting { color: #d62728; }
+.legend span.inactive { color: #888; }
+</style>
+</head>
+<body>
+"#;
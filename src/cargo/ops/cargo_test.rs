@@ -1,20 +1,149 @@
+use std::collections::VecDeque;
 use std::ffi::{OsString, OsStr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{mpsc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 
-use ops::{self, Compilation};
-use util::{self, CargoTestError, Test, ProcessError};
-use util::errors::CargoResult;
-use core::Workspace;
+use crossbeam;
+use jobserver::Client;
+
+use ops::{self, Compilation, MessageFormat};
+use ops::test_report::{self, OutputParser, SuiteStarted, TestSuite};
+use util::{self, CargoTestError, Config, Test, ProcessBuilder, ProcessError};
+use util::errors::{CargoError, CargoResult, CargoResultExt};
+use util::machine_message;
+use core::{PackageId, TargetKind, Workspace};
 
 pub struct TestOptions<'a> {
     pub compile_opts: ops::CompileOptions<'a>,
     pub no_run: bool,
     pub no_fail_fast: bool,
     pub only_doc: bool,
+    /// Where to write a JUnit XML report of the tests which were run.
+    pub junit: Option<PathBuf>,
+    /// The number of test binaries to run at once, overriding
+    /// `build.test-jobs`.
+    pub test_jobs: Option<u32>,
+    /// The part of the tests to run, if they're split up between machines.
+    pub partition: Option<Partition>,
+}
+
+/// One of several parts the tests are split up into with `--partition`, so
+/// that each part can be run by a different machine.
+///
+/// A test is always assigned to the same part given the same tests, and every
+/// test is in exactly one part.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Partition {
+    pub strategy: PartitionStrategy,
+    /// The part to run, counting from 1
+    pub shard: usize,
+    /// The number of parts
+    pub total: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PartitionStrategy {
+    /// Deal out the test binaries, and the doctests of each library, in turn.
+    Count,
+    /// Assign the individual tests of each binary, and the doctests of each
+    /// library, by a hash of their names.
+    Hash,
+}
+
+impl Partition {
+    /// Whether the `index`th test binary or doctested library, or the test
+    /// called `key`, belongs to this part.
+    fn includes(&self, index: usize, key: &str) -> bool {
+        let part = match self.strategy {
+            PartitionStrategy::Count => index,
+            PartitionStrategy::Hash => (util::hash_u64(&key) % self.total as u64) as usize,
+        };
+        part % self.total == self.shard - 1
+    }
+}
+
+impl FromStr for Partition {
+    type Err = CargoError;
+
+    fn from_str(s: &str) -> CargoResult<Partition> {
+        let parse = || {
+            let mut parts = s.splitn(2, ':');
+            let strategy = match parts.next() {
+                Some("count") => PartitionStrategy::Count,
+                Some("hash") => PartitionStrategy::Hash,
+                _ => return None,
+            };
+            let mut parts = parts.next()?.splitn(2, '/');
+            let shard = parts.next()?.parse().ok()?;
+            let total = parts.next()?.parse().ok()?;
+            if shard == 0 || shard > total {
+                return None
+            }
+            Some(Partition { strategy, shard, total })
+        };
+        match parse() {
+            Some(partition) => Ok(partition),
+            None => bail!("invalid partition `{}`, expected `count:K/N` or \
+                           `hash:K/N` where K is between 1 and N", s),
+        }
+    }
+}
+
+impl<'a> TestOptions<'a> {
+    /// Whether the results of individual tests are parsed out of the output
+    /// of the test binaries, which are otherwise left alone.
+    fn reports_results(&self) -> bool {
+        self.junit.is_some() || self.compile_opts.message_format == MessageFormat::Json
+    }
+
+    /// The number of test binaries to run at once, one after another unless
+    /// configured otherwise.
+    fn test_jobs(&self) -> CargoResult<u32> {
+        if let Some(jobs) = self.test_jobs {
+            if jobs == 0 {
+                bail!("--test-jobs must be positive")
+            }
+            return Ok(jobs)
+        }
+        match self.compile_opts.config.get_i64("build.test-jobs")? {
+            Some(v) => {
+                if v.val <= 0 {
+                    bail!("build.test-jobs must be positive, but found {} in {}",
+                          v.val, v.definition)
+                } else if v.val >= i64::from(u32::max_value()) {
+                    bail!("build.test-jobs is too large: found {} in {}", v.val,
+                          v.definition)
+                } else {
+                    Ok(v.val as u32)
+                }
+            }
+            None => Ok(1),
+        }
+    }
 }
 
 pub fn run_tests(ws: &Workspace,
                  options: &TestOptions,
                  test_args: &[String]) -> CargoResult<Option<CargoTestError>> {
+    let mut suites = Vec::new();
+    let result = run_tests_inner(ws, options, test_args, &mut suites);
+    // The report covers whatever ran, even if running the tests failed.
+    if let Some(ref path) = options.junit {
+        if !options.no_run && (result.is_ok() || !suites.is_empty()) {
+            test_report::write_junit(path, &suites)?;
+        }
+    }
+    result
+}
+
+fn run_tests_inner(ws: &Workspace,
+                   options: &TestOptions,
+                   test_args: &[String],
+                   suites: &mut Vec<TestSuite>) -> CargoResult<Option<CargoTestError>> {
+    let jobs = options.test_jobs()?;
     let compilation = compile_tests(ws, options)?;
 
     if options.no_run {
@@ -22,9 +151,9 @@ pub fn run_tests(ws: &Workspace,
     }
     let (test, mut errors) = if options.only_doc {
         assert!(options.compile_opts.filter.is_specific());
-        run_doc_tests(options, test_args, &compilation)?
+        run_doc_tests(options, test_args, &compilation, suites)?
     } else {
-        run_unit_tests(options, test_args, &compilation)?
+        run_unit_tests(options, test_args, &compilation, jobs, suites)?
     };
 
     // If we have an error and want to fail fast, return
@@ -41,7 +170,7 @@ pub fn run_tests(ws: &Workspace,
         }
     }
 
-    let (doctest, docerrors) = run_doc_tests(options, test_args, &compilation)?;
+    let (doctest, docerrors) = run_doc_tests(options, test_args, &compilation, suites)?;
     let test = if docerrors.is_empty() { test } else { doctest };
     errors.extend(docerrors);
     if errors.is_empty() {
@@ -61,7 +190,9 @@ pub fn run_benches(ws: &Workspace,
     if options.no_run {
         return Ok(None)
     }
-    let (test, errors) = run_unit_tests(options, &args, &compilation)?;
+    // Benchmarks are always run one at a time so they don't disturb each
+    // other's measurements.
+    let (test, errors) = run_unit_tests(options, &args, &compilation, 1, &mut Vec::new())?;
     match errors.len() {
         0 => Ok(None),
         _ => Ok(Some(CargoTestError::new(test, errors))),
@@ -78,41 +209,40 @@ fn compile_tests<'a>(ws: &Workspace<'a>,
     Ok(compilation)
 }
 
-/// Run the unit and integration tests of a project.
+/// Run the unit and integration tests of a project, running up to `jobs` test
+/// binaries at once.
 fn run_unit_tests(options: &TestOptions,
                   test_args: &[String],
-                  compilation: &Compilation)
+                  compilation: &Compilation,
+                  jobs: u32,
+                  suites: &mut Vec<TestSuite>)
                   -> CargoResult<(Test, Vec<ProcessError>)> {
     let config = options.compile_opts.config;
-    let cwd = options.compile_opts.config.cwd();
 
     let mut errors = Vec::new();
 
-    for &(ref pkg, ref kind, ref test, ref exe) in &compilation.tests {
-        let to_display = match util::without_prefix(exe, cwd) {
-            Some(path) => path,
-            None => &**exe,
-        };
-        let mut cmd = compilation.target_process(exe, pkg)?;
-        cmd.args(test_args);
-        config.shell().concise(|shell| {
-            shell.status("Running", to_display.display().to_string())
-        })?;
-        config.shell().verbose(|shell| {
-            shell.status("Running", cmd.to_string())
-        })?;
+    let commands = unit_test_commands(options, test_args, compilation)?;
+    if jobs > 1 && commands.len() > 1 {
+        run_unit_tests_parallel(options, compilation, commands, jobs,
+                                suites, &mut errors)?;
+    } else {
+        for (index, cmd) in commands {
+            let (ref pkg, ref kind, ref test, ref exe) = compilation.tests[index];
+            print_running(config, exe, &cmd)?;
 
-        let result = cmd.exec();
+            let result = exec_tests(options, &cmd, pkg.package_id(),
+                                    kind_name(kind), test, suites);
 
-        match result {
-            Err(e) => {
-                let e = e.downcast::<ProcessError>()?;
-                errors.push((kind.clone(), test.clone(), pkg.name().to_string(), e));
-                if !options.no_fail_fast {
-                    break;
+            match result {
+                Err(e) => {
+                    let e = e.downcast::<ProcessError>()?;
+                    errors.push((kind.clone(), test.clone(), pkg.name().to_string(), e));
+                    if !options.no_fail_fast {
+                        break;
+                    }
                 }
+                Ok(()) => {}
             }
-            Ok(()) => {}
         }
     }
 
@@ -124,9 +254,217 @@ fn run_unit_tests(options: &TestOptions,
     }
 }
 
+/// The commands running the test binaries of `compilation`, along with the
+/// index of each binary. With `--partition` the binaries of other parts are
+/// left out, and with the `hash` strategy each binary is restricted to the
+/// tests of this part, listing them to know their names and running the
+/// binary once for each of them.
+fn unit_test_commands(options: &TestOptions,
+                      test_args: &[String],
+                      compilation: &Compilation)
+                      -> CargoResult<Vec<(usize, ProcessBuilder)>> {
+    let mut commands = Vec::new();
+    for (index, &(ref pkg, ref kind, ref test, ref exe)) in compilation.tests.iter().enumerate() {
+        let mut cmd = compilation.target_process(exe, pkg)?;
+        cmd.args(test_args);
+
+        if let Some(ref partition) = options.partition {
+            let key = format!("{}::{}::{}", pkg.name(), kind_name(kind), test);
+            match partition.strategy {
+                PartitionStrategy::Count => {
+                    if !partition.includes(index, &key) {
+                        continue
+                    }
+                }
+                PartitionStrategy::Hash => {
+                    let names = list_tests(&cmd)?.into_iter().filter(|name| {
+                        partition.includes(index, &format!("{}::{}", key, name))
+                    }).collect::<Vec<_>>();
+                    // Older versions of libtest only look at the first name
+                    // they're given, so each test is run on its own
+                    for name in names {
+                        let mut cmd = cmd.clone();
+                        cmd.arg("--exact").arg(name);
+                        commands.push((index, cmd));
+                    }
+                    continue
+                }
+            }
+        }
+        commands.push((index, cmd));
+    }
+    Ok(commands)
+}
+
+/// Lists the names of the tests, or benchmarks, a test binary would run.
+fn list_tests(cmd: &ProcessBuilder) -> CargoResult<Vec<String>> {
+    let mut cmd = cmd.clone();
+    cmd.arg("--list");
+    let output = cmd.exec_with_output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().filter_map(|line| {
+        if line.ends_with(": test") {
+            Some(line[..line.len() - ": test".len()].to_string())
+        } else if line.ends_with(": bench") {
+            Some(line[..line.len() - ": bench".len()].to_string())
+        } else {
+            None
+        }
+    }).collect())
+}
+
+/// A test binary run by `run_unit_tests_parallel`, with its output buffered.
+struct Finished {
+    /// The index of the binary in `Compilation::tests`
+    index: usize,
+    cmd: ProcessBuilder,
+    stdout: String,
+    stderr: String,
+    suite: TestSuite,
+    result: CargoResult<()>,
+}
+
+/// Runs the test binaries on up to `jobs` threads, each of which after the
+/// first needs a token from the jobserver to run a binary. The output of a
+/// binary is printed in one piece once it has finished, so it doesn't get
+/// interleaved with the output of the others.
+///
+/// Without `--no-fail-fast` no more binaries are started after one has
+/// failed, but the ones already running are waited for.
+fn run_unit_tests_parallel(options: &TestOptions,
+                           compilation: &Compilation,
+                           commands: Vec<(usize, ProcessBuilder)>,
+                           jobs: u32,
+                           suites: &mut Vec<TestSuite>,
+                           errors: &mut Vec<(TargetKind, String, String, ProcessError)>)
+                           -> CargoResult<()> {
+    let config = options.compile_opts.config;
+
+    // As with compiling, if Cargo runs under a jobserver then the binaries
+    // take part in it, and otherwise we make our own with `jobs - 1` tokens.
+    let jobserver = match config.jobserver_from_env() {
+        Some(c) => c.clone(),
+        None => Client::new(jobs as usize - 1).chain_err(|| {
+            "failed to create jobserver"
+        })?,
+    };
+
+    let workers = jobs.min(commands.len() as u32);
+    let queue = commands.into_iter().map(|(index, cmd)| {
+        let (ref pkg, ref kind, ref test, _) = compilation.tests[index];
+        (index, cmd, pkg.package_id().clone(), kind_name(kind), test.clone())
+    }).collect::<VecDeque<_>>();
+    let queue = Mutex::new(queue);
+    let stop = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel::<CargoResult<Finished>>();
+
+    crossbeam::scope(|scope| {
+        for worker in 0..workers {
+            let tx = tx.clone();
+            let (queue, stop, jobserver) = (&queue, &stop, &jobserver);
+            scope.spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    // The first worker runs binaries with our own token.
+                    let token = if worker == 0 {
+                        None
+                    } else {
+                        match jobserver.acquire() {
+                            Ok(token) => Some(token),
+                            Err(e) => {
+                                let e = format_err!("failed to acquire jobserver token: {}", e);
+                                drop(tx.send(Err(e)));
+                                break
+                            }
+                        }
+                    };
+                    let (index, cmd, pkg, kind, name) = match queue.lock().unwrap().pop_front() {
+                        Some(job) => job,
+                        None => break,
+                    };
+                    let finished = exec_tests_buffered(index, cmd, &pkg, kind, &name);
+                    drop(token);
+                    if tx.send(Ok(finished)).is_err() {
+                        break
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        // Should anything go wrong here, dropping `rx` stops the workers once
+        // they're done with their current binary.
+        for finished in rx {
+            let finished = finished?;
+            let (ref pkg, ref kind, ref test, ref exe) = compilation.tests[finished.index];
+            print_running(config, exe, &finished.cmd)?;
+            if options.compile_opts.message_format != MessageFormat::Json {
+                print!("{}", finished.stdout);
+            }
+            write!(config.shell().err(), "{}", finished.stderr)?;
+            if options.reports_results() {
+                finish_suite(options, finished.suite, false, suites);
+            }
+
+            if let Err(e) = finished.result {
+                let e = e.downcast::<ProcessError>()?;
+                errors.push((kind.clone(), test.clone(), pkg.name().to_string(), e));
+                if !options.no_fail_fast {
+                    stop.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Runs a test binary for `run_unit_tests_parallel`, collecting its output
+/// and the results of its tests.
+fn exec_tests_buffered(index: usize,
+                       cmd: ProcessBuilder,
+                       pkg: &PackageId,
+                       kind: &str,
+                       name: &str) -> Finished {
+    let mut parser = OutputParser::new();
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    let start = Instant::now();
+    let result = cmd.exec_with_streaming(
+        &mut |line| {
+            parser.line(line);
+            stdout.push_str(line);
+            stdout.push('\n');
+            Ok(())
+        },
+        &mut |line| {
+            stderr.push_str(line);
+            stderr.push('\n');
+            Ok(())
+        },
+        false,
+    ).map(|_| ());
+
+    let suite = parser.finish(pkg, kind, name, start.elapsed(), result.is_ok());
+    Finished { index, cmd, stdout, stderr, suite, result }
+}
+
+/// Prints the `Running` status of a test binary.
+fn print_running(config: &Config, exe: &Path, cmd: &ProcessBuilder) -> CargoResult<()> {
+    let to_display = match util::without_prefix(exe, config.cwd()) {
+        Some(path) => path,
+        None => exe,
+    };
+    config.shell().concise(|shell| {
+        shell.status("Running", to_display.display().to_string())
+    })?;
+    config.shell().verbose(|shell| {
+        shell.status("Running", cmd.to_string())
+    })
+}
+
 fn run_doc_tests(options: &TestOptions,
                  test_args: &[String],
-                 compilation: &Compilation)
+                 compilation: &Compilation,
+                 suites: &mut Vec<TestSuite>)
                  -> CargoResult<(Test, Vec<ProcessError>)> {
     let mut errors = Vec::new();
     let config = options.compile_opts.config;
@@ -141,8 +479,20 @@ fn run_doc_tests(options: &TestOptions,
                          .map(|t| (t.src_path(), t.name(), t.crate_name())))
     });
 
+    // The doctests of a library are counted as coming after all the test
+    // binaries for `--partition`. Even with the `hash` strategy they're
+    // assigned as a whole, as rustdoc splits the arguments it passes to the
+    // test harness on whitespace, which doctest names contain.
+    let mut index = compilation.tests.len();
     for (package, tests) in libs {
         for (lib, name, crate_name) in tests {
+            let included = options.partition.map_or(true, |partition| {
+                partition.includes(index, &format!("{}::doc::{}", package.name(), name))
+            });
+            index += 1;
+            if !included {
+                continue
+            }
             config.shell().status("Doc-tests", name)?;
             let mut p = compilation.rustdoc_process(package)?;
             p.arg("--test").arg(lib)
@@ -203,7 +553,8 @@ fn run_doc_tests(options: &TestOptions,
             config.shell().verbose(|shell| {
                 shell.status("Running", p.to_string())
             })?;
-            if let Err(e) = p.exec() {
+            if let Err(e) = exec_tests(options, &p, package.package_id(),
+                                       "doc", name, suites) {
                 let e = e.downcast::<ProcessError>()?;
                 errors.push(e);
                 if !options.no_fail_fast {
@@ -214,3 +565,88 @@ fn run_doc_tests(options: &TestOptions,
     }
     Ok((Test::Doc, errors))
 }
+
+/// Runs a test binary, or rustdoc testing a library, parsing the results of
+/// the individual tests out of its output if they're to be reported.
+fn exec_tests(options: &TestOptions,
+              cmd: &ProcessBuilder,
+              pkg: &PackageId,
+              kind: &str,
+              name: &str,
+              suites: &mut Vec<TestSuite>) -> CargoResult<()> {
+    if !options.reports_results() {
+        return cmd.exec()
+    }
+
+    let config = options.compile_opts.config;
+    let json = options.compile_opts.message_format == MessageFormat::Json;
+    let started = |test_count| {
+        if json {
+            machine_message::emit(&SuiteStarted {
+                package_id: pkg,
+                kind,
+                name,
+                test_count,
+            });
+        }
+    };
+
+    let mut parser = OutputParser::new();
+    let mut announced = false;
+    let start = Instant::now();
+    let result = cmd.exec_with_streaming(
+        &mut |line| {
+            if let Some(test_count) = parser.line(line) {
+                started(test_count);
+                announced = true;
+            }
+            // With JSON messages stdout is reserved for them.
+            if !json {
+                println!("{}", line);
+            }
+            Ok(())
+        },
+        &mut |line| {
+            writeln!(config.shell().err(), "{}", line)?;
+            Ok(())
+        },
+        false,
+    );
+
+    let suite = parser.finish(pkg, kind, name, start.elapsed(), result.is_ok());
+    finish_suite(options, suite, announced, suites);
+    result.map(|_| ())
+}
+
+/// Prints the results of a finished suite as JSON messages if requested,
+/// announcing it first unless it announced itself while running, and keeps
+/// it for the JUnit report.
+fn finish_suite(options: &TestOptions,
+                suite: TestSuite,
+                announced: bool,
+                suites: &mut Vec<TestSuite>) {
+    if options.compile_opts.message_format == MessageFormat::Json {
+        if !announced {
+            machine_message::emit(&SuiteStarted {
+                package_id: &suite.package_id,
+                kind: &suite.kind,
+                name: &suite.name,
+                test_count: suite.tests.len(),
+            });
+        }
+        test_report::emit(&suite);
+    }
+    suites.push(suite);
+}
+
+/// The name of the kind of a tested target, as used in reports.
+fn kind_name(kind: &TargetKind) -> &'static str {
+    match *kind {
+        TargetKind::Lib(..) => "lib",
+        TargetKind::Bin => "bin",
+        TargetKind::Test => "test",
+        TargetKind::Bench => "bench",
+        TargetKind::ExampleBin | TargetKind::ExampleLib(..) => "example",
+        TargetKind::CustomBuild => "custom-build",
+    }
+}
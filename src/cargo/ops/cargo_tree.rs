@@ -0,0 +1,236 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::str::{self, FromStr};
+
+use core::{PackageId, Resolve, Workspace};
+use core::dependency::Kind;
+use ops::{self, Packages};
+use util::{Cfg, Config};
+use util::errors::{CargoResult, CargoResultExt};
+
+pub struct TreeOptions<'a> {
+    pub features: Vec<String>,
+    pub no_default_features: bool,
+    pub all_features: bool,
+    /// The packages whose dependency trees are printed.
+    pub spec: Packages<'a>,
+    /// Only include dependencies which are active for this target triple.
+    pub target: Option<&'a str>,
+    /// The dependency kinds of the edges which are followed.
+    pub kinds: Vec<Kind>,
+    /// Print the reverse dependencies of this package instead.
+    pub invert: Option<&'a str>,
+    /// Print the reverse dependencies of every package present in the graph
+    /// at more than one version.
+    pub duplicates: bool,
+    /// Print the features activated for each package.
+    pub show_features: bool,
+}
+
+/// A graph of the resolved packages where each edge is labeled with the kind
+/// of dependency which introduced it.
+struct Graph<'a> {
+    edges: HashMap<&'a PackageId, Vec<(&'a PackageId, Kind)>>,
+}
+
+impl<'a> Graph<'a> {
+    fn new() -> Graph<'a> {
+        Graph { edges: HashMap::new() }
+    }
+
+    fn link(&mut self, from: &'a PackageId, to: &'a PackageId, kind: Kind) {
+        let edges = self.edges.entry(from).or_insert_with(Vec::new);
+        if !edges.contains(&(to, kind)) {
+            edges.push((to, kind));
+        }
+    }
+
+    fn edges(&self, id: &PackageId, kind: Kind) -> Vec<&'a PackageId> {
+        let mut ret = self.edges.get(id).map(|edges| {
+            edges.iter()
+                 .filter(|&&(_, k)| k == kind)
+                 .map(|&(id, _)| id)
+                 .collect::<Vec<_>>()
+        }).unwrap_or_default();
+        ret.sort();
+        ret
+    }
+
+    fn invert(&self) -> Graph<'a> {
+        let mut inverted = Graph::new();
+        for (&from, edges) in self.edges.iter() {
+            for &(to, kind) in edges {
+                inverted.link(to, from, kind);
+            }
+        }
+        inverted
+    }
+}
+
+/// Prints the resolved dependency graph of a workspace as a tree.
+///
+/// The output is returned rather than printed so that the caller decides
+/// where it goes.
+pub fn tree(ws: &Workspace, opts: &TreeOptions) -> CargoResult<String> {
+    let specs = opts.spec.into_package_id_specs(ws)?;
+    let (packages, resolve) = ops::resolve_ws_precisely(ws,
+                                                        None,
+                                                        &opts.features,
+                                                        opts.all_features,
+                                                        opts.no_default_features,
+                                                        &specs)?;
+
+    let cfg = match opts.target {
+        Some(target) => Some(target_cfg(ws.config(), target)?),
+        None => None,
+    };
+
+    packages.get_many(resolve.iter())?;
+
+    let mut graph = Graph::new();
+    for id in resolve.iter() {
+        let pkg = packages.get(id)?;
+        for dep_id in resolve.deps_not_replaced(id) {
+            let deps = pkg.dependencies().iter().filter(|dep| {
+                dep.matches_id(dep_id) && opts.kinds.contains(&dep.kind())
+            }).filter(|dep| {
+                match (dep.platform(), opts.target) {
+                    (Some(platform), Some(target)) => {
+                        platform.matches(target, cfg.as_ref().map(|c| &c[..]))
+                    }
+                    _ => true,
+                }
+            });
+            let dep_id = resolve.replacement(dep_id).unwrap_or(dep_id);
+            for dep in deps {
+                graph.link(id, dep_id, dep.kind());
+            }
+        }
+    }
+
+    let mut out = String::new();
+    if opts.duplicates {
+        let inverted = graph.invert();
+        let mut by_name = BTreeMap::new();
+        for id in resolve.iter() {
+            by_name.entry(id.name()).or_insert_with(Vec::new).push(id);
+        }
+        for ids in by_name.values_mut().filter(|ids| ids.len() > 1) {
+            ids.sort();
+            for id in ids.iter() {
+                print_tree(id, &inverted, &resolve, opts, &mut out);
+            }
+        }
+    } else if let Some(spec) = opts.invert {
+        let id = resolve.query(spec)?;
+        print_tree(id, &graph.invert(), &resolve, opts, &mut out);
+    } else {
+        for spec in specs.iter() {
+            let id = spec.query(resolve.iter())?;
+            print_tree(id, &graph, &resolve, opts, &mut out);
+        }
+    }
+    Ok(out)
+}
+
+/// Asks rustc for the `cfg` values which are set when compiling for `target`.
+fn target_cfg(config: &Config, target: &str) -> CargoResult<Vec<Cfg>> {
+    let mut process = config.rustc()?.process();
+    process.arg("--print=cfg")
+           .arg("--target").arg(target)
+           .env_remove("RUST_LOG");
+    let output = process.exec_with_output().chain_err(|| {
+        format!("failed to run `rustc` to learn about target `{}`", target)
+    })?;
+    let output = str::from_utf8(&output.stdout).map_err(|_| {
+        format_err!("rustc didn't return utf8 output")
+    })?;
+    output.lines().map(Cfg::from_str).collect()
+}
+
+fn print_tree(root: &PackageId,
+              graph: &Graph,
+              resolve: &Resolve,
+              opts: &TreeOptions,
+              out: &mut String) {
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    let mut visited = HashSet::new();
+    let mut levels_continue = Vec::new();
+    print_package(root, graph, resolve, opts, &mut visited,
+                  &mut levels_continue, out);
+}
+
+fn print_package<'a>(id: &'a PackageId,
+                     graph: &Graph<'a>,
+                     resolve: &Resolve,
+                     opts: &TreeOptions,
+                     visited: &mut HashSet<&'a PackageId>,
+                     levels_continue: &mut Vec<bool>,
+                     out: &mut String) {
+    if let Some((&last, rest)) = levels_continue.split_last() {
+        for &continues in rest {
+            out.push_str(if continues { "│   " } else { "    " });
+        }
+        out.push_str(if last { "├── " } else { "└── " });
+    }
+
+    write!(out, "{}", id).unwrap();
+    if opts.show_features {
+        let features = resolve.features_sorted(id);
+        if !features.is_empty() {
+            write!(out, " [{}]", features.join(", ")).unwrap();
+        }
+    }
+
+    let new = visited.insert(id);
+    if !new {
+        out.push_str(" (*)\n");
+        return
+    }
+    out.push('\n');
+
+    for &kind in [Kind::Normal, Kind::Build, Kind::Development].iter() {
+        let deps = graph.edges(id, kind);
+        if deps.is_empty() {
+            continue
+        }
+
+        let heading = match kind {
+            Kind::Normal => None,
+            Kind::Build => Some("[build-dependencies]"),
+            Kind::Development => Some("[dev-dependencies]"),
+        };
+        if let Some(heading) = heading {
+            for &continues in levels_continue.iter() {
+                out.push_str(if continues { "│   " } else { "    " });
+            }
+            out.push_str(heading);
+            out.push('\n');
+        }
+
+        for (i, &dep) in deps.iter().enumerate() {
+            levels_continue.push(i + 1 < deps.len());
+            print_package(dep, graph, resolve, opts, visited,
+                          levels_continue, out);
+            levels_continue.pop();
+        }
+    }
+}
+
+/// Parses a comma or whitespace separated list of dependency kinds as
+/// accepted by `cargo tree --kind`.
+pub fn parse_dependency_kinds(s: &str) -> CargoResult<Vec<Kind>> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+     .filter(|s| !s.is_empty())
+     .map(|s| {
+        match s {
+            "normal" => Ok(Kind::Normal),
+            "build" => Ok(Kind::Build),
+            "dev" => Ok(Kind::Development),
+            _ => bail!("invalid dependency kind `{}`, expected one of \
+                        `normal`, `build` or `dev`", s),
+        }
+    }).collect()
+}
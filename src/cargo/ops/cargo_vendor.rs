@@ -0,0 +1,197 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use hex;
+use serde_json;
+
+use core::{GitReference, SourceId, Workspace};
+use ops;
+use sources::PathSource;
+use util::Sha256;
+use util::errors::{CargoResult, CargoResultExt};
+use util::paths;
+
+pub struct VendorOptions<'a> {
+    /// The directory into which all packages are copied, as it should be
+    /// written in the generated `[source]` configuration.
+    pub destination: &'a Path,
+    /// Don't remove packages from the destination which are no longer part
+    /// of the dependency graph.
+    pub no_delete: bool,
+}
+
+/// The contents of `.cargo-checksum.json` as verified by `DirectorySource`.
+#[derive(Serialize)]
+struct VendorChecksum<'a> {
+    package: Option<&'a str>,
+    files: BTreeMap<String, String>,
+}
+
+/// Name of the `[source]` entry which the generated configuration points
+/// replaced sources at.
+const VENDORED_SOURCES: &'static str = "vendored-sources";
+
+/// Copies every non-path dependency of the workspace into `opts.destination`
+/// in the layout that `DirectorySource` understands.
+///
+/// Returns the `.cargo/config` snippet which replaces the original sources
+/// with the vendored directory.
+pub fn vendor(ws: &Workspace, opts: &VendorOptions) -> CargoResult<String> {
+    let config = ws.config();
+    let (packages, resolve) = ops::resolve_ws(ws)?;
+
+    let mut ids = resolve.iter()
+                         .filter(|id| !id.source_id().is_path())
+                         .collect::<Vec<_>>();
+    ids.sort();
+
+    // Every source is replaced with the same directory, which can only hold
+    // one package with a given name and version
+    let mut dir_names = HashMap::new();
+    for id in ids.iter() {
+        let dir_name = format!("{}-{}", id.name(), id.version());
+        if let Some(prev) = dir_names.insert(dir_name, id) {
+            bail!("found duplicate version of package `{} v{}` vendored from \
+                   two sources:\n\n  source 1: {}\n  source 2: {}",
+                  id.name(), id.version(), prev.source_id(), id.source_id())
+        }
+    }
+    packages.get_many(ids.iter().cloned())?;
+
+    let dst = config.cwd().join(opts.destination);
+    fs::create_dir_all(&dst).chain_err(|| {
+        format!("failed to create directory `{}`", dst.display())
+    })?;
+
+    let mut sources = BTreeSet::new();
+    let mut vendored = HashSet::new();
+    for id in ids {
+        let pkg = packages.get(id)?;
+        let dir_name = format!("{}-{}", id.name(), id.version());
+        let pkg_dst = dst.join(&dir_name);
+
+        config.shell().status("Vendoring", id)?;
+        if pkg_dst.exists() {
+            paths::remove_dir_all(&pkg_dst)?;
+        }
+
+        let src = PathSource::new(pkg.root(), id.source_id(), config);
+        let mut files = BTreeMap::new();
+        for file in src.list_files(pkg)? {
+            let relative = file.strip_prefix(pkg.root()).unwrap();
+            let cksum = copy_and_checksum(&file, &pkg_dst.join(relative))
+                .chain_err(|| {
+                    format!("failed to copy `{}` for package `{}`",
+                            relative.display(), id)
+                })?;
+            let relative = relative.to_str().ok_or_else(|| {
+                format_err!("non-utf8 path in package `{}`: {}",
+                            id, relative.display())
+            })?;
+            // Checksum files are keyed with forward slashes on all platforms
+            files.insert(relative.replace("\\", "/"), cksum);
+        }
+
+        let checksum = VendorChecksum {
+            package: resolve.checksums().get(id)
+                            .and_then(|c| c.as_ref())
+                            .map(|s| &s[..]),
+            files,
+        };
+        let json = serde_json::to_string(&checksum)?;
+        paths::write(&pkg_dst.join(".cargo-checksum.json"), json.as_bytes())?;
+
+        sources.insert(id.source_id().clone());
+        vendored.insert(dir_name);
+    }
+
+    if !opts.no_delete {
+        remove_stale(&dst, &vendored, ws)?;
+    }
+
+    Ok(source_config(&sources, opts.destination))
+}
+
+/// Removes previously vendored packages which are no longer needed.
+///
+/// Only directories containing a `.cargo-checksum.json` are considered, so
+/// anything else that lives next to the vendored sources is left alone.
+fn remove_stale(dst: &Path,
+                vendored: &HashSet<String>,
+                ws: &Workspace) -> CargoResult<()> {
+    let entries = dst.read_dir().chain_err(|| {
+        format!("failed to read directory `{}`", dst.display())
+    })?;
+    for entry in entries {
+        let path = entry?.path();
+        let is_stale = match path.file_name().and_then(|s| s.to_str()) {
+            Some(name) => !vendored.contains(name),
+            None => false,
+        };
+        if is_stale && path.join(".cargo-checksum.json").exists() {
+            ws.config().shell().status("Removing", path.display())?;
+            paths::remove_dir_all(&path)?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_and_checksum(src: &Path, dst: &Path) -> CargoResult<String> {
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut src = File::open(src)?;
+    let mut dst = File::create(dst)?;
+    let mut cksum = Sha256::new();
+    let mut buf = [0; 64 * 1024];
+    loop {
+        let n = src.read(&mut buf)?;
+        if n == 0 {
+            break
+        }
+        cksum.update(&buf[..n]);
+        dst.write_all(&buf[..n])?;
+    }
+    // Preserve the executable bit of scripts and the like
+    let permissions = src.metadata()?.permissions();
+    dst.set_permissions(permissions)?;
+    Ok(hex::encode(cksum.finish()))
+}
+
+/// Generates the `[source]` tables which `SourceConfigMap` needs to replace
+/// `sources` with the vendored directory at `destination`.
+fn source_config(sources: &BTreeSet<SourceId>, destination: &Path) -> String {
+    let mut config = String::new();
+    for source in sources.iter() {
+        if source.is_default_registry() {
+            config.push_str("[source.crates-io]\n");
+        } else {
+            let url = source.url().to_string();
+            config.push_str(&format!("[source.\"{}\"]\n", url));
+            if source.is_registry() {
+                config.push_str(&format!("registry = \"{}\"\n", url));
+            } else if let Some(reference) = source.git_reference() {
+                config.push_str(&format!("git = \"{}\"\n", url));
+                match *reference {
+                    GitReference::Branch(ref b) if b == "master" => {}
+                    GitReference::Branch(ref b) => {
+                        config.push_str(&format!("branch = \"{}\"\n", b));
+                    }
+                    GitReference::Tag(ref t) => {
+                        config.push_str(&format!("tag = \"{}\"\n", t));
+                    }
+                    GitReference::Rev(ref r) => {
+                        config.push_str(&format!("rev = \"{}\"\n", r));
+                    }
+                }
+            }
+        }
+        config.push_str(&format!("replace-with = \"{}\"\n\n", VENDORED_SOURCES));
+    }
+    config.push_str(&format!("[source.{}]\n", VENDORED_SOURCES));
+    config.push_str(&format!("directory = \"{}\"\n",
+                             destination.display().to_string().replace("\\", "/")));
+    config
+}
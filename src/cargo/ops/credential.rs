@@ -0,0 +1,181 @@
+//! Obtaining and storing the API tokens of registries.
+//!
+//! Tokens are kept by a credential provider. Unless configured otherwise
+//! this is Cargo's own plaintext `credentials` file in `$CARGO_HOME`, but
+//! `registry.credential-process`, or `registries.<name>.credential-process`
+//! for an alternative registry, can name a program to keep them instead.
+//! Setting it to `cargo:token` selects the built-in store explicitly.
+//!
+//! The program is run once per operation with its configured arguments. It's
+//! sent a single line of JSON on stdin:
+//!
+//! ```json
+//! {"v":1,"action":"get","registry":"my-registry","index-url":"https://..."}
+//! ```
+//!
+//! where `action` is one of `get`, `store` or `erase`, `registry` is `null`
+//! for the default registry and `store` requests carry the `token` to keep.
+//! The program answers with a JSON object on stdout, `{"token":"..."}` for
+//! a `get` (`{}` if it has no token) and `{}` or nothing at all otherwise.
+//! Failures are reported with `{"error":"..."}` or a non-zero exit status.
+//! Its stderr is inherited so it can print messages of its own.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use serde_json;
+
+use core::SourceId;
+use util::{process, Config};
+use util::config;
+use util::errors::{CargoResult, CargoResultExt};
+
+/// The version of the protocol spoken with credential processes.
+const PROTOCOL_VERSION: u32 = 1;
+
+/// The name of the built-in provider, for use in `credential-process`.
+const BUILTIN_PROVIDER: &'static str = "cargo:token";
+
+#[derive(Serialize)]
+struct Request<'a> {
+    v: u32,
+    action: &'a str,
+    registry: Option<&'a str>,
+    #[serde(rename = "index-url")]
+    index_url: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<&'a str>,
+}
+
+#[derive(Deserialize, Default)]
+struct Response {
+    token: Option<String>,
+    error: Option<String>,
+}
+
+/// Returns the token for `registry`, or the default registry if `None`,
+/// whose index is at `sid`.
+pub fn get_token(config: &Config,
+                 registry: Option<&str>,
+                 sid: &SourceId) -> CargoResult<Option<String>> {
+    match credential_process(config, registry)? {
+        Some((program, args)) => {
+            let response = run(&program, &args, "get", registry, sid, None)?;
+            Ok(response.token)
+        }
+        None => {
+            let key = match registry {
+                Some(registry) => format!("registries.{}.token", registry),
+                None => "registry.token".to_string(),
+            };
+            Ok(config.get_string(&key)?.map(|p| p.val))
+        }
+    }
+}
+
+/// Hands `token` to the credential provider of `registry` to keep.
+pub fn store_token(config: &Config,
+                   registry: Option<&str>,
+                   sid: &SourceId,
+                   token: &str) -> CargoResult<()> {
+    match credential_process(config, registry)? {
+        Some((program, args)) => {
+            run(&program, &args, "store", registry, sid, Some(token))?;
+            Ok(())
+        }
+        None => {
+            // Avoid rewriting the file if nothing changed.
+            if get_token(config, registry, sid)?.as_ref().map(|s| &s[..]) == Some(token) {
+                return Ok(())
+            }
+            config::save_credentials(config,
+                                     token.to_string(),
+                                     registry.map(|s| s.to_string()))
+        }
+    }
+}
+
+/// Asks the credential provider of `registry` to forget its token.
+pub fn erase_token(config: &Config,
+                   registry: Option<&str>,
+                   sid: &SourceId) -> CargoResult<()> {
+    match credential_process(config, registry)? {
+        Some((program, args)) => {
+            run(&program, &args, "erase", registry, sid, None)?;
+            Ok(())
+        }
+        None => config::erase_credentials(config, registry.map(|s| s.to_string())),
+    }
+}
+
+/// Looks up the configured credential process of `registry`, `None` meaning
+/// the built-in provider.
+fn credential_process(config: &Config, registry: Option<&str>)
+                      -> CargoResult<Option<(PathBuf, Vec<String>)>> {
+    let key = match registry {
+        Some(registry) => format!("registries.{}.credential-process", registry),
+        None => "registry.credential-process".to_string(),
+    };
+    Ok(config.get_path_and_args(&key)?.map(|v| v.val).and_then(|(program, args)| {
+        if program == Path::new(BUILTIN_PROVIDER) {
+            None
+        } else {
+            Some((program, args))
+        }
+    }))
+}
+
+fn run(program: &Path,
+       args: &[String],
+       action: &str,
+       registry: Option<&str>,
+       sid: &SourceId,
+       token: Option<&str>) -> CargoResult<Response> {
+    let index_url = sid.url().to_string();
+    let request = Request {
+        v: PROTOCOL_VERSION,
+        action,
+        registry,
+        index_url: &index_url,
+        token,
+    };
+    let mut request = serde_json::to_string(&request)?;
+    request.push('\n');
+
+    let mut cmd = process(program);
+    cmd.args(args);
+    let describe = || format!("failed to run credential process {}", cmd);
+
+    let mut child = cmd.build_command()
+                       .stdin(Stdio::piped())
+                       .stdout(Stdio::piped())
+                       .stderr(Stdio::inherit())
+                       .spawn()
+                       .chain_err(&describe)?;
+    // The process may exit without reading all of its request, so failing
+    // to write it only matters if the process otherwise succeeded.
+    let written = child.stdin.take().unwrap().write_all(request.as_bytes());
+    let mut stdout = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut stdout).chain_err(&describe)?;
+    let status = child.wait().chain_err(&describe)?;
+    if !status.success() {
+        bail!("credential process {} failed to {} the token ({})",
+              cmd, action, status)
+    }
+    written.chain_err(&describe)?;
+
+    let response = if stdout.trim().is_empty() {
+        Response::default()
+    } else {
+        serde_json::from_str::<Response>(&stdout).chain_err(|| {
+            format!("credential process {} returned invalid output: {}",
+                    cmd, stdout.trim())
+        })?
+    };
+    if let Some(error) = response.error {
+        bail!("credential process {} failed to {} the token: {}",
+              cmd, action, error)
+    }
+    Ok(response)
+}
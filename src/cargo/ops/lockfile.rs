@@ -3,7 +3,7 @@ use std::io::prelude::*;
 use toml;
 
 use core::{Resolve, resolver, Workspace};
-use core::resolver::WorkspaceResolve;
+use core::resolver::{ResolveVersion, WorkspaceResolve};
 use util::Filesystem;
 use util::errors::{CargoResult, CargoResultExt};
 use util::toml as cargo_toml;
@@ -23,8 +23,18 @@ pub fn load_pkg_lockfile(ws: &Workspace) -> CargoResult<Option<Resolve>> {
 
     let resolve = (|| -> CargoResult<Option<Resolve>> {
         let resolve : toml::Value = cargo_toml::parse(&s, f.path(), ws.config())?;
+        let has_metadata = resolve.get("metadata").is_some();
         let v: resolver::EncodableResolve = resolve.try_into()?;
-        Ok(Some(v.into_resolve(ws)?))
+        let mut resolve = v.into_resolve(ws)?;
+        // Without any dependencies or checksums a version 2 lock file can
+        // only be told apart by not ending in a blank line
+        if resolve.version() == ResolveVersion::V1 && !has_metadata &&
+           resolve.replacements().is_empty() &&
+           resolve.iter().all(|id| resolve.deps(id).next().is_none()) &&
+           !s.ends_with("\n\n") && !s.ends_with("\r\n\r\n") {
+            resolve.set_version(ResolveVersion::V2);
+        }
+        Ok(Some(resolve))
     })().chain_err(|| {
         format!("failed to parse lock file at: {}", f.path().display())
     })?;
@@ -65,6 +75,12 @@ pub fn write_pkg_lockfile(ws: &Workspace, resolve: &Resolve) -> CargoResult<()>
     if let Some(meta) = toml.get("metadata") {
         out.push_str("[metadata]\n");
         out.push_str(&meta.to_string());
+    } else if resolve.version() == ResolveVersion::V2 {
+        // Version 2 lock files don't end in a blank line, which would
+        // otherwise be left over from the last package
+        let len = out.trim_right().len();
+        out.truncate(len);
+        out.push_str("\n");
     }
 
     // If the lockfile contents haven't changed so don't rewrite it. This is
@@ -76,7 +92,7 @@ pub fn write_pkg_lockfile(ws: &Workspace, resolve: &Resolve) -> CargoResult<()>
     }
 
     if !ws.config().lock_update_allowed() {
-        if ws.config().cli_unstable().offline {
+        if ws.config().offline() {
             bail!("can't update in the offline mode");
         }
 
@@ -136,6 +152,10 @@ fn emit_package(dep: &toml::value::Table, out: &mut String) {
         out.push_str(&format!("source = {}\n", &dep["source"]));
     }
 
+    if let Some(checksum) = dep.get("checksum") {
+        out.push_str(&format!("checksum = {}\n", checksum));
+    }
+
     if let Some(s) = dep.get("dependencies") {
         let slice = s.as_array().unwrap();
 
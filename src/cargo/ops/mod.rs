@@ -1,8 +1,9 @@
+pub use self::cargo_add::{add, remove, AddOptions, RemoveOptions};
 pub use self::cargo_clean::{clean, CleanOptions};
 pub use self::cargo_compile::{compile, compile_with_exec, compile_ws, CompileOptions};
 pub use self::cargo_compile::{CompileFilter, CompileMode, FilterRule, MessageFormat, Packages};
 pub use self::cargo_read_manifest::{read_package, read_packages};
-pub use self::cargo_rustc::{compile_targets, Compilation, Kind, Unit};
+pub use self::cargo_rustc::{compile_targets, Compilation, FeaturesFor, Kind, Unit};
 pub use self::cargo_rustc::{Context, is_bad_artifact_name};
 pub use self::cargo_rustc::{BuildOutput, BuildConfig, TargetConfig};
 pub use self::cargo_rustc::{Executor, DefaultExecutor};
@@ -15,23 +16,31 @@ pub use self::cargo_generate_lockfile::{update_lockfile};
 pub use self::cargo_generate_lockfile::UpdateOptions;
 pub use self::lockfile::{load_pkg_lockfile, write_pkg_lockfile};
 pub use self::cargo_test::{run_tests, run_benches, TestOptions};
+pub use self::cargo_test::{Partition, PartitionStrategy};
 pub use self::cargo_package::{package, PackageOpts};
 pub use self::registry::{publish, registry_configuration, RegistryConfig};
-pub use self::registry::{registry_login, search, needs_custom_http_transport, http_handle};
+pub use self::registry::{registry_login, registry_logout, search, needs_custom_http_transport, http_handle};
 pub use self::registry::{modify_owners, yank, OwnersOptions, PublishOpts};
 pub use self::registry::configure_http_handle;
 pub use self::cargo_fetch::fetch;
+pub use self::cargo_fix::{fix, FixOptions};
+pub use self::cargo_outdated::{outdated, OutdatedOptions, OutdatedReport, OutdatedDependency};
 pub use self::cargo_pkgid::pkgid;
+pub use self::cargo_tree::{tree, parse_dependency_kinds, TreeOptions};
+pub use self::cargo_vendor::{vendor, VendorOptions};
 pub use self::resolve::{resolve_ws, resolve_ws_precisely, resolve_ws_with_method, resolve_with_previous};
 pub use self::cargo_output_metadata::{output_metadata, OutputMetadataOptions, ExportInfo};
 
+mod cargo_add;
 mod cargo_clean;
 mod cargo_compile;
 mod cargo_doc;
 mod cargo_fetch;
+mod cargo_fix;
 mod cargo_generate_lockfile;
 mod cargo_install;
 mod cargo_new;
+mod cargo_outdated;
 mod cargo_output_metadata;
 mod cargo_package;
 mod cargo_pkgid;
@@ -39,6 +48,10 @@ mod cargo_read_manifest;
 mod cargo_run;
 mod cargo_rustc;
 mod cargo_test;
+mod cargo_tree;
+mod cargo_vendor;
+mod credential;
 mod lockfile;
 mod registry;
 mod resolve;
+mod test_report;
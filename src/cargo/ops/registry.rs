@@ -15,8 +15,9 @@ use core::{Package, SourceId, Workspace};
 use core::dependency::Kind;
 use core::manifest::ManifestMetadata;
 use ops;
+use ops::credential;
 use sources::{RegistrySource};
-use util::config::{self, Config};
+use util::config::Config;
 use util::paths;
 use util::ToUrl;
 use util::errors::{CargoResult, CargoResultExt};
@@ -24,7 +25,6 @@ use util::important_paths::find_root_manifest_for_wd;
 
 pub struct RegistryConfig {
     pub index: Option<String>,
-    pub token: Option<String>,
 }
 
 pub struct PublishOpts<'cfg> {
@@ -42,6 +42,10 @@ pub struct PublishOpts<'cfg> {
 pub fn publish(ws: &Workspace, opts: &PublishOpts) -> CargoResult<()> {
     let pkg = ws.current()?;
 
+    if ws.config().offline() {
+        bail!("can't publish `{}` in the offline mode (--offline)", pkg.name());
+    }
+
     // Allow publishing if a registry has been provided, or if there are no nightly
     // features enabled.
     if opts.registry.is_none() && !pkg.manifest().features().activated().is_empty() {
@@ -65,7 +69,8 @@ pub fn publish(ws: &Workspace, opts: &PublishOpts) -> CargoResult<()> {
     let (mut registry, reg_id) = registry(opts.config,
                                           opts.token.clone(),
                                           opts.index.clone(),
-                                          opts.registry.clone())?;
+                                          opts.registry.clone(),
+                                          true)?;
     verify_dependencies(pkg, &reg_id)?;
 
     // Prepare a tarball, with a non-surpressable warning if metadata
@@ -195,6 +200,7 @@ fn transmit(config: &Config,
         license_file: license_file.clone(),
         badges: badges.clone(),
         links: links.clone(),
+        rust_version: pkg.manifest().rust_version().map(|v| v.to_string()),
     }, tarball);
 
     match publish {
@@ -228,39 +234,50 @@ fn transmit(config: &Config,
 pub fn registry_configuration(config: &Config,
                               registry: Option<String>) -> CargoResult<RegistryConfig> {
 
-    let (index, token) = match registry {
-        Some(registry) => {
-            (Some(config.get_registry_index(&registry)?.to_string()),
-             config.get_string(&format!("registries.{}.token", registry))?.map(|p| p.val))
-        }
-        None => {
-            // Checking out for default index and token
-            (config.get_string("registry.index")?.map(|p| p.val),
-             config.get_string("registry.token")?.map(|p| p.val))
-        }
+    let index = match registry {
+        Some(registry) => Some(config.get_registry_index(&registry)?.to_string()),
+        // Checking out for default index
+        None => config.get_string("registry.index")?.map(|p| p.val),
     };
 
     Ok(RegistryConfig {
         index,
-        token
     })
 }
 
+/// Returns the source of `registry`, or of the default registry if `None`.
+fn registry_source_id(config: &Config, registry: Option<&str>) -> CargoResult<SourceId> {
+    match registry {
+        Some(registry) => SourceId::alt_registry(config, registry),
+        None => SourceId::crates_io(config),
+    }
+}
+
+/// Creates a client for the API of a registry.
+///
+/// Unless `token` is given, the credential provider of the registry is only
+/// asked for one if `authenticate` is set.
 pub fn registry(config: &Config,
                 token: Option<String>,
                 index: Option<String>,
-                registry: Option<String>) -> CargoResult<(Registry, SourceId)> {
+                registry: Option<String>,
+                authenticate: bool) -> CargoResult<(Registry, SourceId)> {
     // Parse all configuration options
     let RegistryConfig {
-        token: token_config,
         index: index_config,
     } = registry_configuration(config, registry.clone())?;
-    let token = token.or(token_config);
-    let sid = match (index_config, index, registry) {
-        (_, _, Some(registry)) => SourceId::alt_registry(config, &registry)?,
+    let sid = match (index_config, index, &registry) {
+        (_, _, &Some(ref registry)) => SourceId::alt_registry(config, registry)?,
         (Some(index), _, _) | (None, Some(index), _) => SourceId::for_registry(&index.to_url()?)?,
         (None, None, _) => SourceId::crates_io(config)?,
     };
+    let token = match token {
+        Some(token) => Some(token),
+        None if authenticate => {
+            credential::get_token(config, registry.as_ref().map(|s| &s[..]), &sid)?
+        }
+        None => None,
+    };
     let api_host = {
         let mut src = RegistrySource::remote(&sid, config);
         src.update().chain_err(|| {
@@ -371,18 +388,15 @@ fn http_timeout(config: &Config) -> CargoResult<Option<i64>> {
 pub fn registry_login(config: &Config,
                       token: String,
                       registry: Option<String>) -> CargoResult<()> {
-    let RegistryConfig {
-        token: old_token,
-        ..
-    } = registry_configuration(config, registry.clone())?;
-
-    if let Some(old_token) = old_token {
-        if old_token == token {
-            return Ok(());
-        }
-    }
+    let registry = registry.as_ref().map(|s| &s[..]);
+    let sid = registry_source_id(config, registry)?;
+    credential::store_token(config, registry, &sid, &token)
+}
 
-    config::save_credentials(config, token, registry)
+pub fn registry_logout(config: &Config, registry: Option<String>) -> CargoResult<()> {
+    let registry = registry.as_ref().map(|s| &s[..]);
+    let sid = registry_source_id(config, registry)?;
+    credential::erase_token(config, registry, &sid)
 }
 
 pub struct OwnersOptions {
@@ -408,7 +422,8 @@ pub fn modify_owners(config: &Config, opts: &OwnersOptions) -> CargoResult<()> {
     let (mut registry, _) = registry(config,
                                      opts.token.clone(),
                                      opts.index.clone(),
-                                     opts.registry.clone())?;
+                                     opts.registry.clone(),
+                                     true)?;
 
     if let Some(ref v) = opts.to_add {
         let v = v.iter().map(|s| &s[..]).collect::<Vec<_>>();
@@ -466,7 +481,7 @@ pub fn yank(config: &Config,
         None => bail!("a version must be specified to yank")
     };
 
-    let (mut registry, _) = registry(config, token, index, reg)?;
+    let (mut registry, _) = registry(config, token, index, reg, true)?;
 
     if undo {
         config.shell().status("Unyank", format!("{}:{}", name, version))?;
@@ -500,7 +515,7 @@ pub fn search(query: &str,
         prefix
     }
 
-    let (mut registry, _) = registry(config, None, index, reg)?;
+    let (mut registry, _) = registry(config, None, index, reg, false)?;
     let (crates, total_crates) = registry.search(query, limit).chain_err(|| {
         "failed to retrieve search results from the registry"
     })?;
@@ -5,7 +5,7 @@ use core::registry::PackageRegistry;
 use core::resolver::{self, Resolve, Method};
 use sources::PathSource;
 use ops;
-use util::profile;
+use util::{profile, RustVersion};
 use util::errors::{CargoResult, CargoResultExt};
 
 /// Resolve all dependencies for the workspace using the previous
@@ -277,9 +277,11 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
         None => root_replace.to_vec(),
     };
 
+    let rust_version = resolve_rust_version(ws)?;
     let mut resolved = resolver::resolve(&summaries,
                                          &replace,
                                          registry,
+                                         rust_version.as_ref(),
                                          Some(ws.config()),
                                          warn)?;
     resolved.register_used_patches(registry.patches());
@@ -289,6 +291,30 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
     Ok(resolved)
 }
 
+/// The `rust-version` which dependencies should preferably support, if the
+/// `resolver.incompatible-rust-versions` configuration is set to `fallback`.
+///
+/// This is the lowest `rust-version` of the workspace members, or the version
+/// of the current compiler if none of them declares one.
+fn resolve_rust_version(ws: &Workspace) -> CargoResult<Option<RustVersion>> {
+    let config = ws.config();
+    match config.get_string("resolver.incompatible-rust-versions")? {
+        None => return Ok(None),
+        Some(ref v) if v.val == "allow" => return Ok(None),
+        Some(ref v) if v.val == "fallback" => {}
+        Some(v) => {
+            bail!("`resolver.incompatible-rust-versions` must be one of \
+                   `allow` or `fallback`, but found `{}` in {}",
+                  v.val, v.definition)
+        }
+    }
+    let lowest = ws.members().filter_map(|m| m.manifest().rust_version()).min();
+    match lowest {
+        Some(v) => Ok(Some(v.clone())),
+        None => Ok(Some(RustVersion::from(&config.rustc()?.version))),
+    }
+}
+
 /// Read the `paths` configuration variable to discover all path overrides that
 /// have been configured.
 fn add_overrides<'a>(registry: &mut PackageRegistry<'a>,
@@ -0,0 +1,333 @@
+//! Per-test results of `cargo test`.
+//!
+//! The output of libtest, whether from a test binary or from rustdoc running
+//! doctests, is parsed line by line into the results of the individual tests
+//! of a suite. Finished suites are reported as JSON messages with
+//! `--message-format json`, and collected into a JUnit XML file with
+//! `--junit`.
+
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use core::PackageId;
+use util::errors::{CargoResult, CargoResultExt};
+use util::machine_message::{self, Message};
+
+/// What happened to a single test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TestOutcome {
+    Ok,
+    Failed,
+    Ignored,
+    /// A benchmark which was run
+    Measured,
+}
+
+#[derive(Debug)]
+pub struct TestCase {
+    pub name: String,
+    pub outcome: TestOutcome,
+    /// The output captured by libtest, which it only prints for failed tests
+    pub stdout: Option<String>,
+}
+
+/// The results of one test binary, or of the doctests of one library.
+#[derive(Debug)]
+pub struct TestSuite {
+    pub package_id: PackageId,
+    /// The kind of target tested, like `lib` or `test`, or `doc` for doctests
+    pub kind: String,
+    pub name: String,
+    pub tests: Vec<TestCase>,
+    pub filtered_out: usize,
+    pub time: Duration,
+    /// Whether the process exited successfully
+    pub success: bool,
+}
+
+impl TestSuite {
+    pub fn count(&self, outcome: TestOutcome) -> usize {
+        self.tests.iter().filter(|t| t.outcome == outcome).count()
+    }
+
+    fn classname(&self) -> String {
+        format!("{}::{}::{}", self.package_id.name(), self.kind, self.name)
+    }
+}
+
+/// Incrementally parses the stdout of libtest.
+pub struct OutputParser {
+    /// The number of tests announced by `running N tests`
+    test_count: Option<usize>,
+    tests: Vec<TestCase>,
+    filtered_out: usize,
+    /// The test whose output is being read, from a `---- NAME stdout ----`
+    /// header up to the next header or the list of failures.
+    capturing: Option<(usize, String)>,
+}
+
+impl OutputParser {
+    pub fn new() -> OutputParser {
+        OutputParser {
+            test_count: None,
+            tests: Vec::new(),
+            filtered_out: 0,
+            capturing: None,
+        }
+    }
+
+    /// Feeds a line of output to the parser, returning the number of tests
+    /// about to run if the line announced them.
+    pub fn line(&mut self, line: &str) -> Option<usize> {
+        if self.capturing.is_some() {
+            if line.starts_with("---- ") || line == "failures:" {
+                self.finish_capture();
+            } else {
+                let stdout = &mut self.capturing.as_mut().unwrap().1;
+                stdout.push_str(line);
+                stdout.push('\n');
+                return None
+            }
+        }
+
+        if line.starts_with("running ") && self.test_count.is_none() {
+            let count = line["running ".len()..].split(' ').next()
+                                                .and_then(|n| n.parse().ok());
+            self.test_count = count;
+            return count
+        }
+
+        if line.starts_with("test result: ") {
+            self.summary(&line["test result: ".len()..]);
+        } else if line.starts_with("test ") {
+            self.result(&line["test ".len()..]);
+        } else if line.starts_with("---- ") && line.ends_with(" stdout ----") {
+            let name = &line["---- ".len()..line.len() - " stdout ----".len()];
+            if let Some(i) = self.tests.iter().position(|t| t.name == name) {
+                self.capturing = Some((i, String::new()));
+            }
+        }
+        None
+    }
+
+    /// Parses `NAME ... RESULT` lines.
+    fn result(&mut self, line: &str) {
+        let idx = match line.rfind(" ... ") {
+            Some(idx) => idx,
+            None => return,
+        };
+        let (name, result) = (&line[..idx], &line[idx + " ... ".len()..]);
+        let outcome = if result == "ok" {
+            TestOutcome::Ok
+        } else if result.starts_with("FAILED") {
+            TestOutcome::Failed
+        } else if result.starts_with("ignored") {
+            TestOutcome::Ignored
+        } else if result.starts_with("bench:") {
+            TestOutcome::Measured
+        } else {
+            return
+        };
+        self.tests.push(TestCase {
+            name: name.to_string(),
+            outcome,
+            stdout: None,
+        });
+    }
+
+    /// Parses the `ok. 1 passed; 0 failed; ...` summary, of which only the
+    /// number of filtered out tests isn't known from the individual results.
+    fn summary(&mut self, line: &str) {
+        for part in line.split(|c| c == '.' || c == ';') {
+            let part = part.trim();
+            if part.ends_with(" filtered out") {
+                if let Ok(n) = part[..part.len() - " filtered out".len()].parse() {
+                    self.filtered_out = n;
+                }
+            }
+        }
+    }
+
+    fn finish_capture(&mut self) {
+        if let Some((i, stdout)) = self.capturing.take() {
+            let stdout = stdout.trim_right_matches('\n');
+            self.tests[i].stdout = Some(stdout.to_string());
+        }
+    }
+
+    pub fn finish(mut self,
+                  package_id: &PackageId,
+                  kind: &str,
+                  name: &str,
+                  time: Duration,
+                  success: bool) -> TestSuite {
+        self.finish_capture();
+        TestSuite {
+            package_id: package_id.clone(),
+            kind: kind.to_string(),
+            name: name.to_string(),
+            tests: self.tests,
+            filtered_out: self.filtered_out,
+            time,
+            success,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SuiteStarted<'a> {
+    pub package_id: &'a PackageId,
+    pub kind: &'a str,
+    pub name: &'a str,
+    pub test_count: usize,
+}
+
+impl<'a> Message for SuiteStarted<'a> {
+    fn reason(&self) -> &str {
+        "test-suite-started"
+    }
+}
+
+#[derive(Serialize)]
+struct TestResult<'a> {
+    package_id: &'a PackageId,
+    kind: &'a str,
+    suite: &'a str,
+    name: &'a str,
+    outcome: TestOutcome,
+    stdout: Option<&'a str>,
+}
+
+impl<'a> Message for TestResult<'a> {
+    fn reason(&self) -> &str {
+        "test-result"
+    }
+}
+
+#[derive(Serialize)]
+struct SuiteFinished<'a> {
+    package_id: &'a PackageId,
+    kind: &'a str,
+    name: &'a str,
+    passed: usize,
+    failed: usize,
+    ignored: usize,
+    measured: usize,
+    filtered_out: usize,
+    /// The time the suite took to run, in seconds
+    exec_time: f64,
+    success: bool,
+}
+
+impl<'a> Message for SuiteFinished<'a> {
+    fn reason(&self) -> &str {
+        "test-suite-finished"
+    }
+}
+
+/// Prints the results of a finished suite as JSON messages, the suite having
+/// been announced with `SuiteStarted` already.
+pub fn emit(suite: &TestSuite) {
+    for test in suite.tests.iter() {
+        machine_message::emit(&TestResult {
+            package_id: &suite.package_id,
+            kind: &suite.kind,
+            suite: &suite.name,
+            name: &test.name,
+            outcome: test.outcome,
+            stdout: test.stdout.as_ref().map(|s| &s[..]),
+        });
+    }
+    machine_message::emit(&SuiteFinished {
+        package_id: &suite.package_id,
+        kind: &suite.kind,
+        name: &suite.name,
+        passed: suite.count(TestOutcome::Ok),
+        failed: suite.count(TestOutcome::Failed),
+        ignored: suite.count(TestOutcome::Ignored),
+        measured: suite.count(TestOutcome::Measured),
+        filtered_out: suite.filtered_out,
+        exec_time: secs(suite.time),
+        success: suite.success,
+    });
+}
+
+/// Writes all suites into a single JUnit XML report at `path`.
+pub fn write_junit(path: &Path, suites: &[TestSuite]) -> CargoResult<()> {
+    let mut out = String::new();
+    let total = |outcome| suites.iter().map(|s| s.count(outcome)).sum::<usize>();
+    let time = suites.iter().map(|s| secs(s.time)).sum::<f64>();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(out, "<testsuites tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+             suites.iter().map(|s| s.tests.len()).sum::<usize>(),
+             total(TestOutcome::Failed),
+             total(TestOutcome::Ignored),
+             time).unwrap();
+
+    for suite in suites {
+        let classname = escape(&suite.classname());
+        writeln!(out, "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" \
+                       skipped=\"{}\" time=\"{:.3}\">",
+                 classname,
+                 suite.tests.len(),
+                 suite.count(TestOutcome::Failed),
+                 suite.count(TestOutcome::Ignored),
+                 secs(suite.time)).unwrap();
+        for test in suite.tests.iter() {
+            write!(out, "    <testcase name=\"{}\" classname=\"{}\"",
+                   escape(&test.name), classname).unwrap();
+            match test.outcome {
+                TestOutcome::Ok | TestOutcome::Measured => out.push_str("/>\n"),
+                TestOutcome::Ignored => out.push_str(">\n      <skipped/>\n    </testcase>\n"),
+                TestOutcome::Failed => {
+                    out.push_str(">\n      <failure message=\"test failed\">");
+                    if let Some(ref stdout) = test.stdout {
+                        out.push_str(&escape(stdout));
+                    }
+                    out.push_str("</failure>\n");
+                    out.push_str("    </testcase>\n");
+                }
+            }
+        }
+        out.push_str("  </testsuite>\n");
+    }
+    out.push_str("</testsuites>\n");
+
+    (|| -> CargoResult<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        File::create(path)?.write_all(out.as_bytes())?;
+        Ok(())
+    })().chain_err(|| {
+        format!("failed to write JUnit report to `{}`", path.display())
+    })?;
+    Ok(())
+}
+
+/// Escapes text for XML, dropping the control characters it can't contain
+/// at all, such as the escape codes of colored output.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c < ' ' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn secs(d: Duration) -> f64 {
+    d.as_secs() as f64 + f64::from(d.subsec_nanos()) / 1_000_000_000.0
+}
@@ -6,7 +6,7 @@ use core::source::{Source, SourceId};
 use core::GitReference;
 use core::{Package, PackageId, Summary, Registry, Dependency};
 use util::Config;
-use util::errors::CargoResult;
+use util::errors::{CargoResult, CargoResultExt};
 use util::hex::short_hash;
 use sources::PathSource;
 use sources::git::utils::{GitRemote, GitRevision};
@@ -151,8 +151,8 @@ impl<'cfg> Source for GitSource<'cfg> {
 
         let db_path = lock.parent().join("db").join(&self.ident);
 
-        if self.config.cli_unstable().offline && !db_path.exists() {
-            bail!("can't checkout from '{}': you are in the offline mode (-Z offline)",
+        if self.config.offline() && !db_path.exists() {
+            bail!("can't checkout from '{}': you are in the offline mode (--offline)",
                 self.remote.url());
         }
 
@@ -164,7 +164,7 @@ impl<'cfg> Source for GitSource<'cfg> {
         let should_update = actual_rev.is_err() ||
                             self.source_id.precise().is_none();
 
-        let (db, actual_rev) = if should_update && !self.config.cli_unstable().offline {
+        let (db, actual_rev) = if should_update && !self.config.offline() {
             self.config.shell().status("Updating",
                 format!("git repository `{}`", self.remote.url()))?;
 
@@ -172,7 +172,14 @@ impl<'cfg> Source for GitSource<'cfg> {
 
             self.remote.checkout(&db_path, &self.reference, self.config)?
         } else {
-            (self.remote.db_at(&db_path)?, actual_rev.unwrap())
+            // In offline mode we make do with whatever the existing database
+            // has, so the revision must already be present in it.
+            let actual_rev = actual_rev.chain_err(|| {
+                format!("can't checkout from '{}': the requested revision is \
+                         not available in the local database and you are in \
+                         the offline mode (--offline)", self.remote.url())
+            })?;
+            (self.remote.db_at(&db_path)?, actual_rev)
         };
 
         // Don’t use the full hash,
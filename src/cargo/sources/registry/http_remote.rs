@@ -0,0 +1,292 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::io::SeekFrom;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::str;
+
+use curl::easy::List;
+use hex;
+use serde_json;
+
+use core::{PackageId, SourceId};
+use sources::registry::{RegistryData, RegistryConfig, MaybeLock, INDEX_LOCK, CRATE_TEMPLATE, VERSION_TEMPLATE};
+use util::network;
+use util::paths;
+use util::{Config, FileLock, Filesystem, Sha256, ToUrl};
+use util::errors::{CargoResult, CargoResultExt, HttpNot200};
+
+/// A registry whose index is served as plain files over HTTP.
+///
+/// Index files are requested one at a time as the resolver asks for them and
+/// cached in `index_path`, along with the `ETag` and `Last-Modified` headers
+/// they were served with so they can be cheaply revalidated later on.
+pub struct HttpRegistry<'cfg> {
+    index_path: Filesystem,
+    cache_path: Filesystem,
+    source_id: SourceId,
+    config: &'cfg Config,
+    /// The URL which index files are relative to, without `sparse+`
+    url: String,
+    /// Whether the index has been updated, in which case cached files are
+    /// revalidated with the server before they're used
+    updated: bool,
+    /// Index files which have already been revalidated
+    fresh: RefCell<HashSet<PathBuf>>,
+}
+
+/// The validators the server sent along with a cached index file.
+#[derive(Serialize, Deserialize, Default)]
+struct CacheHeaders {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl<'cfg> HttpRegistry<'cfg> {
+    pub fn new(source_id: &SourceId, config: &'cfg Config, name: &str)
+               -> HttpRegistry<'cfg> {
+        let url = source_id.url().to_string();
+        let url = url.trim_left_matches("sparse+").trim_right_matches('/');
+        HttpRegistry {
+            index_path: config.registry_index_path().join(name),
+            cache_path: config.registry_cache_path().join(name),
+            source_id: source_id.clone(),
+            config,
+            url: url.to_string(),
+            updated: false,
+            fresh: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Brings the cached copy of the index file at `path` up to date with the
+    /// server, downloading it if it's missing or has changed.
+    ///
+    /// Returns `false` if the server doesn't have the file.
+    fn fetch(&self, path: &Path, file: &Path) -> CargoResult<bool> {
+        let _lock = self.index_path.open_rw(Path::new(INDEX_LOCK),
+                                            self.config,
+                                            "the registry index")?;
+        let headers_file = file.with_extension("headers");
+        let cached = if file.exists() {
+            paths::read_bytes(&headers_file).ok().and_then(|headers| {
+                serde_json::from_slice::<CacheHeaders>(&headers).ok()
+            }).unwrap_or_default()
+        } else {
+            CacheHeaders::default()
+        };
+
+        let relative = path.to_str().ok_or_else(|| {
+            format_err!("invalid index path `{}`", path.display())
+        })?;
+        let url = format!("{}/{}", self.url, relative.replace("\\", "/"));
+
+        let mut handle = self.config.http()?.borrow_mut();
+        handle.get(true)?;
+        handle.url(&url)?;
+        handle.follow_location(true)?;
+        let mut list = List::new();
+        if let Some(ref etag) = cached.etag {
+            list.append(&format!("If-None-Match: {}", etag))?;
+        }
+        if let Some(ref last_modified) = cached.last_modified {
+            list.append(&format!("If-Modified-Since: {}", last_modified))?;
+        }
+        handle.http_headers(list)?;
+
+        let mut body = Vec::new();
+        let mut headers = CacheHeaders::default();
+        let result = network::with_retry(self.config, || {
+            body = Vec::new();
+            headers = CacheHeaders::default();
+            {
+                let mut handle = handle.transfer();
+                handle.write_function(|buf| {
+                    body.extend_from_slice(buf);
+                    Ok(buf.len())
+                })?;
+                handle.header_function(|header| {
+                    if let Ok(header) = str::from_utf8(header) {
+                        let mut parts = header.splitn(2, ':');
+                        let name = parts.next().unwrap().trim().to_lowercase();
+                        let value = parts.next().map(|s| s.trim().to_string());
+                        match &name[..] {
+                            "etag" => headers.etag = value,
+                            "last-modified" => headers.last_modified = value,
+                            _ => {}
+                        }
+                    }
+                    true
+                })?;
+                handle.perform()?;
+            }
+            match handle.response_code()? {
+                0 | 200 | 304 | 404 | 410 => Ok(()),
+                code => {
+                    let url = handle.effective_url()?.unwrap_or(&url);
+                    Err(HttpNot200 { code, url: url.to_string() }.into())
+                }
+            }
+        });
+        // The handle is shared with everything else using HTTP, so don't
+        // leave the validators of this file behind for the next request
+        handle.http_headers(List::new())?;
+        result.chain_err(|| {
+            format!("failed to fetch `{}` from the index", relative)
+        })?;
+        let code = handle.response_code()?;
+        self.fresh.borrow_mut().insert(path.to_path_buf());
+
+        match code {
+            304 => Ok(true),
+            404 | 410 => {
+                if file.exists() {
+                    paths::remove_file(file)?;
+                }
+                if headers_file.exists() {
+                    paths::remove_file(&headers_file)?;
+                }
+                Ok(false)
+            }
+            _ => {
+                if let Some(parent) = file.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                paths::write(file, &body)?;
+                paths::write(&headers_file, &serde_json::to_vec(&headers)?)?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+impl<'cfg> RegistryData for HttpRegistry<'cfg> {
+    fn index_path(&self) -> &Filesystem {
+        &self.index_path
+    }
+
+    fn load(&self,
+            _root: &Path,
+            path: &Path,
+            data: &mut FnMut(&[u8]) -> CargoResult<()>) -> CargoResult<()> {
+        let file = self.index_path.clone().into_path_unlocked().join(path);
+        let stale = self.updated && !self.fresh.borrow().contains(path);
+        if !self.config.network_allowed() {
+            // Without the network the cached copy is all there is, whether
+            // it's stale or not
+            if !file.exists() {
+                let flag = if self.config.frozen() {"--frozen"} else {"--offline"};
+                bail!("can't fetch `{}` from the index of {} because {} was \
+                       specified", path.display(),
+                      self.source_id.display_registry(), flag)
+            }
+        } else if stale || !file.exists() {
+            if !self.fetch(path, &file)? {
+                return Ok(())
+            }
+        }
+        data(&paths::read_bytes(&file)?)
+    }
+
+    fn config(&mut self) -> CargoResult<Option<RegistryConfig>> {
+        let mut config = None;
+        self.load(Path::new(""), Path::new("config.json"), &mut |json| {
+            config = Some(serde_json::from_slice(json)?);
+            Ok(())
+        })?;
+        Ok(config)
+    }
+
+    fn update_index(&mut self) -> CargoResult<()> {
+        if self.config.offline() {
+            return Ok(());
+        }
+        if self.config.cli_unstable().no_index_update {
+            return Ok(());
+        }
+
+        // Ensure that we'll actually be able to acquire an HTTP handle later
+        // on, so problems with `.cargo/config` are reported up front just
+        // like they are for git-based registries.
+        self.config.http()?;
+
+        // Nothing is actually fetched here, instead each index file is
+        // revalidated the next time it's loaded.
+        if !self.updated {
+            self.config.shell().status("Updating", self.source_id.display_registry())?;
+            self.updated = true;
+        }
+        Ok(())
+    }
+
+    fn download(&mut self, pkg: &PackageId, _checksum: &str)
+                -> CargoResult<MaybeLock> {
+        let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
+        let path = Path::new(&filename);
+
+        if let Ok(dst) = self.cache_path.open_ro(path, self.config, &filename) {
+            let meta = dst.file().metadata()?;
+            if meta.len() > 0 {
+                return Ok(MaybeLock::Ready(dst))
+            }
+        }
+
+        let config = self.config()?.ok_or_else(|| {
+            format_err!("the registry index is missing `config.json`")
+        })?;
+        let mut url = config.dl.clone();
+        if !url.contains(CRATE_TEMPLATE) && !url.contains(VERSION_TEMPLATE) {
+            write!(url, "/{}/{}/download", CRATE_TEMPLATE, VERSION_TEMPLATE).unwrap();
+        }
+        let url = url
+            .replace(CRATE_TEMPLATE, pkg.name())
+            .replace(VERSION_TEMPLATE, &pkg.version().to_string())
+            .to_url()?;
+
+        Ok(MaybeLock::Download {
+            url: url.to_string(),
+            descriptor: pkg.to_string(),
+        })
+    }
+
+    fn finish_download(&mut self, pkg: &PackageId, checksum: &str, data: &[u8])
+                       -> CargoResult<FileLock> {
+        let mut state = Sha256::new();
+        state.update(data);
+        if hex::encode(state.finish()) != checksum {
+            bail!("failed to verify the checksum of `{}`", pkg)
+        }
+
+        let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
+        let mut dst = self.cache_path.open_rw(Path::new(&filename),
+                                              self.config,
+                                              &filename)?;
+        let meta = dst.file().metadata()?;
+        if meta.len() > 0 {
+            return Ok(dst)
+        }
+
+        dst.write_all(data).chain_err(|| {
+            format!("failed to write `{}`", dst.path().display())
+        })?;
+        dst.seek(SeekFrom::Start(0))?;
+        Ok(dst)
+    }
+
+    fn reports_missing_files(&self) -> bool {
+        true
+    }
+
+    fn is_crate_downloaded(&self, pkg: &PackageId) -> bool {
+        let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
+        let path = Path::new(&filename);
+
+        if let Ok(dst) = self.cache_path.open_ro(path, self.config, &filename) {
+            if let Ok(meta) = dst.file().metadata() {
+                return meta.len() > 0;
+            }
+        }
+        false
+    }
+}
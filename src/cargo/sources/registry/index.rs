@@ -7,7 +7,7 @@ use semver::Version;
 
 use core::dependency::Dependency;
 use core::{SourceId, Summary, PackageId};
-use sources::registry::{RegistryPackage, INDEX_LOCK};
+use sources::registry::{RegistryPackage, INDEX_LOCK, INDEX_V_MAX};
 use sources::registry::RegistryData;
 use util::{CargoResult, internal, Filesystem, Config};
 
@@ -110,20 +110,13 @@ impl<'cfg> RegistryIndex<'cfg> {
                                 .map(|s| s.trim())
                                 .filter(|l| !l.is_empty());
 
-            let online = !self.config.cli_unstable().offline;
             // Attempt forwards-compatibility on the index by ignoring
             // everything that we ourselves don't understand, that should
             // allow future cargo implementations to break the
             // interpretation of each line here and older cargo will simply
             // ignore the new lines.
             ret.extend(lines.filter_map(|line| {
-                self.parse_registry_package(line).ok().and_then(|v|{
-                    if online || load.is_crate_downloaded(v.0.package_id()) {
-                        Some(v)
-                    } else {
-                        None
-                    }
-                })
+                self.parse_registry_package(line).ok()
             }));
 
             Ok(())
@@ -131,8 +124,9 @@ impl<'cfg> RegistryIndex<'cfg> {
 
         // We ignore lookup failures as those are just crates which don't exist
         // or we haven't updated the registry yet. If we actually ran the
-        // closure though then we care about those errors.
-        if hit_closure {
+        // closure though then we care about those errors, as we do about any
+        // error from a registry which tells missing crates apart itself.
+        if hit_closure || load.reports_missing_files() {
             err?;
         }
 
@@ -146,13 +140,22 @@ impl<'cfg> RegistryIndex<'cfg> {
     fn parse_registry_package(&mut self, line: &str)
                               -> CargoResult<(Summary, bool)> {
         let RegistryPackage {
-            name, vers, cksum, deps, features, yanked, links
+            name, vers, cksum, deps, mut features, features2, yanked, links,
+            rust_version, v
         } = super::DEFAULT_ID.set(&self.source_id, || {
             serde_json::from_str::<RegistryPackage>(line)
         })?;
+        let v = v.unwrap_or(1);
+        if v > INDEX_V_MAX {
+            bail!("unsupported index version {} for `{}`", v, name)
+        }
+        features.extend(features2.unwrap_or_default());
         let pkgid = PackageId::new(&name, &vers, &self.source_id)?;
         let summary = Summary::new(pkgid, deps.inner, features, links)?;
         let summary = summary.set_checksum(cksum.clone());
+        // A `rust-version` this Cargo can't make sense of is only a hint for
+        // the resolver, so it's ignored rather than making the entry unusable.
+        let summary = summary.set_rust_version(rust_version.and_then(|v| v.parse().ok()));
         if self.hashes.contains_key(&name[..]) {
             self.hashes.get_mut(&name[..]).unwrap().insert(vers, cksum);
         } else {
@@ -169,6 +172,8 @@ impl<'cfg> RegistryIndex<'cfg> {
                  f: &mut FnMut(Summary))
                  -> CargoResult<()> {
         let source_id = self.source_id.clone();
+        let offline = self.config.offline();
+        let prefer_offline = self.config.prefer_offline()?;
         let summaries = self.summaries(dep.name(), load)?;
         let summaries = summaries.iter().filter(|&&(_, yanked)| {
             dep.source_id().precise().is_some() || !yanked
@@ -189,11 +194,25 @@ impl<'cfg> RegistryIndex<'cfg> {
             }
         });
 
-        for summary in summaries {
-            if dep.matches(&summary) {
-                f(summary);
+        let mut summaries = summaries.filter(|s| dep.matches(s))
+                                     .collect::<Vec<_>>();
+
+        // When preferring to stay offline only the versions which have
+        // already been downloaded are considered. If none of them match we
+        // fall back to everything the index knows about, unless the network
+        // is off limits entirely.
+        if prefer_offline {
+            let downloaded = summaries.iter().filter(|s| {
+                load.is_crate_downloaded(s.package_id())
+            }).cloned().collect::<Vec<_>>();
+            if !downloaded.is_empty() || offline {
+                summaries = downloaded;
             }
         }
+
+        for summary in summaries {
+            f(summary);
+        }
         Ok(())
     }
 }
@@ -4,10 +4,10 @@ use std::path::Path;
 
 use core::PackageId;
 use hex;
-use sources::registry::{RegistryData, RegistryConfig};
+use sources::registry::{RegistryData, RegistryConfig, MaybeLock};
 use util::FileLock;
 use util::paths;
-use util::{Config, Sha256, Filesystem};
+use util::{Config, Sha256, Filesystem, internal};
 use util::errors::{CargoResult, CargoResultExt};
 
 pub struct LocalRegistry<'cfg> {
@@ -66,7 +66,7 @@ impl<'cfg> RegistryData for LocalRegistry<'cfg> {
     }
 
     fn download(&mut self, pkg: &PackageId, checksum: &str)
-                -> CargoResult<FileLock> {
+                -> CargoResult<MaybeLock> {
         let crate_file = format!("{}-{}.crate", pkg.name(), pkg.version());
         let mut crate_file = self.root.open_ro(&crate_file,
                                                self.config,
@@ -76,7 +76,7 @@ impl<'cfg> RegistryData for LocalRegistry<'cfg> {
         // checksum below as it is in theory already verified.
         let dst = format!("{}-{}", pkg.name(), pkg.version());
         if self.src_path.join(dst).into_path_unlocked().exists() {
-            return Ok(crate_file)
+            return Ok(MaybeLock::Ready(crate_file))
         }
 
         self.config.shell().status("Unpacking", pkg)?;
@@ -100,6 +100,11 @@ impl<'cfg> RegistryData for LocalRegistry<'cfg> {
 
         crate_file.seek(SeekFrom::Start(0))?;
 
-        Ok(crate_file)
+        Ok(MaybeLock::Ready(crate_file))
+    }
+
+    fn finish_download(&mut self, pkg: &PackageId, _checksum: &str, _data: &[u8])
+                       -> CargoResult<FileLock> {
+        Err(internal(format!("local registries never download `{}`", pkg)))
     }
 }
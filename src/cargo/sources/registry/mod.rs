@@ -33,6 +33,9 @@
 //! query-able version of the registry's database for a list of versions of a
 //! package as well as a list of dependencies for each version.
 //!
+//! (Registries may alternatively serve their index as plain files over HTTP,
+//! see the "Sparse registries" section below.)
+//!
 //! Using git to host this index provides a number of benefits:
 //!
 //! * The entire index can be stored efficiently locally on disk. This means
@@ -109,6 +112,19 @@
 //! modifications to this file that should happen over time are yanks of a
 //! particular version.
 //!
+//! ## Sparse registries
+//!
+//! Hosting a git repository isn't always an option, for example for a mirror
+//! living on a static file server. If a registry's URL is prefixed with
+//! `sparse+`, like `sparse+https://example.com/index/`, then rather than
+//! cloning the index Cargo requests the individual files it needs (using the
+//! same paths as above, relative to that URL) over HTTP.
+//!
+//! Fetched files are cached in the index directory described below. When the
+//! index is updated, each cached file is revalidated with the `ETag` and
+//! `Last-Modified` headers the server sent along with it the first time it's
+//! read again, so unchanged files are never transferred twice.
+//!
 //! # Downloading Packages
 //!
 //! The purpose of the Index was to provide an efficient method to resolve the
@@ -170,6 +186,7 @@ use serde::de;
 use tar::Archive;
 
 use core::{Source, SourceId, PackageId, Package, Summary, Registry};
+use core::{Downloads, MaybePackage};
 use core::dependency::{Dependency, Kind};
 use sources::PathSource;
 use util::{CargoResult, Config, internal, FileLock, Filesystem};
@@ -212,16 +229,32 @@ pub struct RegistryConfig {
     pub api: Option<String>,
 }
 
+/// The version of the index format which this Cargo understands.
+///
+/// Version 2 added the `features2` field. Entries with a newer version are
+/// ignored.
+const INDEX_V_MAX: u32 = 2;
+
 #[derive(Deserialize)]
 struct RegistryPackage<'a> {
     name: Cow<'a, str>,
     vers: Version,
     deps: DependencyList,
     features: BTreeMap<String, Vec<String>>,
+    /// Features using the `dep:name` or `name?/feat` syntax, which are kept
+    /// apart from `features` so that older versions of Cargo don't see them.
+    #[serde(default)]
+    features2: Option<BTreeMap<String, Vec<String>>>,
     cksum: String,
     yanked: Option<bool>,
     #[serde(default)]
     links: Option<String>,
+    /// The `rust-version` declared in the package's manifest.
+    #[serde(default)]
+    rust_version: Option<String>,
+    /// The version of the index format of this entry, 1 if not specified.
+    #[serde(default)]
+    v: Option<u32>,
 }
 
 struct DependencyList {
@@ -250,13 +283,31 @@ pub trait RegistryData {
     fn update_index(&mut self) -> CargoResult<()>;
     fn download(&mut self,
                 pkg: &PackageId,
-                checksum: &str) -> CargoResult<FileLock>;
+                checksum: &str) -> CargoResult<MaybeLock>;
+    fn finish_download(&mut self,
+                       pkg: &PackageId,
+                       checksum: &str,
+                       data: &[u8]) -> CargoResult<FileLock>;
 
     fn is_crate_downloaded(&self, _pkg: &PackageId) -> bool { true }
+
+    /// Whether `load` succeeds without calling `data` for crates which aren't
+    /// in the index, so any error it returns is a real failure.
+    fn reports_missing_files(&self) -> bool { false }
+}
+
+/// The result of `RegistryData::download`
+pub enum MaybeLock {
+    /// The `.crate` file is available locally
+    Ready(FileLock),
+    /// The `.crate` file needs to be downloaded from `url`, and then handed
+    /// to `RegistryData::finish_download`
+    Download { url: String, descriptor: String },
 }
 
 mod index;
 mod remote;
+mod http_remote;
 mod local;
 
 fn short_name(id: &SourceId) -> String {
@@ -269,6 +320,10 @@ impl<'cfg> RegistrySource<'cfg> {
     pub fn remote(source_id: &SourceId,
                   config: &'cfg Config) -> RegistrySource<'cfg> {
         let name = short_name(source_id);
+        if source_id.is_sparse() {
+            let ops = http_remote::HttpRegistry::new(source_id, config, &name);
+            return RegistrySource::new(source_id, config, &name, Box::new(ops), false)
+        }
         let ops = remote::RemoteRegistry::new(source_id, config, &name);
         RegistrySource::new(source_id, config, &name, Box::new(ops), true)
     }
@@ -358,6 +413,28 @@ impl<'cfg> RegistrySource<'cfg> {
         Ok(dst.clone())
     }
 
+    fn get_pkg(&mut self, package: &PackageId, path: &FileLock)
+               -> CargoResult<Package> {
+        let path = self.unpack_package(package, path).chain_err(|| {
+            internal(format!("failed to unpack package `{}`", package))
+        })?;
+        let mut src = PathSource::new(&path, &self.source_id, self.config);
+        src.update()?;
+        let pkg = src.download(package)?;
+
+        // Unfortunately the index and the actual Cargo.toml in the index can
+        // differ due to historical Cargo bugs. To paper over these we trash the
+        // *summary* loaded from the Cargo.toml we just downloaded with the one
+        // we loaded from the index.
+        let summaries = self.index.summaries(package.name(), &mut *self.ops)?;
+        let summary = summaries.iter().map(|s| &s.0).find(|s| {
+            s.package_id() == package
+        }).expect("summary not found");
+        let mut manifest = pkg.manifest().clone();
+        manifest.set_summary(summary.clone());
+        Ok(Package::new(manifest, pkg.manifest_path()))
+    }
+
     fn do_update(&mut self) -> CargoResult<()> {
         self.ops.update_index()?;
         let path = self.ops.index_path();
@@ -422,26 +499,34 @@ impl<'cfg> Source for RegistrySource<'cfg> {
     }
 
     fn download(&mut self, package: &PackageId) -> CargoResult<Package> {
+        match self.start_download(package)? {
+            MaybePackage::Ready(pkg) => Ok(pkg),
+            MaybePackage::Download { url, descriptor } => {
+                let mut downloads = Downloads::new(self.config)?;
+                downloads.enqueue(package.clone(), url, descriptor)?;
+                let (_, data) = downloads.wait()?;
+                self.finish_download(package, data)
+            }
+        }
+    }
+
+    fn start_download(&mut self, package: &PackageId) -> CargoResult<MaybePackage> {
         let hash = self.index.hash(package, &mut *self.ops)?;
-        let path = self.ops.download(package, &hash)?;
-        let path = self.unpack_package(package, &path).chain_err(|| {
-            internal(format!("failed to unpack package `{}`", package))
-        })?;
-        let mut src = PathSource::new(&path, &self.source_id, self.config);
-        src.update()?;
-        let pkg = src.download(package)?;
+        match self.ops.download(package, &hash)? {
+            MaybeLock::Ready(file) => {
+                self.get_pkg(package, &file).map(MaybePackage::Ready)
+            }
+            MaybeLock::Download { url, descriptor } => {
+                Ok(MaybePackage::Download { url, descriptor })
+            }
+        }
+    }
 
-        // Unfortunately the index and the actual Cargo.toml in the index can
-        // differ due to historical Cargo bugs. To paper over these we trash the
-        // *summary* loaded from the Cargo.toml we just downloaded with the one
-        // we loaded from the index.
-        let summaries = self.index.summaries(package.name(), &mut *self.ops)?;
-        let summary = summaries.iter().map(|s| &s.0).find(|s| {
-            s.package_id() == package
-        }).expect("summary not found");
-        let mut manifest = pkg.manifest().clone();
-        manifest.set_summary(summary.clone());
-        Ok(Package::new(manifest, pkg.manifest_path()))
+    fn finish_download(&mut self, package: &PackageId, data: Vec<u8>)
+                       -> CargoResult<Package> {
+        let hash = self.index.hash(package, &mut *self.ops)?;
+        let file = self.ops.finish_download(package, &hash, &data)?;
+        self.get_pkg(package, &file)
     }
 
     fn fingerprint(&self, pkg: &Package) -> CargoResult<String> {
@@ -13,11 +13,10 @@ use lazycell::LazyCell;
 
 use core::{PackageId, SourceId};
 use sources::git;
-use sources::registry::{RegistryData, RegistryConfig, INDEX_LOCK, CRATE_TEMPLATE, VERSION_TEMPLATE};
-use util::network;
+use sources::registry::{RegistryData, RegistryConfig, MaybeLock, INDEX_LOCK, CRATE_TEMPLATE, VERSION_TEMPLATE};
 use util::{FileLock, Filesystem};
-use util::{Config, Sha256, ToUrl, Progress};
-use util::errors::{CargoResult, CargoResultExt, HttpNot200};
+use util::{Config, Sha256, ToUrl};
+use util::errors::{CargoResult, CargoResultExt};
 
 pub struct RemoteRegistry<'cfg> {
     index_path: Filesystem,
@@ -154,7 +153,7 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
     }
 
     fn update_index(&mut self) -> CargoResult<()> {
-        if self.config.cli_unstable().offline {
+        if self.config.offline() {
             return Ok(());
         }
         if self.config.cli_unstable().no_index_update {
@@ -187,8 +186,8 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
         Ok(())
     }
 
-    fn download(&mut self, pkg: &PackageId, checksum: &str)
-                -> CargoResult<FileLock> {
+    fn download(&mut self, pkg: &PackageId, _checksum: &str)
+                -> CargoResult<MaybeLock> {
         let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
         let path = Path::new(&filename);
 
@@ -201,15 +200,9 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
         if let Ok(dst) = self.cache_path.open_ro(path, self.config, &filename) {
             let meta = dst.file().metadata()?;
             if meta.len() > 0 {
-                return Ok(dst)
+                return Ok(MaybeLock::Ready(dst))
             }
         }
-        let mut dst = self.cache_path.open_rw(path, self.config, &filename)?;
-        let meta = dst.file().metadata()?;
-        if meta.len() > 0 {
-            return Ok(dst)
-        }
-        self.config.shell().status("Downloading", pkg)?;
 
         let config = self.config()?.unwrap();
         let mut url = config.dl.clone();
@@ -221,53 +214,37 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
             .replace(VERSION_TEMPLATE, &pkg.version().to_string())
             .to_url()?;
 
-        // TODO: don't download into memory, but ensure that if we ctrl-c a
-        //       download we should resume either from the start or the middle
-        //       on the next time
-        let url = url.to_string();
-        let mut handle = self.config.http()?.borrow_mut();
-        handle.get(true)?;
-        handle.url(&url)?;
-        handle.follow_location(true)?;
-        let mut state = Sha256::new();
-        let mut body = Vec::new();
-        network::with_retry(self.config, || {
-            state = Sha256::new();
-            body = Vec::new();
-            let mut pb = Progress::new("Fetch", self.config);
-            {
-                handle.progress(true)?;
-                let mut handle = handle.transfer();
-                handle.progress_function(|dl_total, dl_cur, _, _| {
-                    pb.tick(dl_cur as usize, dl_total as usize).is_ok()
-                })?;
-                handle.write_function(|buf| {
-                    state.update(buf);
-                    body.extend_from_slice(buf);
-                    Ok(buf.len())
-                })?;
-                handle.perform()?;
-            }
-            let code = handle.response_code()?;
-            if code != 200 && code != 0 {
-                let url = handle.effective_url()?.unwrap_or(&url);
-                Err(HttpNot200 { code, url: url.to_string() }.into())
-            } else {
-                Ok(())
-            }
-        })?;
+        Ok(MaybeLock::Download {
+            url: url.to_string(),
+            descriptor: pkg.to_string(),
+        })
+    }
 
+    fn finish_download(&mut self, pkg: &PackageId, checksum: &str, data: &[u8])
+                       -> CargoResult<FileLock> {
         // Verify what we just downloaded
+        let mut state = Sha256::new();
+        state.update(data);
         if hex::encode(state.finish()) != checksum {
             bail!("failed to verify the checksum of `{}`", pkg)
         }
 
-        dst.write_all(&body)?;
+        let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
+        let mut dst = self.cache_path.open_rw(Path::new(&filename),
+                                              self.config,
+                                              &filename)?;
+        // Another process may have finished downloading the same crate while
+        // we were busy, in which case there's nothing left to do.
+        let meta = dst.file().metadata()?;
+        if meta.len() > 0 {
+            return Ok(dst)
+        }
+
+        dst.write_all(data)?;
         dst.seek(SeekFrom::Start(0))?;
         Ok(dst)
     }
 
-
     fn is_crate_downloaded(&self, pkg: &PackageId) -> bool {
         let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
         let path = Path::new(&filename);
@@ -1,4 +1,5 @@
 use core::{Source, Registry, PackageId, Package, Dependency, Summary, SourceId};
+use core::MaybePackage;
 use util::errors::{CargoResult, CargoResultExt};
 
 pub struct ReplacedSource<'cfg> {
@@ -66,6 +67,31 @@ impl<'cfg> Source for ReplacedSource<'cfg> {
         Ok(pkg.map_source(&self.replace_with, &self.to_replace))
     }
 
+    fn start_download(&mut self, id: &PackageId) -> CargoResult<MaybePackage> {
+        let id = id.with_source_id(&self.replace_with);
+        let pkg = self.inner.start_download(&id).chain_err(|| {
+            format!("failed to download replaced source {}",
+                    self.to_replace)
+        })?;
+        Ok(match pkg {
+            MaybePackage::Ready(pkg) => {
+                MaybePackage::Ready(pkg.map_source(&self.replace_with,
+                                                   &self.to_replace))
+            }
+            other => other,
+        })
+    }
+
+    fn finish_download(&mut self, id: &PackageId, data: Vec<u8>)
+                       -> CargoResult<Package> {
+        let id = id.with_source_id(&self.replace_with);
+        let pkg = self.inner.finish_download(&id, data).chain_err(|| {
+            format!("failed to download replaced source {}",
+                    self.to_replace)
+        })?;
+        Ok(pkg.map_source(&self.replace_with, &self.to_replace))
+    }
+
     fn fingerprint(&self, id: &Package) -> CargoResult<String> {
         self.inner.fingerprint(id)
     }
@@ -1,8 +1,9 @@
 use std::cell::{RefCell, RefMut};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::collections::hash_map::HashMap;
 use std::env;
+use std::ffi::OsString;
 use std::fmt;
 use std::fs::{self, File};
 use std::io::SeekFrom;
@@ -57,6 +58,9 @@ pub struct Config {
     frozen: bool,
     /// `locked` is set if we should not update lock files
     locked: bool,
+    /// `offline` is set if we should never access the network, but otherwise
+    /// continue operating if possible
+    offline: bool,
     /// A global static IPC control mechanism (used for managing parallel builds)
     jobserver: Option<jobserver::Client>,
     /// Cli flags of the form "-Z something"
@@ -65,6 +69,8 @@ pub struct Config {
     easy: LazyCell<RefCell<Easy>>,
     /// Cache of the `SourceId` for crates.io
     crates_io_source_id: LazyCell<SourceId>,
+    /// Environment variables from the `[env]` table to set for processes
+    env_config: LazyCell<BTreeMap<String, OsString>>,
 }
 
 impl Config {
@@ -93,6 +99,7 @@ impl Config {
             extra_verbose: false,
             frozen: false,
             locked: false,
+            offline: false,
             jobserver: unsafe {
                 if GLOBAL_JOBSERVER.is_null() {
                     None
@@ -103,6 +110,7 @@ impl Config {
             cli_flags: CliUnstable::default(),
             easy: LazyCell::new(),
             crates_io_source_id: LazyCell::new(),
+            env_config: LazyCell::new(),
         }
     }
 
@@ -455,6 +463,7 @@ impl Config {
                      color: &Option<String>,
                      frozen: bool,
                      locked: bool,
+                     offline: bool,
                      unstable_flags: &[String]) -> CargoResult<()> {
         let extra_verbose = verbose >= 2;
         let verbose = if verbose == 0 {None} else {Some(true)};
@@ -462,6 +471,7 @@ impl Config {
         // Ignore errors in the configuration files.
         let cfg_verbose = self.get_bool("term.verbose").unwrap_or(None).map(|v| v.val);
         let cfg_color = self.get_string("term.color").unwrap_or(None).map(|v| v.val);
+        let cfg_offline = self.get_bool("net.offline").unwrap_or(None).map(|v| v.val);
 
         let color = color.as_ref().or_else(|| cfg_color.as_ref());
 
@@ -494,6 +504,7 @@ impl Config {
         self.extra_verbose = extra_verbose;
         self.frozen = frozen;
         self.locked = locked;
+        self.offline = offline || cfg_offline.unwrap_or(false);
         self.cli_flags.parse(unstable_flags)?;
 
         Ok(())
@@ -508,7 +519,25 @@ impl Config {
     }
 
     pub fn network_allowed(&self) -> bool {
-        !self.frozen() && !self.cli_unstable().offline
+        !self.frozen() && !self.offline()
+    }
+
+    /// Whether Cargo was asked to run without touching the network, either
+    /// with `--offline` or the `net.offline` configuration key.
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Whether resolution should favor versions of registry packages which
+    /// have already been downloaded, even though the network is available.
+    ///
+    /// This is implied by `offline`, and can otherwise be requested with the
+    /// `net.prefer-offline` configuration key.
+    pub fn prefer_offline(&self) -> CargoResult<bool> {
+        if self.offline() {
+            return Ok(true)
+        }
+        Ok(self.get_bool("net.prefer-offline")?.map(|v| v.val).unwrap_or(false))
     }
 
     pub fn frozen(&self) -> bool {
@@ -668,6 +697,56 @@ impl Config {
     {
         Ok(self.crates_io_source_id.try_borrow_with(f)?.clone())
     }
+
+    /// The environment variables from the `[env]` table of the configuration
+    /// which should be set for the processes that Cargo spawns.
+    ///
+    /// Each entry is either a string or a table with a `value` and the
+    /// optional `force` and `relative` flags. Variables which are already set
+    /// in Cargo's own environment are left out unless `force` is set, and the
+    /// values of `relative` entries are paths relative to the directory
+    /// containing the `.cargo` directory they were defined in.
+    pub fn env_config(&self) -> CargoResult<&BTreeMap<String, OsString>> {
+        self.env_config.try_borrow_with(|| {
+            let mut vars = BTreeMap::new();
+            let table = match self.get_table("env")? {
+                Some(table) => table.val,
+                None => return Ok(vars),
+            };
+            for (key, value) in table.iter() {
+                let name = format!("env.{}", key);
+                let (val, force, relative) = match *value {
+                    CV::String(ref s, _) => (&s[..], false, false),
+                    CV::Table(ref t, ref path) => {
+                        let val = match t.get("value") {
+                            Some(v) => v.string(&format!("{}.value", name))?.0,
+                            None => bail!("missing `value` for `{}` in {}",
+                                          name, path.display()),
+                        };
+                        let flag = |flag: &str| -> CargoResult<bool> {
+                            match t.get(flag) {
+                                Some(v) => Ok(v.boolean(&format!("{}.{}", name, flag))?.0),
+                                None => Ok(false),
+                            }
+                        };
+                        (val, flag("force")?, flag("relative")?)
+                    }
+                    _ => return self.expected("string or table", &name, value.clone()),
+                };
+                if !force && env::var_os(key).is_some() {
+                    continue
+                }
+                let val = if relative {
+                    let root = value.definition_path().parent().unwrap().parent().unwrap();
+                    root.join(val).into_os_string()
+                } else {
+                    OsString::from(val)
+                };
+                vars.insert(key.clone(), val);
+            }
+            Ok(vars)
+        })
+    }
 }
 
 #[derive(Eq, PartialEq, Clone, Copy)]
@@ -931,28 +1010,27 @@ fn walk_tree<F>(pwd: &Path, mut walk: F) -> CargoResult<()>
 pub fn save_credentials(cfg: &Config,
                         token: String,
                         registry: Option<String>) -> CargoResult<()> {
+    edit_credentials(cfg, Some(token), registry)
+}
+
+/// Removes the token of `registry`, or of the default registry, from the
+/// credentials file.
+pub fn erase_credentials(cfg: &Config, registry: Option<String>) -> CargoResult<()> {
+    if !cfg.home_path.clone().into_path_unlocked().join("credentials").exists() {
+        return Ok(())
+    }
+    edit_credentials(cfg, None, registry)
+}
+
+fn edit_credentials(cfg: &Config,
+                    token: Option<String>,
+                    registry: Option<String>) -> CargoResult<()> {
     let mut file = {
         cfg.home_path.create_dir()?;
         cfg.home_path.open_rw(Path::new("credentials"), cfg,
                               "credentials' config file")?
     };
 
-    let (key, value) = {
-        let key = "token".to_string();
-        let value = ConfigValue::String(token, file.path().to_path_buf());
-        let mut map = HashMap::new();
-        map.insert(key, value);
-        let table = CV::Table(map, file.path().to_path_buf());
-
-        if let Some(registry) = registry {
-            let mut map = HashMap::new();
-            map.insert(registry, table);
-            ("registries".into(), CV::Table(map, file.path().to_path_buf()))
-        } else {
-            ("registry".into(), table)
-        }
-    };
-
     let mut contents = String::new();
     file.read_to_string(&mut contents).chain_err(|| {
         format!("failed to read configuration file `{}`", file.path().display())
@@ -967,9 +1045,43 @@ pub fn save_credentials(cfg: &Config,
         toml.as_table_mut().unwrap().insert("registry".into(), map.into());
     }
 
-    toml.as_table_mut()
-        .unwrap()
-        .insert(key, value.into_toml());
+    match token {
+        Some(token) => {
+            let (key, value) = {
+                let key = "token".to_string();
+                let value = ConfigValue::String(token, file.path().to_path_buf());
+                let mut map = HashMap::new();
+                map.insert(key, value);
+                let table = CV::Table(map, file.path().to_path_buf());
+
+                if let Some(registry) = registry {
+                    let mut map = HashMap::new();
+                    map.insert(registry, table);
+                    ("registries".into(), CV::Table(map, file.path().to_path_buf()))
+                } else {
+                    ("registry".into(), table)
+                }
+            };
+
+            toml.as_table_mut()
+                .unwrap()
+                .insert(key, value.into_toml());
+        }
+        None => {
+            let table = toml.as_table_mut().unwrap();
+            let table = match registry {
+                Some(registry) => {
+                    table.get_mut("registries")
+                         .and_then(|t| t.as_table_mut())
+                         .and_then(|t| t.get_mut(&registry))
+                }
+                None => table.get_mut("registry"),
+            };
+            if let Some(table) = table.and_then(|t| t.as_table_mut()) {
+                table.remove("token");
+            }
+        }
+    }
 
     let contents = toml.to_string();
     file.seek(SeekFrom::Start(0))?;
@@ -219,13 +219,13 @@ impl Filesystem {
         match state {
             State::Exclusive => {
                 acquire(config, msg, &path,
-                        &|| f.try_lock_exclusive(),
-                        &|| f.lock_exclusive())?;
+                        &|| FileExt::try_lock_exclusive(&f),
+                        &|| FileExt::lock_exclusive(&f))?;
             }
             State::Shared => {
                 acquire(config, msg, &path,
-                        &|| f.try_lock_shared(),
-                        &|| f.lock_shared())?;
+                        &|| FileExt::try_lock_shared(&f),
+                        &|| FileExt::lock_shared(&f))?;
             }
             State::Unlocked => {}
 
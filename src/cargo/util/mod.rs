@@ -11,7 +11,7 @@ pub use self::lev_distance::{lev_distance};
 pub use self::paths::{join_paths, path2bytes, bytes2path, dylib_path};
 pub use self::paths::{normalize_path, dylib_path_envvar, without_prefix};
 pub use self::process_builder::{process, ProcessBuilder};
-pub use self::rustc::Rustc;
+pub use self::rustc::{Rustc, RustVersion};
 pub use self::sha256::Sha256;
 pub use self::to_semver::ToSemver;
 pub use self::to_url::ToUrl;
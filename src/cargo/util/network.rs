@@ -49,18 +49,45 @@ fn maybe_spurious(err: &Error) -> bool {
 pub fn with_retry<T, F>(config: &Config, mut callback: F) -> CargoResult<T>
     where F: FnMut() -> CargoResult<T>
 {
-    let mut remaining = config.net_retry()?;
+    let mut retry = Retry::new(config)?;
     loop {
-        match callback() {
-            Ok(ret) => return Ok(ret),
-            Err(ref e) if maybe_spurious(e) && remaining > 0 => {
+        if let Some(ret) = retry.check(callback())? {
+            return Ok(ret)
+        }
+    }
+}
+
+/// The retry logic of `with_retry`, for network operations which can't be
+/// expressed as a single blocking closure, such as transfers driven by a curl
+/// `Multi` handle.
+pub struct Retry<'a> {
+    config: &'a Config,
+    remaining: i64,
+}
+
+impl<'a> Retry<'a> {
+    pub fn new(config: &'a Config) -> CargoResult<Retry<'a>> {
+        Ok(Retry {
+            config,
+            remaining: config.net_retry()?,
+        })
+    }
+
+    /// Inspects the outcome of one attempt of the operation.
+    ///
+    /// Returns `Ok(None)` if the attempt failed spuriously and should be made
+    /// again, in which case a warning has already been printed.
+    pub fn check<T>(&mut self, result: CargoResult<T>) -> CargoResult<Option<T>> {
+        match result {
+            Ok(ret) => Ok(Some(ret)),
+            Err(ref e) if maybe_spurious(e) && self.remaining > 0 => {
                 let msg = format!("spurious network error ({} tries \
-                          remaining): {}", remaining, e);
-                config.shell().warn(msg)?;
-                remaining -= 1;
+                          remaining): {}", self.remaining, e);
+                self.config.shell().warn(msg)?;
+                self.remaining -= 1;
+                Ok(None)
             }
-            //todo impl from
-            Err(e) => return Err(e.into()),
+            Err(e) => Err(e),
         }
     }
 }
@@ -1,6 +1,10 @@
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
 
-use util::{self, CargoResult, internal, ProcessBuilder};
+use semver::Version;
+
+use util::{self, CargoResult, CargoError, internal, ProcessBuilder};
 
 /// Information on the `rustc` executable
 #[derive(Debug)]
@@ -12,6 +16,8 @@ pub struct Rustc {
     pub wrapper: Option<PathBuf>,
     /// Verbose version information (the output of `rustc -vV`)
     pub verbose_version: String,
+    /// The version of the compiler, this comes from verbose_version.
+    pub version: Version,
     /// The host triple (arch-platform-OS), this comes from verbose_version.
     pub host: String,
 }
@@ -39,10 +45,20 @@ impl Rustc {
             triple.to_string()
         };
 
+        let version = {
+            let release = verbose_version.lines().find(|l| {
+                l.starts_with("release: ")
+            }).map(|l| &l[9..]).ok_or_else(|| internal("rustc -v didn't have a line for `release:`"))?;
+            Version::parse(release).map_err(|e| {
+                internal(format!("rustc -v returned an invalid release `{}`: {}", release, e))
+            })?
+        };
+
         Ok(Rustc {
             path,
             wrapper,
             verbose_version,
+            version,
             host,
         })
     }
@@ -60,3 +76,53 @@ impl Rustc {
         }
     }
 }
+
+/// The oldest version of rustc a package supports, as declared by the
+/// `rust-version` key of its manifest.
+///
+/// Only the `major.minor` or `major.minor.patch` form is accepted, and
+/// pre-release compilers count as the release they lead up to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RustVersion {
+    version: Version,
+    text: String,
+}
+
+impl RustVersion {
+    /// Whether a package requiring this version can be built by `rustc`.
+    pub fn is_compatible_with(&self, rustc: &RustVersion) -> bool {
+        self.version <= rustc.version
+    }
+}
+
+impl FromStr for RustVersion {
+    type Err = CargoError;
+
+    fn from_str(s: &str) -> CargoResult<RustVersion> {
+        let parts = s.split('.').map(|part| {
+            if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+                return None
+            }
+            part.parse::<u64>().ok()
+        }).collect::<Option<Vec<_>>>();
+        let version = match parts {
+            Some(ref p) if p.len() == 2 => Version::new(p[0], p[1], 0),
+            Some(ref p) if p.len() == 3 => Version::new(p[0], p[1], p[2]),
+            _ => bail!("expected a version like \"1.32\", found `{}`", s),
+        };
+        Ok(RustVersion { version, text: s.to_string() })
+    }
+}
+
+impl<'a> From<&'a Version> for RustVersion {
+    fn from(version: &'a Version) -> RustVersion {
+        let version = Version::new(version.major, version.minor, version.patch);
+        RustVersion { text: version.to_string(), version }
+    }
+}
+
+impl fmt::Display for RustVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.text.fmt(f)
+    }
+}
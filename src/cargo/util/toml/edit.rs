@@ -0,0 +1,528 @@
+//! In-place editing of the dependency tables of a `Cargo.toml`.
+//!
+//! `cargo add` and `cargo rm` must leave everything they don't touch exactly
+//! as it was written, which a round trip through `toml` can't do. Instead the
+//! manifest is treated as a list of lines, and only the part of TOML which
+//! dependency tables are made of is understood: table headers and `key =
+//! value` lines. Everything else, including comments and multi-line values,
+//! is carried over verbatim.
+
+use std::fmt;
+
+use core::dependency::Kind;
+use util::errors::CargoResult;
+
+/// Identifies one of the dependency tables of a manifest.
+pub struct DepTable<'a> {
+    pub kind: Kind,
+    /// The platform of a `[target.<platform>.dependencies]` table, either a
+    /// target triple or a `cfg(..)` expression.
+    pub target: Option<&'a str>,
+}
+
+impl<'a> DepTable<'a> {
+    /// The names this table may be spelled with, the preferred one first.
+    fn names(&self) -> &'static [&'static str] {
+        match self.kind {
+            Kind::Normal => &["dependencies"],
+            Kind::Development => &["dev-dependencies", "dev_dependencies"],
+            Kind::Build => &["build-dependencies", "build_dependencies"],
+        }
+    }
+
+    fn matches(&self, path: &[String]) -> bool {
+        let name = match (self.target, path.len()) {
+            (None, 1) => &path[0],
+            (Some(target), 3) if path[0] == "target" && path[1] == target => &path[2],
+            _ => return false,
+        };
+        self.names().contains(&&name[..])
+    }
+}
+
+impl<'a> fmt::Display for DepTable<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.target {
+            Some(target) => write!(f, "target.{}.{}", key(target), self.names()[0]),
+            None => write!(f, "{}", self.names()[0]),
+        }
+    }
+}
+
+/// A manifest being edited, see the module documentation.
+pub struct ManifestEditor {
+    lines: Vec<String>,
+    newline: &'static str,
+    trailing_newline: bool,
+}
+
+enum Line {
+    /// A table header, with its dotted key unquoted. Headers of arrays of
+    /// tables have an empty path as they are never dependency tables.
+    Header(Vec<String>),
+    /// A `key = value` line, with the key unquoted.
+    Entry(String),
+    /// A line belonging to the value of the `Entry` before it.
+    Continuation,
+    /// Blank lines, comments and anything else.
+    Other,
+}
+
+enum Location {
+    /// A `name = ...` line within the dependency table.
+    Entry(usize),
+    /// A `[dependencies.name]` table spanning the given lines.
+    Table(usize, usize),
+}
+
+impl ManifestEditor {
+    pub fn new(contents: &str) -> ManifestEditor {
+        ManifestEditor {
+            lines: contents.lines().map(|s| s.to_string()).collect(),
+            newline: if contents.contains("\r\n") { "\r\n" } else { "\n" },
+            trailing_newline: contents.is_empty() || contents.ends_with('\n'),
+        }
+    }
+
+    /// Adds the dependency `name` to `table`, or replaces it if it's already
+    /// listed there.
+    ///
+    /// `fields` are the keys of the dependency with their values already
+    /// formatted as TOML. A dependency with nothing but a version is written
+    /// as `name = "version"`, otherwise as an inline table.
+    pub fn insert(&mut self, table: &DepTable, name: &str, fields: &[(&str, String)]) {
+        let lines = classify(&self.lines);
+        match find(&lines, table, name) {
+            Some(Location::Entry(i)) => {
+                let end = value_end(&lines, i);
+                let indent = indentation(&self.lines[i]).to_string();
+                let line = format!("{}{}", indent, entry(name, fields));
+                self.lines.splice(i..end, Some(line));
+            }
+            Some(Location::Table(start, end)) => {
+                // Keep the header and any comments, but replace all the keys
+                let mut body = Vec::new();
+                let mut i = start + 1;
+                while i < end {
+                    match lines[i] {
+                        Line::Entry(_) | Line::Continuation => {}
+                        _ => body.push(self.lines[i].clone()),
+                    }
+                    i += 1;
+                }
+                let keys = fields.iter().map(|&(k, ref v)| format!("{} = {}", k, v));
+                self.lines.splice(start + 1..end, keys.chain(body));
+            }
+            None => self.insert_new(&lines, table, name, fields),
+        }
+    }
+
+    fn insert_new(&mut self, lines: &[Line], table: &DepTable, name: &str,
+                  fields: &[(&str, String)]) {
+        let header = lines.iter().position(|line| {
+            match *line {
+                Line::Header(ref path) => table.matches(path),
+                _ => false,
+            }
+        });
+        let header = match header {
+            Some(header) => header,
+            None => {
+                if self.lines.last().map(|l| !l.trim().is_empty()).unwrap_or(false) {
+                    self.lines.push(String::new());
+                }
+                self.lines.push(format!("[{}]", table));
+                self.lines.push(entry(name, fields));
+                return
+            }
+        };
+
+        let end = table_end(lines, header);
+        let entries = (header + 1..end).filter_map(|i| {
+            match lines[i] {
+                Line::Entry(ref key) => Some((i, key)),
+                _ => None,
+            }
+        }).collect::<Vec<_>>();
+
+        // Keep the table sorted if it already is, otherwise append to it
+        let sorted = entries.windows(2).all(|w| w[0].1 <= w[1].1);
+        let next = if sorted {
+            entries.iter().find(|&&(_, key)| &key[..] > name).map(|&(i, _)| i)
+        } else {
+            None
+        };
+        let at = match (next, entries.last()) {
+            (Some(i), _) => i,
+            (None, Some(&(i, _))) => value_end(lines, i),
+            (None, None) => header + 1,
+        };
+        let indent = entries.first()
+                            .map(|&(i, _)| indentation(&self.lines[i]))
+                            .unwrap_or("")
+                            .to_string();
+        self.lines.insert(at, format!("{}{}", indent, entry(name, fields)));
+    }
+
+    /// Removes the dependency `name` from `table`.
+    pub fn remove(&mut self, table: &DepTable, name: &str) -> CargoResult<()> {
+        let lines = classify(&self.lines);
+        match find(&lines, table, name) {
+            Some(Location::Entry(i)) => {
+                let end = value_end(&lines, i);
+                self.lines.drain(i..end);
+            }
+            Some(Location::Table(start, end)) => {
+                self.lines.drain(start..end);
+                if end == lines.len() {
+                    while self.lines.last().map(|l| l.trim().is_empty()).unwrap_or(false) {
+                        self.lines.pop();
+                    }
+                }
+            }
+            None => {
+                bail!("the dependency `{}` could not be found in `{}`", name, table)
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets `name` to the already formatted `value` in the table at `table`,
+    /// or among the keys before the first table if `table` is empty.
+    ///
+    /// A key which isn't there yet is added after the other keys of the
+    /// table.
+    pub fn set(&mut self, table: &[&str], name: &str, value: &str) -> CargoResult<()> {
+        let lines = classify(&self.lines);
+        let (start, end) = if table.is_empty() {
+            let end = lines.iter().position(|line| {
+                match *line {
+                    Line::Header(_) => true,
+                    _ => false,
+                }
+            }).unwrap_or(lines.len());
+            (0, end)
+        } else {
+            let header = lines.iter().position(|line| {
+                match *line {
+                    Line::Header(ref path) => path.iter().map(|s| &s[..]).eq(table.iter().cloned()),
+                    _ => false,
+                }
+            });
+            match header {
+                Some(header) => (header + 1, table_end(&lines, header)),
+                None => bail!("the table `[{}]` could not be found", table.join(".")),
+            }
+        };
+
+        let line = format!("{} = {}", key(name), value);
+        let entries = (start..end).filter(|&i| {
+            match lines[i] {
+                Line::Entry(_) => true,
+                _ => false,
+            }
+        }).collect::<Vec<_>>();
+        let existing = entries.iter().cloned().find(|&i| {
+            match lines[i] {
+                Line::Entry(ref key) => key == name,
+                _ => false,
+            }
+        });
+        match (existing, entries.last()) {
+            (Some(i), _) => {
+                let end = value_end(&lines, i);
+                let indent = indentation(&self.lines[i]).to_string();
+                self.lines.splice(i..end, Some(format!("{}{}", indent, line)));
+            }
+            (None, Some(&i)) => {
+                let indent = indentation(&self.lines[i]).to_string();
+                self.lines.insert(value_end(&lines, i), format!("{}{}", indent, line));
+            }
+            (None, None) => self.lines.insert(start, line),
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ManifestEditor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                f.write_str(self.newline)?;
+            }
+            f.write_str(line)?;
+        }
+        if self.trailing_newline && !self.lines.is_empty() {
+            f.write_str(self.newline)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats `s` as a TOML basic string.
+pub fn string(s: &str) -> String {
+    let mut ret = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\t' => ret.push_str("\\t"),
+            c if c.is_control() => ret.push_str(&format!("\\u{:04X}", c as u32)),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+/// Formats `items` as a TOML array of strings.
+pub fn string_array<'a, I>(items: I) -> String
+    where I: IntoIterator<Item = &'a str>
+{
+    let items = items.into_iter().map(string).collect::<Vec<_>>();
+    format!("[{}]", items.join(", "))
+}
+
+/// Formats `s` as a TOML key, quoting it only if needed.
+fn key(s: &str) -> String {
+    if !s.is_empty() && s.chars().all(is_bare) {
+        s.to_string()
+    } else if !s.contains('\'') && !s.chars().any(|c| c.is_control()) {
+        format!("'{}'", s)
+    } else {
+        string(s)
+    }
+}
+
+fn entry(name: &str, fields: &[(&str, String)]) -> String {
+    match fields.len() {
+        1 if fields[0].0 == "version" => format!("{} = {}", key(name), fields[0].1),
+        _ => {
+            let fields = fields.iter()
+                               .map(|&(k, ref v)| format!("{} = {}", k, v))
+                               .collect::<Vec<_>>();
+            format!("{} = {{ {} }}", key(name), fields.join(", "))
+        }
+    }
+}
+
+fn find(lines: &[Line], table: &DepTable, name: &str) -> Option<Location> {
+    for (i, line) in lines.iter().enumerate() {
+        let path = match *line {
+            Line::Header(ref path) => path,
+            _ => continue,
+        };
+        let end = table_end(lines, i);
+        if table.matches(path) {
+            for j in i + 1..end {
+                if let Line::Entry(ref key) = lines[j] {
+                    if key == name {
+                        return Some(Location::Entry(j))
+                    }
+                }
+            }
+        } else if let Some((last, parent)) = path.split_last() {
+            if last == name && table.matches(parent) {
+                return Some(Location::Table(i, end))
+            }
+        }
+    }
+    None
+}
+
+/// The line after the last one of the table whose header is at `header`.
+fn table_end(lines: &[Line], header: usize) -> usize {
+    lines[header + 1..].iter().position(|line| {
+        match *line {
+            Line::Header(_) => true,
+            _ => false,
+        }
+    }).map(|i| header + 1 + i).unwrap_or(lines.len())
+}
+
+/// The line after the last one of the value of the entry at `entry`.
+fn value_end(lines: &[Line], entry: usize) -> usize {
+    lines[entry + 1..].iter().position(|line| {
+        match *line {
+            Line::Continuation => false,
+            _ => true,
+        }
+    }).map(|i| entry + 1 + i).unwrap_or(lines.len())
+}
+
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_left().len()]
+}
+
+fn classify(lines: &[String]) -> Vec<Line> {
+    let mut scan = ValueScan { depth: 0, string: None };
+    lines.iter().map(|line| {
+        if scan.in_value() {
+            scan.scan(line);
+            return Line::Continuation
+        }
+        let line = line.trim();
+        if line.starts_with("[[") {
+            Line::Header(Vec::new())
+        } else if line.starts_with('[') {
+            match parse_key(&line[1..]) {
+                Some((path, rest)) => {
+                    if rest.starts_with(']') {
+                        Line::Header(path)
+                    } else {
+                        Line::Other
+                    }
+                }
+                None => Line::Other,
+            }
+        } else {
+            match parse_key(line) {
+                Some((mut path, rest)) => {
+                    if !rest.starts_with('=') {
+                        return Line::Other
+                    }
+                    scan.scan(&rest[1..]);
+                    if path.len() == 1 {
+                        Line::Entry(path.remove(0))
+                    } else {
+                        Line::Other
+                    }
+                }
+                None => Line::Other,
+            }
+        }
+    }).collect()
+}
+
+fn is_bare(c: char) -> bool {
+    c.is_ascii() && (c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Parses the dotted key at the start of `s`, returning its unquoted parts
+/// and whatever follows it.
+fn parse_key(s: &str) -> Option<(Vec<String>, &str)> {
+    let mut path = Vec::new();
+    let mut s = s;
+    loop {
+        s = s.trim_left();
+        let (part, rest) = if s.starts_with('"') {
+            let end = find_unescaped(&s[1..], "\"")?;
+            (unescape(&s[1..end + 1]), &s[end + 2..])
+        } else if s.starts_with('\'') {
+            let end = s[1..].find('\'')?;
+            (s[1..end + 1].to_string(), &s[end + 2..])
+        } else {
+            let end = s.find(|c: char| !is_bare(c)).unwrap_or(s.len());
+            if end == 0 {
+                return None
+            }
+            (s[..end].to_string(), &s[end..])
+        };
+        path.push(part);
+        s = rest.trim_left();
+        if s.starts_with('.') {
+            s = &s[1..];
+        } else {
+            return Some((path, s))
+        }
+    }
+}
+
+fn unescape(s: &str) -> String {
+    let mut ret = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            ret.push(c);
+            continue
+        }
+        match chars.next() {
+            Some('n') => ret.push('\n'),
+            Some('t') => ret.push('\t'),
+            Some('r') => ret.push('\r'),
+            Some(c) if c == '"' || c == '\\' => ret.push(c),
+            Some(c) => {
+                ret.push('\\');
+                ret.push(c);
+            }
+            None => ret.push('\\'),
+        }
+    }
+    ret
+}
+
+/// Finds the first `delim` in `s` which isn't escaped with a backslash.
+fn find_unescaped(s: &str, delim: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if s[i..].starts_with(delim) {
+            return Some(i)
+        }
+    }
+    None
+}
+
+/// Tracks whether a value continues onto the following lines, which is the
+/// case for unterminated arrays, inline tables and multi-line strings.
+struct ValueScan {
+    depth: usize,
+    string: Option<&'static str>,
+}
+
+impl ValueScan {
+    fn in_value(&self) -> bool {
+        self.depth > 0 || self.string.is_some()
+    }
+
+    fn scan(&mut self, line: &str) {
+        let mut s = line;
+        loop {
+            if let Some(delim) = self.string {
+                let end = if delim.starts_with('\'') {
+                    s.find(delim)
+                } else {
+                    find_unescaped(s, delim)
+                };
+                match end {
+                    Some(end) => {
+                        self.string = None;
+                        s = &s[end + delim.len()..];
+                        continue
+                    }
+                    None => break,
+                }
+            }
+            let i = match s.find(|c: char| "\"'[]{}#".contains(c)) {
+                Some(i) => i,
+                None => break,
+            };
+            s = &s[i..];
+            for &delim in ["\"\"\"", "'''", "\"", "'"].iter() {
+                if s.starts_with(delim) {
+                    self.string = Some(delim);
+                    s = &s[delim.len()..];
+                    break
+                }
+            }
+            if self.string.is_some() {
+                continue
+            }
+            match s.as_bytes()[0] {
+                b'#' => break,
+                b'[' | b'{' => self.depth += 1,
+                _ => self.depth = self.depth.saturating_sub(1),
+            }
+            s = &s[1..];
+        }
+        // Only multi-line strings may span lines
+        if self.string.map(|d| d.len() == 1).unwrap_or(false) {
+            self.string = None;
+        }
+    }
+}
@@ -5,6 +5,7 @@ use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::str;
 
+use lazycell::LazyCell;
 use semver::{self, VersionReq};
 use serde::ser;
 use serde::de::{self, Deserialize};
@@ -13,18 +14,21 @@ use toml;
 use url::Url;
 
 use core::{SourceId, Profiles, PackageIdSpec, GitReference, WorkspaceConfig, WorkspaceRootConfig};
+use core::find_workspace_root;
 use core::{Summary, Manifest, Target, Dependency, PackageId};
-use core::{EitherManifest, Epoch, VirtualManifest, Features, Feature};
+use core::{EitherManifest, Epoch, VirtualManifest, Features, Feature, ResolveBehavior};
 use core::dependency::{Kind, Platform};
 use core::manifest::{LibKind, Profile, ManifestMetadata, Lto};
 use sources::CRATES_IO;
 use util::paths;
-use util::{self, ToUrl, Config};
+use util::{self, ToUrl, Config, RustVersion};
 use util::errors::{CargoError, CargoResult, CargoResultExt};
 
 mod targets;
 use self::targets::targets;
 
+pub mod edit;
+
 pub fn read_manifest(path: &Path, source_id: &SourceId, config: &Config)
                      -> CargoResult<(EitherManifest, Vec<PathBuf>)> {
     trace!("read_manifest; path={}; source-id={}", path.display(), source_id);
@@ -138,7 +142,7 @@ type TomlExampleTarget = TomlTarget;
 type TomlTestTarget = TomlTarget;
 type TomlBenchTarget = TomlTarget;
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 #[serde(untagged)]
 pub enum TomlDependency {
     Simple(String),
@@ -194,9 +198,10 @@ pub struct DetailedTomlDependency {
     #[serde(rename = "default_features")]
     default_features2: Option<bool>,
     package: Option<String>,
+    workspace: Option<bool>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct TomlManifest {
     cargo_features: Option<Vec<String>>,
@@ -223,14 +228,20 @@ pub struct TomlManifest {
     badges: Option<BTreeMap<String, BTreeMap<String, String>>>,
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug, Default)]
-pub struct TomlProfiles {
-    test: Option<TomlProfile>,
-    doc: Option<TomlProfile>,
-    bench: Option<TomlProfile>,
-    dev: Option<TomlProfile>,
-    release: Option<TomlProfile>,
-}
+/// All `[profile.*]` tables, keyed by the name of the profile.
+///
+/// Besides the built-in profiles any number of custom profiles can be
+/// defined, each of which `inherits` the settings of another one.
+pub type TomlProfiles = BTreeMap<String, TomlProfile>;
+
+/// Profiles which always exist, and so don't `inherits` from anything.
+const BUILTIN_PROFILES: &[&str] = &["dev", "release", "test", "bench", "doc"];
+
+/// Names which can't be used for custom profiles, as the profile's output
+/// directory would clash with something else in the target directory.
+const RESERVED_PROFILE_NAMES: &[&str] = &[
+    "debug", "build", "deps", "examples", "incremental", "package", "cargo-timings",
+];
 
 #[derive(Clone, Debug)]
 pub struct TomlOptLevel(String);
@@ -339,6 +350,46 @@ pub struct TomlProfile {
     #[serde(rename = "overflow-checks")]
     overflow_checks: Option<bool>,
     incremental: Option<bool>,
+    inherits: Option<String>,
+    overrides: Option<BTreeMap<String, TomlProfile>>,
+    #[serde(rename = "build-override")]
+    build_override: Option<Box<TomlProfile>>,
+}
+
+impl TomlProfile {
+    fn validate_overrides(&self, name: &str) -> CargoResult<()> {
+        if let Some(ref overrides) = self.overrides {
+            for (spec, toml) in overrides {
+                let which = format!("profile.{}.overrides.\"{}\"", name, spec);
+                toml.validate_override(&which)?;
+            }
+        }
+        if let Some(ref toml) = self.build_override {
+            toml.validate_override(&format!("profile.{}.build-override", name))?;
+        }
+        Ok(())
+    }
+
+    fn validate_override(&self, which: &str) -> CargoResult<()> {
+        if self.overrides.is_some() || self.build_override.is_some() {
+            bail!("`{}` cannot contain nested overrides", which)
+        }
+        if self.inherits.is_some() {
+            bail!("`inherits` may not be specified in `{}`", which)
+        }
+        // These all have to agree across the whole crate graph, or only mean
+        // something for the final artifact, so they can't be overridden.
+        if self.lto.is_some() {
+            bail!("`lto` may not be specified in `{}`", which)
+        }
+        if self.panic.is_some() {
+            bail!("`panic` may not be specified in `{}`", which)
+        }
+        if self.rpath.is_some() {
+            bail!("`rpath` may not be specified in `{}`", which)
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -416,11 +467,76 @@ impl<'de> de::Deserialize<'de> for VecStringOrBool {
     }
 }
 
+/// A key of `[package]` which a workspace member may either set itself, or
+/// inherit from `[workspace.package]` with `key.workspace = true`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum MaybeWorkspace<T> {
+    Defined(T),
+    Workspace(TomlWorkspaceField),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TomlWorkspaceField {
+    workspace: bool,
+}
+
+impl<'de, T: Deserialize<'de>> de::Deserialize<'de> for MaybeWorkspace<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: de::Deserializer<'de>
+    {
+        let value = toml::Value::deserialize(deserializer)?;
+        if value.is_table() {
+            TomlWorkspaceField::deserialize(value)
+                .map(MaybeWorkspace::Workspace)
+                .map_err(de::Error::custom)
+        } else {
+            T::deserialize(value)
+                .map(MaybeWorkspace::Defined)
+                .map_err(de::Error::custom)
+        }
+    }
+}
+
+impl<T: Clone> MaybeWorkspace<T> {
+    /// Returns this value, or the one inherited from the workspace with
+    /// `inherit`.
+    fn resolve<F>(&self, key: &str, inherit: F) -> CargoResult<MaybeWorkspace<T>>
+        where F: FnOnce() -> CargoResult<T>
+    {
+        match *self {
+            MaybeWorkspace::Defined(..) => Ok(self.clone()),
+            MaybeWorkspace::Workspace(TomlWorkspaceField { workspace: true }) => {
+                let value = inherit().chain_err(|| {
+                    format!("error inheriting `{}` from the workspace root manifest", key)
+                })?;
+                Ok(MaybeWorkspace::Defined(value))
+            }
+            MaybeWorkspace::Workspace(TomlWorkspaceField { workspace: false }) => {
+                bail!("`workspace` cannot be false for key `package.{}`", key)
+            }
+        }
+    }
+
+    /// Returns the value of a key after `TomlManifest::inherit` has replaced
+    /// every inherited value.
+    fn defined(&self) -> &T {
+        match *self {
+            MaybeWorkspace::Defined(ref value) => value,
+            MaybeWorkspace::Workspace(..) => panic!("value wasn't inherited from the workspace"),
+        }
+    }
+}
+
+fn defined<T: Clone>(value: &Option<MaybeWorkspace<T>>) -> Option<T> {
+    value.as_ref().map(|v| v.defined().clone())
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct TomlProject {
     name: String,
-    version: semver::Version,
-    authors: Option<Vec<String>>,
+    version: MaybeWorkspace<semver::Version>,
+    authors: Option<MaybeWorkspace<Vec<String>>>,
     build: Option<StringOrBool>,
     links: Option<String>,
     exclude: Option<Vec<String>>,
@@ -433,31 +549,187 @@ pub struct TomlProject {
     im_a_teapot: Option<bool>,
 
     // package metadata
-    description: Option<String>,
-    homepage: Option<String>,
-    documentation: Option<String>,
+    description: Option<MaybeWorkspace<String>>,
+    homepage: Option<MaybeWorkspace<String>>,
+    documentation: Option<MaybeWorkspace<String>>,
     readme: Option<String>,
-    keywords: Option<Vec<String>>,
-    categories: Option<Vec<String>>,
-    license: Option<String>,
+    keywords: Option<MaybeWorkspace<Vec<String>>>,
+    categories: Option<MaybeWorkspace<Vec<String>>>,
+    license: Option<MaybeWorkspace<String>>,
     #[serde(rename = "license-file")]
     license_file: Option<String>,
-    repository: Option<String>,
+    repository: Option<MaybeWorkspace<String>>,
     metadata: Option<toml::Value>,
     rust: Option<String>,
+    #[serde(rename = "rust-version")]
+    rust_version: Option<MaybeWorkspace<String>>,
+    resolver: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TomlWorkspace {
     members: Option<Vec<String>>,
     #[serde(rename = "default-members")]
     default_members: Option<Vec<String>>,
     exclude: Option<Vec<String>>,
+    resolver: Option<String>,
+    package: Option<TomlWorkspacePackage>,
+    dependencies: Option<BTreeMap<String, TomlDependency>>,
+}
+
+/// The `[workspace.package]` table, with the keys of `[package]` which
+/// members can inherit.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TomlWorkspacePackage {
+    version: Option<semver::Version>,
+    authors: Option<Vec<String>>,
+    description: Option<String>,
+    homepage: Option<String>,
+    documentation: Option<String>,
+    keywords: Option<Vec<String>>,
+    categories: Option<Vec<String>>,
+    license: Option<String>,
+    repository: Option<String>,
+    #[serde(rename = "rust-version")]
+    rust_version: Option<String>,
+}
+
+/// The values a workspace root offers for its members to inherit.
+#[derive(Clone, Debug, Default)]
+pub struct InheritableFields {
+    package: Option<TomlWorkspacePackage>,
+    dependencies: Option<BTreeMap<String, TomlDependency>>,
+    root: PathBuf,
+}
+
+impl InheritableFields {
+    fn new(workspace: &TomlWorkspace, root: &Path) -> InheritableFields {
+        InheritableFields {
+            package: workspace.package.clone(),
+            dependencies: workspace.dependencies.clone(),
+            root: root.to_path_buf(),
+        }
+    }
+
+    fn package<T, F>(&self, key: &str, get: F) -> CargoResult<T>
+        where T: Clone,
+              F: FnOnce(&TomlWorkspacePackage) -> Option<&T>
+    {
+        match self.package.as_ref().and_then(get) {
+            Some(value) => Ok(value.clone()),
+            None => bail!("`workspace.package.{}` was not defined", key),
+        }
+    }
+
+    /// Returns `name` from `[workspace.dependencies]`, with its path made
+    /// relative to the workspace root rather than to the member.
+    fn dependency(&self, name: &str) -> CargoResult<DetailedTomlDependency> {
+        let dep = match self.dependencies.as_ref().and_then(|deps| deps.get(name)) {
+            Some(dep) => dep,
+            None => bail!("`workspace.dependencies.{}` was not defined", name),
+        };
+        let mut dep = match *dep {
+            TomlDependency::Simple(ref version) => {
+                DetailedTomlDependency {
+                    version: Some(version.clone()),
+                    ..Default::default()
+                }
+            }
+            TomlDependency::Detailed(ref d) => d.clone(),
+        };
+        if dep.workspace.is_some() || dep.optional.is_some() {
+            bail!("`workspace.dependencies.{}` cannot specify `workspace` or `optional`",
+                  name)
+        }
+        if let Some(path) = dep.path.take() {
+            dep.path = Some(self.root.join(path).display().to_string());
+        }
+        Ok(dep)
+    }
+}
+
+/// Looks up the values a manifest inherits from its workspace root, only
+/// once something is actually inherited.
+struct WorkspaceInheritance<'a> {
+    manifest: &'a TomlManifest,
+    package_root: &'a Path,
+    config: &'a Config,
+    fields: LazyCell<InheritableFields>,
+}
+
+impl<'a> WorkspaceInheritance<'a> {
+    fn get(&self) -> CargoResult<&InheritableFields> {
+        self.fields.try_borrow_with(|| {
+            // A workspace root inherits from itself
+            if let Some(ref workspace) = self.manifest.workspace {
+                return Ok(InheritableFields::new(workspace, self.package_root))
+            }
+            let manifest_path = self.package_root.join("Cargo.toml");
+            let pointer = self.manifest.package.as_ref()
+                              .or_else(|| self.manifest.project.as_ref())
+                              .and_then(|p| p.workspace.as_ref());
+            let root = match pointer {
+                Some(root) => {
+                    paths::normalize_path(&self.package_root.join(root).join("Cargo.toml"))
+                }
+                None => {
+                    match find_workspace_root(&manifest_path, self.config)? {
+                        Some(root) => root,
+                        None => bail!("failed to find a workspace root"),
+                    }
+                }
+            };
+            let source_id = SourceId::for_path(root.parent().unwrap())?;
+            let (manifest, _nested_paths) = read_manifest(&root, &source_id, self.config)?;
+            let workspace_config = match manifest {
+                EitherManifest::Real(ref m) => m.workspace_config().clone(),
+                EitherManifest::Virtual(ref vm) => vm.workspace_config().clone(),
+            };
+            match workspace_config {
+                WorkspaceConfig::Root(ref config) => Ok(config.inheritable().clone()),
+                WorkspaceConfig::Member { .. } => {
+                    bail!("root of a workspace inferred but wasn't a root: {}",
+                          root.display())
+                }
+            }
+        })
+    }
 }
 
 impl TomlProject {
     pub fn to_package_id(&self, source_id: &SourceId) -> CargoResult<PackageId> {
-        PackageId::new(&self.name, self.version.clone(), source_id)
+        PackageId::new(&self.name, self.version.defined().clone(), source_id)
+    }
+
+    /// Replaces the keys inherited from `[workspace.package]` with their
+    /// values.
+    fn inherit(&mut self, ws: &WorkspaceInheritance) -> CargoResult<()> {
+        fn inherit<T, F>(value: &mut Option<MaybeWorkspace<T>>,
+                         key: &str,
+                         ws: &WorkspaceInheritance,
+                         get: F) -> CargoResult<()>
+            where T: Clone,
+                  F: FnOnce(&TomlWorkspacePackage) -> Option<&T>
+        {
+            if let Some(ref mut value) = *value {
+                *value = value.resolve(key, || ws.get()?.package(key, get))?;
+            }
+            Ok(())
+        }
+
+        self.version = self.version.resolve("version", || {
+            ws.get()?.package("version", |p| p.version.as_ref())
+        })?;
+        inherit(&mut self.authors, "authors", ws, |p| p.authors.as_ref())?;
+        inherit(&mut self.description, "description", ws, |p| p.description.as_ref())?;
+        inherit(&mut self.homepage, "homepage", ws, |p| p.homepage.as_ref())?;
+        inherit(&mut self.documentation, "documentation", ws, |p| p.documentation.as_ref())?;
+        inherit(&mut self.keywords, "keywords", ws, |p| p.keywords.as_ref())?;
+        inherit(&mut self.categories, "categories", ws, |p| p.categories.as_ref())?;
+        inherit(&mut self.license, "license", ws, |p| p.license.as_ref())?;
+        inherit(&mut self.repository, "repository", ws, |p| p.repository.as_ref())?;
+        inherit(&mut self.rust_version, "rust-version", ws, |p| p.rust_version.as_ref())?;
+        Ok(())
     }
 }
 
@@ -556,6 +828,45 @@ impl TomlManifest {
         }
     }
 
+    /// Returns a copy of this manifest with every value inherited from the
+    /// workspace with `workspace = true` replaced by the value itself.
+    fn inherit(&self, package_root: &Path, config: &Config) -> CargoResult<TomlManifest> {
+        let ws = WorkspaceInheritance {
+            manifest: self,
+            package_root,
+            config,
+            fields: LazyCell::new(),
+        };
+        let mut me = self.clone();
+        if let Some(ref mut project) = me.package {
+            project.inherit(&ws)?;
+        }
+        if let Some(ref mut project) = me.project {
+            project.inherit(&ws)?;
+        }
+
+        let mut tables = vec![
+            &mut me.dependencies,
+            &mut me.dev_dependencies,
+            &mut me.dev_dependencies2,
+            &mut me.build_dependencies,
+            &mut me.build_dependencies2,
+        ];
+        for platform in me.target.iter_mut().flat_map(|t| t.values_mut()) {
+            tables.push(&mut platform.dependencies);
+            tables.push(&mut platform.dev_dependencies);
+            tables.push(&mut platform.dev_dependencies2);
+            tables.push(&mut platform.build_dependencies);
+            tables.push(&mut platform.build_dependencies2);
+        }
+        for deps in tables.into_iter().flat_map(|t| t.iter_mut()) {
+            for (name, dep) in deps.iter_mut() {
+                *dep = dep.inherit(name, &ws)?;
+            }
+        }
+        Ok(me)
+    }
+
     fn to_real_manifest(me: &Rc<TomlManifest>,
                         source_id: &SourceId,
                         package_root: &Path,
@@ -565,6 +876,10 @@ impl TomlManifest {
         let mut warnings = vec![];
         let mut errors = vec![];
 
+        // Everything below, as well as the manifest written by `cargo
+        // package`, only sees the inherited values
+        let me = &Rc::new(me.inherit(package_root, config)?);
+
         // Parse features first so they will be available when parsing other parts of the toml
         let empty = Vec::new();
         let cargo_features = me.cargo_features.as_ref().unwrap_or(&empty);
@@ -675,19 +990,26 @@ impl TomlManifest {
         let exclude = project.exclude.clone().unwrap_or_default();
         let include = project.include.clone().unwrap_or_default();
 
+        let rust_version = match defined(&project.rust_version) {
+            Some(v) => Some(v.parse::<RustVersion>().chain_err(|| {
+                "failed to parse the `rust-version` key"
+            })?),
+            None => None,
+        };
         let summary = Summary::new(pkgid, deps, me.features.clone()
-            .unwrap_or_else(BTreeMap::new), project.links.clone())?;
+            .unwrap_or_else(BTreeMap::new), project.links.clone())?
+            .set_rust_version(rust_version);
         let metadata = ManifestMetadata {
-            description: project.description.clone(),
-            homepage: project.homepage.clone(),
-            documentation: project.documentation.clone(),
+            description: defined(&project.description),
+            homepage: defined(&project.homepage),
+            documentation: defined(&project.documentation),
             readme: project.readme.clone(),
-            authors: project.authors.clone().unwrap_or_default(),
-            license: project.license.clone(),
+            authors: defined(&project.authors).unwrap_or_default(),
+            license: defined(&project.license),
             license_file: project.license_file.clone(),
-            repository: project.repository.clone(),
-            keywords: project.keywords.clone().unwrap_or_default(),
-            categories: project.categories.clone().unwrap_or_default(),
+            repository: defined(&project.repository),
+            keywords: defined(&project.keywords).unwrap_or_default(),
+            categories: defined(&project.categories).unwrap_or_default(),
             badges: me.badges.clone().unwrap_or_default(),
             links: project.links.clone(),
         };
@@ -698,6 +1020,7 @@ impl TomlManifest {
                 WorkspaceConfig::Root(
                     WorkspaceRootConfig::new(
                         &package_root, &config.members, &config.default_members, &config.exclude,
+                        InheritableFields::new(config, package_root),
                     )
                 )
             }
@@ -709,7 +1032,7 @@ impl TomlManifest {
                        `[workspace]`, only one can be specified")
             }
         };
-        let profiles = build_profiles(&me.profile);
+        let profiles = build_profiles(&me.profile)?;
         let publish = match project.publish {
             Some(VecStringOrBool::VecString(ref vecstring)) => {
                 features.require(Feature::alternative_registries()).chain_err(|| {
@@ -741,6 +1064,9 @@ impl TomlManifest {
         } else {
                 Epoch::Epoch2015
         };
+        let resolver = me.workspace.as_ref().and_then(|w| w.resolver.as_ref())
+                                            .or(project.resolver.as_ref());
+        let resolve_behavior = parse_resolve_behavior(resolver)?;
         let mut manifest = Manifest::new(summary,
                                          targets,
                                          exclude,
@@ -756,6 +1082,7 @@ impl TomlManifest {
                                          features,
                                          epoch,
                                          project.im_a_teapot,
+                                         resolve_behavior,
                                          Rc::clone(me));
         if project.license_file.is_some() && project.license.is_some() {
             manifest.add_warning("only one of `license` or \
@@ -821,12 +1148,13 @@ impl TomlManifest {
             };
             (me.replace(&mut cx)?, me.patch(&mut cx)?)
         };
-        let profiles = build_profiles(&me.profile);
+        let profiles = build_profiles(&me.profile)?;
         let workspace_config = match me.workspace {
             Some(ref config) => {
                 WorkspaceConfig::Root(
                     WorkspaceRootConfig::new(
                         &root, &config.members, &config.default_members, &config.exclude,
+                        InheritableFields::new(config, root),
                     )
                 )
             }
@@ -834,7 +1162,11 @@ impl TomlManifest {
                 bail!("virtual manifests must be configured with [workspace]");
             }
         };
-        Ok((VirtualManifest::new(replace, patch, workspace_config, profiles), nested_paths))
+        let resolve_behavior = parse_resolve_behavior(
+            me.workspace.as_ref().and_then(|w| w.resolver.as_ref()))?;
+        Ok((VirtualManifest::new(replace, patch, workspace_config, profiles,
+                                 resolve_behavior),
+            nested_paths))
     }
 
     fn replace(&self, cx: &mut Context)
@@ -925,6 +1257,37 @@ fn unique_build_targets(targets: &[Target], package_root: &Path) -> Result<(), S
 }
 
 impl TomlDependency {
+    /// Replaces a dependency with `workspace = true` by the one of the same
+    /// name in `[workspace.dependencies]`, adding the features and
+    /// optionality set by the member.
+    fn inherit(&self, name: &str, ws: &WorkspaceInheritance) -> CargoResult<TomlDependency> {
+        let details = match *self {
+            TomlDependency::Detailed(ref d) if d.workspace.is_some() => d,
+            _ => return Ok(self.clone()),
+        };
+        if details.workspace == Some(false) {
+            bail!("`workspace` cannot be false for dependency `{}`", name)
+        }
+        if details.version.is_some() || details.registry.is_some() ||
+           details.registry_index.is_some() || details.path.is_some() ||
+           details.git.is_some() || details.branch.is_some() ||
+           details.tag.is_some() || details.rev.is_some() ||
+           details.default_features.is_some() || details.default_features2.is_some() ||
+           details.package.is_some() {
+            bail!("dependency `{}` is inherited from the workspace, so only \
+                   `features` and `optional` can be specified for it", name)
+        }
+        let mut dep = ws.get()?.dependency(name).chain_err(|| {
+            format!("error inheriting dependency `{}` from the workspace root manifest",
+                    name)
+        })?;
+        if let Some(ref features) = details.features {
+            dep.features.get_or_insert_with(Vec::new).extend(features.iter().cloned());
+        }
+        dep.optional = details.optional;
+        Ok(TomlDependency::Detailed(dep))
+    }
+
     fn to_dependency(&self,
                      name: &str,
                      cx: &mut Context,
@@ -950,6 +1313,11 @@ impl DetailedTomlDependency {
                      cx: &mut Context,
                      kind: Option<Kind>)
                      -> CargoResult<Dependency> {
+        if self.workspace.is_some() {
+            bail!("dependency ({}) can only be inherited from the workspace \
+                   in the dependency tables of a package", name)
+        }
+
         if self.version.is_none() && self.path.is_none() &&
            self.git.is_none() {
             let msg = format!("dependency ({}) specified without \
@@ -1125,7 +1493,7 @@ impl ser::Serialize for PathValue {
 }
 
 /// Corresponds to a `target` entry, but `TomlTarget` is already used.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 struct TomlPlatform {
     dependencies: Option<BTreeMap<String, TomlDependency>>,
     #[serde(rename = "build-dependencies")]
@@ -1165,29 +1533,200 @@ impl fmt::Debug for PathValue {
     }
 }
 
-fn build_profiles(profiles: &Option<TomlProfiles>) -> Profiles {
+fn parse_resolve_behavior(resolver: Option<&String>)
+                          -> CargoResult<Option<ResolveBehavior>> {
+    match resolver {
+        Some(resolver) => match resolver.parse() {
+            Ok(behavior) => Ok(Some(behavior)),
+            Err(()) => bail!("the `resolver` key must be one of: `1`, `2`"),
+        },
+        None => Ok(None),
+    }
+}
+
+fn build_profiles(profiles: &Option<TomlProfiles>) -> CargoResult<Profiles> {
     let profiles = profiles.as_ref();
+    let mut ret = profile_group(profiles, None)?;
+
+    let profiles = match profiles {
+        Some(profiles) => profiles,
+        None => return Ok(ret),
+    };
+    for (name, toml) in profiles.iter() {
+        if BUILTIN_PROFILES.contains(&&name[..]) {
+            if toml.inherits.is_some() {
+                bail!("`inherits` may not be specified in the built-in `{}` profile",
+                      name)
+            }
+            continue
+        }
+        validate_profile_name(name)?;
+        let custom = CustomProfile::new(profiles, name)?;
+        let group = profile_group(Some(profiles), Some(&custom))?;
+        ret.custom.insert(name.clone(), (custom.release, group));
+    }
+    Ok(ret)
+}
+
+fn validate_profile_name(name: &str) -> CargoResult<()> {
+    if name.is_empty() {
+        bail!("profile names cannot be empty")
+    }
+    if let Some(ch) = name.chars()
+                          .find(|ch| !ch.is_alphanumeric() && *ch != '_' && *ch != '-') {
+        bail!("invalid character `{}` in profile name `{}`, \
+               only alphanumeric characters, `-` and `_` are allowed", ch, name)
+    }
+    if RESERVED_PROFILE_NAMES.contains(&name) {
+        bail!("profile name `{}` is reserved", name)
+    }
+    Ok(())
+}
+
+/// A custom profile, along with all of the custom profiles it inherits from.
+struct CustomProfile<'a> {
+    name: &'a str,
+    /// Whether the profile is ultimately based on `release` rather than `dev`
+    release: bool,
+    /// The tables of the profile and its ancestors, from the furthest
+    /// ancestor down to the profile itself
+    chain: Vec<&'a TomlProfile>,
+}
+
+impl<'a> CustomProfile<'a> {
+    fn new(profiles: &'a TomlProfiles, name: &'a str) -> CargoResult<CustomProfile<'a>> {
+        let mut chain = Vec::new();
+        let mut seen = HashSet::new();
+        seen.insert(name);
+        let mut current = name;
+        let release = loop {
+            let toml = &profiles[current];
+            chain.push(toml);
+            let parent = match toml.inherits {
+                Some(ref parent) => parent,
+                None => bail!("profile `{}` is missing an `inherits` directive \
+                               (`inherits` is required for all profiles except \
+                               `dev` or `release`)", current),
+            };
+            match &parent[..] {
+                "dev" => break false,
+                "release" => break true,
+                p if BUILTIN_PROFILES.contains(&p) => {
+                    bail!("profile `{}` inherits from `{}`, but only `dev`, \
+                           `release` and custom profiles can be inherited from",
+                          current, p)
+                }
+                p if !profiles.contains_key(p) => {
+                    bail!("profile `{}` inherits from `{}`, but that profile \
+                           is not defined", current, p)
+                }
+                p => {
+                    if !seen.insert(p) {
+                        bail!("profile inheritance loop detected with profile \
+                               `{}` inheriting `{}`", current, p)
+                    }
+                    current = p;
+                }
+            }
+        };
+        chain.reverse();
+        Ok(CustomProfile { name, release, chain })
+    }
+
+    /// The built-in profile this one is ultimately based on
+    fn root(&self) -> &'static str {
+        if self.release { "release" } else { "dev" }
+    }
+}
+
+/// Builds a set of profiles along with all of its package overrides and
+/// `build-override` profiles, for `custom` if given.
+fn profile_group(profiles: Option<&TomlProfiles>,
+                 custom: Option<&CustomProfile>) -> CargoResult<Profiles> {
+    // Every package mentioned in an `overrides` table of any profile gets a
+    // whole set of profiles of its own.
+    let mut specs = BTreeSet::new();
+    let mut has_build_override = false;
+    if let Some(profiles) = profiles {
+        for (name, toml) in profiles.iter() {
+            toml.validate_overrides(name)?;
+            if let Some(ref overrides) = toml.overrides {
+                specs.extend(overrides.keys().map(|s| &s[..]));
+            }
+            has_build_override |= toml.build_override.is_some();
+        }
+    }
+
+    let mut ret = profile_set(profiles, custom, false, None);
+    for spec in specs.iter() {
+        let id = PackageIdSpec::parse(spec).chain_err(|| {
+            format!("invalid package id specification `{}` in profile overrides",
+                    spec)
+        })?;
+        ret.overrides.push((id, profile_set(profiles, custom, false, Some(spec))));
+    }
+    if has_build_override {
+        let mut build = profile_set(profiles, custom, true, None);
+        for (spec, &(ref id, _)) in specs.iter().zip(ret.overrides.iter()) {
+            build.overrides.push((id.clone(),
+                                  profile_set(profiles, custom, true, Some(spec))));
+        }
+        ret.build_override = Some(Box::new(build));
+    }
+    Ok(ret)
+}
+
+/// Builds one set of profiles, with `build-override` and the overrides for
+/// `spec` layered on top of each profile when requested. The settings of
+/// `custom` are layered on top of every profile derived from its root.
+fn profile_set(profiles: Option<&TomlProfiles>,
+               custom: Option<&CustomProfile>,
+               build: bool,
+               spec: Option<&str>) -> Profiles {
+    let apply = |profile: Profile, toml: &TomlProfile| {
+        let mut profile = merge_toml(profile, toml);
+        if build {
+            if let Some(ref build_override) = toml.build_override {
+                profile = merge_toml(profile, build_override);
+            }
+        }
+        if let Some(spec) = spec {
+            if let Some(toml) = toml.overrides.as_ref().and_then(|o| o.get(spec)) {
+                profile = merge_toml(profile, toml);
+            }
+        }
+        profile
+    };
+    let merge = |profile: Profile, root: &str, name: &str| {
+        let mut profile = match profiles.and_then(|p| p.get(name)) {
+            Some(toml) => apply(profile, toml),
+            None => profile,
+        };
+        if let Some(custom) = custom {
+            if custom.root() == root {
+                for toml in custom.chain.iter() {
+                    profile = apply(profile, toml);
+                }
+                profile.name = custom.name.to_string();
+            }
+        }
+        profile
+    };
     let mut profiles = Profiles {
-        release: merge(Profile::default_release(),
-                       profiles.and_then(|p| p.release.as_ref())),
-        dev: merge(Profile::default_dev(),
-                   profiles.and_then(|p| p.dev.as_ref())),
-        test: merge(Profile::default_test(),
-                    profiles.and_then(|p| p.test.as_ref())),
-        test_deps: merge(Profile::default_dev(),
-                         profiles.and_then(|p| p.dev.as_ref())),
-        bench: merge(Profile::default_bench(),
-                     profiles.and_then(|p| p.bench.as_ref())),
-        bench_deps: merge(Profile::default_release(),
-                          profiles.and_then(|p| p.release.as_ref())),
-        doc: merge(Profile::default_doc(),
-                   profiles.and_then(|p| p.doc.as_ref())),
+        release: merge(Profile::default_release(), "release", "release"),
+        dev: merge(Profile::default_dev(), "dev", "dev"),
+        test: merge(Profile::default_test(), "dev", "test"),
+        test_deps: merge(Profile::default_dev(), "dev", "dev"),
+        bench: merge(Profile::default_bench(), "release", "bench"),
+        bench_deps: merge(Profile::default_release(), "release", "release"),
+        doc: merge(Profile::default_doc(), "dev", "doc"),
         custom_build: Profile::default_custom_build(),
-        check: merge(Profile::default_check(),
-                     profiles.and_then(|p| p.dev.as_ref())),
-        check_test: merge(Profile::default_check_test(),
-                          profiles.and_then(|p| p.dev.as_ref())),
+        check: merge(Profile::default_check(), "dev", "dev"),
+        check_test: merge(Profile::default_check_test(), "dev", "dev"),
         doctest: Profile::default_doctest(),
+        overrides: Vec::new(),
+        build_override: None,
+        custom: BTreeMap::new(),
     };
     // The test/bench targets cannot have panic=abort because they'll all get
     // compiled with --test which requires the unwind runtime currently
@@ -1197,14 +1736,11 @@ fn build_profiles(profiles: &Option<TomlProfiles>) -> Profiles {
     profiles.bench_deps.panic = None;
     return profiles;
 
-    fn merge(profile: Profile, toml: Option<&TomlProfile>) -> Profile {
-        let &TomlProfile {
+    fn merge_toml(profile: Profile, toml: &TomlProfile) -> Profile {
+        let TomlProfile {
             ref opt_level, ref lto, codegen_units, ref debug, debug_assertions, rpath,
-            ref panic, ref overflow_checks, ref incremental,
-        } = match toml {
-            Some(toml) => toml,
-            None => return profile,
-        };
+            ref panic, ref overflow_checks, ref incremental, ..
+        } = *toml;
         let debug = match *debug {
             Some(U32OrBool::U32(debug)) => Some(Some(debug)),
             Some(U32OrBool::Bool(true)) => Some(Some(2)),
@@ -1212,13 +1748,14 @@ fn build_profiles(profiles: &Option<TomlProfiles>) -> Profiles {
             None => None,
         };
         Profile {
+            name: profile.name,
             opt_level: opt_level.clone().unwrap_or(TomlOptLevel(profile.opt_level)).0,
             lto: match *lto {
                 Some(StringOrBool::Bool(b)) => Lto::Bool(b),
                 Some(StringOrBool::String(ref n)) => Lto::Named(n.clone()),
                 None => profile.lto,
             },
-            codegen_units,
+            codegen_units: codegen_units.or(profile.codegen_units),
             rustc_args: None,
             rustdoc_args: None,
             debuginfo: debug.unwrap_or(profile.debuginfo),
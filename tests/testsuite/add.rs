@@ -0,0 +1,299 @@
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn add_newest_version() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.2.0").publish();
+    Package::new("bar", "0.3.0").yanked(true).publish();
+    Package::new("bar", "1.0.0-beta.1").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"[package]
+name = "foo"  # not bar
+version = "0.1.0"
+authors = []
+
+[dependencies]
+aaa = "1.0"     # first
+zzz = { version = "0.1", default-features = false }
+
+[features]
+default = []
+"#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("add").arg("bar"),
+                execs().with_status(0)
+                       .with_stderr("\
+[UPDATING] registry `[..]`
+[ADDING] bar v0.2.0 to dependencies
+"));
+
+    assert_eq!(p.read_file("Cargo.toml"), r#"[package]
+name = "foo"  # not bar
+version = "0.1.0"
+authors = []
+
+[dependencies]
+aaa = "1.0"     # first
+bar = "0.2.0"
+zzz = { version = "0.1", default-features = false }
+
+[features]
+default = []
+"#);
+}
+
+#[test]
+fn add_version_requirement() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.1.1").publish();
+    Package::new("bar", "0.2.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"[package]
+name = "foo"
+version = "0.1.0"
+authors = []
+
+[dependencies]
+bar = "0.2"
+"#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("add").arg("bar@0.1"),
+                execs().with_status(0)
+                       .with_stderr("\
+[UPDATING] registry `[..]`
+[ADDING] bar v0.1.1 to dependencies
+"));
+
+    assert_eq!(p.read_file("Cargo.toml"), r#"[package]
+name = "foo"
+version = "0.1.0"
+authors = []
+
+[dependencies]
+bar = "0.1"
+"#);
+
+    assert_that(p.cargo("add").arg("bar@0.3"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] could not find `bar` in registry `[..]` with version `0.3`"));
+}
+
+#[test]
+fn add_to_new_tables() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"[package]
+name = "foo"
+version = "0.1.0"
+authors = []
+"#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("add").arg("bar").arg("--dev"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[ADDING] bar v0.1.0 to dev-dependencies"));
+    assert_that(p.cargo("add").arg("bar").arg("--build")
+                 .arg("--features").arg("a b"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[ADDING] bar v0.1.0 to build-dependencies"));
+    assert_that(p.cargo("add").arg("bar").arg("--optional")
+                 .arg("--target").arg("cfg(unix)"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[ADDING] bar v0.1.0 to target.'cfg(unix)'.dependencies"));
+
+    assert_eq!(p.read_file("Cargo.toml"), r#"[package]
+name = "foo"
+version = "0.1.0"
+authors = []
+
+[dev-dependencies]
+bar = "0.1.0"
+
+[build-dependencies]
+bar = { version = "0.1.0", features = ["a", "b"] }
+
+[target.'cfg(unix)'.dependencies]
+bar = { version = "0.1.0", optional = true }
+"#);
+}
+
+#[test]
+fn add_path_replaces_table() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"[package]
+name = "foo"
+version = "0.1.0"
+authors = []
+
+[dependencies.bar]
+# pinned for now
+version = "=0.1.0"
+
+[dependencies]
+baz = "0.1"
+"#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("add").arg("bar@0.1").arg("--path").arg("bar"),
+                execs().with_status(0)
+                       .with_stderr("\
+[ADDING] bar (local) to dependencies
+"));
+
+    assert_eq!(p.read_file("Cargo.toml"), r#"[package]
+name = "foo"
+version = "0.1.0"
+authors = []
+
+[dependencies.bar]
+version = "0.1"
+path = "bar"
+# pinned for now
+
+[dependencies]
+baz = "0.1"
+"#);
+}
+
+#[test]
+fn add_path_relative_to_cwd() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"[package]
+name = "foo"
+version = "0.1.0"
+authors = []
+"#)
+        .file("src/lib.rs", "")
+        .file("libs/bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("libs/bar/src/lib.rs", "")
+        .file("libs/baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("libs/baz/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("add").arg("bar").arg("--path").arg("../libs/bar")
+                 .cwd(p.root().join("src")),
+                execs().with_status(0));
+    assert_that(p.cargo("add").arg("baz").arg("--path").arg("baz")
+                 .arg("--manifest-path").arg("../Cargo.toml")
+                 .cwd(p.root().join("libs")),
+                execs().with_status(0));
+
+    assert_eq!(p.read_file("Cargo.toml"), r#"[package]
+name = "foo"
+version = "0.1.0"
+authors = []
+
+[dependencies]
+bar = { path = "libs/bar" }
+baz = { path = "libs/baz" }
+"#);
+    assert_that(p.cargo("build"), execs().with_status(0));
+}
+
+#[test]
+fn add_path_with_another_package() {
+    let manifest = r#"
+        [package]
+        name = "foo"
+        version = "0.1.0"
+        authors = []
+    "#;
+    let p = project("foo")
+        .file("Cargo.toml", manifest)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("add").arg("bar").arg("--path").arg("bar"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] the package at `bar` is called `baz`, not `bar`
+"));
+    assert_eq!(p.read_file("Cargo.toml"), manifest);
+}
+
+#[test]
+fn add_unknown_crate() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("add").arg("baz"),
+                execs().with_status(101)
+                       .with_stderr("\
+[UPDATING] registry `[..]`
+[ERROR] could not find `baz` in registry `[..]`
+"));
+}
+
+#[test]
+fn add_invalid_manifest_unchanged() {
+    Package::new("bar", "0.1.0").publish();
+
+    let manifest = r#"
+        [package]
+        name = "foo"
+        version = "0.1.0"
+        authors = []
+    "#;
+    let p = project("foo")
+        .file("Cargo.toml", manifest)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("add").arg("bar").arg("--dev").arg("--optional"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] the edited manifest would be invalid, `[..]Cargo.toml` has been left unchanged")
+                       .with_stderr_contains("\
+Caused by:
+  Dev-dependencies are not allowed to be optional: `bar`"));
+
+    assert_eq!(p.read_file("Cargo.toml"), manifest);
+}
@@ -865,7 +865,7 @@ fn cargo_compile_path_with_offline() {
         .file("bar/src/lib.rs", "")
         .build();
 
-    assert_that(p.cargo("build").masquerade_as_nightly_cargo().arg("-Zoffline"),
+    assert_that(p.cargo("build").arg("--offline"),
                 execs().with_status(0));
 }
 
@@ -908,7 +908,7 @@ fn cargo_compile_with_downloaded_dependency_with_offline() {
         .file("src/lib.rs", "")
         .build();
 
-    assert_that(p2.cargo("build").masquerade_as_nightly_cargo().arg("-Zoffline"),
+    assert_that(p2.cargo("build").arg("--offline"),
                 execs().with_status(0)
                     .with_stderr(format!("\
 [COMPILING] present_dep v1.2.3
@@ -931,15 +931,15 @@ fn cargo_compile_offline_not_try_update() {
         .file("src/lib.rs", "")
         .build();
 
-    assert_that(p.cargo("build").masquerade_as_nightly_cargo().arg("-Zoffline"),
+    assert_that(p.cargo("build").arg("--offline"),
                 execs().with_status(101)
                     .with_stderr("\
 error: no matching package named `not_cached_dep` found
 location searched: registry `[..]`
 required by package `bar v0.1.0 ([..])`
-As a reminder, you're using offline mode (-Z offline) \
+As a reminder, you're using offline mode (--offline) \
 which can sometimes cause surprising resolution failures, \
-if this error is too confusing you may with to retry \
+if this error is too confusing you may wish to retry \
 without the offline flag."));
 }
 
@@ -998,7 +998,7 @@ fn main(){
 }")
         .build();
 
-    assert_that(p2.cargo("run").masquerade_as_nightly_cargo().arg("-Zoffline"),
+    assert_that(p2.cargo("run").arg("--offline"),
                 execs().with_status(0)
                     .with_stderr(format!("\
 [COMPILING] present_dep v1.2.3
@@ -1128,16 +1128,16 @@ fn compile_offline_while_transitive_dep_not_cached() {
 
     drop( File::create(bar_path).ok().unwrap().write_all(&content) );
 
-    assert_that(p.cargo("build").masquerade_as_nightly_cargo().arg("-Zoffline"),
+    assert_that(p.cargo("build").arg("--offline"),
         execs().with_status(101)
             .with_stderr("\
 error: no matching package named `bar` found
 location searched: registry `[..]`
 required by package `foo v0.1.0`
     ... which is depended on by `transitive_load_test v0.0.1 ([..]/transitive_load_test)`
-As a reminder, you're using offline mode (-Z offline) \
+As a reminder, you're using offline mode (--offline) \
 which can sometimes cause surprising resolution failures, \
-if this error is too confusing you may with to retry \
+if this error is too confusing you may wish to retry \
 without the offline flag."));
 }
 
@@ -3140,6 +3140,7 @@ fn compiler_json_error_format() {
     {
         "reason":"compiler-artifact",
         "profile": {
+            "name": "dev",
             "debug_assertions": true,
             "debuginfo": null,
             "opt_level": "0",
@@ -3180,6 +3181,7 @@ fn compiler_json_error_format() {
             "src_path":"[..]main.rs"
         },
         "profile": {
+            "name": "dev",
             "debug_assertions": true,
             "debuginfo": null,
             "opt_level": "0",
@@ -3200,6 +3202,7 @@ fn compiler_json_error_format() {
     {
         "reason":"compiler-artifact",
         "profile": {
+            "name": "dev",
             "debug_assertions": true,
             "debuginfo": null,
             "opt_level": "0",
@@ -3228,6 +3231,7 @@ fn compiler_json_error_format() {
             "src_path":"[..]main.rs"
         },
         "profile": {
+            "name": "dev",
             "debug_assertions": true,
             "debuginfo": null,
             "opt_level": "0",
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::prelude::*;
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use bufstream::BufStream;
+use cargotest::support::{execs, paths, project, Project};
+use cargotest::support::registry::Package;
+use hamcrest::assert_that;
+
+fn project_using_bar(name: &str, cache: &str) -> Project {
+    project(name)
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "{}"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#, name))
+        .file("src/main.rs", r#"
+            extern crate bar;
+            fn main() { assert_eq!(bar::bar(), 1); }
+        "#)
+        .file(".cargo/config", &format!(r#"
+            [build-cache]
+            {}
+        "#, cache))
+        .build()
+}
+
+fn publish_bar() {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() -> u32 { 1 }")
+        .publish();
+}
+
+fn cache_entries(dir: &Path) -> usize {
+    if !dir.exists() {
+        return 0
+    }
+    fs::read_dir(dir).unwrap().map(|d| {
+        fs::read_dir(d.unwrap().path()).unwrap().count()
+    }).sum()
+}
+
+#[test]
+fn shared_between_workspaces() {
+    publish_bar();
+    let cache_dir = paths::root().join("cache");
+    let config = format!("dir = '{}'", cache_dir.display());
+    let p1 = project_using_bar("foo", &config);
+    let p2 = project_using_bar("baz", &config);
+
+    assert_that(p1.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[COMPILING] bar v0.1.0")
+                       .with_stderr_contains("[CACHE] 0 hits, 1 miss"));
+    assert_eq!(cache_entries(&cache_dir), 1);
+
+    assert_that(p2.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("[RUNNING] `rustc --crate-name bar [..]")
+                       .with_stderr_contains("[CACHE] 1 hit, 0 misses"));
+    assert_that(p2.cargo("run"), execs().with_status(0));
+
+    // A fresh build doesn't look anything up
+    assert_that(p1.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("[CACHE] [..]"));
+}
+
+#[test]
+fn transitive_dependencies() {
+    Package::new("baz", "0.1.0")
+        .file("src/lib.rs", "pub fn baz() -> u32 { 1 }")
+        .publish();
+    Package::new("bar", "0.1.0")
+        .dep("baz", "0.1.0")
+        .file("src/lib.rs", "extern crate baz; pub fn bar() -> u32 { baz::baz() }")
+        .publish();
+    let cache_dir = paths::root().join("cache");
+    let config = format!("dir = '{}'", cache_dir.display());
+    let p1 = project_using_bar("foo", &config);
+    let p2 = project_using_bar("qux", &config);
+
+    assert_that(p1.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[CACHE] 0 hits, 2 misses"));
+    assert_that(p2.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[CACHE] 2 hits, 0 misses"));
+    assert_that(p2.cargo("run"), execs().with_status(0));
+}
+
+#[test]
+fn different_profiles_are_cached_apart() {
+    publish_bar();
+    let cache_dir = paths::root().join("cache");
+    let config = format!("dir = '{}'", cache_dir.display());
+    let p = project_using_bar("foo", &config);
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[CACHE] 0 hits, 1 miss"));
+    assert_that(p.cargo("build").arg("--release"),
+                execs().with_status(0)
+                       .with_stderr_contains("[CACHE] 0 hits, 1 miss"));
+    assert_eq!(cache_entries(&cache_dir), 2);
+}
+
+#[test]
+fn different_env_config_is_cached_apart() {
+    publish_bar();
+    let cache_dir = paths::root().join("cache");
+    let config = format!("dir = '{}'", cache_dir.display());
+    let p1 = project_using_bar("foo", &config);
+    let p2 = project_using_bar("baz", &format!("{}\n[env]\nBAR_ENV = 'x'", config));
+
+    assert_that(p1.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[CACHE] 0 hits, 1 miss"));
+    assert_that(p2.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[CACHE] 0 hits, 1 miss"));
+    assert_eq!(cache_entries(&cache_dir), 2);
+}
+
+#[test]
+fn path_dependencies_are_not_cached() {
+    let cache_dir = paths::root().join("cache");
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file(".cargo/config", &format!(r#"
+            [build-cache]
+            dir = '{}'
+        "#, cache_dir.display()))
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("[CACHE] [..]"));
+    assert_eq!(cache_entries(&cache_dir), 0);
+}
+
+#[test]
+fn evicts_over_max_size() {
+    publish_bar();
+    let cache_dir = paths::root().join("cache");
+    let config = format!("dir = '{}'\nmax-size = '1B'", cache_dir.display());
+    let p = project_using_bar("foo", &config);
+
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("[CACHE] 0 hits, 1 miss")
+                       .with_stderr_contains("[..]Evicted 1 entries from the build cache"));
+    assert_eq!(cache_entries(&cache_dir), 0);
+}
+
+#[test]
+fn bad_max_size() {
+    publish_bar();
+    let p = project_using_bar("foo", "dir = 'cache'\nmax-size = 'lots'");
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] `build-cache.max-size` must be a size like \"10GiB\" or \"500MB\", \
+but found `lots` in [..]config"));
+}
+
+/// Serves a build cache kept in memory, answering `GET` and `PUT` requests.
+fn serve_cache() -> (String, Arc<Mutex<Vec<String>>>) {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+    thread::spawn(move || {
+        let mut entries = HashMap::new();
+        for conn in server.incoming() {
+            let mut conn = BufStream::new(conn.unwrap());
+            let mut request = String::new();
+            let mut len = 0;
+            let mut expect_continue = false;
+            loop {
+                let mut line = String::new();
+                conn.read_line(&mut line).unwrap();
+                let line = line.trim();
+                if line.is_empty() {
+                    break
+                }
+                let lower = line.to_lowercase();
+                if request.is_empty() {
+                    request = line.to_string();
+                } else if lower.starts_with("content-length:") {
+                    len = line["content-length:".len()..].trim().parse().unwrap();
+                } else if lower.starts_with("expect: 100-continue") {
+                    expect_continue = true;
+                }
+            }
+            let mut parts = request.split(' ');
+            let method = parts.next().unwrap().to_string();
+            let path = parts.next().unwrap().to_string();
+
+            let response = if method == "PUT" {
+                if expect_continue {
+                    conn.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").unwrap();
+                    conn.flush().unwrap();
+                }
+                let mut body = vec![0; len];
+                conn.read_exact(&mut body).unwrap();
+                entries.insert(path.clone(), body);
+                b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_vec()
+            } else if let Some(body) = entries.get(&path) {
+                let mut response = format!("HTTP/1.1 200 OK\r\n\
+                                            Content-Length: {}\r\n\
+                                            Connection: close\r\n\r\n",
+                                           body.len()).into_bytes();
+                response.extend(body);
+                response
+            } else {
+                b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_vec()
+            };
+            log.lock().unwrap().push(method);
+            conn.write_all(&response).unwrap();
+            conn.flush().unwrap();
+        }
+    });
+    (format!("http://{}/cache", addr), requests)
+}
+
+#[test]
+fn remote_cache() {
+    publish_bar();
+    let (url, requests) = serve_cache();
+    let config = format!("url = '{}'", url);
+    let p1 = project_using_bar("foo", &config);
+    let p2 = project_using_bar("baz", &config);
+
+    assert_that(p1.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[CACHE] 0 hits, 1 miss"));
+    assert_eq!(*requests.lock().unwrap(), vec!["GET", "PUT"]);
+
+    assert_that(p2.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("[RUNNING] `rustc --crate-name bar [..]")
+                       .with_stderr_contains("[CACHE] 1 hit, 0 misses"));
+    assert_eq!(*requests.lock().unwrap(), vec!["GET", "PUT", "GET"]);
+    assert_that(p2.cargo("run"), execs().with_status(0));
+}
+
+#[test]
+fn local_cache_in_front_of_remote() {
+    publish_bar();
+    let (url, requests) = serve_cache();
+    let cache_dir = paths::root().join("cache");
+    let p1 = project_using_bar("foo", &format!("url = '{}'", url));
+    let p2 = project_using_bar("baz", &format!("url = '{}'\ndir = '{}'",
+                                               url, cache_dir.display()));
+
+    assert_that(p1.cargo("build"), execs().with_status(0));
+
+    // The entry found remotely is kept locally as well
+    assert_that(p2.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[CACHE] 1 hit, 0 misses"));
+    assert_eq!(cache_entries(&cache_dir), 1);
+    assert_eq!(*requests.lock().unwrap(), vec!["GET", "PUT", "GET"]);
+}
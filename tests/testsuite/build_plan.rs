@@ -0,0 +1,124 @@
+use cargotest::support::{project, execs};
+use hamcrest::{assert_that, existing_file, is_not};
+
+#[test]
+fn simple() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build").arg("--build-plan"),
+                execs().with_status(0)
+                       .with_stderr("")
+                       .with_json(r#"
+    {
+        "invocations": [
+            {
+                "package_id": "foo 0.1.0 (path+file://[..])",
+                "target_name": "foo",
+                "target_kind": ["bin"],
+                "kind": "host",
+                "deps": [],
+                "requires_build_script_output": false,
+                "outputs": ["[..][/]target[/]debug[/]deps[/]foo-[..][EXE]"],
+                "program": "rustc",
+                "args": "{...}",
+                "env": "{...}",
+                "cwd": "[..]"
+            }
+        ]
+    }
+    "#));
+    assert_that(&p.bin("foo"), is_not(existing_file()));
+}
+
+#[test]
+fn dependencies_and_build_script() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("build.rs", "fn main() {}")
+        .file("src/main.rs", "extern crate bar; fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("--build-plan"),
+                execs().with_status(0)
+                       .with_json(r#"
+    {
+        "invocations": [
+            {
+                "package_id": "bar 0.1.0 (path+file://[..])",
+                "target_name": "bar",
+                "target_kind": ["lib"],
+                "kind": "host",
+                "deps": [],
+                "requires_build_script_output": false,
+                "outputs": ["[..][/]target[/]debug[/]deps[/]libbar-[..].rlib"],
+                "program": "rustc",
+                "args": "{...}",
+                "env": "{...}",
+                "cwd": "[..]"
+            },
+            {
+                "package_id": "foo 0.1.0 (path+file://[..])",
+                "target_name": "build-script-build",
+                "target_kind": ["custom-build"],
+                "kind": "host",
+                "deps": [],
+                "requires_build_script_output": false,
+                "outputs": ["[..][/]target[/]debug[/]build[/]foo-[..][/]build_script_build-[..][EXE]"],
+                "program": "rustc",
+                "args": "{...}",
+                "env": "{...}",
+                "cwd": "[..]"
+            },
+            {
+                "package_id": "foo 0.1.0 (path+file://[..])",
+                "target_name": "build-script-build",
+                "target_kind": ["custom-build"],
+                "kind": "host",
+                "deps": [1],
+                "requires_build_script_output": false,
+                "outputs": ["[..][/]target[/]debug[/]build[/]foo-[..][/]out"],
+                "program": "[..]build-script-build[EXE]",
+                "args": [],
+                "env": "{...}",
+                "cwd": "[..]"
+            },
+            {
+                "package_id": "foo 0.1.0 (path+file://[..])",
+                "target_name": "foo",
+                "target_kind": ["bin"],
+                "kind": "host",
+                "deps": [0, 2],
+                "requires_build_script_output": true,
+                "outputs": ["[..][/]target[/]debug[/]deps[/]foo-[..][EXE]"],
+                "program": "rustc",
+                "args": "{...}",
+                "env": "{...}",
+                "cwd": "[..]"
+            }
+        ]
+    }
+    "#));
+}
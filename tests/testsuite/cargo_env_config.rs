@@ -0,0 +1,193 @@
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn env_basic() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/main.rs", r#"
+            use std::env;
+            fn main() {
+                println!("compile-time:{}", env!("ENV_TEST_1233"));
+                println!("run-time:{}", env::var("ENV_TEST_1233").unwrap());
+            }
+        "#)
+        .file(".cargo/config", r#"
+            [env]
+            ENV_TEST_1233 = "Hello"
+        "#)
+        .build();
+
+    assert_that(p.cargo("run"),
+                execs().with_status(0)
+                       .with_stdout("\
+compile-time:Hello
+run-time:Hello
+"));
+}
+
+#[test]
+fn env_invalid() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [env]
+            ENV_TEST_BOOL = false
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] invalid configuration for key `env.ENV_TEST_BOOL`
+expected a string or table, but found a boolean for `env.ENV_TEST_BOOL` in [..]"));
+}
+
+#[test]
+fn env_force() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/main.rs", r#"
+            fn main() {
+                println!("ENV_TEST_FORCED:{}", env!("ENV_TEST_FORCED"));
+                println!("ENV_TEST_UNFORCED:{}", env!("ENV_TEST_UNFORCED"));
+            }
+        "#)
+        .file(".cargo/config", r#"
+            [env]
+            ENV_TEST_FORCED = { value = "from-config", force = true }
+            ENV_TEST_UNFORCED = "from-config"
+        "#)
+        .build();
+
+    assert_that(p.cargo("run")
+                 .env("ENV_TEST_FORCED", "from-env")
+                 .env("ENV_TEST_UNFORCED", "from-env"),
+                execs().with_status(0)
+                       .with_stdout("\
+ENV_TEST_FORCED:from-config
+ENV_TEST_UNFORCED:from-env
+"));
+}
+
+#[test]
+fn env_relative() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/main.rs", r#"
+            use std::path::Path;
+            fn main() {
+                let rel = Path::new(env!("ENV_TEST_REL"));
+                let abs = Path::new(env!("ENV_TEST_ABS"));
+                assert!(rel.is_absolute());
+                assert!(rel.ends_with("foo/bar"));
+                assert!(!abs.is_absolute());
+            }
+        "#)
+        .file(".cargo/config", r#"
+            [env]
+            ENV_TEST_REL = { value = "foo/bar", relative = true }
+            ENV_TEST_ABS = { value = "foo/bar", relative = false }
+        "#)
+        .build();
+
+    assert_that(p.cargo("run"), execs().with_status(0));
+}
+
+#[test]
+fn env_change_rebuilds() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", r#"
+            pub const VALUE: &str = env!("ENV_TEST_VALUE");
+        "#)
+        .file(".cargo/config", r#"
+            [env]
+            ENV_TEST_VALUE = "one"
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("[FRESH] foo v0.1.0 ([..])"));
+
+    p.change_file(".cargo/config", r#"
+        [env]
+        ENV_TEST_VALUE = "two"
+    "#);
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr("\
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+"));
+}
+
+#[test]
+fn env_build_script() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            use std::env;
+            fn main() {
+                println!("cargo:rerun-if-env-changed=ENV_TEST_BUILD");
+                println!("cargo:warning={}", env::var("ENV_TEST_BUILD").unwrap());
+            }
+        "#)
+        .file(".cargo/config", r#"
+            [env]
+            ENV_TEST_BUILD = "one"
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[COMPILING] foo v0.1.0 ([..])")
+                       .with_stderr_contains("warning: one"));
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("[FRESH] foo v0.1.0 ([..])"));
+
+    p.change_file(".cargo/config", r#"
+        [env]
+        ENV_TEST_BUILD = "two"
+    "#);
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[COMPILING] foo v0.1.0 ([..])")
+                       .with_stderr_contains("warning: two"));
+}
@@ -225,8 +225,12 @@ impl Project {
     }
 
     pub fn read_lockfile(&self) -> String {
+        self.read_file("Cargo.lock")
+    }
+
+    pub fn read_file(&self, path: &str) -> String {
         let mut buffer = String::new();
-        fs::File::open(self.root().join("Cargo.lock")).unwrap()
+        fs::File::open(self.root().join(path)).unwrap()
             .read_to_string(&mut buffer).unwrap();
         buffer
     }
@@ -870,6 +874,12 @@ fn substitute_macros(input: &str) -> String {
         ("[REPLACING]",   "   Replacing"),
         ("[UNPACKING]",   "   Unpacking"),
         ("[SUMMARY]",     "     Summary"),
+        ("[VENDORING]",   "   Vendoring"),
+        ("[VENDORED]",    "    Vendored"),
+        ("[FIXED]",       "       Fixed"),
+        ("[MIGRATED]",    "    Migrated"),
+        ("[CACHE]",       "       Cache"),
+        ("[MIGRATING]",   "   Migrating"),
         ("[EXE]", if cfg!(windows) {".exe"} else {""}),
         ("[/]", if cfg!(windows) {"\\"} else {"/"}),
     ];
@@ -1,9 +1,14 @@
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::prelude::*;
+use std::net::{SocketAddr, TcpListener};
 use std::path::{PathBuf, Path};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use cargo::util::Sha256;
+use bufstream::BufStream;
 use flate2::Compression;
 use flate2::write::GzEncoder;
 use git2;
@@ -28,6 +33,190 @@ pub fn alt_dl_url() -> String {
 pub fn alt_api_path() -> PathBuf { paths::root().join("alt_api") }
 pub fn alt_api_url() -> Url { Url::from_file_path(&*alt_api_path()).ok().unwrap() }
 
+/// A static file server exposing `registry_path()` over HTTP, to test
+/// registries using the sparse index protocol.
+pub struct HttpServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl HttpServer {
+    /// The URL to configure the registry with, including `sparse+`
+    pub fn url(&self) -> String {
+        format!("sparse+http://{}/", self.addr)
+    }
+
+    /// Every request served so far, as `<status> <path>`
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Starts serving the registry index over HTTP.
+///
+/// Files are served with an `ETag` derived from their contents, and a
+/// matching `If-None-Match` header is answered with `304 Not Modified`.
+pub fn serve_index() -> HttpServer {
+    init();
+    let server = t!(TcpListener::bind("127.0.0.1:0"));
+    let addr = t!(server.local_addr());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+    // `paths::root` is different for each thread, so figure it out up front
+    let root = registry_path();
+    thread::spawn(move || {
+        for conn in server.incoming() {
+            let mut conn = BufStream::new(t!(conn));
+            let mut path = String::new();
+            let mut if_none_match = None;
+            loop {
+                let mut line = String::new();
+                t!(conn.read_line(&mut line));
+                let line = line.trim();
+                if line.is_empty() {
+                    break
+                }
+                if line.starts_with("GET ") {
+                    path = line.split(' ').nth(1).unwrap().to_string();
+                } else if line.to_lowercase().starts_with("if-none-match:") {
+                    if_none_match = Some(line["if-none-match:".len()..].trim().to_string());
+                }
+            }
+
+            let file = root.join(&path[1..]);
+            let (status, response) = match File::open(&file) {
+                Ok(mut f) => {
+                    let mut body = Vec::new();
+                    t!(f.read_to_end(&mut body));
+                    let mut cksum = Sha256::new();
+                    cksum.update(&body);
+                    let etag = format!("\"{}\"", hex::encode(cksum.finish()));
+                    if if_none_match.as_ref() == Some(&etag) {
+                        (304, format!("HTTP/1.1 304 Not Modified\r\n\
+                                       ETag: {}\r\n\
+                                       Content-Length: 0\r\n\
+                                       Connection: close\r\n\r\n", etag).into_bytes())
+                    } else {
+                        let mut response = format!("HTTP/1.1 200 OK\r\n\
+                                                    ETag: {}\r\n\
+                                                    Content-Length: {}\r\n\
+                                                    Connection: close\r\n\r\n",
+                                                   etag, body.len()).into_bytes();
+                        response.extend(body);
+                        (200, response)
+                    }
+                }
+                Err(_) => {
+                    (404, b"HTTP/1.1 404 Not Found\r\n\
+                            Content-Length: 0\r\n\
+                            Connection: close\r\n\r\n".to_vec())
+                }
+            };
+            log.lock().unwrap().push(format!("{} {}", status, path));
+            t!(conn.write_all(&response));
+            t!(conn.flush());
+        }
+    });
+    HttpServer { addr, requests }
+}
+
+/// A server for the `.crate` files of `dl_path()`, which records how many
+/// downloads were in progress at once.
+pub struct DownloadServer {
+    addr: SocketAddr,
+    state: Arc<(Mutex<InFlight>, Condvar)>,
+}
+
+#[derive(Default)]
+struct InFlight {
+    current: usize,
+    max: usize,
+}
+
+impl DownloadServer {
+    /// The URL to use as `dl` in the registry's `config.json`
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// The most downloads which were in progress at the same time
+    pub fn max_in_flight(&self) -> usize {
+        (self.state.0).lock().unwrap().max
+    }
+}
+
+/// Starts serving crate downloads over HTTP.
+///
+/// Each response is held back until `wait_for` downloads are in progress, or
+/// a few seconds have passed, so downloads which are made at once are seen
+/// to overlap however quickly they're served.
+pub fn serve_downloads(wait_for: usize) -> DownloadServer {
+    init();
+    let server = t!(TcpListener::bind("127.0.0.1:0"));
+    let addr = t!(server.local_addr());
+    let state = Arc::new((Mutex::new(InFlight::default()), Condvar::new()));
+    let shared = state.clone();
+    let root = dl_path();
+    thread::spawn(move || {
+        for conn in server.incoming() {
+            let conn = t!(conn);
+            let (state, root) = (shared.clone(), root.clone());
+            thread::spawn(move || {
+                let mut conn = BufStream::new(conn);
+                let mut path = String::new();
+                loop {
+                    let mut line = String::new();
+                    t!(conn.read_line(&mut line));
+                    let line = line.trim();
+                    if line.is_empty() {
+                        break
+                    }
+                    if line.starts_with("GET ") {
+                        path = line.split(' ').nth(1).unwrap().to_string();
+                    }
+                }
+
+                let mut body = Vec::new();
+                let response = match File::open(root.join(&path[1..])) {
+                    Ok(mut f) => {
+                        t!(f.read_to_end(&mut body));
+                        format!("HTTP/1.1 200 OK\r\n\
+                                 Content-Length: {}\r\n\
+                                 Connection: close\r\n\r\n", body.len())
+                    }
+                    Err(_) => {
+                        "HTTP/1.1 404 Not Found\r\n\
+                         Content-Length: 0\r\n\
+                         Connection: close\r\n\r\n".to_string()
+                    }
+                };
+                t!(conn.write_all(response.as_bytes()));
+                t!(conn.flush());
+
+                // Only the body is held back, as curl won't open another
+                // connection to the host until it has seen a response.
+                let (ref lock, ref cvar) = *state;
+                {
+                    let mut in_flight = lock.lock().unwrap();
+                    in_flight.current += 1;
+                    in_flight.max = in_flight.max.max(in_flight.current);
+                    cvar.notify_all();
+                    let deadline = Instant::now() + Duration::from_secs(5);
+                    while in_flight.max < wait_for && Instant::now() < deadline {
+                        in_flight = cvar.wait_timeout(in_flight, Duration::from_millis(100))
+                                        .unwrap().0;
+                    }
+                }
+
+                t!(conn.write_all(&body));
+                t!(conn.flush());
+                lock.lock().unwrap().current -= 1;
+            });
+        }
+    });
+    DownloadServer { addr, state }
+}
+
 pub struct Package {
     name: String,
     vers: String,
@@ -36,6 +225,7 @@ pub struct Package {
     extra_files: Vec<(String, String)>,
     yanked: bool,
     features: HashMap<String, Vec<String>>,
+    rust_version: Option<String>,
     local: bool,
     alternative: bool,
 }
@@ -47,6 +237,7 @@ struct Dependency {
     target: Option<String>,
     features: Vec<String>,
     registry: Option<String>,
+    optional: bool,
 }
 
 pub fn init() {
@@ -98,6 +289,7 @@ impl Package {
             extra_files: Vec::new(),
             yanked: false,
             features: HashMap::new(),
+            rust_version: None,
             local: false,
             alternative: false,
         }
@@ -124,32 +316,36 @@ impl Package {
     }
 
     pub fn dep(&mut self, name: &str, vers: &str) -> &mut Package {
-        self.full_dep(name, vers, None, "normal", &[], None)
+        self.full_dep(name, vers, None, "normal", &[], None, false)
+    }
+
+    pub fn optional_dep(&mut self, name: &str, vers: &str) -> &mut Package {
+        self.full_dep(name, vers, None, "normal", &[], None, true)
     }
 
     pub fn feature_dep(&mut self,
                        name: &str,
                        vers: &str,
                        features: &[&str]) -> &mut Package {
-        self.full_dep(name, vers, None, "normal", features, None)
+        self.full_dep(name, vers, None, "normal", features, None, false)
     }
 
     pub fn target_dep(&mut self,
                       name: &str,
                       vers: &str,
                       target: &str) -> &mut Package {
-        self.full_dep(name, vers, Some(target), "normal", &[], None)
+        self.full_dep(name, vers, Some(target), "normal", &[], None, false)
     }
 
     pub fn registry_dep(&mut self,
                         name: &str,
                         vers: &str,
                         registry: &str) -> &mut Package {
-        self.full_dep(name, vers, None, "normal", &[], Some(registry))
+        self.full_dep(name, vers, None, "normal", &[], Some(registry), false)
     }
 
     pub fn dev_dep(&mut self, name: &str, vers: &str) -> &mut Package {
-        self.full_dep(name, vers, None, "dev", &[], None)
+        self.full_dep(name, vers, None, "dev", &[], None, false)
     }
 
     fn full_dep(&mut self,
@@ -158,7 +354,8 @@ impl Package {
                 target: Option<&str>,
                 kind: &str,
                 features: &[&str],
-                registry: Option<&str>) -> &mut Package {
+                registry: Option<&str>,
+                optional: bool) -> &mut Package {
         self.deps.push(Dependency {
             name: name.to_string(),
             vers: vers.to_string(),
@@ -166,10 +363,22 @@ impl Package {
             target: target.map(|s| s.to_string()),
             features: features.iter().map(|s| s.to_string()).collect(),
             registry: registry.map(|s| s.to_string()),
+            optional,
         });
         self
     }
 
+    pub fn feature(&mut self, name: &str, deps: &[&str]) -> &mut Package {
+        let deps = deps.iter().map(|s| s.to_string()).collect();
+        self.features.insert(name.to_string(), deps);
+        self
+    }
+
+    pub fn rust_version(&mut self, rust_version: &str) -> &mut Package {
+        self.rust_version = Some(rust_version.to_string());
+        self
+    }
+
     pub fn yanked(&mut self, yanked: bool) -> &mut Package {
         self.yanked = yanked;
         self
@@ -186,7 +395,7 @@ impl Package {
                 "features": dep.features,
                 "default_features": true,
                 "target": dep.target,
-                "optional": false,
+                "optional": dep.optional,
                 "kind": dep.kind,
                 "registry": dep.registry,
             })
@@ -196,14 +405,27 @@ impl Package {
             t!(t!(File::open(&self.archive_dst())).read_to_end(&mut c));
             cksum(&c)
         };
-        let line = json!({
+        // Features using the newer syntax go into `features2`
+        let (features2, features): (HashMap<_, _>, HashMap<_, _>) =
+            self.features.iter().partition(|&(_, list)| {
+                list.iter().any(|f| f.starts_with("dep:") || f.contains("?/"))
+            });
+        let mut line = json!({
             "name": self.name,
             "vers": self.vers,
             "deps": deps,
             "cksum": cksum,
-            "features": self.features,
+            "features": features,
             "yanked": self.yanked,
-        }).to_string();
+        });
+        if !features2.is_empty() {
+            line["features2"] = json!(features2);
+            line["v"] = json!(2);
+        }
+        if let Some(ref rust_version) = self.rust_version {
+            line["rust_version"] = json!(rust_version);
+        }
+        let line = line.to_string();
 
         let file = match self.name.len() {
             1 => format!("1/{}", self.name),
@@ -254,6 +476,9 @@ impl Package {
             version = "{}"
             authors = []
         "#, self.name, self.vers);
+        if let Some(ref rust_version) = self.rust_version {
+            manifest.push_str(&format!("rust-version = \"{}\"\n", rust_version));
+        }
         for dep in self.deps.iter() {
             let target = match dep.target {
                 None => String::new(),
@@ -267,7 +492,15 @@ impl Package {
             manifest.push_str(&format!(r#"
                 [{}{}dependencies.{}]
                 version = "{}"
-            "#, target, kind, dep.name, dep.vers));
+                optional = {}
+            "#, target, kind, dep.name, dep.vers, dep.optional));
+        }
+        if !self.features.is_empty() {
+            manifest.push_str("\n[features]\n");
+            for (name, list) in self.features.iter() {
+                let list = list.iter().map(|f| format!("\"{}\"", f)).collect::<Vec<_>>();
+                manifest.push_str(&format!("{} = [{}]\n", name, list.join(", ")));
+            }
         }
 
         let dst = self.archive_dst();
@@ -0,0 +1,245 @@
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+use cargotest::{ChannelChanger, cargo_process};
+use cargotest::support::{execs, paths, project, publish};
+use hamcrest::{assert_that, existing_file, is_not};
+
+/// Builds a credential process which logs its requests to `requests` and
+/// keeps the token in `token`, both in the directory passed as its argument.
+/// If a file named `fail` exists there, every request fails.
+fn credential_process() -> PathBuf {
+    let p = project("cred")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "cred"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", r##"
+            use std::env;
+            use std::fs::{self, File, OpenOptions};
+            use std::io::{self, Read, Write};
+            use std::path::PathBuf;
+            use std::process;
+
+            fn main() {
+                let dir = PathBuf::from(env::args().nth(1).unwrap());
+                let mut request = String::new();
+                io::stdin().read_to_string(&mut request).unwrap();
+                OpenOptions::new().create(true).append(true)
+                    .open(dir.join("requests")).unwrap()
+                    .write_all(request.as_bytes()).unwrap();
+
+                if dir.join("fail").exists() {
+                    println!(r#"{{"error":"the keyring is locked"}}"#);
+                    return
+                }
+
+                let token = dir.join("token");
+                if request.contains(r#""action":"get""#) {
+                    match File::open(&token) {
+                        Ok(mut f) => {
+                            let mut s = String::new();
+                            f.read_to_string(&mut s).unwrap();
+                            println!(r#"{{"token":"{}"}}"#, s);
+                        }
+                        Err(_) => println!("{{}}"),
+                    }
+                } else if request.contains(r#""action":"store""#) {
+                    let start = request.find(r#""token":""#).unwrap() + 9;
+                    let end = start + request[start..].find('"').unwrap();
+                    File::create(&token).unwrap()
+                        .write_all(request[start..end].as_bytes()).unwrap();
+                } else if request.contains(r#""action":"erase""#) {
+                    fs::remove_file(&token).unwrap();
+                } else {
+                    process::exit(1);
+                }
+            }
+        "##)
+        .build();
+    assert_that(p.cargo("build"), execs().with_status(0));
+    p.bin("cred")
+}
+
+fn state_dir() -> PathBuf {
+    let dir = paths::root().join("cred-state");
+    t!(fs::create_dir_all(&dir));
+    dir
+}
+
+fn read(path: PathBuf) -> String {
+    let mut contents = String::new();
+    t!(t!(File::open(&path)).read_to_string(&mut contents));
+    contents
+}
+
+fn configure(config: &str) {
+    let path = paths::root().join(".cargo/config");
+    t!(fs::create_dir_all(path.parent().unwrap()));
+    t!(t!(File::create(&path)).write_all(config.as_bytes()));
+}
+
+#[test]
+fn login_and_logout() {
+    let cred = credential_process();
+    let state = state_dir();
+    configure(&format!(r#"
+        [registry]
+        credential-process = "{} {}"
+    "#, cred.display(), state.display()));
+
+    assert_that(cargo_process().arg("login")
+                .arg("--host").arg("https://example.com").arg("secret"),
+                execs().with_status(0));
+    assert_eq!(read(state.join("token")), "secret");
+    assert_eq!(read(state.join("requests")),
+               "{\"v\":1,\"action\":\"store\",\"registry\":null,\
+                \"index-url\":\"https://github.com/rust-lang/crates.io-index\",\
+                \"token\":\"secret\"}\n");
+
+    // The token never ends up in plaintext
+    assert_that(&paths::home().join(".cargo/credentials"), is_not(existing_file()));
+
+    assert_that(cargo_process().arg("logout"), execs().with_status(0));
+    assert_that(&state.join("token"), is_not(existing_file()));
+    assert!(read(state.join("requests")).ends_with(
+        "{\"v\":1,\"action\":\"erase\",\"registry\":null,\
+         \"index-url\":\"https://github.com/rust-lang/crates.io-index\"}\n"));
+}
+
+#[test]
+fn alternative_registry() {
+    let cred = credential_process();
+    let state = state_dir();
+    configure(&format!(r#"
+        [registries.alternative]
+        index = "https://example.com/index"
+        credential-process = ["{}", "{}"]
+    "#, cred.display(), state.display()));
+
+    assert_that(cargo_process().arg("login").masquerade_as_nightly_cargo()
+                .arg("--registry").arg("alternative").arg("secret")
+                .arg("-Zunstable-options"),
+                execs().with_status(0));
+    assert_eq!(read(state.join("requests")),
+               "{\"v\":1,\"action\":\"store\",\"registry\":\"alternative\",\
+                \"index-url\":\"https://example.com/index\",\
+                \"token\":\"secret\"}\n");
+}
+
+#[test]
+fn publish_with_token_from_process() {
+    publish::setup();
+    let cred = credential_process();
+    let state = state_dir();
+    configure(&format!(r#"
+        [registry]
+        credential-process = "{} {}"
+    "#, cred.display(), state.display()));
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("publish").arg("--no-verify")
+                 .arg("--index").arg(publish::registry().to_string()),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] no upload token found, please run `cargo login`"));
+
+    t!(t!(File::create(state.join("token"))).write_all(b"secret"));
+    assert_that(p.cargo("publish").arg("--no-verify")
+                 .arg("--index").arg(publish::registry().to_string()),
+                execs().with_status(0)
+                       .with_stderr_contains("[UPLOADING] foo v0.0.1 [..]"));
+    assert_that(&publish::upload_path().join("api/v1/crates/new"), existing_file());
+
+    let requests = read(state.join("requests"));
+    assert_eq!(requests.lines().count(), 2);
+    assert!(requests.lines().all(|l| {
+        l == format!("{{\"v\":1,\"action\":\"get\",\"registry\":null,\
+                      \"index-url\":\"{}\"}}", publish::registry())
+    }), "{}", requests);
+}
+
+#[test]
+fn owner_and_yank_ask_for_token() {
+    publish::setup();
+    let cred = credential_process();
+    let state = state_dir();
+    configure(&format!(r#"
+        [registry]
+        credential-process = "{} {}"
+    "#, cred.display(), state.display()));
+    t!(t!(File::create(state.join("fail"))).write_all(b""));
+
+    assert_that(cargo_process().arg("yank").arg("foo").arg("--vers").arg("0.0.1")
+                 .arg("--index").arg(publish::registry().to_string()),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] credential process `[..]cred[..]` failed to get the token: \
+the keyring is locked"));
+
+    assert_that(cargo_process().arg("owner").arg("foo").arg("--list")
+                 .arg("--index").arg(publish::registry().to_string()),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] credential process `[..]cred[..]` failed to get the token: \
+the keyring is locked"));
+
+    assert_eq!(read(state.join("requests")).lines().count(), 2);
+}
+
+#[test]
+fn builtin_provider() {
+    let cred = credential_process();
+    let state = state_dir();
+    configure(&format!(r#"
+        [registry]
+        credential-process = "{} {}"
+
+        [registries.alternative]
+        index = "https://example.com/index"
+        credential-process = "cargo:token"
+    "#, cred.display(), state.display()));
+
+    assert_that(cargo_process().arg("login").masquerade_as_nightly_cargo()
+                .arg("--registry").arg("alternative").arg("secret")
+                .arg("-Zunstable-options"),
+                execs().with_status(0));
+    assert_that(&state.join("requests"), is_not(existing_file()));
+    let credentials = paths::home().join(".cargo/credentials");
+    assert!(read(credentials.clone()).contains("secret"));
+
+    assert_that(cargo_process().arg("logout").masquerade_as_nightly_cargo()
+                .arg("--registry").arg("alternative")
+                .arg("-Zunstable-options"),
+                execs().with_status(0));
+    assert!(!read(credentials).contains("secret"));
+}
+
+#[test]
+fn process_not_found() {
+    let state = state_dir();
+    configure(&format!(r#"
+        [registry]
+        credential-process = "{} {}"
+    "#, paths::root().join("does-not-exist").display(), state.display()));
+
+    assert_that(cargo_process().arg("login")
+                .arg("--host").arg("https://example.com").arg("secret"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] failed to run credential process `[..]does-not-exist [..]`"));
+}
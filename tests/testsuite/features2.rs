@@ -0,0 +1,186 @@
+use cargotest::support::{project, execs, ProjectBuilder};
+use hamcrest::assert_that;
+
+/// A project where `foo` uses `common` through the dependency section in
+/// `deps`, and its binary asserts whether `common/f` is enabled.
+fn resolver_project(resolver: &str, deps: &str, enabled: bool) -> ProjectBuilder {
+    project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+            {}
+
+            [dependencies]
+            common = {{ path = "common" }}
+
+            {}
+        "#, resolver, deps))
+        .file("src/main.rs", &format!(r#"
+            extern crate common;
+            fn main() {{
+                assert_eq!(common::f(), {});
+            }}
+        "#, enabled))
+        .file("common/Cargo.toml", r#"
+            [package]
+            name = "common"
+            version = "0.1.0"
+            authors = []
+
+            [features]
+            f = []
+        "#)
+        .file("common/src/lib.rs", r#"
+            pub fn f() -> bool {
+                cfg!(feature = "f")
+            }
+        "#)
+}
+
+#[test]
+fn build_dep_features_unified_by_default() {
+    let p = resolver_project("", r#"
+            [build-dependencies]
+            common = { path = "common", features = ["f"] }
+    "#, true)
+        .file("build.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("run"), execs().with_status(0));
+}
+
+#[test]
+fn build_dep_features_not_on_target() {
+    let p = resolver_project(r#"resolver = "2""#, r#"
+            [build-dependencies]
+            common = { path = "common", features = ["f"] }
+    "#, false)
+        .file("build.rs", r#"
+            extern crate common;
+            fn main() {
+                assert!(common::f());
+            }
+        "#)
+        .build();
+
+    assert_that(p.cargo("run"), execs().with_status(0));
+}
+
+#[test]
+fn build_dep_with_same_features_built_once() {
+    let p = resolver_project(r#"resolver = "2""#, r#"
+            [build-dependencies]
+            common = { path = "common" }
+    "#, false)
+        .file("build.rs", r#"
+            extern crate common;
+            fn main() {
+                assert!(!common::f());
+            }
+        "#)
+        .build();
+
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr("\
+[COMPILING] common v0.1.0 ([..])
+[RUNNING] `rustc --crate-name common [..]`
+[COMPILING] foo v0.1.0 ([..])
+[RUNNING] `rustc --crate-name build_script_build [..]`
+[RUNNING] `[..]build-script-build`
+[RUNNING] `rustc --crate-name foo [..]`
+[FINISHED] [..]
+"));
+}
+
+#[test]
+fn dev_dep_features_only_for_tests() {
+    let p = resolver_project(r#"resolver = "2""#, r#"
+            [dev-dependencies]
+            common = { path = "common", features = ["f"] }
+    "#, false)
+        .file("tests/t.rs", r#"
+            extern crate common;
+            #[test]
+            fn t() {
+                assert!(common::f());
+            }
+        "#)
+        .build();
+
+    assert_that(p.cargo("run"), execs().with_status(0));
+    assert_that(p.cargo("test").arg("--test").arg("t"),
+                execs().with_status(0));
+}
+
+#[test]
+fn inactive_platform_features() {
+    let p = resolver_project(r#"resolver = "2""#, r#"
+            [target.'cfg(not_a_real_cfg)'.dependencies]
+            common = { path = "common", features = ["f"] }
+    "#, false)
+        .build();
+
+    assert_that(p.cargo("run"), execs().with_status(0));
+}
+
+#[test]
+fn resolver_in_virtual_workspace() {
+    let p = project("ws")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["foo"]
+            resolver = "2"
+        "#)
+        .file("foo/Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [target.'cfg(not_a_real_cfg)'.dependencies]
+            common = { path = "../common", features = ["f"] }
+
+            [dependencies]
+            common = { path = "../common" }
+        "#)
+        .file("foo/src/main.rs", r#"
+            extern crate common;
+            fn main() {
+                assert!(!common::f());
+            }
+        "#)
+        .file("common/Cargo.toml", r#"
+            [package]
+            name = "common"
+            version = "0.1.0"
+            authors = []
+
+            [features]
+            f = []
+        "#)
+        .file("common/src/lib.rs", r#"
+            pub fn f() -> bool {
+                cfg!(feature = "f")
+            }
+        "#)
+        .build();
+
+    assert_that(p.cargo("run").arg("-p").arg("foo"), execs().with_status(0));
+}
+
+#[test]
+fn invalid_resolver() {
+    let p = resolver_project(r#"resolver = "3""#, "", false).build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+error: failed to parse manifest at `[..]`
+
+Caused by:
+  the `resolver` key must be one of: `1`, `2`
+"));
+}
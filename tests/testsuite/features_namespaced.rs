@@ -0,0 +1,218 @@
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn dep_feature_enables_optional_dep() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar", optional = true }
+
+            [features]
+            fancy = ["dep:bar"]
+        "#)
+        .file("src/lib.rs", r#"
+            #[cfg(feature = "fancy")]
+            extern crate bar;
+            #[cfg(feature = "bar")]
+            compile_error!("bar is not a feature");
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr("\
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+"));
+    assert_that(p.cargo("build").arg("--features").arg("fancy"),
+                execs().with_status(0)
+                       .with_stderr("\
+[COMPILING] bar v0.1.0 ([..])
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+"));
+}
+
+#[test]
+fn no_implicit_feature() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar", optional = true }
+
+            [features]
+            fancy = ["dep:bar"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("--features").arg("bar"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] Package `foo v0.1.0 ([..])` does not have these features: `bar`
+"));
+}
+
+#[test]
+fn feature_named_like_dep() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar", optional = true }
+
+            [features]
+            bar = ["dep:bar"]
+        "#)
+        .file("src/lib.rs", r#"
+            #[cfg(feature = "bar")]
+            extern crate bar;
+            #[cfg(not(feature = "bar"))]
+            compile_error!("bar is not enabled");
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("--features").arg("bar"),
+                execs().with_status(0)
+                       .with_stderr("\
+[COMPILING] bar v0.1.0 ([..])
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+"));
+}
+
+#[test]
+fn dep_feature_not_optional() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+
+            [features]
+            fancy = ["dep:bar"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  Feature `fancy` includes `dep:bar`, but `bar` is not an optional dependency
+"));
+}
+
+#[test]
+fn plain_reference_to_namespaced_dep() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar", optional = true }
+
+            [features]
+            a = ["dep:bar"]
+            b = ["bar"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  Feature `b` includes `bar` which is neither a dependency nor another feature.
+The optional dependency `bar` is enabled with `dep:bar`
+"));
+}
+
+#[test]
+fn registry_features2() {
+    Package::new("baz", "0.1.0").publish();
+    Package::new("bar", "0.1.0")
+        .optional_dep("baz", "0.1.0")
+        .feature("fancy", &["dep:baz"])
+        .publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = { version = "0.1.0", features = ["fancy"] }
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[COMPILING] baz v0.1.0")
+                       .with_stderr_contains("[COMPILING] bar v0.1.0"));
+}
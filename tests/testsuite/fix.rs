@@ -0,0 +1,170 @@
+use std::fs::File;
+use std::io::prelude::*;
+
+use cargotest::{is_nightly, ChannelChanger};
+use cargotest::support::{execs, git, project};
+use hamcrest::assert_that;
+
+#[test]
+fn fix_unused_mut() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", r#"
+            pub fn foo() -> u32 {
+                let mut x = 1;
+                x
+            }
+        "#)
+        .build();
+
+    assert_that(p.cargo("fix").arg("--allow-dirty"),
+                execs().with_status(0)
+                       .with_stderr_contains("[FIXED] src[/]lib.rs (1 fix)"));
+
+    let mut contents = String::new();
+    File::open(p.root().join("src/lib.rs")).unwrap()
+        .read_to_string(&mut contents).unwrap();
+    assert!(contents.contains("let x = 1;"), "{}", contents);
+
+    // Nothing is left to fix the second time around
+    assert_that(p.cargo("fix").arg("--allow-dirty"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("[FIXED] [..]"));
+}
+
+#[test]
+fn fix_all_targets_by_default() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file("tests/foo.rs", r#"
+            #[test]
+            fn foo() {
+                let mut x = 1;
+                assert_eq!(x, 1);
+            }
+        "#)
+        .build();
+
+    assert_that(p.cargo("fix").arg("--allow-dirty"),
+                execs().with_status(0)
+                       .with_stderr_contains("[FIXED] tests[/]foo.rs (1 fix)"));
+}
+
+#[test]
+fn errors_are_not_fixed() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", r#"
+            pub fn foo() {
+                let mut x = 1;
+                missing();
+            }
+        "#)
+        .build();
+
+    assert_that(p.cargo("fix").arg("--allow-dirty"),
+                execs().with_status(101)
+                       .with_stderr_contains("[..]missing[..]"));
+
+    let mut contents = String::new();
+    File::open(p.root().join("src/lib.rs")).unwrap()
+        .read_to_string(&mut contents).unwrap();
+    assert!(contents.contains("let mut x = 1;"), "{}", contents);
+}
+
+#[test]
+fn dirty_working_directory() {
+    let p = git::new("foo", |p| {
+        p.file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+    }).unwrap();
+    File::create(p.root().join("src/lib.rs")).unwrap()
+        .write_all(b"pub fn foo() { let mut x = 1; drop(x); }").unwrap();
+
+    assert_that(p.cargo("fix"),
+                execs().with_status(101)
+                       .with_stderr("\
+error: the working directory of this workspace has uncommitted changes, and \
+`cargo fix` can potentially perform destructive changes; if you'd like to \
+suppress this error pass `--allow-dirty`, or commit the changes to these files:
+
+  * src[/]lib.rs
+
+"));
+
+    assert_that(p.cargo("fix").arg("--allow-dirty"),
+                execs().with_status(0)
+                       .with_stderr_contains("[FIXED] src[/]lib.rs (1 fix)"));
+}
+
+#[test]
+fn clean_working_directory() {
+    let p = git::new("foo", |p| {
+        p.file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file(".gitignore", "/target\n/Cargo.lock\n")
+        .file("src/lib.rs", "pub fn foo() { let mut x = 1; drop(x); }")
+    }).unwrap();
+
+    assert_that(p.cargo("fix"),
+                execs().with_status(0)
+                       .with_stderr_contains("[FIXED] src[/]lib.rs (1 fix)"));
+}
+
+#[test]
+fn epoch_updates_manifest() {
+    if !is_nightly() {
+        return
+    }
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("fix").arg("--epoch").arg("--allow-dirty")
+                 .masquerade_as_nightly_cargo(),
+                execs().with_status(0)
+                       .with_stderr_contains("[MIGRATED] `foo` to the 2018 epoch"));
+
+    let mut contents = String::new();
+    File::open(p.root().join("Cargo.toml")).unwrap()
+        .read_to_string(&mut contents).unwrap();
+    assert!(contents.starts_with("cargo-features = [\"epoch\"]\n"), "{}", contents);
+    assert!(contents.contains("rust = \"2018\"\n"), "{}", contents);
+
+    assert_that(p.cargo("fix").arg("--epoch").arg("--allow-dirty")
+                 .masquerade_as_nightly_cargo(),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[WARNING] `foo` is already on the latest epoch (2018)"));
+}
@@ -11,7 +11,6 @@ use cargo::util::process;
 use cargotest::sleep_ms;
 use cargotest::support::paths::{self, CargoPathExt};
 use cargotest::support::{git, project, execs, main_file, path2url};
-use cargotest::ChannelChanger;
 use hamcrest::{assert_that,existing_file};
 
 #[test]
@@ -90,7 +89,7 @@ fn cargo_compile_forbird_git_httpsrepo_offline() {
         .build();
 
 
-    assert_that(p.cargo("build").masquerade_as_nightly_cargo().arg("-Zoffline"),
+    assert_that(p.cargo("build").arg("--offline"),
                 execs().with_status(101).
                     with_stderr("\
 error: failed to load source for a dependency on `dep1`
@@ -99,7 +98,7 @@ Caused by:
   Unable to update https://github.com/some_user/dep1.git
 
 Caused by:
-  can't checkout from 'https://github.com/some_user/dep1.git': you are in the offline mode (-Z offline)"));
+  can't checkout from 'https://github.com/some_user/dep1.git': you are in the offline mode (--offline)"));
 }
 
 
@@ -175,7 +174,7 @@ fn cargo_compile_offline_with_cached_git_dep() {
     let root = project.root();
     let git_root = git_project.root();
 
-    assert_that(project.cargo("build").masquerade_as_nightly_cargo().arg("-Zoffline"),
+    assert_that(project.cargo("build").arg("--offline"),
                 execs().with_stderr(format!("\
 [COMPILING] dep1 v0.5.0 ({}#[..])
 [COMPILING] foo v0.5.0 ({})
@@ -200,8 +199,7 @@ fn cargo_compile_offline_with_cached_git_dep() {
             rev = "{}"
     "#, git_project.url(), rev1).as_bytes()).unwrap() );
 
-    let _out = project.cargo("build").masquerade_as_nightly_cargo()
-        .arg("-Zoffline").exec_with_output();
+    let _out = project.cargo("build").arg("--offline").exec_with_output();
     assert_that(process(&project.bin("foo")),
                 execs().with_stdout("hello from cached git repo rev1\n"));
 }
@@ -21,18 +21,22 @@ extern crate winapi;
 mod cargotest;
 mod hamcrest;
 
+mod add;
 mod alt_registry;
 mod bad_config;
 mod bad_manifest_path;
 mod bench;
 mod build_auth;
+mod build_cache;
 mod build_lib;
 mod build;
+mod build_plan;
 mod build_script_env;
 mod build_script;
 mod cargo_alias_config;
 mod cargo_features;
 mod cargo_command;
+mod cargo_env_config;
 mod cfg;
 mod check;
 mod clean;
@@ -40,13 +44,17 @@ mod concurrent;
 mod config;
 mod corrupt_git;
 mod cross_compile;
+mod credential_process;
 mod cross_publish;
 mod death;
 mod dep_info;
 mod directory;
 mod doc;
 mod features;
+mod features2;
+mod features_namespaced;
 mod fetch;
+mod fix;
 mod freshness;
 mod generate_lockfile;
 mod git;
@@ -59,6 +67,8 @@ mod login;
 mod metadata;
 mod net_config;
 mod new;
+mod offline;
+mod outdated;
 mod overrides;
 mod package;
 mod patch;
@@ -72,16 +82,25 @@ mod registry;
 mod rename_deps;
 mod required_features;
 mod resolve;
+mod rm;
 mod run;
 mod rustc;
+mod rust_version;
 mod rustdocflags;
 mod rustdoc;
 mod rustflags;
 mod search;
 mod small_fd_limits;
+mod sparse_registry;
 mod test;
+mod test_report;
+mod timings;
 mod tool_paths;
+mod tree;
+mod vendor;
 mod verify_project;
 mod version;
 mod warn_on_failure;
+mod weak_dep_features;
+mod workspace_inheritance;
 mod workspaces;
@@ -1,3 +1,6 @@
+use std::fs::File;
+use std::io::prelude::*;
+
 use cargotest::support::git;
 use cargotest::support::registry::Package;
 use cargotest::support::{execs, project, lines_match};
@@ -448,3 +451,264 @@ fn locked_correct_error() {
 error: the lock file needs to be updated but --locked was passed to prevent this
 "));
 }
+
+fn assert_lockfile_matches(actual: &str, expected: &str) {
+    for (l, r) in expected.lines().zip(actual.lines()) {
+        assert!(lines_match(l, r), "Lines differ:\n{}\n\n{}", l, r);
+    }
+    assert_eq!(actual.lines().count(), expected.lines().count(),
+               "Lock files differ:\n{}", actual);
+}
+
+#[test]
+fn v1_lockfile_format_is_kept() {
+    Package::new("foo", "0.1.0").publish();
+    Package::new("baz", "0.1.0").publish();
+
+    let lockfile = r#"[[package]]
+name = "bar"
+version = "0.0.1"
+dependencies = [
+ "foo 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)",
+]
+
+[[package]]
+name = "foo"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[metadata]
+"checksum foo 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)" = "[..]"
+"#;
+
+    let p = project("bar")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            foo = "0.1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+    assert_lockfile_matches(&p.read_lockfile(), lockfile);
+
+    // Adding a dependency writes the lock file in the same format
+    File::create(p.root().join("Cargo.toml")).unwrap().write_all(br#"
+        [package]
+        name = "bar"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        foo = "0.1.0"
+        baz = "0.1.0"
+    "#).unwrap();
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_lockfile_matches(&p.read_lockfile(), r#"[[package]]
+name = "bar"
+version = "0.0.1"
+dependencies = [
+ "baz 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)",
+ "foo 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)",
+]
+
+[[package]]
+name = "baz"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "foo"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[metadata]
+"checksum baz 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)" = "[..]"
+"checksum foo 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)" = "[..]"
+"#);
+}
+
+#[test]
+fn dependency_free_lockfile_format_is_kept() {
+    Package::new("foo", "0.1.0").publish();
+
+    // Without dependencies the formats only differ in version 1 ending with
+    // a blank line
+    let v1 = project("v1")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "v1"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            foo = "0.1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .file("Cargo.lock", r#"[[package]]
+name = "v1"
+version = "0.0.1"
+
+"#)
+        .build();
+    assert_that(v1.cargo("build"), execs().with_status(0));
+    assert_lockfile_matches(&v1.read_lockfile(), r#"[[package]]
+name = "foo"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "v1"
+version = "0.0.1"
+dependencies = [
+ "foo 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)",
+]
+
+[metadata]
+"checksum foo 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)" = "[..]"
+"#);
+
+    let v2 = project("v2")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "v2"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            foo = "0.1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .file("Cargo.lock", r#"[[package]]
+name = "v2"
+version = "0.0.1"
+"#)
+        .build();
+    assert_that(v2.cargo("build"), execs().with_status(0));
+    assert_lockfile_matches(&v2.read_lockfile(), r#"[[package]]
+name = "foo"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "[..]"
+
+[[package]]
+name = "v2"
+version = "0.0.1"
+dependencies = [
+ "foo",
+]
+"#);
+}
+
+#[test]
+fn v2_names_versions_only_when_needed() {
+    Package::new("foo", "0.1.0").publish();
+    Package::new("foo", "0.2.0").publish();
+    Package::new("baz", "0.1.0").dep("foo", "0.1.0").publish();
+
+    let p = project("bar")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            foo = "0.2.0"
+            baz = "0.1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("update").arg("--lockfile-version").arg("2"),
+                execs().with_status(0));
+    let lockfile = p.read_lockfile();
+    assert_lockfile_matches(&lockfile, r#"[[package]]
+name = "bar"
+version = "0.0.1"
+dependencies = [
+ "baz",
+ "foo 0.2.0",
+]
+
+[[package]]
+name = "baz"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "[..]"
+dependencies = [
+ "foo 0.1.0",
+]
+
+[[package]]
+name = "foo"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "[..]"
+
+[[package]]
+name = "foo"
+version = "0.2.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "[..]"
+"#);
+
+    // Reading the lock file back doesn't change anything
+    assert_that(p.cargo("build").arg("--locked"), execs().with_status(0));
+    assert_eq!(p.read_lockfile(), lockfile);
+}
+
+#[test]
+fn migrate_lockfile_version() {
+    Package::new("foo", "0.1.0").publish();
+
+    let p = project("bar")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            foo = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+    let v1 = p.read_lockfile();
+    assert!(v1.contains("[metadata]"), "{}", v1);
+    assert!(v1.contains("\"foo 0.1.0 (registry+"), "{}", v1);
+
+    // Migrating doesn't update any dependency
+    Package::new("foo", "0.1.1").publish();
+
+    assert_that(p.cargo("update").arg("--lockfile-version").arg("2"),
+                execs().with_status(0)
+                       .with_stderr("[MIGRATING] Cargo.lock to version 2"));
+    let v2 = p.read_lockfile();
+    assert!(v2.contains("checksum = "), "{}", v2);
+    assert!(!v2.contains("[metadata]"), "{}", v2);
+    assert!(v2.contains(" \"foo\",\n"), "{}", v2);
+
+    // Later builds keep the version 2 format
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_eq!(p.read_lockfile(), v2);
+
+    assert_that(p.cargo("update").arg("--lockfile-version").arg("1"),
+                execs().with_status(0)
+                       .with_stderr("[MIGRATING] Cargo.lock to version 1"));
+    assert_eq!(p.read_lockfile(), v1);
+
+    assert_that(p.cargo("update").arg("--lockfile-version").arg("1"),
+                execs().with_status(0).with_stderr(""));
+
+    assert_that(p.cargo("update").arg("--lockfile-version").arg("3"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] invalid lock file version `3`, expected `1` or `2`"));
+}
@@ -0,0 +1,180 @@
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn offline_from_config() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [net]
+            offline = true
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+As a reminder, you're using offline mode (--offline) [..]"));
+}
+
+#[test]
+fn offline_from_env() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("fetch").env("CARGO_NET_OFFLINE", "true"),
+                execs().with_status(101).with_stderr("\
+[ERROR] can't fetch dependencies in the offline mode (--offline)"));
+}
+
+#[test]
+fn fetch_fails_early() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("fetch").arg("--offline"),
+                execs().with_status(101).with_stderr("\
+[ERROR] can't fetch dependencies in the offline mode (--offline)"));
+}
+
+#[test]
+fn publish_fails_early() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("publish").arg("--offline"),
+                execs().with_status(101).with_stderr("\
+[ERROR] can't publish `foo` in the offline mode (--offline)"));
+}
+
+#[test]
+fn install_from_registry_fails_early() {
+    Package::new("bar", "0.1.0")
+        .file("src/main.rs", "fn main() {}")
+        .publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("install").arg("bar").arg("--offline"),
+                execs().with_status(101).with_stderr("\
+[ERROR] can't install from `registry [..]` in the offline mode (--offline), \
+use --path to install a local crate instead"));
+}
+
+#[test]
+fn prefer_offline_uses_downloaded_version() {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn version() -> &'static str { \"0.1.0\" }")
+        .publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "=0.1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    Package::new("bar", "0.1.1")
+        .file("src/lib.rs", "pub fn version() -> &'static str { \"0.1.1\" }")
+        .publish();
+
+    let p2 = project("baz")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", r#"
+            extern crate bar;
+            fn main() { println!("{}", bar::version()); }
+        "#)
+        .file(".cargo/config", r#"
+            [net]
+            prefer-offline = true
+        "#)
+        .build();
+
+    assert_that(p2.cargo("run"),
+                execs().with_status(0)
+                       .with_stdout("0.1.0\n")
+                       .with_stderr_does_not_contain("[DOWNLOADING] [..]"));
+}
+
+#[test]
+fn prefer_offline_falls_back_to_network() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [net]
+            prefer-offline = true
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[DOWNLOADING] bar v0.1.0 [..]"));
+}
@@ -0,0 +1,341 @@
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn simple() {
+    Package::new("a", "1.0.0").publish();
+    Package::new("b", "1.0.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = "1.0"
+            b = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    Package::new("a", "1.0.1").publish();
+    Package::new("a", "2.0.0").publish();
+
+    assert_that(p.cargo("outdated"),
+                execs().with_status(0).with_stdout("\
+Name  Locked  Compatible  Latest
+a     1.0.0   1.0.1       2.0.0
+"));
+
+    // The lock file is left alone
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[COMPILING] a v1.0.0")
+                       .with_stderr_does_not_contain("[..]v1.0.1[..]"));
+}
+
+#[test]
+fn quiet() {
+    Package::new("a", "1.0.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+    Package::new("a", "2.0.0").publish();
+
+    assert_that(p.cargo("outdated").arg("-q"),
+                execs().with_status(0).with_stdout("").with_stderr(""));
+}
+
+#[test]
+fn up_to_date() {
+    Package::new("a", "1.0.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    Package::new("a", "1.0.1").yanked(true).publish();
+    Package::new("a", "1.1.0-beta").publish();
+
+    assert_that(p.cargo("outdated"),
+                execs().with_status(0)
+                       .with_stdout("")
+                       .with_stderr_contains("\
+[FINISHED] all dependencies are up to date"));
+}
+
+#[test]
+fn prerelease_in_use() {
+    Package::new("a", "1.0.0-beta.1").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = "1.0.0-beta.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    Package::new("a", "1.0.0-beta.2").publish();
+
+    assert_that(p.cargo("outdated"),
+                execs().with_status(0).with_stdout("\
+Name  Locked        Compatible    Latest
+a     1.0.0-beta.1  1.0.0-beta.2  1.0.0-beta.2
+"));
+}
+
+#[test]
+fn only_major_version_behind() {
+    Package::new("a", "1.0.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    Package::new("a", "2.0.0").publish();
+
+    assert_that(p.cargo("outdated"),
+                execs().with_status(0).with_stdout("\
+Name  Locked  Compatible  Latest
+a     1.0.0   ---         2.0.0
+"));
+}
+
+#[test]
+fn transitive_and_depth() {
+    Package::new("c", "0.1.0").publish();
+    Package::new("b", "1.0.0").dep("c", "0.1").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            b = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    Package::new("c", "0.1.1").publish();
+    Package::new("c", "0.2.0").publish();
+
+    assert_that(p.cargo("outdated"),
+                execs().with_status(0).with_stdout("\
+Name  Locked  Compatible  Latest
+c     0.1.0   0.1.1       0.2.0
+"));
+
+    assert_that(p.cargo("outdated").arg("--depth").arg("1"),
+                execs().with_status(0)
+                       .with_stdout("")
+                       .with_stderr_contains("\
+[FINISHED] all dependencies are up to date"));
+}
+
+#[test]
+fn compatible_with_every_requirement() {
+    Package::new("c", "1.0.0").publish();
+    Package::new("b", "1.0.0").dep("c", "~1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            b = "1.0"
+            c = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    Package::new("c", "1.0.1").publish();
+    Package::new("c", "1.1.0").publish();
+
+    assert_that(p.cargo("outdated"),
+                execs().with_status(0).with_stdout("\
+Name  Locked  Compatible  Latest
+c     1.0.0   1.0.1       1.1.0
+"));
+}
+
+#[test]
+fn json_output() {
+    Package::new("c", "0.1.0").publish();
+    Package::new("b", "1.0.0").dep("c", "0.1").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            b = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    Package::new("b", "2.0.0").publish();
+    Package::new("c", "0.1.1").publish();
+
+    assert_that(p.cargo("outdated").arg("--message-format").arg("json"),
+                execs().with_status(0).with_json(r#"
+{
+    "dependencies": [
+        {
+            "name": "b",
+            "source": "registry+https://github.com/rust-lang/crates.io-index",
+            "locked": "1.0.0",
+            "compatible": "1.0.0",
+            "latest": "2.0.0",
+            "depth": 1
+        },
+        {
+            "name": "c",
+            "source": "registry+https://github.com/rust-lang/crates.io-index",
+            "locked": "0.1.0",
+            "compatible": "0.1.1",
+            "latest": "0.1.1",
+            "depth": 2
+        }
+    ]
+}
+"#));
+}
+
+#[test]
+fn workspace_members() {
+    Package::new("a", "1.0.0").publish();
+    Package::new("b", "1.0.0").publish();
+
+    let p = project("ws")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["foo", "bar"]
+        "#)
+        .file("foo/Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = "1.0"
+        "#)
+        .file("foo/src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            b = "1.0"
+            foo = { path = "../foo" }
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    Package::new("a", "1.1.0").publish();
+    Package::new("b", "1.1.0").publish();
+
+    assert_that(p.cargo("outdated").arg("-p").arg("foo"),
+                execs().with_status(0).with_stdout("\
+Name  Locked  Compatible  Latest
+a     1.0.0   1.1.0       1.1.0
+"));
+
+    assert_that(p.cargo("outdated").arg("--workspace"),
+                execs().with_status(0).with_stdout("\
+Name  Locked  Compatible  Latest
+a     1.0.0   1.1.0       1.1.0
+b     1.0.0   1.1.0       1.1.0
+"));
+
+    assert_that(p.cargo("outdated").arg("--workspace").arg("--exclude").arg("bar"),
+                execs().with_status(0).with_stdout("\
+Name  Locked  Compatible  Latest
+a     1.0.0   1.1.0       1.1.0
+"));
+}
+
+#[test]
+fn no_lockfile() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("outdated"),
+                execs().with_status(101).with_stderr("\
+[ERROR] no Cargo.lock found for `[..]foo`, run `cargo generate-lockfile` \
+to create one first
+"));
+}
@@ -5,8 +5,9 @@ use std::path::{Path, PathBuf};
 
 use git2;
 use cargotest::{cargo_process, process, ChannelChanger};
-use cargotest::support::{project, execs, paths, git, path2url, cargo_exe, registry};
+use cargotest::support::{project, execs, paths, git, path2url, cargo_exe, registry, Project};
 use cargotest::support::registry::Package;
+use filetime::{set_file_times, FileTime};
 use flate2::read::GzDecoder;
 use hamcrest::{assert_that, existing_file, contains};
 use tar::Archive;
@@ -1153,3 +1154,108 @@ fn lock_file_and_workspace() {
             })
     );
 }
+
+fn reproducible_project() -> Project {
+    project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("src/lib.rs", "")
+        .file("build.sh", "#!/bin/sh\n")
+        .build()
+}
+
+fn read_crate(p: &Project) -> Vec<u8> {
+    let mut contents = Vec::new();
+    File::open(&p.root().join("target/package/foo-0.0.1.crate")).unwrap()
+        .read_to_end(&mut contents).unwrap();
+    contents
+}
+
+#[test]
+fn reproducible_tarball() {
+    let p = reproducible_project();
+
+    assert_that(p.cargo("package").arg("--no-verify"),
+                execs().with_status(0));
+    let first = read_crate(&p);
+
+    // Neither the timestamps nor the permissions of the files, other than
+    // whether they're executable, end up in the tarball.
+    let old = FileTime::from_seconds_since_1970(1_000_000_000, 0);
+    for file in &["Cargo.toml", "src/main.rs", "src/lib.rs", "build.sh"] {
+        set_file_times(&p.root().join(file), old, old).unwrap();
+    }
+    #[cfg(unix)]
+    {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&p.root().join("src/lib.rs"),
+                            fs::Permissions::from_mode(0o600)).unwrap();
+    }
+
+    assert_that(p.cargo("package").arg("--no-verify"),
+                execs().with_status(0));
+    assert!(first == read_crate(&p), "packaging twice gave different tarballs");
+}
+
+#[test]
+fn normalized_tarball_headers() {
+    let p = reproducible_project();
+    #[cfg(unix)]
+    {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&p.root().join("build.sh"),
+                            fs::Permissions::from_mode(0o700)).unwrap();
+    }
+
+    assert_that(p.cargo("package").arg("--no-verify")
+                 .env("SOURCE_DATE_EPOCH", "1234567890"),
+                execs().with_status(0));
+
+    let contents = read_crate(&p);
+    let gz = GzDecoder::new(&contents[..]);
+    assert_eq!(gz.header().unwrap().mtime(), 0);
+    let mut ar = Archive::new(gz);
+    let mut paths = Vec::new();
+    for entry in ar.entries().unwrap() {
+        let entry = entry.unwrap();
+        let header = entry.header();
+        let path = header.path().unwrap().to_str().unwrap().replace("\\", "/");
+        assert_eq!(header.mtime().unwrap(), 1234567890, "{}", path);
+        assert_eq!(header.uid().unwrap(), 0, "{}", path);
+        assert_eq!(header.gid().unwrap(), 0, "{}", path);
+        let mode = if cfg!(unix) && path.ends_with("build.sh") { 0o755 } else { 0o644 };
+        assert_eq!(header.mode().unwrap(), mode, "{}", path);
+        paths.push(path);
+    }
+    assert_eq!(paths, [
+        "foo-0.0.1/Cargo.toml",
+        "foo-0.0.1/Cargo.toml.orig",
+        "foo-0.0.1/build.sh",
+        "foo-0.0.1/src/lib.rs",
+        "foo-0.0.1/src/main.rs",
+    ]);
+}
+
+#[test]
+fn invalid_source_date_epoch() {
+    let p = reproducible_project();
+
+    assert_that(p.cargo("package").arg("--no-verify")
+                 .env("SOURCE_DATE_EPOCH", "yesterday"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] failed to prepare local package for uploading
+
+Caused by:
+  invalid `SOURCE_DATE_EPOCH` of `yesterday`, expected the number of seconds \
+since the Unix epoch"));
+}
@@ -1,8 +1,10 @@
 use std::env;
+use std::fs::File;
+use std::io::Write;
 
 use cargotest::is_nightly;
 use cargotest::support::{project, execs};
-use hamcrest::assert_that;
+use hamcrest::{assert_that, existing_file};
 
 #[test]
 fn profile_overrides() {
@@ -281,3 +283,290 @@ fn profile_in_virtual_manifest_works() {
 [RUNNING] `rustc [..]`
 [FINISHED] dev [optimized] target(s) in [..]"));
 }
+
+#[test]
+fn package_override() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+
+            [profile.dev]
+            opt-level = 0
+
+            [profile.dev.overrides.bar]
+            opt-level = 3
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name bar bar[/]src[/]lib.rs [..]-C opt-level=3 [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name foo src[/]lib.rs [..]-C debuginfo=2 [..]`")
+                       .with_stderr_does_not_contain("\
+[RUNNING] `rustc --crate-name foo [..]opt-level[..]`"));
+
+    // Changing the override only rebuilds the package it applies to.
+    File::create(p.root().join("Cargo.toml")).unwrap().write_all(br#"
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        bar = { path = "bar" }
+
+        [profile.dev]
+        opt-level = 0
+
+        [profile.dev.overrides.bar]
+        opt-level = 2
+    "#).unwrap();
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name bar bar[/]src[/]lib.rs [..]-C opt-level=2 [..]`"));
+}
+
+#[test]
+fn build_override() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+
+            [build-dependencies]
+            bar = { path = "bar" }
+
+            [profile.dev.build-override]
+            opt-level = 1
+            debug = false
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", "extern crate bar; fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name bar bar[/]src[/]lib.rs [..]-C opt-level=1 [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name build_script_build build.rs [..]-C opt-level=1 [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name foo src[/]lib.rs [..]-C debuginfo=2 [..]`"));
+}
+
+#[test]
+fn override_cannot_set_panic() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.dev.overrides.bar]
+            panic = "abort"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `panic` may not be specified in `profile.dev.overrides.\"bar\"`
+"));
+}
+
+#[test]
+fn override_invalid_spec() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.dev.overrides."bar:a.b"]
+            opt-level = 3
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  invalid package id specification `bar:a.b` in profile overrides
+
+Caused by:
+  Error parsing major identifier
+"));
+}
+
+#[test]
+fn custom_profile() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.profiling]
+            inherits = "release"
+            debug = true
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build").arg("-v").arg("--profile").arg("profiling"),
+                execs().with_status(0).with_stderr(&format!("\
+[COMPILING] foo v0.0.1 ({url})
+[RUNNING] `rustc --crate-name foo src[/]main.rs --crate-type bin \
+        --emit=dep-info,link \
+        -C opt-level=3 \
+        -C debuginfo=2 \
+        -C metadata=[..] \
+        --out-dir {dir}[/]target[/]profiling[/]deps \
+        -L dependency={dir}[/]target[/]profiling[/]deps`
+[FINISHED] profiling [optimized + debuginfo] target(s) in [..]
+",
+                    dir = p.root().display(),
+                    url = p.url())));
+    assert_that(&p.root().join("target/profiling")
+                         .join(format!("foo{}", env::consts::EXE_SUFFIX)),
+                existing_file());
+}
+
+#[test]
+fn custom_profile_inherits_custom_profile() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.ci]
+            inherits = "dev"
+            opt-level = 1
+
+            [profile.ci-release]
+            inherits = "ci"
+            debug-assertions = false
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("-v").arg("--profile").arg("ci-release"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name foo src[/]lib.rs [..]-C opt-level=1 [..]\
+-C debuginfo=2 [..]--out-dir [..]target[/]ci-release[/]deps [..]`")
+                       .with_stderr_does_not_contain("[..]debug-assertions=on[..]")
+                       .with_stderr_contains("\
+[FINISHED] ci-release [optimized + debuginfo] target(s) in [..]"));
+}
+
+#[test]
+fn custom_profile_missing_inherits() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.ci]
+            opt-level = 1
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  profile `ci` is missing an `inherits` directive \
+(`inherits` is required for all profiles except `dev` or `release`)
+"));
+}
+
+#[test]
+fn custom_profile_inheritance_loop() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.a]
+            inherits = "b"
+
+            [profile.b]
+            inherits = "a"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  profile inheritance loop detected with profile `b` inheriting `a`
+"));
+}
+
+#[test]
+fn undefined_profile() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("--profile").arg("nope"),
+                execs().with_status(101).with_stderr("\
+[ERROR] profile `nope` is not defined
+"));
+    assert_that(p.cargo("build").arg("--profile").arg("dev").arg("--release"),
+                execs().with_status(101).with_stderr("\
+[ERROR] conflicting usage of --profile=dev and --release
+"));
+}
@@ -552,7 +552,6 @@ fn update_lockfile() {
 
 #[test]
 fn update_offline(){
-    use cargotest::ChannelChanger;
     let p = project("foo")
         .file("Cargo.toml", r#"
             [project]
@@ -565,7 +564,7 @@ fn update_offline(){
         "#)
         .file("src/main.rs", "fn main() {}")
         .build();
-    assert_that(p.cargo("update").masquerade_as_nightly_cargo().arg("-Zoffline"),
+    assert_that(p.cargo("update").arg("--offline"),
     execs().with_status(101).
         with_stderr("error: you can't update in the offline mode[..]"));
 }
@@ -1469,3 +1468,80 @@ Caused by:
   [..] contains a file at \"foo-0.1.0/src/lib.rs\" which isn't under \"foo-0.2.0\"
 "));
 }
+
+#[test]
+fn downloads_dependencies_together() {
+    Package::new("baz", "0.1.0").publish();
+    Package::new("bar", "0.1.0").dep("baz", "0.1").publish();
+    Package::new("qux", "0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+            qux = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    // Serve the index over HTTP as well, so the download URL can point at
+    // a server which sees the downloads
+    let downloads = registry::serve_downloads(2);
+    let index = registry::serve_index();
+    File::create(registry::registry_path().join("config.json")).unwrap()
+        .write_all(format!(r#"{{"dl":"{}"}}"#, downloads.url()).as_bytes()).unwrap();
+    fs::create_dir(p.root().join(".cargo")).unwrap();
+    File::create(p.root().join(".cargo/config")).unwrap().write_all(format!(r#"
+        [source.crates-io]
+        replace-with = "sparse-mirror"
+
+        [source.sparse-mirror]
+        registry = "{}"
+    "#, index.url()).as_bytes()).unwrap();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[DOWNLOADING] bar v0.1.0 [..]")
+                       .with_stderr_contains("[DOWNLOADING] baz v0.1.0 [..]")
+                       .with_stderr_contains("[DOWNLOADING] qux v0.1.0 [..]")
+                       .with_stderr_contains("[COMPILING] foo v0.0.1 [..]"));
+    // At most two connections are opened to the same host
+    assert_eq!(downloads.max_in_flight(), 2);
+
+    // Everything is cached now
+    assert_that(p.cargo("fetch"),
+                execs().with_status(0).with_stderr(""));
+}
+
+#[test]
+fn download_without_multiplexing() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [http]
+            multiplexing = false
+        "#)
+        .build();
+
+    assert_that(p.cargo("fetch"),
+                execs().with_status(0).with_stderr("\
+[UPDATING] registry `[..]`
+[DOWNLOADING] bar v0.1.0 (registry `file://[..]`)
+"));
+}
@@ -31,7 +31,7 @@ fn resolve(pkg: &PackageId, deps: Vec<Dependency>, registry: &[Summary])
     let mut registry = MyRegistry(registry);
     let summary = Summary::new(pkg.clone(), deps, BTreeMap::new(), None).unwrap();
     let method = Method::Everything;
-    let resolve = resolver::resolve(&[(summary, method)], &[], &mut registry, None, false)?;
+    let resolve = resolver::resolve(&[(summary, method)], &[], &mut registry, None, None, false)?;
     let res = resolve.iter().cloned().collect();
     Ok(res)
 }
@@ -0,0 +1,105 @@
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn rm_dependencies() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"[package]
+name = "foo"
+version = "0.1.0"
+authors = []
+
+[dependencies]
+bar = "0.1"   # going away
+baz = { version = "0.1", features = [
+    "a",
+    "b",
+] }
+quux = "0.1"
+
+[build-dependencies.bar]
+version = "0.1"
+
+[target.'cfg(unix)'.dependencies]
+bar = "0.1"
+"#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("rm").arg("bar").arg("baz"),
+                execs().with_status(0)
+                       .with_stderr("\
+[REMOVING] bar from dependencies
+[REMOVING] baz from dependencies
+"));
+    assert_that(p.cargo("rm").arg("bar").arg("--build"),
+                execs().with_status(0)
+                       .with_stderr("\
+[REMOVING] bar from build-dependencies
+"));
+    assert_that(p.cargo("rm").arg("bar").arg("--target").arg("cfg(unix)"),
+                execs().with_status(0)
+                       .with_stderr("\
+[REMOVING] bar from target.'cfg(unix)'.dependencies
+"));
+
+    assert_eq!(p.read_file("Cargo.toml"), r#"[package]
+name = "foo"
+version = "0.1.0"
+authors = []
+
+[dependencies]
+quux = "0.1"
+
+[target.'cfg(unix)'.dependencies]
+"#);
+}
+
+#[test]
+fn rm_missing_dependency() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("rm").arg("bar").arg("--dev"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] the dependency `bar` could not be found in `dev-dependencies`
+"));
+}
+
+#[test]
+fn rm_invalid_manifest_unchanged() {
+    let manifest = r#"
+        [package]
+        name = "foo"
+        version = "0.1.0"
+        authors = []
+
+        [dependencies]
+        bar = { version = "0.1", optional = true }
+
+        [features]
+        default = ["bar"]
+    "#;
+    let p = project("foo")
+        .file("Cargo.toml", manifest)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("rm").arg("bar"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] the edited manifest would be invalid, `[..]Cargo.toml` has been left unchanged"));
+
+    assert_eq!(p.read_file("Cargo.toml"), manifest);
+}
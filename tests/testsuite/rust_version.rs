@@ -0,0 +1,182 @@
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs, Project};
+use hamcrest::assert_that;
+
+#[test]
+fn rust_version_satisfied() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            rust-version = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+}
+
+#[test]
+fn rust_version_too_high() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            rust-version = "1.9876.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+error: package `foo v0.0.1 ([..])` cannot be built because it requires rustc \
+1.9876.0 or newer, while the currently active rustc version is [..]
+Use `--ignore-rust-version` to build anyway
+"));
+    assert_that(p.cargo("build").arg("--ignore-rust-version"),
+                execs().with_status(0));
+}
+
+#[test]
+fn rust_version_bad() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            rust-version = "^1.43"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+error: failed to parse manifest at `[..]`
+
+Caused by:
+  failed to parse the `rust-version` key
+
+Caused by:
+  expected a version like \"1.32\", found `^1.43`
+"));
+}
+
+#[test]
+fn dependency_rust_version_too_high() {
+    Package::new("bar", "0.1.0").rust_version("1.9876").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+error: package `bar v0.1.0` cannot be built because it requires rustc 1.9876 \
+or newer, while the currently active rustc version is [..]
+"));
+}
+
+#[test]
+fn inherit_rust_version() {
+    let p = project("ws")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["foo"]
+
+            [workspace.package]
+            rust-version = "1.9876"
+        "#)
+        .file("foo/Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            rust-version.workspace = true
+        "#)
+        .file("foo/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+error: package `foo v0.0.1 ([..])` cannot be built because it requires rustc \
+1.9876 or newer[..]
+"));
+}
+
+fn fallback_project(config: &str) -> Project {
+    Package::new("bar", "1.0.0").rust_version("1.0").publish();
+    Package::new("bar", "1.1.0").rust_version("1.9876").publish();
+
+    project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            rust-version = "1.0"
+
+            [dependencies]
+            bar = "1"
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", config)
+        .build()
+}
+
+#[test]
+fn resolve_newest_by_default() {
+    let p = fallback_project("");
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[DOWNLOADING] bar v1.1.0 ([..])
+"));
+}
+
+#[test]
+fn resolve_with_rust_version_fallback() {
+    let p = fallback_project(r#"
+        [resolver]
+        incompatible-rust-versions = "fallback"
+    "#);
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[DOWNLOADING] bar v1.0.0 ([..])
+"));
+}
+
+#[test]
+fn resolve_with_invalid_fallback() {
+    let p = fallback_project(r#"
+        [resolver]
+        incompatible-rust-versions = "maybe"
+    "#);
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+error: `resolver.incompatible-rust-versions` must be one of `allow` or \
+`fallback`, but found `maybe` in [..]
+"));
+}
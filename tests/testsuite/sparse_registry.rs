@@ -0,0 +1,167 @@
+use cargotest::support::registry::{self, Package};
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+fn config(server: &registry::HttpServer) -> String {
+    format!(r#"
+        [source.crates-io]
+        replace-with = "sparse-mirror"
+
+        [source.sparse-mirror]
+        registry = "{}"
+    "#, server.url())
+}
+
+#[test]
+fn simple() {
+    let server = registry::serve_index();
+    Package::new("bar", "0.0.1").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", &config(&server))
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr(&format!("\
+[UPDATING] registry `{url}`
+[DOWNLOADING] bar v0.0.1 (registry `{url}`)
+[COMPILING] bar v0.0.1
+[COMPILING] foo v0.0.1 ({dir})
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..] secs
+",
+        url = server.url(),
+        dir = p.url())));
+
+    // Only the files which were needed were requested
+    assert_eq!(server.requests(), vec![
+        "200 /3/b/bar".to_string(),
+        "200 /config.json".to_string(),
+    ]);
+}
+
+#[test]
+fn revalidates_cached_files() {
+    let server = registry::serve_index();
+    Package::new("bar", "0.0.1").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", &config(&server))
+        .build();
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+    assert_that(p.cargo("update"), execs().with_status(0));
+    assert_eq!(server.requests(), vec![
+        "200 /3/b/bar".to_string(),
+        "304 /3/b/bar".to_string(),
+    ]);
+
+    Package::new("bar", "0.0.2").publish();
+    assert_that(p.cargo("update"),
+                execs().with_status(0).with_stderr("\
+[UPDATING] registry `[..]`
+[UPDATING] bar v0.0.1 -> v0.0.2
+"));
+    assert_eq!(server.requests().last().unwrap(), "200 /3/b/bar");
+}
+
+#[test]
+fn missing_crate() {
+    let server = registry::serve_index();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            baz = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", &config(&server))
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] no matching package named `baz` found"));
+    assert_eq!(server.requests(), vec!["404 /3/b/baz".to_string()]);
+}
+
+#[test]
+fn offline_without_cached_index() {
+    let server = registry::serve_index();
+    Package::new("bar", "0.0.1").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", &config(&server))
+        .build();
+
+    assert_that(p.cargo("build").arg("--offline"),
+                execs().with_status(101).with_stderr_contains(&format!("\
+[..]can't fetch `3/b/bar` from the index of registry `{}` because --offline \
+was specified", server.url())));
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn index_fetch_errors_are_reported() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [source.crates-io]
+            replace-with = "sparse-mirror"
+
+            [source.sparse-mirror]
+            registry = "sparse+http://127.0.0.1:1/"
+
+            [net]
+            retry = 0
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[..]failed to fetch `3/b/bar` from the index")
+                       .with_stderr_does_not_contain("[..]no matching package[..]"));
+}
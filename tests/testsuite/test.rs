@@ -1,13 +1,14 @@
+use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 use std::str;
 
 use cargo;
 use cargotest::{sleep_ms, is_nightly, rustc_host};
-use cargotest::support::{project, execs, basic_bin_manifest, basic_lib_manifest, cargo_exe};
+use cargotest::support::{project, execs, basic_bin_manifest, basic_lib_manifest, cargo_exe, Project};
 use cargotest::support::paths::CargoPathExt;
 use cargotest::support::registry::Package;
-use hamcrest::{assert_that, existing_file, is_not};
+use hamcrest::{assert_that, existing_dir, existing_file, is_not};
 use cargo::util::process;
 
 #[test]
@@ -2969,3 +2970,354 @@ fn test_hint_workspace() {
                     "[ERROR] test failed, to rerun pass '-p b --lib'")
                        .with_status(101));
 }
+
+/// A project with two integration tests which each wait for the other one to
+/// have started, so they only pass when run at the same time.
+fn rendezvous_project(config: &str) -> Project {
+    let test = |me: &str, other: &str| format!(r#"
+        use std::fs::File;
+        use std::path::Path;
+        use std::thread;
+        use std::time::Duration;
+
+        #[test]
+        fn {me}() {{
+            File::create("{me}.started").unwrap();
+            for _ in 0..600 {{
+                if Path::new("{other}.started").exists() {{
+                    return
+                }}
+                thread::sleep(Duration::from_millis(100));
+            }}
+            panic!("{other} never started");
+        }}
+    "#, me = me, other = other);
+    project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file("tests/a.rs", &test("a", "b"))
+        .file("tests/b.rs", &test("b", "a"))
+        .file(".cargo/config", config)
+        .build()
+}
+
+#[test]
+fn test_jobs_runs_binaries_at_once() {
+    let p = rendezvous_project("");
+
+    assert_that(p.cargo("test").arg("--test-jobs").arg("2").arg("--tests"),
+                execs().with_status(0)
+                       .with_stderr_contains("[RUNNING] target[/]debug[/]deps[/]a-[..][EXE]")
+                       .with_stderr_contains("[RUNNING] target[/]debug[/]deps[/]b-[..][EXE]")
+                       .with_stdout_contains("\
+running 1 test
+test a ... ok")
+                       .with_stdout_contains("\
+running 1 test
+test b ... ok"));
+}
+
+#[test]
+fn test_jobs_from_config() {
+    let p = rendezvous_project(r#"
+        [build]
+        test-jobs = 2
+    "#);
+
+    assert_that(p.cargo("test").arg("--tests"),
+                execs().with_status(0)
+                       .with_stdout_contains("test a ... ok")
+                       .with_stdout_contains("test b ... ok"));
+}
+
+#[test]
+fn test_jobs_must_be_positive() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_lib_manifest("foo"))
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [build]
+            test-jobs = 0
+        "#)
+        .build();
+
+    assert_that(p.cargo("test"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] build.test-jobs must be positive, but found 0 in [..]config
+"));
+    assert_that(p.cargo("test").arg("--test-jobs").arg("0"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] --test-jobs must be positive
+"));
+}
+
+#[test]
+fn test_jobs_no_fail_fast() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file("tests/a.rs", r#"
+            #[test]
+            fn a() { panic!("a failed") }
+        "#)
+        .file("tests/b.rs", r#"
+            #[test]
+            fn b() {}
+        "#)
+        .file("tests/c.rs", r#"
+            #[test]
+            fn c() { panic!("c failed") }
+        "#)
+        .build();
+
+    assert_that(p.cargo("test").arg("--test-jobs").arg("2")
+                 .arg("--tests").arg("--no-fail-fast"),
+                execs().with_status(101)
+                       .with_stdout_contains("test a ... FAILED")
+                       .with_stdout_contains("test b ... ok")
+                       .with_stdout_contains("test c ... FAILED")
+                       .with_stderr_contains("[ERROR] test failed."));
+}
+
+fn partition_project() -> Project {
+    let lib = (0..20).map(|i| format!("#[test] fn t{}() {{}}\n", i))
+                     .collect::<String>();
+    project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", &format!(r#"
+            /// ```
+            /// foo::answer();
+            /// ```
+            pub fn answer() {{}}
+            {}
+        "#, lib))
+        .file("tests/a.rs", "#[test] fn a() {}")
+        .file("tests/b.rs", "#[test] fn b() {}")
+        .file("tests/c.rs", "#[test] fn c() {}")
+        .build()
+}
+
+/// The tests which ran, as printed by the test harness.
+fn tests_run(p: &Project, partition: &str) -> Vec<String> {
+    let output = p.cargo("test").arg("--partition").arg(partition)
+                  .exec_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    stdout.lines().filter(|line| line.starts_with("test ") && line.ends_with(" ... ok"))
+          .map(|line| line.to_string())
+          .collect()
+}
+
+#[test]
+fn partition_count() {
+    let p = partition_project();
+
+    assert_that(p.cargo("test").arg("--partition").arg("count:1/2"),
+                execs().with_status(0)
+                       .with_stderr_contains("[RUNNING] target[/]debug[/]deps[/]foo-[..][EXE]")
+                       .with_stderr_contains("[RUNNING] target[/]debug[/]deps[/]b-[..][EXE]")
+                       .with_stderr_contains("[DOCTEST] foo")
+                       .with_stderr_does_not_contain("[RUNNING] target[/]debug[/]deps[/]a-[..]")
+                       .with_stderr_does_not_contain("[RUNNING] target[/]debug[/]deps[/]c-[..]"));
+    assert_that(p.cargo("test").arg("--partition").arg("count:2/2"),
+                execs().with_status(0)
+                       .with_stderr_contains("[RUNNING] target[/]debug[/]deps[/]a-[..][EXE]")
+                       .with_stderr_contains("[RUNNING] target[/]debug[/]deps[/]c-[..][EXE]")
+                       .with_stderr_does_not_contain("[RUNNING] target[/]debug[/]deps[/]foo-[..]")
+                       .with_stderr_does_not_contain("[RUNNING] target[/]debug[/]deps[/]b-[..]")
+                       .with_stderr_does_not_contain("[DOCTEST] foo"));
+}
+
+#[test]
+fn partition_hash() {
+    let p = partition_project();
+
+    let mut all = Vec::new();
+    for shard in 1..4 {
+        let partition = format!("hash:{}/3", shard);
+        let tests = tests_run(&p, &partition);
+        assert_eq!(tests, tests_run(&p, &partition));
+        // Twenty tests in three parts shouldn't all end up in one of them.
+        assert!(tests.len() < 20, "{:?}", tests);
+        all.extend(tests);
+    }
+    all.sort();
+
+    let mut expected = tests_run(&p, "count:1/1");
+    expected.sort();
+    assert_eq!(expected.len(), 24);
+    assert_eq!(all, expected);
+}
+
+#[test]
+fn partition_hash_runs_each_test_once() {
+    // Each name is a prefix of the next, so only matching exactly tells them
+    // apart
+    let p = project("foo")
+        .file("Cargo.toml", &basic_lib_manifest("foo"))
+        .file("src/lib.rs", r#"
+            #[test] fn t() {}
+            #[test] fn tt() {}
+            #[test] fn ttt() {}
+        "#)
+        .build();
+
+    let output = p.cargo("test").arg("--lib").arg("--partition").arg("hash:1/1")
+                  .exec_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    for name in &["t", "tt", "ttt"] {
+        assert_eq!(stdout.matches(&format!("test {} ... ok", name)).count(), 1,
+                   "{}", stdout);
+    }
+    assert_eq!(stdout.matches("running 1 test").count(), 3, "{}", stdout);
+    assert_eq!(stderr.matches("Running").count(), 3, "{}", stderr);
+}
+
+#[test]
+fn partition_invalid() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_lib_manifest("foo"))
+        .file("src/lib.rs", "")
+        .build();
+
+    for partition in &["hash:0/3", "hash:4/3", "count", "count:1", "random:1/2"] {
+        assert_that(p.cargo("test").arg("--partition").arg(partition),
+                    execs().with_status(101)
+                           .with_stderr(&format!("\
+[ERROR] invalid partition `{}`, expected `count:K/N` or `hash:K/N` where K is \
+between 1 and N
+", partition)));
+    }
+}
+
+#[test]
+fn bin_exe_env_for_integration_tests() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [[bin]]
+            name = "foo-cli"
+            path = "src/main.rs"
+        "#)
+        .file("src/main.rs", r#"
+            fn main() { println!("hello from the cli"); }
+        "#)
+        .file("tests/cli.rs", r#"
+            use std::process::Command;
+
+            #[test]
+            fn runs_cli() {
+                let output = Command::new(env!("CARGO_BIN_EXE_foo-cli")).output().unwrap();
+                assert!(output.status.success());
+                assert_eq!(String::from_utf8(output.stdout).unwrap(), "hello from the cli\n");
+            }
+        "#)
+        .file("benches/cli.rs", r#"
+            #![allow(dead_code)]
+            const CLI: &str = env!("CARGO_BIN_EXE_foo-cli");
+        "#)
+        .build();
+
+    assert_that(p.cargo("test"),
+                execs().with_status(0)
+                       .with_stdout_contains("test runs_cli ... ok"));
+    assert_that(p.cargo("test").arg("--release").arg("--benches"),
+                execs().with_status(0));
+    assert_that(p.cargo("test").arg("--release"),
+                execs().with_status(0)
+                       .with_stdout_contains("test runs_cli ... ok"));
+    assert_that(p.cargo("test").env("CARGO_TARGET_DIR", p.root().join("other")),
+                execs().with_status(0)
+                       .with_stdout_contains("test runs_cli ... ok"));
+    assert_that(&p.root().join("other/debug").join(format!("foo-cli{}", env::consts::EXE_SUFFIX)),
+                existing_file());
+}
+
+#[test]
+fn bin_exe_env_when_checking() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", "fn main() {}")
+        .file("tests/cli.rs", r#"
+            const EXE: &str = env!("CARGO_BIN_EXE_foo");
+
+            const fn is_rmeta(path: &str) -> bool {
+                let (path, ext) = (path.as_bytes(), b".rmeta");
+                if path.len() < ext.len() {
+                    return false
+                }
+                let mut i = 0;
+                while i < ext.len() {
+                    if path[path.len() - ext.len() + i] != ext[i] {
+                        return false
+                    }
+                    i += 1;
+                }
+                true
+            }
+
+            const _: () = assert!(!is_rmeta(EXE));
+        "#)
+        .build();
+
+    assert_that(p.cargo("check").arg("--tests"), execs().with_status(0));
+}
+
+#[test]
+fn target_tmpdir_for_integration_tests() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_lib_manifest("foo"))
+        .file("src/lib.rs", "")
+        .file("tests/scratch.rs", r#"
+            use std::fs::File;
+            use std::io::Write;
+            use std::path::Path;
+
+            #[test]
+            fn writes_scratch_file() {
+                let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+                File::create(dir.join("scratch.txt")).unwrap()
+                    .write_all(b"scratch").unwrap();
+            }
+        "#)
+        .build();
+
+    assert_that(p.cargo("test"),
+                execs().with_status(0)
+                       .with_stdout_contains("test writes_scratch_file ... ok"));
+    assert_that(&p.root().join("target/tmp/foo/scratch.txt"), existing_file());
+}
+
+#[test]
+fn target_tmpdir_not_created_by_build_plan() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_lib_manifest("foo"))
+        .file("src/lib.rs", "")
+        .file("tests/scratch.rs", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("--tests").arg("--build-plan"),
+                execs().with_status(0));
+    assert_that(&p.root().join("target/tmp"), is_not(existing_dir()));
+}
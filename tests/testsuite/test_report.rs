@@ -0,0 +1,176 @@
+use std::fs::File;
+use std::io::prelude::*;
+
+use cargotest::support::{execs, project, Project};
+use hamcrest::assert_that;
+use serde_json::{self, Value};
+
+fn lib_with_tests() -> Project {
+    project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", r#"
+            /// ```
+            /// assert_eq!(foo::answer(), 42);
+            /// ```
+            pub fn answer() -> u32 { 42 }
+
+            #[test]
+            fn passes() {}
+
+            #[test]
+            fn fails() {
+                println!("<hello> from the test");
+                assert!(false);
+            }
+
+            #[test]
+            #[ignore]
+            fn ignored() {}
+        "#)
+        .file("tests/it.rs", r#"
+            #[test]
+            fn integration() {}
+        "#)
+        .build()
+}
+
+/// The test messages printed by cargo, ignoring the ones from the compiler.
+fn test_messages(stdout: &[u8]) -> Vec<Value> {
+    let stdout = String::from_utf8_lossy(stdout);
+    stdout.lines().map(|line| {
+        serde_json::from_str::<Value>(line).unwrap_or_else(|e| {
+            panic!("invalid JSON message `{}`: {}", line, e)
+        })
+    }).filter(|msg| msg["reason"].as_str().unwrap().starts_with("test-"))
+      .collect()
+}
+
+#[test]
+fn json_messages() {
+    let p = lib_with_tests();
+
+    let output = p.cargo("test").arg("--message-format").arg("json")
+                  .arg("--no-fail-fast")
+                  .exec_with_output().unwrap_err();
+    let output = output.downcast::<::cargo::util::ProcessError>().unwrap()
+                       .output.unwrap();
+    let messages = test_messages(&output.stdout);
+    // Newer versions of rustdoc name doctests by their absolute path.
+    let root = format!("{}/", p.root().display());
+    let summary = messages.iter().map(|msg| {
+        let reason = msg["reason"].as_str().unwrap();
+        let name = msg["name"].as_str().unwrap().replace(&root, "");
+        match reason {
+            "test-result" => format!("{} {} {}", reason, name,
+                                     msg["outcome"].as_str().unwrap()),
+            _ => format!("{} {} {}", reason, msg["kind"].as_str().unwrap(), name),
+        }
+    }).collect::<Vec<_>>();
+    assert_eq!(summary, [
+        "test-suite-started lib foo",
+        "test-result fails failed",
+        "test-result ignored ignored",
+        "test-result passes ok",
+        "test-suite-finished lib foo",
+        "test-suite-started test it",
+        "test-result integration ok",
+        "test-suite-finished test it",
+        "test-suite-started doc foo",
+        "test-result src/lib.rs - answer (line 2) ok",
+        "test-suite-finished doc foo",
+    ]);
+
+    assert_eq!(messages[0]["test_count"], 3);
+    assert!(messages[0]["package_id"].as_str().unwrap().starts_with("foo 0.0.1 "));
+
+    let fails = &messages[1];
+    assert_eq!(fails["suite"], "foo");
+    let stdout = fails["stdout"].as_str().unwrap();
+    assert!(stdout.starts_with("<hello> from the test\n"), "{}", stdout);
+    assert!(stdout.contains("assertion failed: false"), "{}", stdout);
+    assert_eq!(messages[3]["stdout"], Value::Null);
+
+    let finished = &messages[4];
+    assert_eq!(finished["passed"], 1);
+    assert_eq!(finished["failed"], 1);
+    assert_eq!(finished["ignored"], 1);
+    assert_eq!(finished["measured"], 0);
+    assert_eq!(finished["filtered_out"], 0);
+    assert_eq!(finished["success"], false);
+    assert!(finished["exec_time"].as_f64().unwrap() >= 0.0);
+    assert_eq!(messages[7]["success"], true);
+}
+
+#[test]
+fn json_messages_with_filter() {
+    let p = lib_with_tests();
+
+    let output = p.cargo("test").arg("--message-format").arg("json")
+                  .arg("--lib").arg("passes")
+                  .exec_with_output().unwrap();
+    let messages = test_messages(&output.stdout);
+    assert_eq!(messages.len(), 3);
+    assert_eq!(messages[0]["test_count"], 1);
+    assert_eq!(messages[1]["name"], "passes");
+    assert_eq!(messages[2]["filtered_out"], 2);
+    assert_eq!(messages[2]["success"], true);
+}
+
+#[test]
+fn junit_report() {
+    let p = lib_with_tests();
+
+    assert_that(p.cargo("test").arg("--junit").arg("target/report.xml")
+                 .arg("--no-fail-fast"),
+                execs().with_status(101)
+                       .with_stdout_contains("test passes ... ok")
+                       .with_stdout_contains("test integration ... ok"));
+
+    let mut report = String::new();
+    File::open(p.root().join("target/report.xml")).unwrap()
+        .read_to_string(&mut report).unwrap();
+    let lines = report.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    assert!(lines[1].starts_with(r#"<testsuites tests="5" failures="1" skipped="1" time=""#),
+            "{}", report);
+    assert!(lines[2].starts_with(r#"  <testsuite name="foo::lib::foo" tests="3" failures="1" skipped="1" time=""#),
+            "{}", report);
+    assert_eq!(lines[3], r#"    <testcase name="fails" classname="foo::lib::foo">"#);
+    assert_eq!(lines[4], r#"      <failure message="test failed">&lt;hello&gt; from the test"#);
+    assert!(report.contains(r#"
+    <testcase name="ignored" classname="foo::lib::foo">
+      <skipped/>
+    </testcase>
+    <testcase name="passes" classname="foo::lib::foo"/>
+  </testsuite>
+"#), "{}", report);
+    assert!(report.contains(r#"
+    <testcase name="integration" classname="foo::test::it"/>
+  </testsuite>
+"#), "{}", report);
+    let report = report.replace(&format!("{}/", p.root().display()), "");
+    assert!(report.contains(r#"
+    <testcase name="src/lib.rs - answer (line 2)" classname="foo::doc::foo"/>
+  </testsuite>
+</testsuites>
+"#), "{}", report);
+}
+
+#[test]
+fn junit_report_on_fail_fast() {
+    let p = lib_with_tests();
+
+    assert_that(p.cargo("test").arg("--junit").arg("report.xml"),
+                execs().with_status(101));
+
+    let mut report = String::new();
+    File::open(p.root().join("report.xml")).unwrap()
+        .read_to_string(&mut report).unwrap();
+    assert!(report.contains(r#"<testsuites tests="3" failures="1" skipped="1""#), "{}", report);
+    assert!(!report.contains("foo::test::it"), "{}", report);
+}
@@ -0,0 +1,93 @@
+use std::fs::File;
+use std::io::prelude::*;
+
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs};
+use hamcrest::{assert_that, existing_dir, existing_file, is_not};
+use serde_json::{self, Value};
+
+#[test]
+fn timings_works() {
+    Package::new("dep", "0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            dep = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build").arg("--timings"),
+                execs().with_status(0).with_stderr_contains("\
+[..]Timing report saved to [..]cargo-timings[..]cargo-timing.html"));
+
+    let dir = p.root().join("target/cargo-timings");
+    assert_that(&dir.join("cargo-timing.html"), existing_file());
+    assert_that(&dir.join("cargo-timing.json"), existing_file());
+
+    let mut json = String::new();
+    File::open(dir.join("cargo-timing.json")).unwrap()
+        .read_to_string(&mut json).unwrap();
+    let report: Value = serde_json::from_str(&json).unwrap();
+    let units = report["units"].as_array().unwrap();
+    let names = units.iter().map(|u| {
+        format!("{} {}", u["name"].as_str().unwrap(), u["target"].as_str().unwrap())
+    }).collect::<Vec<_>>();
+    assert!(names.contains(&"dep lib".to_string()), "{:?}", names);
+    assert!(names.contains(&"foo lib".to_string()), "{:?}", names);
+    assert!(names.contains(&"foo bin \"foo\"".to_string()), "{:?}", names);
+
+    // `dep` is what the library was waiting on
+    let dep = units.iter().position(|u| u["name"] == "dep").unwrap();
+    assert!(units[dep]["rlib_time"].is_number());
+    assert!(!units[dep]["unlocked_units"].as_array().unwrap().is_empty());
+    assert!(!report["concurrency"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn timings_check_records_rmeta() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("check").arg("--timings"), execs().with_status(0));
+
+    let mut json = String::new();
+    File::open(p.root().join("target/cargo-timings/cargo-timing.json")).unwrap()
+        .read_to_string(&mut json).unwrap();
+    let report: Value = serde_json::from_str(&json).unwrap();
+    let unit = &report["units"][0];
+    assert_eq!(unit["mode"], "check");
+    assert!(unit["rmeta_time"].is_number());
+    assert!(unit["rlib_time"].is_null());
+}
+
+#[test]
+fn no_report_without_flag() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr_does_not_contain("[..]Timing[..]"));
+    assert_that(&p.root().join("target/cargo-timings"), is_not(existing_dir()));
+}
@@ -0,0 +1,198 @@
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn simple() {
+    Package::new("c", "1.0.0").publish();
+    Package::new("b", "1.0.0").dep("c", "1.0").publish();
+    Package::new("a", "1.0.0").dep("c", "1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = "1.0"
+            b = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("tree"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.1 ([..])
+├── a v1.0.0
+│   └── c v1.0.0
+└── b v1.0.0
+    └── c v1.0.0 (*)
+"));
+}
+
+#[test]
+fn quiet() {
+    Package::new("a", "1.0.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("tree").arg("-q"),
+                execs().with_status(0).with_stdout("").with_stderr(""));
+}
+
+#[test]
+fn dependency_kinds() {
+    Package::new("normaldep", "1.0.0").publish();
+    Package::new("builddep", "1.0.0").publish();
+    Package::new("devdep", "1.0.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            normaldep = "1.0"
+
+            [build-dependencies]
+            builddep = "1.0"
+
+            [dev-dependencies]
+            devdep = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("tree"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.1 ([..])
+└── normaldep v1.0.0
+[build-dependencies]
+└── builddep v1.0.0
+[dev-dependencies]
+└── devdep v1.0.0
+"));
+
+    assert_that(p.cargo("tree").arg("--kind").arg("normal,dev"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.1 ([..])
+└── normaldep v1.0.0
+[dev-dependencies]
+└── devdep v1.0.0
+"));
+
+    assert_that(p.cargo("tree").arg("--kind").arg("bogus"),
+                execs().with_status(101).with_stderr("\
+[ERROR] invalid dependency kind `bogus`, expected one of `normal`, `build` or `dev`
+"));
+}
+
+#[test]
+fn invert_and_duplicates() {
+    Package::new("c", "1.0.0").publish();
+    Package::new("c", "2.0.0").publish();
+    Package::new("a", "1.0.0").dep("c", "1.0").publish();
+    Package::new("b", "1.0.0").dep("c", "2.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = "1.0"
+            b = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("tree").arg("--invert").arg("c:1.0.0"),
+                execs().with_status(0).with_stdout("\
+c v1.0.0
+└── a v1.0.0
+    └── foo v0.0.1 ([..])
+"));
+
+    assert_that(p.cargo("tree").arg("--duplicates"),
+                execs().with_status(0).with_stdout("\
+c v1.0.0
+└── a v1.0.0
+    └── foo v0.0.1 ([..])
+
+c v2.0.0
+└── b v1.0.0
+    └── foo v0.0.1 ([..])
+"));
+}
+
+#[test]
+fn target_filtering() {
+    Package::new("unixdep", "1.0.0").publish();
+    Package::new("windep", "1.0.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [target.'cfg(unix)'.dependencies]
+            unixdep = "1.0"
+
+            [target.'cfg(windows)'.dependencies]
+            windep = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("tree").arg("--target").arg("x86_64-pc-windows-msvc"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.1 ([..])
+└── windep v1.0.0
+"));
+}
+
+#[test]
+fn show_features() {
+    Package::new("a", "1.0.0")
+        .feature("std", &[])
+        .feature("serde", &[])
+        .publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = { version = "1.0", features = ["std", "serde"] }
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("tree").arg("--show-features"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.1 ([..])
+└── a v1.0.0 [serde, std]
+"));
+}
@@ -0,0 +1,184 @@
+use std::fs::{self, File};
+use std::io::prelude::*;
+
+use cargotest::support::registry::Package;
+use cargotest::support::{git, project, execs};
+use hamcrest::{assert_that, existing_dir, existing_file, is_not};
+
+#[test]
+fn vendor_simple() {
+    Package::new("bar", "0.1.0")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn bar() {}")
+        .publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#)
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .build();
+
+    assert_that(p.cargo("vendor"),
+                execs().with_status(0)
+                       .with_stdout("\
+[source.crates-io]
+replace-with = \"vendored-sources\"
+
+[source.vendored-sources]
+directory = \"vendor\"
+")
+                       .with_stderr("\
+[UPDATING] registry `[..]`
+[DOWNLOADING] bar v0.1.0 ([..])
+[VENDORING] bar v0.1.0
+[VENDORED] dependencies into `vendor`
+"));
+
+    assert_that(&p.root().join("vendor/bar-0.1.0/src/lib.rs"), existing_file());
+    assert_that(&p.root().join("vendor/bar-0.1.0/.cargo-checksum.json"),
+                existing_file());
+
+    fs::create_dir(p.root().join(".cargo")).unwrap();
+    File::create(p.root().join(".cargo/config")).unwrap().write_all(br#"
+        [source.crates-io]
+        replace-with = "vendored-sources"
+
+        [source.vendored-sources]
+        directory = "vendor"
+    "#).unwrap();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr("\
+[COMPILING] bar v0.1.0
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+"));
+}
+
+#[test]
+fn removes_stale_packages() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("baz", "0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+            baz = "0.1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("vendor"), execs().with_status(0));
+    assert_that(&p.root().join("vendor/baz-0.1.0"), existing_dir());
+
+    p.change_file("Cargo.toml", r#"
+        [package]
+        name = "foo"
+        version = "0.1.0"
+        authors = []
+
+        [dependencies]
+        bar = "0.1.0"
+    "#);
+
+    assert_that(p.cargo("vendor").arg("--no-delete"), execs().with_status(0));
+    assert_that(&p.root().join("vendor/baz-0.1.0"), existing_dir());
+
+    assert_that(p.cargo("vendor"),
+                execs().with_status(0).with_stderr_contains("\
+[REMOVING] [..]baz-0.1.0
+"));
+    assert_that(&p.root().join("vendor/baz-0.1.0"), is_not(existing_dir()));
+    assert_that(&p.root().join("vendor/bar-0.1.0"), existing_dir());
+}
+
+#[test]
+fn custom_directory() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("vendor").arg("third-party"),
+                execs().with_status(0).with_stdout_contains("\
+directory = \"third-party\"
+"));
+    assert_that(&p.root().join("third-party/bar-0.1.0/Cargo.toml"),
+                existing_file());
+}
+
+#[test]
+fn duplicate_version_from_two_sources() {
+    Package::new("bar", "0.1.0").publish();
+    let git = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+            baz = { path = "baz" }
+        "#)
+        .file("src/lib.rs", "")
+        .file("baz/Cargo.toml", &format!(r#"
+            [package]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = {{ git = '{}' }}
+        "#, git.url()))
+        .file("baz/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("vendor"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] found duplicate version of package `bar v0.1.0` vendored from two sources:
+
+  source 1: [..]
+  source 2: [..]
+"));
+    assert_that(&p.root().join("vendor"), is_not(existing_dir()));
+}
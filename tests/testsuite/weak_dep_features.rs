@@ -0,0 +1,141 @@
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs, ProjectBuilder};
+use hamcrest::assert_that;
+
+fn weak_project(features: &str) -> ProjectBuilder {
+    project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = {{ path = "bar", optional = true }}
+
+            [features]
+            {}
+        "#, features))
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+
+            [features]
+            feat = []
+        "#)
+        .file("bar/src/lib.rs", r#"
+            #[cfg(not(feature = "feat"))]
+            compile_error!("feat is not enabled");
+        "#)
+}
+
+#[test]
+fn weak_does_not_enable_dep() {
+    let p = weak_project(r#"f = ["bar?/feat"]"#).build();
+
+    assert_that(p.cargo("build").arg("--features").arg("f"),
+                execs().with_status(0)
+                       .with_stderr("\
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+"));
+    assert_that(p.cargo("build").arg("--features").arg("f bar"),
+                execs().with_status(0)
+                       .with_stderr("\
+[COMPILING] bar v0.1.0 ([..])
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+"));
+}
+
+#[test]
+fn weak_with_namespaced_dep() {
+    let p = weak_project(r#"
+            f = ["bar?/feat"]
+            g = ["dep:bar"]
+    "#).build();
+
+    assert_that(p.cargo("build").arg("--features").arg("g"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[..]feat is not enabled[..]"));
+    assert_that(p.cargo("build").arg("--features").arg("f g"),
+                execs().with_status(0));
+}
+
+#[test]
+fn weak_across_dependents() {
+    // `foo` is activated once with `f` and once with `bar` enabled, which
+    // together enable `bar/feat`
+    Package::new("bar", "0.1.0")
+        .feature("feat", &[])
+        .file("src/lib.rs", r#"
+            #[cfg(not(feature = "feat"))]
+            compile_error!("feat is not enabled");
+        "#)
+        .publish();
+    Package::new("foo", "0.1.0")
+        .optional_dep("bar", "0.1.0")
+        .feature("f", &["bar?/feat"])
+        .publish();
+    Package::new("a", "0.1.0")
+        .feature_dep("foo", "0.1.0", &["f"])
+        .publish();
+    Package::new("b", "0.1.0")
+        .feature_dep("foo", "0.1.0", &["bar"])
+        .publish();
+
+    let p = project("top")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "top"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            a = "0.1.0"
+            b = "0.1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+}
+
+#[test]
+fn weak_on_required_dep() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+
+            [features]
+            f = ["bar?/feat"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  Feature `f` includes `bar?/feat` with a `?`, but `bar` is not an optional dependency
+"));
+}
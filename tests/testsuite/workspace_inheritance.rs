@@ -0,0 +1,235 @@
+use std::fs::File;
+use std::io::prelude::*;
+
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs};
+use flate2::read::GzDecoder;
+use hamcrest::assert_that;
+use tar::Archive;
+
+#[test]
+fn inherit_package_and_dependencies() {
+    Package::new("dep", "0.1.0").feature("f", &[]).publish();
+
+    let p = project("ws")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["foo"]
+
+            [workspace.package]
+            version = "1.2.3"
+            authors = ["Rustaceans"]
+            description = "an inherited description"
+            license = "MIT"
+            repository = "https://example.com/repo"
+
+            [workspace.dependencies]
+            dep = "0.1"
+        "#)
+        .file("foo/Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version.workspace = true
+            authors.workspace = true
+            description.workspace = true
+            license.workspace = true
+            repository = { workspace = true }
+
+            [dependencies]
+            dep = { workspace = true, features = ["f"] }
+        "#)
+        .file("foo/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("package").arg("--no-verify").cwd(p.root().join("foo")),
+                execs().with_status(0));
+
+    let f = File::open(&p.root().join("target/package/foo-1.2.3.crate")).unwrap();
+    let mut rdr = GzDecoder::new(f);
+    let mut contents = Vec::new();
+    rdr.read_to_end(&mut contents).unwrap();
+    let mut ar = Archive::new(&contents[..]);
+    let mut entry = ar.entries().unwrap()
+                        .map(|f| f.unwrap())
+                        .find(|e| e.path().unwrap().ends_with("Cargo.toml"))
+                        .unwrap();
+    let mut contents = String::new();
+    entry.read_to_string(&mut contents).unwrap();
+    assert_eq!(&contents[..],
+r#"# THIS FILE IS AUTOMATICALLY GENERATED BY CARGO
+#
+# When uploading crates to the registry Cargo will automatically
+# "normalize" Cargo.toml files for maximal compatibility
+# with all versions of Cargo and also rewrite `path` dependencies
+# to registry (e.g. crates.io) dependencies
+#
+# If you believe there's an error in this file please file an
+# issue against the rust-lang/cargo repository. If you're
+# editing this file be aware that the upstream Cargo.toml
+# will likely look very different (and much more reasonable)
+
+[package]
+name = "foo"
+version = "1.2.3"
+authors = ["Rustaceans"]
+description = "an inherited description"
+license = "MIT"
+repository = "https://example.com/repo"
+[dependencies.dep]
+version = "0.1"
+features = ["f"]
+"#);
+}
+
+#[test]
+fn inherit_in_root_package() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = { workspace = true }
+            authors = []
+
+            [workspace]
+
+            [workspace.package]
+            version = "1.2.3"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr("\
+[COMPILING] foo v1.2.3 ([..])
+[FINISHED] [..]
+"));
+}
+
+#[test]
+fn inherit_path_dependency() {
+    let p = project("ws")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["crates/foo"]
+
+            [workspace.dependencies]
+            bar = { path = "crates/bar" }
+        "#)
+        .file("crates/foo/Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar.workspace = true
+        "#)
+        .file("crates/foo/src/lib.rs", "extern crate bar;")
+        .file("crates/bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("crates/bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("-p").arg("foo"),
+                execs().with_status(0)
+                       .with_stderr("\
+[COMPILING] bar v0.1.0 ([..])
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+"));
+}
+
+#[test]
+fn inherit_undefined_key() {
+    let p = project("ws")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["foo"]
+
+            [workspace.package]
+            authors = []
+        "#)
+        .file("foo/Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version.workspace = true
+            authors.workspace = true
+        "#)
+        .file("foo/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+error: failed to parse manifest at `[..]foo[/]Cargo.toml`
+
+Caused by:
+  error inheriting `version` from the workspace root manifest
+
+Caused by:
+  `workspace.package.version` was not defined
+"));
+}
+
+#[test]
+fn inherit_without_workspace() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors.workspace = true
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+error: failed to parse manifest at `[..]`
+
+Caused by:
+  error inheriting `authors` from the workspace root manifest
+
+Caused by:
+  failed to find a workspace root
+"));
+}
+
+#[test]
+fn inherited_dependency_with_version() {
+    let p = project("ws")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["foo"]
+
+            [workspace.dependencies]
+            dep = "0.1"
+        "#)
+        .file("foo/Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            dep = { workspace = true, version = "0.2" }
+        "#)
+        .file("foo/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+error: failed to parse manifest at `[..]foo[/]Cargo.toml`
+
+Caused by:
+  dependency `dep` is inherited from the workspace, so only `features` and \
+`optional` can be specified for it
+"));
+}